@@ -16,6 +16,9 @@ pub mod stock;
 use serde::{Deserialize, Serializer};
 pub use stock::*;
 
+pub mod resample;
+pub use resample::*;
+
 /// 股票市场： 深圳/上海/上海
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
 #[repr(u8)]
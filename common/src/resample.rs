@@ -0,0 +1,240 @@
+//! k线重采样，将较细频率的k线合并为较粗频率，如1分钟线合并为5分钟线，日线合并为周线，月线
+//!
+//! 分钟线的时间为周期结束时间，A股交易时段为9:30-11:30，13:00-15:00，
+//! 合并后的分钟线按交易时段切分，不跨越午间休市，如60分钟线为10:30，11:30，14:00，15:00。
+//! 日线，周线，月线的时间为周期内最后一个交易日的0点。
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::{Bar, BarFreq};
+
+/// 每个交易时段的分钟数
+const SESSION_MINUTES: i64 = 120;
+
+fn morning_open(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(9, 30, 0).unwrap()
+}
+
+fn noon_open(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(13, 0, 0).unwrap()
+}
+
+/// 分钟线所在周期的结束时间，9:30集合竞价的数据归入第一个周期
+fn minute_period(dt: &NaiveDateTime, minutes: i64) -> NaiveDateTime {
+    let date = dt.date();
+    let offset = if dt.time() <= NaiveTime::from_hms_opt(11, 30, 0).unwrap() {
+        (*dt - morning_open(date)).num_minutes()
+    } else {
+        SESSION_MINUTES + (*dt - noon_open(date)).num_minutes()
+    };
+    let offset = offset.clamp(1, SESSION_MINUTES * 2);
+    let end = (offset + minutes - 1) / minutes * minutes;
+    if end <= SESSION_MINUTES {
+        morning_open(date) + Duration::minutes(end)
+    } else {
+        noon_open(date) + Duration::minutes(end - SESSION_MINUTES)
+    }
+}
+
+/// k线所属周期，相同的为同一周期
+fn period_key(dt: &NaiveDateTime, freq: BarFreq) -> (i32, u32, u32) {
+    match freq {
+        BarFreq::Min1 | BarFreq::Min5 | BarFreq::Min15 | BarFreq::Min30 | BarFreq::Min60 => {
+            let end = minute_period(dt, freq.to_seconds() / 60);
+            (end.year(), end.ordinal(), end.hour() * 60 + end.minute())
+        }
+        BarFreq::Daily | BarFreq::LooseDaily => (dt.year(), dt.ordinal(), 0),
+        BarFreq::Weekly => {
+            let week = dt.iso_week();
+            (week.year(), week.week(), 0)
+        }
+        BarFreq::Monthly => (dt.year(), dt.month(), 0),
+    }
+}
+
+/// 合并同一周期的k线，价格按最后一根k线的复权因子折算，复权因子取最后一根k线的
+fn merge(bars: &[Bar], freq: BarFreq) -> Bar {
+    let first = &bars[0];
+    let last = &bars[bars.len() - 1];
+    let factor = |bar: &Bar| {
+        if bar.hfq_factor > 0.0 && last.hfq_factor > 0.0 {
+            bar.hfq_factor / last.hfq_factor
+        } else {
+            1.0
+        }
+    };
+    let trade_date = match freq {
+        BarFreq::Min1 | BarFreq::Min5 | BarFreq::Min15 | BarFreq::Min30 | BarFreq::Min60 => {
+            minute_period(&last.trade_date, freq.to_seconds() / 60)
+        }
+        _ => last.trade_date.date().and_hms_opt(0, 0, 0).unwrap(),
+    };
+    // 周期开始前的收盘价，由第一根k线的涨跌幅推算
+    let pre_close = first.close / (1.0 + first.chg_pct / 100.0) * factor(first);
+    let close = last.close;
+    Bar {
+        code: first.code.clone(),
+        name: first.name.clone(),
+        trade_date,
+        open: first.open * factor(first),
+        close,
+        high: bars
+            .iter()
+            .map(|b| b.high * factor(b))
+            .fold(f32::MIN, f32::max),
+        low: bars
+            .iter()
+            .map(|b| b.low * factor(b))
+            .fold(f32::MAX, f32::min),
+        volume: bars.iter().map(|b| b.volume).sum(),
+        amount: bars.iter().map(|b| b.amount).sum(),
+        turnover: bars.iter().map(|b| b.turnover).sum(),
+        chg_pct: if pre_close > 0.0 {
+            (close - pre_close) * 100.0 / pre_close
+        } else {
+            0.0
+        },
+        volume_chg_pct: 0.0,
+        amount_chg_pct: 0.0,
+        hfq_factor: last.hfq_factor,
+    }
+}
+
+/// 将k线重采样为`freq`频率，`bars`需为同一代码按时间升序排列，频率不高于`freq`，
+/// 如分钟线可合并为更粗的分钟线或日线，周线，月线，日线可合并为周线，月线。
+/// 成交量，成交额，换手率为周期内累计，涨跌幅相对周期开始前的收盘价，
+/// 成交量及成交额变更相对上一周期。
+pub fn resample(bars: &[Bar], freq: BarFreq) -> Vec<Bar> {
+    let mut data: Vec<Bar> = Vec::new();
+    let mut start = 0;
+    for i in 1..=bars.len() {
+        let is_end = i == bars.len()
+            || bars[i].code != bars[start].code
+            || period_key(&bars[i].trade_date, freq) != period_key(&bars[start].trade_date, freq);
+        if !is_end {
+            continue;
+        }
+        let mut bar = merge(&bars[start..i], freq);
+        if let Some(pre) = data.last().filter(|pre| pre.code == bar.code) {
+            if pre.volume > 0 {
+                bar.volume_chg_pct =
+                    ((bar.volume as i64 - pre.volume as i64) * 100) as f32 / pre.volume as f32;
+            }
+            if pre.amount > 0.0 {
+                bar.amount_chg_pct = ((bar.amount - pre.amount) * 100.0 / pre.amount) as f32;
+            }
+        }
+        data.push(bar);
+        start = i;
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use crate::{Bar, BarFreq};
+
+    use super::resample;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn bar(trade_date: NaiveDateTime, close: f32, chg_pct: f32) -> Bar {
+        Bar {
+            code: "sh600000".into(),
+            trade_date,
+            open: close,
+            close,
+            high: close + 0.5,
+            low: close - 0.5,
+            volume: 100,
+            amount: 1000.0,
+            turnover: 0.1,
+            chg_pct,
+            hfq_factor: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resample_minute() {
+        // 1分钟线，上午9:31-11:30，下午13:01-15:00
+        let bars: Vec<_> = (1..=120)
+            .map(|m| dt("2023-03-01 09:30") + chrono::Duration::minutes(m))
+            .chain((1..=120).map(|m| dt("2023-03-01 13:00") + chrono::Duration::minutes(m)))
+            .enumerate()
+            .map(|(i, t)| bar(t, 10.0 + i as f32 * 0.01, 0.0))
+            .collect();
+
+        let data = resample(&bars, BarFreq::Min60);
+        let times: Vec<_> = data.iter().map(|b| b.trade_date).collect();
+        assert_eq!(
+            times,
+            vec![
+                dt("2023-03-01 10:30"),
+                dt("2023-03-01 11:30"),
+                dt("2023-03-01 14:00"),
+                dt("2023-03-01 15:00")
+            ]
+        );
+        assert_eq!(data[0].open, 10.0);
+        assert_eq!(data[0].close, bars[59].close);
+        assert_eq!(data[0].high, bars[59].close + 0.5);
+        assert_eq!(data[0].volume, 6000);
+        assert_eq!(data[1].volume_chg_pct, 0.0);
+
+        let data = resample(&bars, BarFreq::Min5);
+        assert_eq!(data.len(), 48);
+        assert_eq!(data[0].trade_date, dt("2023-03-01 09:35"));
+        assert_eq!(data[24].trade_date, dt("2023-03-01 13:05"));
+
+        let data = resample(&bars, BarFreq::Daily);
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].trade_date, dt("2023-03-01 00:00"));
+        assert_eq!(data[0].volume, 24000);
+    }
+
+    #[test]
+    fn test_resample_daily() {
+        let date = |d| {
+            NaiveDate::from_ymd_opt(2023, 3, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        // 20230302除权，复权因子由1变为2
+        let mut bars = vec![
+            bar(date(1), 10.0, 0.0),
+            bar(date(2), 5.5, 10.0),
+            bar(date(3), 6.0, 9.0909),
+            bar(date(6), 6.6, 10.0),
+        ];
+        for b in bars.iter_mut().skip(1) {
+            b.hfq_factor = 2.0;
+        }
+        bars[3].volume = 200;
+
+        let data = resample(&bars, BarFreq::Weekly);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].trade_date, date(3));
+        // 按最后一根k线的复权因子折算
+        assert_eq!(data[0].open, 5.0);
+        assert_eq!(data[0].close, 6.0);
+        assert_eq!(data[0].high, 6.5);
+        assert_eq!(data[0].low, 4.75);
+        assert_eq!(data[0].hfq_factor, 2.0);
+        assert!((data[0].chg_pct - 20.0).abs() < 1e-3);
+        assert_eq!(data[0].volume, 300);
+        assert_eq!(data[1].trade_date, date(6));
+        assert!((data[1].chg_pct - 10.0).abs() < 1e-3);
+        assert!((data[1].volume_chg_pct + 33.333).abs() < 1e-2);
+
+        let data = resample(&bars, BarFreq::Monthly);
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].trade_date, date(6));
+        assert_eq!(data[0].volume, 500);
+    }
+}
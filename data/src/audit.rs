@@ -0,0 +1,637 @@
+//! 数据质量检查
+//!
+//! 按本地保存的交易日历及上市日期检查日线数据，报告缺失，重复，日期乱序，非交易日数据，
+//! 价格不一致，成交量异常及复权因子跳变。检查结果可序列化为json，也可转换为`Resync`重新同步修复。
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    store::{Loader, Query, DATA_DEF_START_DATE},
+    types::{CodeRange, Resync, SyncDataType},
+    Error, Result,
+};
+
+/// 检查选项
+#[derive(Debug, Clone)]
+pub struct AuditOptions {
+    /// 检查的代码范围，None则全部检查
+    pub codes: Option<CodeRange>,
+    /// 检查的截止日期，None则为今天之前的最近交易日
+    pub end: Option<NaiveDate>,
+    /// 复权因子相邻两个交易日的最大变化倍数，超过或复权因子变小则认为可疑
+    pub max_factor_jump: f32,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        Self {
+            codes: None,
+            end: None,
+            max_factor_jump: 3.0,
+        }
+    }
+}
+
+/// 数据问题，日期格式为YYYYMMDD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// 缺失`start`至`end`共`missing`个交易日的数据，停牌期间没有数据也会报告
+    Gap {
+        start: i32,
+        end: i32,
+        missing: usize,
+    },
+    /// 同一交易日有`count`条数据
+    Duplicate { trade_date: i32, count: usize },
+    /// 保存顺序中日期比前一条`prev`早
+    NonMonotonic { trade_date: i32, prev: i32 },
+    /// 非交易日的数据
+    NonTradeDate { trade_date: i32 },
+    /// 价格不一致，如最高价低于最低价，收盘价超出最高最低价范围
+    Ohlc { trade_date: i32, detail: String },
+    /// 成交量为0而价格有变化或成交额不为0，或者有成交量而没有成交额
+    ZeroVolume { trade_date: i32, detail: String },
+    /// 复权因子变小或相对前一交易日`prev`变化过大
+    FactorJump {
+        trade_date: i32,
+        prev: f32,
+        factor: f32,
+    },
+}
+
+/// 某个代码的数据问题
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditIssue {
+    pub code: String,
+    #[serde(flatten)]
+    pub issue: Issue,
+}
+
+/// 一张表的检查结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableAudit {
+    /// 表名，如`stock_daily`
+    pub tab: String,
+    /// 检查的代码数
+    pub codes: usize,
+    /// 检查的数据条数
+    pub bars: usize,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl TableAudit {
+    /// 转换为重新同步选项：缺失或异常的数据从问题的第一个交易日开始重新获取，
+    /// 重复，乱序或非交易日的数据删除后全量重新同步
+    pub fn resync(&self) -> Resync {
+        let mut resync = Resync::default();
+        for item in self.issues.iter() {
+            let trade_date = match &item.issue {
+                Issue::Duplicate { .. }
+                | Issue::NonMonotonic { .. }
+                | Issue::NonTradeDate { .. } => {
+                    resync.full_codes.insert(item.code.clone());
+                    continue;
+                }
+                Issue::Gap { start, .. } => *start,
+                Issue::Ohlc { trade_date, .. }
+                | Issue::ZeroVolume { trade_date, .. }
+                | Issue::FactorJump { trade_date, .. } => *trade_date,
+            };
+            if let Some(date) = to_date(trade_date) {
+                let since = resync.since.entry(item.code.clone()).or_insert(date);
+                if date < *since {
+                    *since = date;
+                }
+            }
+        }
+        let full_codes = resync.full_codes.clone();
+        resync.since.retain(|code, _| !full_codes.contains(code));
+        resync
+    }
+}
+
+/// 检查报告
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// 检查的截止日期
+    pub end: i32,
+    pub tables: Vec<TableAudit>,
+}
+
+/// 待检查的代码，`listing`为上市日期，`delisted`为是否已退市，退市的代码只检查到最后一条数据
+#[derive(Debug, Clone, Default)]
+struct AuditCode {
+    code: String,
+    listing: Option<i32>,
+    delisted: bool,
+}
+
+pub(crate) fn ymd(date: &NaiveDate) -> i32 {
+    date.year() * 10000 + date.month() as i32 * 100 + date.day() as i32
+}
+
+fn to_date(trade_date: i32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(
+        trade_date / 10000,
+        (trade_date / 100 % 100) as u32,
+        (trade_date % 100) as u32,
+    )
+}
+
+/// 表名
+fn table_name(typ: &SyncDataType) -> Result<&'static str> {
+    let tab = match typ {
+        SyncDataType::StockBar => "stock_daily",
+        SyncDataType::IndexBar => "index_daily",
+        SyncDataType::FundBar => "fund_daily",
+        SyncDataType::BondBar => "bond_daily",
+        SyncDataType::StockConceptBar => "stock_concept_daily",
+        SyncDataType::StockIndustryBar => "stock_industry_daily",
+        _ => return Err(Error::Custom(format!("audit {:?} is not supported", typ))),
+    };
+    Ok(tab)
+}
+
+async fn load_codes(loader: &dyn Loader, typ: &SyncDataType) -> Result<Vec<AuditCode>> {
+    let listing = |d: &chrono::NaiveDateTime| {
+        let d = ymd(&d.date());
+        if d > 19900101 {
+            Some(d)
+        } else {
+            None
+        }
+    };
+    let codes = match typ {
+        SyncDataType::StockBar => loader
+            .load_stock_info(Query::new())
+            .await?
+            .iter()
+            .map(|e| AuditCode {
+                code: e.code.clone(),
+                listing: listing(&e.listing_date),
+                delisted: false,
+            })
+            .collect(),
+        SyncDataType::BondBar => loader
+            .load_bond_info(Query::new())
+            .await?
+            .iter()
+            .map(|e| AuditCode {
+                code: e.code.clone(),
+                listing: listing(&e.listing_date),
+                delisted: e.is_delist != 0,
+            })
+            .collect(),
+        SyncDataType::IndexBar => loader
+            .load_index_info(Query::new())
+            .await?
+            .into_iter()
+            .map(|e| AuditCode {
+                code: e.code,
+                ..Default::default()
+            })
+            .collect(),
+        SyncDataType::FundBar => loader
+            .load_fund_info(Query::new())
+            .await?
+            .into_iter()
+            .map(|e| AuditCode {
+                code: e.code,
+                ..Default::default()
+            })
+            .collect(),
+        SyncDataType::StockConceptBar => loader
+            .load_stock_concept(Query::new())
+            .await?
+            .into_iter()
+            .map(|e| AuditCode {
+                code: e.code,
+                ..Default::default()
+            })
+            .collect(),
+        SyncDataType::StockIndustryBar => loader
+            .load_stock_industry(Query::new())
+            .await?
+            .into_iter()
+            .map(|e| AuditCode {
+                code: e.code,
+                ..Default::default()
+            })
+            .collect(),
+        _ => vec![],
+    };
+    Ok(codes)
+}
+
+async fn load_bars(
+    loader: &dyn Loader,
+    typ: &SyncDataType,
+    code: &str,
+) -> Result<Vec<rwqfetch::Bar>> {
+    // 不排序，按保存顺序检查日期是否递增
+    let query = Query::new().code(code);
+    match typ {
+        SyncDataType::StockBar => loader.load_stock_daily(query).await,
+        SyncDataType::IndexBar => loader.load_index_daily(query).await,
+        SyncDataType::FundBar => loader.load_fund_daily(query).await,
+        SyncDataType::BondBar => loader.load_bond_daily(query).await,
+        SyncDataType::StockConceptBar => loader.load_stock_concept_daily(query).await,
+        SyncDataType::StockIndustryBar => loader.load_stock_industry_daily(query).await,
+        _ => Ok(vec![]),
+    }
+}
+
+/// 检查截止日期
+async fn audit_end(loader: &dyn Loader, opts: &AuditOptions) -> Result<(BTreeSet<i32>, i32)> {
+    let calendar: BTreeSet<_> = loader
+        .load_trade_date(Query::new())
+        .await?
+        .into_iter()
+        .map(|e| e.trade_date)
+        .collect();
+    if calendar.is_empty() {
+        return Err(Error::Custom(
+            "trade_date is empty, sync trade_date first".to_owned(),
+        ));
+    }
+    let end = match opts.end {
+        Some(end) => ymd(&end),
+        None => {
+            let today = ymd(&Local::now().date_naive());
+            *calendar.range(..today).next_back().unwrap_or(&today)
+        }
+    };
+    Ok((calendar, end))
+}
+
+/// 检查一张日线表，`typ`为日线类型，如`SyncDataType::StockBar`
+pub async fn audit_table(
+    loader: &dyn Loader,
+    typ: &SyncDataType,
+    opts: &AuditOptions,
+) -> Result<TableAudit> {
+    let (calendar, end) = audit_end(loader, opts).await?;
+    audit_table_with(loader, typ, opts, &calendar, end).await
+}
+
+async fn audit_table_with(
+    loader: &dyn Loader,
+    typ: &SyncDataType,
+    opts: &AuditOptions,
+    calendar: &BTreeSet<i32>,
+    end: i32,
+) -> Result<TableAudit> {
+    let tab = table_name(typ)?;
+    let mut report = TableAudit {
+        tab: tab.to_owned(),
+        ..Default::default()
+    };
+    let codes = load_codes(loader, typ).await?;
+    for code in codes.iter() {
+        if !opts.codes.as_ref().is_none_or(|r| r.contains(&code.code)) {
+            continue;
+        }
+        log::info!("audit {} {}", tab, code.code.as_str());
+        let bars = load_bars(loader, typ, &code.code).await?;
+        report.codes += 1;
+        report.bars += bars.len();
+        report.issues.extend(
+            check_bars(code, &bars, calendar, end, opts)
+                .into_iter()
+                .map(|issue| AuditIssue {
+                    code: code.code.clone(),
+                    issue,
+                }),
+        );
+    }
+    log::info!(
+        "audit {} done, codes={}, bars={}, issues={}",
+        tab,
+        report.codes,
+        report.bars,
+        report.issues.len()
+    );
+    Ok(report)
+}
+
+/// 检查多张日线表
+pub async fn audit(
+    loader: &dyn Loader,
+    typs: &[SyncDataType],
+    opts: &AuditOptions,
+) -> Result<AuditReport> {
+    let (calendar, end) = audit_end(loader, opts).await?;
+    let mut report = AuditReport {
+        end,
+        tables: Vec::new(),
+    };
+    for typ in typs.iter() {
+        let table = audit_table_with(loader, typ, opts, &calendar, end).await?;
+        report.tables.push(table);
+    }
+    Ok(report)
+}
+
+/// 检查一个代码的数据
+fn check_bars(
+    code: &AuditCode,
+    bars: &[rwqfetch::Bar],
+    calendar: &BTreeSet<i32>,
+    end: i32,
+    opts: &AuditOptions,
+) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let dates: Vec<_> = bars.iter().map(|b| ymd(&b.trade_date.date())).collect();
+
+    // 保存顺序
+    for w in dates.windows(2) {
+        if w[1] < w[0] {
+            issues.push(Issue::NonMonotonic {
+                trade_date: w[1],
+                prev: w[0],
+            });
+        }
+    }
+
+    let mut sorted: Vec<_> = dates.iter().copied().zip(bars.iter()).collect();
+    sorted.sort_by_key(|(d, _)| *d);
+
+    // 重复及非交易日
+    let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+    for (d, _) in sorted.iter() {
+        *counts.entry(*d).or_default() += 1;
+    }
+    let first_calendar = calendar.first().copied().unwrap_or_default();
+    let last_calendar = calendar.last().copied().unwrap_or_default();
+    for (d, count) in counts.iter() {
+        if *count > 1 {
+            issues.push(Issue::Duplicate {
+                trade_date: *d,
+                count: *count,
+            });
+        }
+        if *d >= first_calendar && *d <= last_calendar && !calendar.contains(d) {
+            issues.push(Issue::NonTradeDate { trade_date: *d });
+        }
+    }
+
+    // 缺失
+    let def_start = NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d")
+        .map(|d| ymd(&d))
+        .unwrap_or_default();
+    let start = code
+        .listing
+        .or_else(|| counts.keys().next().copied())
+        .unwrap_or(def_start)
+        .max(def_start);
+    let stop = if code.delisted {
+        counts.keys().next_back().copied().unwrap_or_default()
+    } else {
+        end
+    };
+    if start <= stop {
+        let mut gap: Option<(i32, i32, usize)> = None;
+        for d in calendar.range(start..=stop) {
+            if counts.contains_key(d) {
+                if let Some((start, end, missing)) = gap.take() {
+                    issues.push(Issue::Gap {
+                        start,
+                        end,
+                        missing,
+                    });
+                }
+            } else {
+                gap = match gap {
+                    Some((start, _, missing)) => Some((start, *d, missing + 1)),
+                    None => Some((*d, *d, 1)),
+                };
+            }
+        }
+        if let Some((start, end, missing)) = gap {
+            issues.push(Issue::Gap {
+                start,
+                end,
+                missing,
+            });
+        }
+    }
+
+    // 价格，成交量及复权因子
+    let mut prev_factor: Option<f32> = None;
+    for (d, bar) in sorted.iter() {
+        let mut detail = Vec::new();
+        if bar.open <= 0.0 || bar.close <= 0.0 || bar.high <= 0.0 || bar.low <= 0.0 {
+            detail.push("non-positive price");
+        }
+        if bar.high < bar.low {
+            detail.push("high < low");
+        } else {
+            if bar.open < bar.low || bar.open > bar.high {
+                detail.push("open out of [low, high]");
+            }
+            if bar.close < bar.low || bar.close > bar.high {
+                detail.push("close out of [low, high]");
+            }
+        }
+        if !detail.is_empty() {
+            issues.push(Issue::Ohlc {
+                trade_date: *d,
+                detail: detail.join(", "),
+            });
+        }
+
+        let detail = if bar.volume == 0 && bar.amount > 0.0 {
+            Some("zero volume with amount")
+        } else if bar.volume == 0 && bar.high != bar.low {
+            Some("zero volume with price change")
+        } else if bar.volume > 0 && bar.amount <= 0.0 {
+            Some("volume without amount")
+        } else {
+            None
+        };
+        if let Some(detail) = detail {
+            issues.push(Issue::ZeroVolume {
+                trade_date: *d,
+                detail: detail.to_owned(),
+            });
+        }
+
+        // 没有复权因子的数据(如指数)为0，不检查
+        let factor = bar.hfq_factor;
+        if let Some(prev) = prev_factor {
+            if factor < prev * (1.0 - 1e-4) || factor > prev * opts.max_factor_jump {
+                issues.push(Issue::FactorJump {
+                    trade_date: *d,
+                    prev,
+                    factor,
+                });
+            }
+        }
+        if factor > 0.0 {
+            prev_factor = Some(factor);
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::NaiveDate;
+
+    use super::{check_bars, AuditCode, AuditIssue, AuditOptions, Issue, TableAudit};
+
+    fn bar(day: u32, close: f32, factor: f32) -> rwqfetch::Bar {
+        rwqfetch::Bar {
+            code: "sz000001".to_owned(),
+            trade_date: NaiveDate::from_ymd_opt(2023, 3, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            open: close,
+            close,
+            high: close,
+            low: close,
+            volume: 100,
+            amount: 100.0 * close as f64,
+            hfq_factor: factor,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_bars() {
+        let calendar: BTreeSet<_> = [
+            20230301, 20230302, 20230303, 20230306, 20230307, 20230308, 20230309,
+        ]
+        .into();
+        let code = AuditCode {
+            code: "sz000001".to_owned(),
+            listing: Some(20230301),
+            delisted: false,
+        };
+        let mut bars = vec![
+            bar(1, 10.0, 1.0),
+            bar(3, 10.0, 1.0),
+            bar(2, 10.0, 1.0),
+            bar(3, 10.0, 1.0),
+            bar(4, 10.0, 1.0),
+            bar(7, 10.0, 0.5),
+        ];
+        bars[4].high = 9.0;
+        bars[4].volume = 0;
+        let issues = check_bars(&code, &bars, &calendar, 20230309, &AuditOptions::default());
+        assert_eq!(
+            issues,
+            vec![
+                Issue::NonMonotonic {
+                    trade_date: 20230302,
+                    prev: 20230303
+                },
+                Issue::Duplicate {
+                    trade_date: 20230303,
+                    count: 2
+                },
+                Issue::NonTradeDate {
+                    trade_date: 20230304
+                },
+                Issue::Gap {
+                    start: 20230306,
+                    end: 20230306,
+                    missing: 1
+                },
+                Issue::Gap {
+                    start: 20230308,
+                    end: 20230309,
+                    missing: 2
+                },
+                Issue::Ohlc {
+                    trade_date: 20230304,
+                    detail: "high < low".to_owned()
+                },
+                Issue::ZeroVolume {
+                    trade_date: 20230304,
+                    detail: "zero volume with amount".to_owned()
+                },
+                Issue::FactorJump {
+                    trade_date: 20230307,
+                    prev: 1.0,
+                    factor: 0.5
+                },
+            ]
+        );
+
+        // 退市的代码只检查到最后一条数据
+        let code = AuditCode {
+            delisted: true,
+            ..code
+        };
+        let bars = vec![bar(1, 10.0, 1.0), bar(2, 10.0, 1.0)];
+        let issues = check_bars(&code, &bars, &calendar, 20230309, &AuditOptions::default());
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_resync() {
+        let issue = |code: &str, issue| AuditIssue {
+            code: code.to_owned(),
+            issue,
+        };
+        let table = TableAudit {
+            tab: "stock_daily".to_owned(),
+            codes: 2,
+            bars: 10,
+            issues: vec![
+                issue(
+                    "a",
+                    Issue::Gap {
+                        start: 20230306,
+                        end: 20230306,
+                        missing: 1,
+                    },
+                ),
+                issue(
+                    "a",
+                    Issue::ZeroVolume {
+                        trade_date: 20230302,
+                        detail: String::new(),
+                    },
+                ),
+                issue(
+                    "b",
+                    Issue::Gap {
+                        start: 20230306,
+                        end: 20230306,
+                        missing: 1,
+                    },
+                ),
+                issue(
+                    "b",
+                    Issue::Duplicate {
+                        trade_date: 20230303,
+                        count: 2,
+                    },
+                ),
+            ],
+        };
+        let resync = table.resync();
+        assert_eq!(
+            resync.since.into_iter().collect::<Vec<_>>(),
+            vec![("a".to_owned(), NaiveDate::from_ymd_opt(2023, 3, 2).unwrap())]
+        );
+        assert!(resync.full_codes.contains("b"));
+
+        let json = serde_json::to_string(&table.issues[0]).unwrap();
+        assert_eq!(
+            json,
+            r#"{"code":"a","kind":"gap","start":20230306,"end":20230306,"missing":1}"#
+        );
+        let issue: AuditIssue = serde_json::from_str(&json).unwrap();
+        assert_eq!(issue, table.issues[0]);
+    }
+}
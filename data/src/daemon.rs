@@ -0,0 +1,531 @@
+//! 同步守护进程
+//!
+//! 按本地保存的交易日历，在每个交易日的指定时间执行配置的同步任务，如收盘后同步日线，
+//! 次日早上同步融资融券，财报季同步业绩报表。任务失败时按配置重试，每次执行的结果追加写入状态文件，
+//! 重启后当日已执行过的任务不再执行。
+//!
+//! 配置为json格式，如：
+//! ```json
+//! {
+//!     "dest": ["mongodb=mongodb://localhost:27017"],
+//!     "cutoff": "15:05",
+//!     "status": "/user/home/app/daemon-status.jsonl",
+//!     "queue": {"capacity": 8, "batch_size": 5000},
+//!     "rate_limit": {"xueqiu": {"rate": 1, "burst": 1, "max_inflight": 1}},
+//!     "jobs": [
+//!         {"name": "daily", "at": "15:30", "funcs": ["stock_daily", "index_daily"]},
+//!         {"name": "margin", "at": "09:00", "funcs": ["stock_margin"], "skip_basic": true},
+//!         {"name": "yjbb", "at": "20:00", "funcs": ["stock_yjbb"], "months": [1, 4, 7, 8, 10]}
+//!     ]
+//! }
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use rwqfetch::{set_http_mode, set_rate_limit, HttpMode, RateLimit, Site};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::{
+    audit::ymd,
+    store::{get_loader, Loader, Query},
+    syncer::set_sync_cutoff,
+    types::{MinuteSync, Resync, SyncDataType, SyncDest, SyncQueue, SyncReport},
+    Error, Result, Sync,
+};
+
+/// 检查任务是否需要执行的间隔
+const TICK: Duration = Duration::from_secs(60);
+
+fn default_concurrent() -> usize {
+    4
+}
+
+fn default_split_count() -> usize {
+    5
+}
+
+fn default_retries() -> usize {
+    3
+}
+
+fn default_retry_interval() -> u64 {
+    300
+}
+
+/// 同步任务配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonJob {
+    /// 任务名称，同一配置内唯一
+    pub name: String,
+    /// 每个交易日的执行时间，如"15:30"，启动时已过执行时间且当日未执行的立即执行
+    pub at: NaiveTime,
+    /// 同步的数据，格式同sync的funcs，为空则全部同步
+    #[serde(default)]
+    pub funcs: Vec<String>,
+    /// 同步的分钟线，格式同sync的minute
+    #[serde(default)]
+    pub minute: Vec<String>,
+    /// 是否忽略同步基础数据
+    #[serde(default)]
+    pub skip_basic: bool,
+    /// 按日同步的数据重新获取最近的交易日数
+    #[serde(default)]
+    pub overlap_days: usize,
+    /// 执行的月份，为空则每月执行，如财报季为[1, 4, 7, 8, 10]
+    #[serde(default)]
+    pub months: Vec<u32>,
+    /// 失败后的重试次数
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+    /// 重试间隔秒数
+    #[serde(default = "default_retry_interval")]
+    pub retry_interval: u64,
+}
+
+impl DaemonJob {
+    /// `now`时是否需要执行，`last`为最近一次执行的日期(YYYYMMDD)
+    pub fn is_due(&self, now: &NaiveDateTime, is_trade_date: bool, last: Option<i32>) -> bool {
+        let date = now.date();
+        is_trade_date
+            && (self.months.is_empty() || self.months.contains(&date.month()))
+            && now.time() >= self.at
+            && last.is_none_or(|last| last < ymd(&date))
+    }
+
+    fn sync_funcs(&self) -> Result<Option<Vec<SyncDataType>>> {
+        if self.funcs.is_empty() {
+            return Ok(None);
+        }
+        let funcs = self
+            .funcs
+            .iter()
+            .map(|e| SyncDataType::try_from(e.as_str()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(funcs))
+    }
+
+    fn sync_minute(&self) -> Result<Vec<MinuteSync>> {
+        self.minute
+            .iter()
+            .map(|e| MinuteSync::try_from(e.as_str()))
+            .collect()
+    }
+}
+
+/// 守护进程配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// 同步数据存储目的，格式同sync的dest，交易日历从第一个读取
+    pub dest: Vec<String>,
+    /// 并发获取数据任务数
+    #[serde(default = "default_concurrent")]
+    pub concurrent: usize,
+    /// 代码切分份数
+    #[serde(default = "default_split_count")]
+    pub split_count: usize,
+    /// 当日数据可同步的时间，默认为15:05
+    #[serde(default)]
+    pub cutoff: Option<NaiveTime>,
+    /// 执行状态文件，每次执行追加一行json，为空则只输出日志
+    #[serde(default)]
+    pub status: Option<PathBuf>,
+    /// 同步队列选项
+    #[serde(default)]
+    pub queue: SyncQueue,
+    /// 各站点的请求限流，未配置的站点使用默认值
+    #[serde(default)]
+    pub rate_limit: HashMap<Site, RateLimit>,
+    /// 保存请求原始响应的目录，用于在本地重放，重现解释数据出错的问题
+    #[serde(default)]
+    pub record: Option<PathBuf>,
+    /// 同步任务
+    pub jobs: Vec<DaemonJob>,
+}
+
+impl DaemonConfig {
+    /// 从json文件读取配置
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            Error::Custom(format!(
+                "read daemon config {} error: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// 从json读取配置，并检查配置是否有效
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config: Self = serde_json::from_str(json)
+            .map_err(|e| Error::Custom(format!("parse daemon config error: {}", e)))?;
+        config.sync_dest()?;
+        let mut names = HashSet::new();
+        for job in config.jobs.iter() {
+            if !names.insert(job.name.as_str()) {
+                return Err(Error::Custom(format!("duplicate job name: {}", job.name)));
+            }
+            job.sync_funcs()?;
+            job.sync_minute()?;
+        }
+        Ok(config)
+    }
+
+    fn sync_dest(&self) -> Result<Vec<SyncDest>> {
+        if self.dest.is_empty() {
+            return Err(Error::Custom("daemon config dest is empty".to_owned()));
+        }
+        self.dest
+            .iter()
+            .map(|e| {
+                let (source, url) = e
+                    .split_once('=')
+                    .ok_or_else(|| Error::Custom(format!("invalid dest format: {}", e)))?;
+                SyncDest::try_from((source.to_string(), url.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// 任务的一次执行结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRun {
+    /// 任务名称
+    pub name: String,
+    /// 执行的交易日，格式为YYYYMMDD
+    pub trade_date: i32,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// 执行次数，包括重试
+    pub attempts: usize,
+    /// 是否成功
+    pub success: bool,
+    /// 最后一次失败的错误
+    pub error: Option<String>,
+    /// 最后一次同步的结果
+    #[serde(default)]
+    pub report: Option<SyncReport>,
+}
+
+/// 是否交易日，日期超出交易日历范围(如尚未同步)时按周一至周五处理
+pub fn is_trade_date(calendar: &BTreeSet<i32>, date: &NaiveDate) -> bool {
+    let d = ymd(date);
+    match calendar.last() {
+        Some(last) if d <= *last => calendar.contains(&d),
+        _ => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+    }
+}
+
+/// 各任务最近一次执行的日期
+fn last_runs(runs: &[JobRun]) -> HashMap<String, i32> {
+    let mut last = HashMap::new();
+    for run in runs.iter() {
+        let d = last.entry(run.name.clone()).or_insert(run.trade_date);
+        if *d < run.trade_date {
+            *d = run.trade_date;
+        }
+    }
+    last
+}
+
+/// 读取状态文件，忽略无法解析的行
+fn read_status(path: &Path) -> Vec<JobRun> {
+    match std::fs::read_to_string(path) {
+        Ok(s) => s
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn write_status(path: &Path, run: &JobRun) -> Result<()> {
+    let line = serde_json::to_string(run)
+        .map_err(|e| Error::Custom(format!("serialize job run error: {}", e)))?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| Error::Custom(format!("open status {} error: {}", path.display(), e)))?;
+    writeln!(file, "{}", line)
+        .map_err(|e| Error::Custom(format!("write status {} error: {}", path.display(), e)))
+}
+
+/// 同步守护进程
+pub struct Daemon {
+    config: DaemonConfig,
+    dest: Vec<SyncDest>,
+    shutdown: broadcast::Receiver<()>,
+    is_shutdown: bool,
+    last: HashMap<String, i32>,
+}
+
+impl Daemon {
+    /// 构造对象
+    /// `config` 守护进程配置
+    /// `shutdown` 停止信号，正在执行的同步同时停止
+    pub fn new(config: DaemonConfig, shutdown: broadcast::Receiver<()>) -> Result<Self> {
+        let dest = config.sync_dest()?;
+        Ok(Self {
+            config,
+            dest,
+            shutdown,
+            is_shutdown: false,
+            last: HashMap::new(),
+        })
+    }
+
+    /// 是否收到停止信号
+    fn check_shutdown(&mut self) -> bool {
+        if !self.is_shutdown {
+            self.is_shutdown = !matches!(self.shutdown.try_recv(), Err(TryRecvError::Empty));
+        }
+        self.is_shutdown
+    }
+
+    async fn load_calendar(loader: &dyn Loader, calendar: &mut BTreeSet<i32>) {
+        match loader.load_trade_date(Query::new()).await {
+            Ok(data) => *calendar = data.into_iter().map(|e| e.trade_date).collect(),
+            Err(e) => log::error!("load trade_date error: {:?}, use previous calendar", e),
+        }
+    }
+
+    /// 执行一个任务，失败时续传重试，返回执行结果，收到停止信号时返回None
+    async fn run_job(&mut self, job: &DaemonJob, date: &NaiveDate) -> Result<Option<JobRun>> {
+        let start = Local::now().naive_local();
+        let resync = Resync {
+            overlap_days: job.overlap_days,
+            ..Default::default()
+        };
+        let run = format!("{}-{}", job.name, ymd(date));
+        let mut attempts = 0;
+        let mut error = None;
+        let mut report = None;
+        while attempts <= job.retries {
+            attempts += 1;
+            log::info!("start job {}, attempt#{}", job.name, attempts);
+            let mut s = Sync::new(
+                self.dest.clone(),
+                self.shutdown.resubscribe(),
+                job.sync_funcs()?,
+            )
+            .with_resync(resync.clone())
+            .with_minute(job.sync_minute()?)
+            .with_run(run.as_str(), attempts > 1)
+            .with_queue(self.config.queue);
+            let res = s
+                .sync(
+                    job.skip_basic,
+                    self.config.concurrent,
+                    self.config.split_count,
+                )
+                .await;
+            if self.check_shutdown() {
+                log::info!("job {} interrupted", job.name);
+                return Ok(None);
+            }
+            match res {
+                Ok(r) if r.failed() == 0 => {
+                    error = None;
+                    report = Some(r);
+                    break;
+                }
+                Ok(r) => {
+                    log::error!(
+                        "job {} attempt#{} failed: {}",
+                        job.name,
+                        attempts,
+                        r.failed()
+                    );
+                    error = Some(format!("{} fetch or save failures", r.failed()));
+                    report = Some(r);
+                }
+                Err(e) => {
+                    log::error!("job {} attempt#{} error: {:?}", job.name, attempts, e);
+                    error = Some(e.to_string());
+                }
+            }
+            if attempts <= job.retries {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(job.retry_interval)) => {},
+                    _ = self.shutdown.recv() => {
+                        self.is_shutdown = true;
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+        Ok(Some(JobRun {
+            name: job.name.clone(),
+            trade_date: ymd(date),
+            start,
+            end: Local::now().naive_local(),
+            attempts,
+            success: error.is_none(),
+            error,
+            report,
+        }))
+    }
+
+    fn record(&mut self, run: JobRun) {
+        log::info!(
+            "job {} done, success={}, attempts={}, error={:?}",
+            run.name,
+            run.success,
+            run.attempts,
+            run.error
+        );
+        if let Some(path) = self.config.status.as_ref() {
+            if let Err(e) = write_status(path, &run) {
+                log::error!("record job {} status error: {:?}", run.name, e);
+            }
+        }
+        self.last.insert(run.name, run.trade_date);
+    }
+
+    /// 运行直到收到停止信号
+    pub async fn run(&mut self) -> Result<()> {
+        if let Some(cutoff) = self.config.cutoff {
+            set_sync_cutoff(cutoff);
+        }
+        for (site, limit) in self.config.rate_limit.iter() {
+            set_rate_limit(*site, *limit);
+        }
+        if let Some(dir) = self.config.record.as_ref() {
+            set_http_mode(HttpMode::Record(dir.clone()));
+        }
+        if let Some(path) = self.config.status.as_ref() {
+            self.last = last_runs(&read_status(path));
+        }
+        let (_, loader) = get_loader(&self.dest[0], true).await?;
+        let mut calendar = BTreeSet::new();
+        let mut calendar_date = None;
+        let mut jobs = self.config.jobs.clone();
+        jobs.sort_by_key(|job| job.at);
+        log::info!("daemon started, {} job(s)", jobs.len());
+
+        while !self.check_shutdown() {
+            let now = Local::now().naive_local();
+            let date = now.date();
+            if calendar_date != Some(date) {
+                Self::load_calendar(loader.as_ref(), &mut calendar).await;
+                calendar_date = Some(date);
+            }
+            let trade_date = is_trade_date(&calendar, &date);
+            for job in jobs.iter() {
+                if !job.is_due(&now, trade_date, self.last.get(&job.name).copied()) {
+                    continue;
+                }
+                match self.run_job(job, &date).await? {
+                    Some(run) => self.record(run),
+                    None => return Ok(()),
+                }
+                // 任务可能同步了交易日历
+                Self::load_calendar(loader.as_ref(), &mut calendar).await;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => {},
+                _ = self.shutdown.recv() => {
+                    self.is_shutdown = true;
+                }
+            }
+        }
+        log::info!("daemon stopped");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use rwqfetch::Site;
+
+    use super::{is_trade_date, last_runs, DaemonConfig, JobRun};
+
+    fn now(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y%m%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_daemon_config() {
+        let config = DaemonConfig::from_json(
+            r#"{
+                "dest": ["file=/tmp/app"],
+                "cutoff": "15:05",
+                "queue": {"capacity": 2},
+                "rate_limit": {"xueqiu": {"rate": 1.0, "max_inflight": 1}},
+                "jobs": [
+                    {"name": "daily", "at": "15:30", "funcs": ["stock_daily"]},
+                    {"name": "yjbb", "at": "20:00:00", "funcs": ["stock_yjbb"], "months": [4, 8]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.concurrent, 4);
+        assert_eq!(config.record, None);
+        assert_eq!((config.queue.capacity, config.queue.batch_size), (2, 5000));
+        let xueqiu = config.rate_limit[&Site::XueQiu];
+        assert_eq!(
+            (xueqiu.rate, xueqiu.burst, xueqiu.max_inflight),
+            (1.0, 5, 1)
+        );
+        assert_eq!(config.jobs[0].retries, 3);
+        let (daily, yjbb) = (&config.jobs[0], &config.jobs[1]);
+
+        assert!(!daily.is_due(&now("20230301 15:00"), true, None));
+        assert!(daily.is_due(&now("20230301 15:30"), true, None));
+        assert!(daily.is_due(&now("20230301 15:30"), true, Some(20230228)));
+        assert!(!daily.is_due(&now("20230301 16:00"), true, Some(20230301)));
+        assert!(!daily.is_due(&now("20230301 16:00"), false, None));
+        assert!(!yjbb.is_due(&now("20230301 21:00"), true, None));
+        assert!(yjbb.is_due(&now("20230401 21:00"), true, None));
+
+        let invalid = |json| DaemonConfig::from_json(json).is_err();
+        assert!(invalid(r#"{"dest": [], "jobs": []}"#));
+        assert!(invalid(
+            r#"{"dest": ["file=/tmp"], "jobs": [{"name": "a", "at": "15:30", "funcs": ["x"]}]}"#
+        ));
+        assert!(invalid(
+            r#"{"dest": ["file=/tmp"], "jobs": [{"name": "a", "at": "15:30"}, {"name": "a", "at": "16:00"}]}"#
+        ));
+    }
+
+    #[test]
+    fn test_is_trade_date() {
+        let date = |d| NaiveDate::from_ymd_opt(2023, 3, d).unwrap();
+        let calendar: BTreeSet<_> = [20230301, 20230302, 20230303, 20230306].into();
+        assert!(is_trade_date(&calendar, &date(1)));
+        assert!(!is_trade_date(&calendar, &date(4)));
+        // 超出交易日历范围按周一至周五处理
+        assert!(is_trade_date(&calendar, &date(7)));
+        assert!(!is_trade_date(&calendar, &date(11)));
+        assert!(is_trade_date(&BTreeSet::new(), &date(1)));
+    }
+
+    #[test]
+    fn test_last_runs() {
+        let run = |name: &str, trade_date| JobRun {
+            name: name.to_owned(),
+            trade_date,
+            start: now("20230301 15:30"),
+            end: now("20230301 15:40"),
+            attempts: 1,
+            success: true,
+            error: None,
+            report: None,
+        };
+        let last = last_runs(&[run("a", 20230302), run("a", 20230301), run("b", 20230301)]);
+        assert_eq!(last.get("a"), Some(&20230302));
+        assert_eq!(last.get("b"), Some(&20230301));
+    }
+}
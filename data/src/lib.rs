@@ -1,7 +1,7 @@
 //! 此模块有两个作用
 //!
 //! - 获取远程数据并存储到本地，支持的数据目的地为文件，MongoDB，MySQL  
-//!   尽管数据可以同时同步到多个目的地，可对于终端用户而言，应该只使用一个。同步多个，数据量请求量和存储量成倍增加。
+//!   数据可以同时同步到多个目的地，按代码增量同步的数据只获取一次再分别保存，存储量成倍增加。
 //!
 //! - 提供简单统一接口访问各个数据源数据。
 //!
@@ -9,6 +9,8 @@
 
 use thiserror::Error;
 
+pub mod audit;
+pub mod daemon;
 pub mod store;
 
 pub mod sync;
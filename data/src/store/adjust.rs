@@ -0,0 +1,148 @@
+//! 复权价格计算
+//!
+//! 保存的日线为不复权价格，`hfq_factor`为后复权因子(后复权收盘价/不复权收盘价)：
+//! 后复权价格 = 不复权价格 * hfq_factor，
+//! 前复权价格 = 不复权价格 * hfq_factor / 基准日hfq_factor，基准日的前复权价格与不复权价格相同。
+
+use std::collections::HashMap;
+
+use rwqfetch::{AdjustFactor, Bar};
+
+/// 复权因子，未设置(<=0)的按1处理
+pub(crate) fn factor(bar: &Bar) -> f32 {
+    if bar.hfq_factor > 0.0 {
+        bar.hfq_factor
+    } else {
+        1.0
+    }
+}
+
+/// 按复权方式调整开高低收价格，成交量等其他字段不调整
+/// `anchors`: 前复权各代码基准日的复权因子，没有的按1处理
+pub(crate) fn adjust_bars(bars: &mut [Bar], fq: AdjustFactor, anchors: &HashMap<String, f32>) {
+    if fq == AdjustFactor::NFQ {
+        return;
+    }
+    for bar in bars.iter_mut() {
+        let mut ratio = factor(bar);
+        if fq == AdjustFactor::QFQ {
+            ratio /= anchors.get(&bar.code).copied().unwrap_or(1.0);
+        }
+        bar.open *= ratio;
+        bar.close *= ratio;
+        bar.high *= ratio;
+        bar.low *= ratio;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use chrono::NaiveDate;
+    use rwqfetch::{AdjustFactor, Bar};
+
+    use crate::{
+        store::{
+            file::FileDb,
+            table::{insert_many, TableDb, TableLoader},
+            DataType, Loader, Query, TAB_STOCK_DAILY,
+        },
+        FileFormat,
+    };
+
+    use super::adjust_bars;
+
+    fn bars() -> Vec<Bar> {
+        // 20230303除权，复权因子由1变为2
+        [(1, 10.0, 1.0), (2, 10.0, 1.0), (3, 5.0, 2.0), (6, 6.0, 2.0)]
+            .into_iter()
+            .map(|(d, close, hfq_factor)| Bar {
+                code: "sh600000".to_owned(),
+                trade_date: NaiveDate::from_ymd_opt(2023, 3, d)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+                open: close,
+                close,
+                high: close,
+                low: close,
+                volume: 100,
+                hfq_factor,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn close(bars: &[Bar]) -> Vec<f32> {
+        bars.iter().map(|e| e.close).collect()
+    }
+
+    #[test]
+    fn test_adjust_bars() {
+        let anchors: HashMap<_, _> = [("sh600000".to_owned(), 2.0)].into();
+        let mut data = bars();
+        adjust_bars(&mut data, AdjustFactor::NFQ, &anchors);
+        assert_eq!(close(&data), vec![10.0, 10.0, 5.0, 6.0]);
+
+        let mut data = bars();
+        adjust_bars(&mut data, AdjustFactor::HFQ, &anchors);
+        assert_eq!(close(&data), vec![10.0, 10.0, 10.0, 12.0]);
+        assert_eq!(data[3].low, 12.0);
+        assert_eq!(data[3].volume, 100);
+
+        let mut data = bars();
+        adjust_bars(&mut data, AdjustFactor::QFQ, &anchors);
+        assert_eq!(close(&data), vec![5.0, 5.0, 5.0, 6.0]);
+    }
+
+    #[tokio::test]
+    async fn test_load_daily_adjusted() {
+        let root = std::env::temp_dir().join(format!("rwqdata-adjust-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let db = Arc::new(FileDb::new(&root, FileFormat::default()));
+        db.create_schema().await.unwrap();
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars(), false)
+            .await
+            .unwrap();
+
+        let loader = TableLoader::new(db.clone());
+        let load = |fq, anchor| {
+            let loader = &loader;
+            async move {
+                let data = loader
+                    .load_daily_adjusted(DataType::Stock, Query::new(), fq, anchor)
+                    .await
+                    .unwrap();
+                close(&data)
+            }
+        };
+        let date = |d| NaiveDate::from_ymd_opt(2023, 3, d);
+        assert_eq!(
+            load(AdjustFactor::HFQ, None).await,
+            vec![10.0, 10.0, 10.0, 12.0]
+        );
+        // 默认以最新一条数据为基准
+        assert_eq!(
+            load(AdjustFactor::QFQ, None).await,
+            vec![5.0, 5.0, 5.0, 6.0]
+        );
+        assert_eq!(
+            load(AdjustFactor::QFQ, date(2)).await,
+            vec![10.0, 10.0, 10.0, 12.0]
+        );
+        // 基准日非交易日时取之前最近一个交易日
+        assert_eq!(
+            load(AdjustFactor::QFQ, date(4)).await,
+            vec![5.0, 5.0, 5.0, 6.0]
+        );
+        // 基准日早于最早一条数据时以最早一条数据为基准
+        assert_eq!(
+            load(AdjustFactor::QFQ, NaiveDate::from_ymd_opt(2022, 1, 1)).await,
+            vec![10.0, 10.0, 10.0, 12.0]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
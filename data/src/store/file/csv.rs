@@ -0,0 +1,192 @@
+//! CSV编解码
+//!
+//! 文本字段加引号，数值及布尔不加引号，空值为空字段，与Python`csv.QUOTE_NONNUMERIC`一致，
+//! 读取时据此还原字段类型，如代码`000001`不会被当作数值。
+
+use serde_json::{Map, Number, Value};
+
+use crate::{Error, Result};
+
+pub(crate) type Row = Map<String, Value>;
+
+/// 编码一个字段
+fn encode_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(v) => v.to_string(),
+        Value::Number(v) => v.to_string(),
+        Value::String(v) => quote(v),
+        _ => quote(&value.to_string()),
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// 编码表头
+pub(crate) fn encode_header(header: &[String]) -> String {
+    let fields: Vec<_> = header.iter().map(|h| quote(h)).collect();
+    fields.join(",") + "\n"
+}
+
+/// 按表头顺序编码一行，表头中没有的字段忽略
+pub(crate) fn encode_row(header: &[String], row: &Row) -> String {
+    let fields: Vec<_> = header
+        .iter()
+        .map(|h| row.get(h).map(encode_field).unwrap_or_default())
+        .collect();
+    fields.join(",") + "\n"
+}
+
+/// 解码后的字段，`quoted`表示是否为文本
+#[derive(Debug, PartialEq)]
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+impl Field {
+    fn into_value(self) -> Value {
+        if self.quoted {
+            return Value::String(self.text);
+        }
+        let text = self.text.trim();
+        if text.is_empty() {
+            return Value::Null;
+        }
+        if let Ok(v) = text.parse::<bool>() {
+            return Value::Bool(v);
+        }
+        if let Ok(v) = text.parse::<i64>() {
+            return Value::Number(v.into());
+        }
+        if let Ok(v) = text.parse::<u64>() {
+            return Value::Number(v.into());
+        }
+        text.parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(text.to_owned()))
+    }
+}
+
+/// 解析全部记录，引号内可包含逗号及换行
+fn parse_records(content: &str) -> Result<Vec<Vec<Field>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Field {
+        text: String::new(),
+        quoted: false,
+    };
+    let mut in_quote = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quote {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.text.push('"');
+                } else {
+                    in_quote = false;
+                }
+            } else {
+                field.text.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' if field.text.is_empty() && !field.quoted => {
+                field.quoted = true;
+                in_quote = true;
+            }
+            ',' => record.push(std::mem::replace(
+                &mut field,
+                Field {
+                    text: String::new(),
+                    quoted: false,
+                },
+            )),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::replace(
+                    &mut field,
+                    Field {
+                        text: String::new(),
+                        quoted: false,
+                    },
+                ));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.text.push(c),
+        }
+    }
+    if in_quote {
+        return Err(Error::Custom("csv unterminated quote".to_owned()));
+    }
+    if !field.text.is_empty() || field.quoted || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// 解码文件内容，返回表头及按表头组成的数据
+pub(crate) fn decode(content: &str) -> Result<(Vec<String>, Vec<Row>)> {
+    let mut records = parse_records(content)?.into_iter();
+    let header: Vec<_> = match records.next() {
+        Some(header) => header.into_iter().map(|f| f.text).collect(),
+        None => return Ok((vec![], vec![])),
+    };
+    let mut rows = Vec::new();
+    for record in records {
+        if record.len() != header.len() {
+            return Err(Error::Custom(format!(
+                "csv field count mismatch, expect {}, got {}",
+                header.len(),
+                record.len()
+            )));
+        }
+        let row: Row = header
+            .iter()
+            .cloned()
+            .zip(record.into_iter().map(Field::into_value))
+            .collect();
+        rows.push(row);
+    }
+    Ok((header, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map, Value};
+
+    use super::{decode, encode_header, encode_row};
+
+    #[test]
+    fn test_csv_round_trip() {
+        let header: Vec<String> = ["code", "name", "close", "volume", "is_margin", "note"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let row: Map<String, Value> = serde_json::from_value(json!({
+            "code": "000001",
+            "name": "平安\"银行\", A",
+            "close": 1.23,
+            "volume": 100,
+            "is_margin": true,
+            "note": null,
+        }))
+        .unwrap();
+        let content = encode_header(&header) + &encode_row(&header, &row);
+        assert_eq!(
+            content,
+            "\"code\",\"name\",\"close\",\"volume\",\"is_margin\",\"note\"\n\
+             \"000001\",\"平安\"\"银行\"\", A\",1.23,100,true,\n"
+        );
+        let (h, rows) = decode(&content).unwrap();
+        assert_eq!(h, header);
+        assert_eq!(rows, vec![row]);
+    }
+}
@@ -0,0 +1,594 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    store::{
+        query::{Cond, Query},
+        table::{TableDb, TableDef},
+    },
+    types::FileFormat,
+    Error, Result,
+};
+
+use super::{
+    csv::{self, Row},
+    filter::{matches, partition_values, sort},
+    parquet,
+};
+
+/// 分区的数据段数超过时合并为一个数据段
+const MAX_SEGMENTS: usize = 32;
+
+/// 文件存储，每张表保存为一个目录，有分区字段的表按分区保存为子目录，如：
+/// `stock_daily/sh600000/`，`stock_yjbb/2023/`，`stock_info/`
+///
+/// 目录下为按序号命名的数据段文件，如`00000001.parquet`，每次写入追加一个新的数据段，
+/// 唯一键相同的旧数据从其所在的数据段中删除，只需改写包含这些数据的数据段，
+/// 数据段超过`MAX_SEGMENTS`个时合并为一个，整个目录可直接由pandas/polars读取
+pub(crate) struct FileDb {
+    root: PathBuf,
+    format: FileFormat,
+    locks: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+    log::error!("file {} error: {}", path.display(), e);
+    Error::Custom(format!("file {} error: {}", path.display(), e))
+}
+
+/// 字段值转为文件名或唯一键的文本
+fn value_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn row_key(def: &TableDef, row: &Row) -> Vec<String> {
+    def.keys.iter().map(|k| value_text(row.get(*k))).collect()
+}
+
+/// 数据的字段，按首次出现的顺序
+fn header(rows: &[Row]) -> Vec<String> {
+    let mut header: Vec<String> = Vec::new();
+    for row in rows.iter() {
+        for k in row.keys() {
+            if !header.contains(k) {
+                header.push(k.clone());
+            }
+        }
+    }
+    header
+}
+
+impl FileFormat {
+    fn ext(&self) -> &'static str {
+        match self {
+            FileFormat::Parquet => "parquet",
+            FileFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FileDb {
+    pub fn new(root: &Path, format: FileFormat) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            format,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn table_path(&self, def: &TableDef) -> PathBuf {
+        self.root.join(def.name)
+    }
+
+    fn partition_path(&self, def: &TableDef, value: &str) -> PathBuf {
+        let name: String = value
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.table_path(def).join(name)
+    }
+
+    fn row_dir(&self, def: &TableDef, row: &Row) -> PathBuf {
+        match def.partition {
+            Some(p) => self.partition_path(def, &value_text(row.get(p))),
+            None => self.table_path(def),
+        }
+    }
+
+    fn segment_path(&self, dir: &Path, seq: u64) -> PathBuf {
+        dir.join(format!("{:08}.{}", seq, self.format.ext()))
+    }
+
+    fn lock(&self, dir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap();
+        locks.entry(dir.to_path_buf()).or_default().clone()
+    }
+
+    /// 查询需要读取的目录
+    async fn dirs(&self, def: &TableDef, conds: &[Cond]) -> Result<Vec<PathBuf>> {
+        let partition = match def.partition {
+            Some(p) => p,
+            None => return Ok(vec![self.table_path(def)]),
+        };
+        if let Some(values) = partition_values(conds, partition) {
+            return Ok(values.iter().map(|v| self.partition_path(def, v)).collect());
+        }
+        let dir = self.table_path(def);
+        let mut dirs = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(dirs),
+            Err(e) => return Err(io_error(&dir, e)),
+        };
+        while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(&dir, e))? {
+            if entry.path().is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    /// 目录下的数据段，按序号排序
+    async fn segments(&self, dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+        let mut segments = Vec::new();
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(segments),
+            Err(e) => return Err(io_error(dir, e)),
+        };
+        while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(dir, e))? {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != self.format.ext()) {
+                continue;
+            }
+            if let Some(seq) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse().ok())
+            {
+                segments.push((seq, path));
+            }
+        }
+        segments.sort();
+        Ok(segments)
+    }
+
+    /// 读取一个数据段，`columns`不为空时只需返回其中的字段
+    async fn read(&self, path: &Path, columns: Option<&[&str]>) -> Result<Vec<Row>> {
+        let data = tokio::fs::read(path).await.map_err(|e| io_error(path, e))?;
+        let decoded = match self.format {
+            FileFormat::Parquet => parquet::decode(data, columns),
+            FileFormat::Csv => String::from_utf8(data)
+                .map_err(|e| Error::Custom(e.to_string()))
+                .and_then(|content| csv::decode(&content)),
+        };
+        let (_, rows) = decoded
+            .map_err(|e| Error::Custom(format!("decode {} error: {:?}", path.display(), e)))?;
+        Ok(rows)
+    }
+
+    /// 写入一个数据段，先写入临时文件再替换
+    async fn write(&self, path: &Path, rows: &[Row]) -> Result<()> {
+        let header = header(rows);
+        let data = match self.format {
+            FileFormat::Parquet => parquet::encode(&header, rows)?,
+            FileFormat::Csv => {
+                let mut content = csv::encode_header(&header);
+                for row in rows.iter() {
+                    content.push_str(&csv::encode_row(&header, row));
+                }
+                content.into_bytes()
+            }
+        };
+        let tmp = path.with_extension(format!("{}.tmp", self.format.ext()));
+        tokio::fs::write(&tmp, data)
+            .await
+            .map_err(|e| io_error(&tmp, e))?;
+        tokio::fs::rename(&tmp, path)
+            .await
+            .map_err(|e| io_error(path, e))
+    }
+
+    /// 改写一个数据段，没有剩余数据时删除
+    async fn rewrite(&self, path: &Path, rows: &[Row]) -> Result<()> {
+        if rows.is_empty() {
+            return tokio::fs::remove_file(path)
+                .await
+                .map_err(|e| io_error(path, e));
+        }
+        self.write(path, rows).await
+    }
+
+    /// 读取目录下的全部数据，唯一键相同的以序号大的数据段为准
+    async fn load(&self, def: &TableDef, dir: &Path) -> Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        let mut index = HashMap::new();
+        for (_, path) in self.segments(dir).await? {
+            for row in self.read(&path, None).await? {
+                match index.get(&row_key(def, &row)) {
+                    Some(i) => rows[*i] = row,
+                    None => {
+                        index.insert(row_key(def, &row), rows.len());
+                        rows.push(row);
+                    }
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// 追加写入一个目录：新数据写入新的数据段，再从旧数据段中删除唯一键相同的数据
+    async fn append(&self, def: &TableDef, dir: &Path, rows: Vec<Row>) -> Result<()> {
+        let lock = self.lock(dir);
+        let _guard = lock.lock().await;
+
+        let mut data: Vec<Row> = Vec::new();
+        let mut index = HashMap::new();
+        for row in rows {
+            let key = row_key(def, &row);
+            match index.get(&key) {
+                Some(i) => data[*i] = row,
+                None => {
+                    index.insert(key, data.len());
+                    data.push(row);
+                }
+            }
+        }
+        let keys: HashSet<_> = index.into_keys().collect();
+
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| io_error(dir, e))?;
+        let segments = self.segments(dir).await?;
+        let seq = segments.last().map(|(seq, _)| seq + 1).unwrap_or(1);
+        self.write(&self.segment_path(dir, seq), &data).await?;
+
+        for (_, path) in segments.iter() {
+            let old = self.read(path, Some(def.keys)).await?;
+            if !old.iter().any(|r| keys.contains(&row_key(def, r))) {
+                continue;
+            }
+            let mut old = self.read(path, None).await?;
+            old.retain(|r| !keys.contains(&row_key(def, r)));
+            self.rewrite(path, &old).await?;
+        }
+
+        if segments.len() >= MAX_SEGMENTS {
+            self.compact(def, dir).await?;
+        }
+        Ok(())
+    }
+
+    /// 合并目录下的全部数据段
+    async fn compact(&self, def: &TableDef, dir: &Path) -> Result<()> {
+        let segments = self.segments(dir).await?;
+        let seq = match segments.last() {
+            Some((seq, _)) => seq + 1,
+            None => return Ok(()),
+        };
+        let rows = self.load(def, dir).await?;
+        self.write(&self.segment_path(dir, seq), &rows).await?;
+        for (_, path) in segments.iter() {
+            tokio::fs::remove_file(path)
+                .await
+                .map_err(|e| io_error(path, e))?;
+        }
+        Ok(())
+    }
+
+    /// 删除一个目录中满足条件的数据，只改写包含这些数据的数据段
+    async fn remove(&self, dir: &Path, conds: &[Cond]) -> Result<u64> {
+        let lock = self.lock(dir);
+        let _guard = lock.lock().await;
+
+        let mut count = 0;
+        for (_, path) in self.segments(dir).await? {
+            let rows = self.read(&path, None).await?;
+            let len = rows.len();
+            let mut kept = Vec::new();
+            for row in rows {
+                if !matches(&row, conds)? {
+                    kept.push(row);
+                }
+            }
+            if kept.len() < len {
+                count += (len - kept.len()) as u64;
+                self.rewrite(&path, &kept).await?;
+            }
+        }
+        if count > 0 && self.segments(dir).await?.is_empty() {
+            let _ = tokio::fs::remove_dir(dir).await;
+        }
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl TableDb for FileDb {
+    async fn create_schema(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root)
+            .await
+            .map_err(|e| io_error(&self.root, e))
+    }
+
+    async fn find(&self, def: &'static TableDef, query: &Query) -> Result<Vec<String>> {
+        let conds = query.conds()?;
+        let mut data = Vec::new();
+        for dir in self.dirs(def, &conds).await? {
+            for row in self.load(def, &dir).await? {
+                if matches(&row, &conds)? {
+                    data.push(row);
+                }
+            }
+        }
+        sort(&mut data, &query.orders()?);
+        if let Some(limit) = query.get_limit() {
+            data.truncate(limit as usize);
+        }
+        data.iter()
+            .map(|r| {
+                serde_json::to_string(r)
+                    .map_err(|e| Error::Custom(format!("serialize {} error: {}", def.name, e)))
+            })
+            .collect()
+    }
+
+    async fn upsert(&self, def: &'static TableDef, rows: Vec<String>, del_old: bool) -> Result<()> {
+        if del_old {
+            let path = self.table_path(def);
+            match tokio::fs::remove_dir_all(&path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(io_error(&path, e))
+                }
+                _ => {}
+            }
+        }
+        let mut dirs: BTreeMap<PathBuf, Vec<Row>> = BTreeMap::new();
+        for row in rows {
+            let row: Row = serde_json::from_str(&row)
+                .map_err(|e| Error::Custom(format!("parse {} data error: {}", def.name, e)))?;
+            dirs.entry(self.row_dir(def, &row)).or_default().push(row);
+        }
+        for (dir, rows) in dirs {
+            self.append(def, &dir, rows).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, def: &'static TableDef, query: &Query) -> Result<u64> {
+        let conds = query.conds()?;
+        let mut count = 0;
+        for dir in self.dirs(def, &conds).await? {
+            count += self.remove(&dir, &conds).await?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use chrono::NaiveDate;
+
+    use crate::{
+        store::{
+            table::{delete_many, insert_many, query, query_one, TableDb, TableLoader},
+            Cond, Loader, Order, Query, TAB_STOCK_DAILY, TAB_STOCK_INFO,
+        },
+        types::FileFormat,
+    };
+
+    use super::{FileDb, MAX_SEGMENTS};
+
+    fn bar(code: &str, day: u32, close: f32) -> rwqfetch::Bar {
+        rwqfetch::Bar {
+            code: code.to_owned(),
+            name: code.to_owned(),
+            trade_date: NaiveDate::from_ymd_opt(2023, 3, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            close,
+            ..Default::default()
+        }
+    }
+
+    /// 目录下的数据段文件名
+    fn segments(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_file_round_trip() {
+        round_trip(FileFormat::Parquet).await;
+        round_trip(FileFormat::Csv).await;
+    }
+
+    async fn round_trip(format: FileFormat) {
+        let root =
+            std::env::temp_dir().join(format!("rwqdata-file-{:?}-{}", format, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let db = Arc::new(FileDb::new(&root, format));
+        db.create_schema().await.unwrap();
+
+        let bars: Vec<_> = (1..=3)
+            .map(|d| bar("sh600000", d, d as f32))
+            .chain([bar("sz000001", 1, 10.0)])
+            .collect();
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars, false)
+            .await
+            .unwrap();
+        // 增量追加及重复数据覆盖
+        let bars: Vec<_> = (3..=5).map(|d| bar("sh600000", d, d as f32)).collect();
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars, false)
+            .await
+            .unwrap();
+        let ext = format.ext();
+        assert_eq!(
+            segments(&root.join("stock_daily").join("sz000001")),
+            vec![format!("00000001.{}", ext)]
+        );
+
+        let latest: Option<rwqfetch::Bar> = query_one(
+            db.as_ref(),
+            TAB_STOCK_DAILY,
+            Query::new()
+                .code("sh600000")
+                .sort("trade_date", Order::Desc),
+        )
+        .await
+        .unwrap();
+        assert_eq!(latest.unwrap().close, 5.0);
+
+        let loader = TableLoader::new(db.clone());
+        let data = loader
+            .load_stock_daily(
+                Query::new()
+                    .filter(Cond::gte("close", 2.0))
+                    .filter(Cond::lt("close", 10.0))
+                    .sort("trade_date", Order::Asc)
+                    .limit(2),
+            )
+            .await
+            .unwrap();
+        let close: Vec<_> = data.iter().map(|e| e.close).collect();
+        assert_eq!(close, vec![2.0, 3.0]);
+        let data = loader.load_stock_daily(Query::new()).await.unwrap();
+        assert_eq!(data.len(), 6);
+
+        let count = delete_many(
+            db.as_ref(),
+            TAB_STOCK_DAILY,
+            &Query::new()
+                .code("sh600000")
+                .filter(Cond::gte("close", 4.0)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+        let count = delete_many(db.as_ref(), TAB_STOCK_DAILY, &Query::new().code("sz000001"))
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!(!root.join("stock_daily").join("sz000001").exists());
+        let data = loader.load_stock_daily(Query::new()).await.unwrap();
+        assert_eq!(data.len(), 3);
+
+        let info = vec![rwqfetch::StockInfo {
+            code: "000001".to_owned(),
+            name: "平安银行".to_owned(),
+            block: "主板".to_owned(),
+            is_margin: true,
+            listing_date: bar("", 1, 0.0).trade_date,
+        }];
+        insert_many(db.as_ref(), TAB_STOCK_INFO, &info, true)
+            .await
+            .unwrap();
+        insert_many(db.as_ref(), TAB_STOCK_INFO, &info, true)
+            .await
+            .unwrap();
+        let data = loader
+            .load_stock_info(Query::new().code("000001"))
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].name, "平安银行");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_file_append() {
+        let root = std::env::temp_dir().join(format!("rwqdata-append-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let db = Arc::new(FileDb::new(&root, FileFormat::Parquet));
+        db.create_schema().await.unwrap();
+        let dir = root.join("stock_daily").join("sh600000");
+        let bars = |days: std::ops::RangeInclusive<i64>, close: f32| {
+            let start = bar("sh600000", 1, 0.0).trade_date;
+            days.map(|d| rwqfetch::Bar {
+                trade_date: start + chrono::Duration::days(d),
+                close: d as f32 + close,
+                ..bar("sh600000", 1, 0.0)
+            })
+            .collect::<Vec<_>>()
+        };
+        let closes = || async {
+            let data: Vec<rwqfetch::Bar> = query(
+                db.as_ref(),
+                TAB_STOCK_DAILY,
+                &Query::new().sort("trade_date", Order::Asc),
+            )
+            .await
+            .unwrap();
+            data.iter().map(|b| b.close).collect::<Vec<_>>()
+        };
+
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars(0..=2, 0.0), false)
+            .await
+            .unwrap();
+        let first = std::fs::read(dir.join("00000001.parquet")).unwrap();
+        // 新数据追加为新的数据段，不改写旧数据段
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars(3..=4, 0.0), false)
+            .await
+            .unwrap();
+        assert_eq!(segments(&dir), vec!["00000001.parquet", "00000002.parquet"]);
+        assert_eq!(std::fs::read(dir.join("00000001.parquet")).unwrap(), first);
+
+        // 重复的数据只改写所在的数据段
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars(4..=5, 0.5), false)
+            .await
+            .unwrap();
+        assert_eq!(segments(&dir).len(), 3);
+        assert_eq!(std::fs::read(dir.join("00000001.parquet")).unwrap(), first);
+        assert_eq!(closes().await, vec![0.0, 1.0, 2.0, 3.0, 4.5, 5.5]);
+        let count = delete_many(
+            db.as_ref(),
+            TAB_STOCK_DAILY,
+            &Query::new().filter(Cond::eq("close", 3.0)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(segments(&dir), vec!["00000001.parquet", "00000003.parquet"]);
+
+        // 数据段过多时合并
+        for d in 6..(6 + MAX_SEGMENTS as i64) {
+            insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars(d..=d, 0.0), false)
+                .await
+                .unwrap();
+        }
+        let names = segments(&dir);
+        assert!(names.len() < MAX_SEGMENTS, "{:?}", names);
+        let data = closes().await;
+        assert_eq!(data.len(), 5 + MAX_SEGMENTS);
+        assert_eq!(data[..5], [0.0, 1.0, 2.0, 4.5, 5.5]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,195 @@
+//! 查询条件在内存中求值，空值的处理与SQL存储一致
+
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    store::{
+        query::{CmpOp, Cond, Order, QueryValue},
+        sql::SqlValue,
+    },
+    Error, Result,
+};
+
+fn to_value(value: Option<&Value>) -> SqlValue {
+    match value {
+        Some(Value::Bool(v)) => SqlValue::Int(*v as i64),
+        Some(Value::Number(v)) => v
+            .as_i64()
+            .map(SqlValue::Int)
+            .unwrap_or_else(|| SqlValue::Float(v.as_f64().unwrap_or_default())),
+        Some(Value::String(v)) => SqlValue::Text(v.clone()),
+        _ => SqlValue::Null,
+    }
+}
+
+/// 比较，空值或类型不同时不可比较
+fn compare(a: &SqlValue, b: &SqlValue) -> Option<Ordering> {
+    match (a, b) {
+        (SqlValue::Int(a), SqlValue::Int(b)) => Some(a.cmp(b)),
+        (SqlValue::Int(a), SqlValue::Float(b)) => (*a as f64).partial_cmp(b),
+        (SqlValue::Float(a), SqlValue::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (SqlValue::Float(a), SqlValue::Float(b)) => a.partial_cmp(b),
+        (SqlValue::Text(a), SqlValue::Text(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// 数据是否满足全部条件
+pub(crate) fn matches(row: &Map<String, Value>, conds: &[Cond]) -> Result<bool> {
+    for cond in conds {
+        if !cond_matches(row, cond)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn cond_matches(row: &Map<String, Value>, cond: &Cond) -> Result<bool> {
+    match cond {
+        Cond::And(conds) => matches(row, conds),
+        Cond::Or(conds) => {
+            for cond in conds {
+                if cond_matches(row, cond)? {
+                    return Ok(true);
+                }
+            }
+            Ok(conds.is_empty())
+        }
+        Cond::In(field, values) | Cond::NotIn(field, values) => {
+            let field = to_value(row.get(field));
+            let found = values
+                .iter()
+                .any(|v| compare(&field, &SqlValue::from(v)) == Some(Ordering::Equal));
+            // 与SQL一致，空值不满足 NOT IN
+            Ok(if let Cond::In(..) = cond {
+                found
+            } else {
+                !found && (values.is_empty() || field != SqlValue::Null)
+            })
+        }
+        Cond::Cmp(field, op, value) => {
+            let field = to_value(row.get(field));
+            if let QueryValue::Null = value {
+                return match op {
+                    CmpOp::Eq => Ok(field == SqlValue::Null),
+                    CmpOp::Ne => Ok(field != SqlValue::Null),
+                    _ => Err(Error::Custom(format!(
+                        "{} null is not supported",
+                        op.operator()
+                    ))),
+                };
+            }
+            let ord = compare(&field, &SqlValue::from(value));
+            Ok(match op {
+                CmpOp::Eq => ord == Some(Ordering::Equal),
+                CmpOp::Ne => ord.is_some_and(|o| o != Ordering::Equal),
+                CmpOp::Gt => ord == Some(Ordering::Greater),
+                CmpOp::Gte => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+                CmpOp::Lt => ord == Some(Ordering::Less),
+                CmpOp::Lte => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+            })
+        }
+    }
+}
+
+/// 排序，空值排在最前
+pub(crate) fn sort(rows: &mut [Map<String, Value>], orders: &[(String, Order)]) {
+    if orders.is_empty() {
+        return;
+    }
+    rows.sort_by(|a, b| {
+        for (key, order) in orders.iter() {
+            let (a, b) = (to_value(a.get(key)), to_value(b.get(key)));
+            let ord = match (&a, &b) {
+                (SqlValue::Null, SqlValue::Null) => Ordering::Equal,
+                (SqlValue::Null, _) => Ordering::Less,
+                (_, SqlValue::Null) => Ordering::Greater,
+                _ => compare(&a, &b).unwrap_or(Ordering::Equal),
+            };
+            let ord = if *order == Order::Desc {
+                ord.reverse()
+            } else {
+                ord
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+/// 分区字段的取值，条件中分区字段为等于或`IN`时只需读取对应分区
+pub(crate) fn partition_values(conds: &[Cond], partition: &str) -> Option<Vec<String>> {
+    let to_string = |v: &QueryValue| match v {
+        QueryValue::Text(s) => Some(s.clone()),
+        QueryValue::Int(v) => Some(v.to_string()),
+        _ => None,
+    };
+    conds.iter().find_map(|cond| match cond {
+        Cond::Cmp(field, CmpOp::Eq, v) if field == partition => to_string(v).map(|v| vec![v]),
+        Cond::In(field, values) if field == partition => {
+            values.iter().map(to_string).collect::<Option<Vec<_>>>()
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+    use serde_json::{json, Map, Value};
+
+    use crate::store::query::{Cond, Order, Query};
+
+    use super::{matches, partition_values, sort};
+
+    fn row(v: Value) -> Map<String, Value> {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let r = row(json!({"code": "000001", "trade_date": 150, "close": 1.5, "name": null}));
+        let hit = |f| matches(&r, &Query::new().mongo_filter(f).conds().unwrap()).unwrap();
+        assert!(hit(
+            doc! {"code": "000001", "trade_date": {"$gte": 100, "$lt": 200}}
+        ));
+        assert!(!hit(doc! {"code": "000002"}));
+        assert!(hit(doc! {"close": {"$in": [1, 1.5]}, "name": null}));
+        assert!(!hit(doc! {"name": {"$nin": ["a"]}}));
+        assert!(hit(doc! {"name": {"$nin": []}}));
+        assert!(hit(doc! {"$or": [{"code": "x"}, {"close": {"$gt": 1}}]}));
+        assert!(!hit(
+            doc! {"$and": [{"code": "000001"}, {"close": {"$lte": 1}}]}
+        ));
+        assert!(matches(&r, &[Cond::gt("name", None::<i64>)]).is_err());
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut rows = vec![
+            row(json!({"code": "b", "v": 1})),
+            row(json!({"code": "a", "v": 2})),
+            row(json!({"code": "a", "v": 1})),
+        ];
+        sort(
+            &mut rows,
+            &[("code".into(), Order::Asc), ("v".into(), Order::Desc)],
+        );
+        let v: Vec<_> = rows.iter().map(|r| r["v"].as_i64().unwrap()).collect();
+        assert_eq!(v, vec![2, 1, 1]);
+
+        assert_eq!(
+            partition_values(&[Cond::is_in("code", ["a", "b"])], "code"),
+            Some(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(
+            partition_values(&[Cond::eq("code", "a"), Cond::eq("year", 2023)], "year"),
+            Some(vec!["2023".to_owned()])
+        );
+        assert_eq!(partition_values(&[Cond::ne("code", "a")], "code"), None);
+    }
+}
@@ -0,0 +1,6 @@
+mod csv;
+mod file;
+mod filter;
+mod parquet;
+
+pub(crate) use file::FileDb;
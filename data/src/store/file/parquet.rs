@@ -0,0 +1,230 @@
+//! Parquet编解码
+//!
+//! 列类型由数据推断：整数为`Int64`，含小数的为`Float64`，布尔为`Boolean`，文本为`Utf8`，
+//! 全部为空值的为`Null`，嵌套的数组或对象以json文本保存并在列的元数据中标记，读取时还原。
+
+use std::{collections::HashMap, sync::Arc};
+
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, Int64Array, NullArray, RecordBatch,
+    RecordBatchReader, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use bytes::Bytes;
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter, ProjectionMask},
+    basic::Compression,
+    file::properties::WriterProperties,
+};
+use serde_json::{Number, Value};
+
+use super::csv::Row;
+use crate::{Error, Result};
+
+/// 以json文本保存的列的元数据
+const JSON_META: &str = "rwq:json";
+
+fn parquet_error(e: impl std::fmt::Display) -> Error {
+    log::error!("parquet error: {}", e);
+    Error::Custom(format!("parquet error: {}", e))
+}
+
+/// 推断列类型，类型不一致的数值为`Float64`，其他不一致的以json文本保存
+fn column_type(rows: &[Row], name: &str) -> (DataType, bool) {
+    let mut typ = DataType::Null;
+    for value in rows.iter().filter_map(|r| r.get(name)) {
+        let t = match value {
+            Value::Null => continue,
+            Value::Bool(_) => DataType::Boolean,
+            Value::Number(v) if v.is_i64() => DataType::Int64,
+            Value::Number(_) => DataType::Float64,
+            Value::String(_) => DataType::Utf8,
+            _ => return (DataType::Utf8, true),
+        };
+        typ = match (&typ, t) {
+            (DataType::Null, t) => t,
+            (a, b) if *a == b => b,
+            (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => {
+                DataType::Float64
+            }
+            _ => return (DataType::Utf8, true),
+        };
+    }
+    (typ, false)
+}
+
+fn column(rows: &[Row], name: &str, typ: &DataType, json: bool) -> ArrayRef {
+    let values = rows.iter().map(|r| r.get(name).filter(|v| !v.is_null()));
+    if json {
+        let data: Vec<_> = values.map(|v| v.map(Value::to_string)).collect();
+        return Arc::new(StringArray::from(data));
+    }
+    match typ {
+        DataType::Boolean => Arc::new(BooleanArray::from(
+            values
+                .map(|v| v.and_then(Value::as_bool))
+                .collect::<Vec<_>>(),
+        )),
+        DataType::Int64 => Arc::new(Int64Array::from(
+            values
+                .map(|v| v.and_then(Value::as_i64))
+                .collect::<Vec<_>>(),
+        )),
+        DataType::Float64 => Arc::new(Float64Array::from(
+            values
+                .map(|v| v.and_then(Value::as_f64))
+                .collect::<Vec<_>>(),
+        )),
+        DataType::Utf8 => Arc::new(StringArray::from(
+            values
+                .map(|v| v.and_then(Value::as_str))
+                .collect::<Vec<_>>(),
+        )),
+        _ => Arc::new(NullArray::new(rows.len())),
+    }
+}
+
+/// 按表头顺序编码为一个Parquet文件，表头中没有的字段忽略
+pub(crate) fn encode(header: &[String], rows: &[Row]) -> Result<Vec<u8>> {
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+    for name in header.iter() {
+        let (typ, json) = column_type(rows, name);
+        let mut field = Field::new(name, typ.clone(), true);
+        if json {
+            field = field.with_metadata(HashMap::from([(JSON_META.to_owned(), "1".to_owned())]));
+        }
+        columns.push(column(rows, name, &typ, json));
+        fields.push(field);
+    }
+    let schema = Arc::new(Schema::new(fields));
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(parquet_error)?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut data = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut data, schema, Some(props)).map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+    Ok(data)
+}
+
+fn to_value(array: &dyn Array, i: usize, json: bool) -> Result<Value> {
+    if array.is_null(i) {
+        return Ok(Value::Null);
+    }
+    let any = array.as_any();
+    let value = match array.data_type() {
+        DataType::Boolean => any
+            .downcast_ref::<BooleanArray>()
+            .map(|a| Value::Bool(a.value(i))),
+        DataType::Int64 => any
+            .downcast_ref::<Int64Array>()
+            .map(|a| Value::Number(a.value(i).into())),
+        DataType::Float64 => any.downcast_ref::<Float64Array>().map(|a| {
+            Number::from_f64(a.value(i))
+                .map(Value::Number)
+                .unwrap_or(Value::Null)
+        }),
+        DataType::Utf8 => match any.downcast_ref::<StringArray>() {
+            Some(a) if json => Some(serde_json::from_str(a.value(i)).map_err(parquet_error)?),
+            Some(a) => Some(Value::String(a.value(i).to_owned())),
+            None => None,
+        },
+        DataType::Null => Some(Value::Null),
+        _ => None,
+    };
+    value.ok_or_else(|| parquet_error(format!("unsupported column type {}", array.data_type())))
+}
+
+/// 解码一个Parquet文件，返回表头及数据，`columns`不为空时只读取其中的列
+pub(crate) fn decode(data: Vec<u8>, columns: Option<&[&str]>) -> Result<(Vec<String>, Vec<Row>)> {
+    let mut builder =
+        ParquetRecordBatchReaderBuilder::try_new(Bytes::from(data)).map_err(parquet_error)?;
+    if let Some(columns) = columns {
+        let indices: Vec<_> = builder
+            .schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| columns.contains(&f.name().as_str()))
+            .map(|(i, _)| i)
+            .collect();
+        let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+        builder = builder.with_projection(mask);
+    }
+    let reader = builder.build().map_err(parquet_error)?;
+    let header: Vec<_> = reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect();
+    let mut rows = Vec::new();
+    for batch in reader {
+        let batch = batch.map_err(parquet_error)?;
+        let columns: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(f, c)| (f.name().clone(), f.metadata().contains_key(JSON_META), c))
+            .collect();
+        for i in 0..batch.num_rows() {
+            let mut row = Row::new();
+            for (name, json, array) in columns.iter() {
+                row.insert(name.clone(), to_value(array.as_ref(), i, *json)?);
+            }
+            rows.push(row);
+        }
+    }
+    Ok((header, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map, Value};
+
+    use super::{decode, encode};
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let header: Vec<String> = ["code", "close", "volume", "is_margin", "note", "tags"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let rows: Vec<Map<String, Value>> = vec![
+            serde_json::from_value(json!({
+                "code": "000001",
+                "close": 1.23,
+                "volume": 100,
+                "is_margin": true,
+                "note": null,
+                "tags": ["a", "b"],
+            }))
+            .unwrap(),
+            serde_json::from_value(json!({
+                "code": "000002",
+                "close": 2,
+                "volume": null,
+                "is_margin": false,
+                "note": null,
+                "tags": {"k": 1},
+            }))
+            .unwrap(),
+        ];
+        let data = encode(&header, &rows).unwrap();
+        let (h, decoded) = decode(data.clone(), None).unwrap();
+        assert_eq!(h, header);
+        assert_eq!(decoded[0], rows[0]);
+        assert_eq!(decoded[1]["close"], json!(2.0));
+        assert_eq!(decoded[1]["tags"], json!({"k": 1}));
+
+        let (h, decoded) = decode(data, Some(&["code", "volume"])).unwrap();
+        assert_eq!(h, vec!["code".to_owned(), "volume".to_owned()]);
+        assert_eq!(
+            Value::Object(decoded[1].clone()),
+            json!({"code": "000002", "volume": null})
+        );
+    }
+}
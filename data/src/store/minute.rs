@@ -0,0 +1,210 @@
+//! 分钟线同步的公共部分，MongoDB及按表存储共用
+//!
+//! 分钟线按品种和频率保存到不同的表，与日线一样按(code, trade_date)覆盖写入。
+
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use rwqfetch::{BarFreq, BondInfo, FundInfo, StockInfo};
+
+use crate::{
+    store::{Cache, DataType},
+    syncer::AsyncFunc,
+    types::{MinuteSync, Resync, SyncData},
+    Result,
+};
+
+/// 同步的代码，可转债需要正股信息
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MinuteCode {
+    pub code: String,
+    pub name: String,
+    pub stock_code: String,
+    pub stock_name: String,
+}
+
+impl From<&StockInfo> for MinuteCode {
+    fn from(info: &StockInfo) -> Self {
+        Self {
+            code: info.code.clone(),
+            name: info.name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&FundInfo> for MinuteCode {
+    fn from(info: &FundInfo) -> Self {
+        Self {
+            code: info.code.clone(),
+            name: info.name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&BondInfo> for MinuteCode {
+    fn from(info: &BondInfo) -> Self {
+        Self {
+            code: info.code.clone(),
+            name: info.name.clone(),
+            stock_code: info.stock_code.clone(),
+            stock_name: info.stock_name.clone(),
+        }
+    }
+}
+
+/// 品种对应的全部代码，按代码排序
+pub(crate) fn minute_codes(cache: &Cache, typ: DataType) -> Vec<MinuteCode> {
+    let mut codes: Vec<MinuteCode> = match typ {
+        DataType::Stock => cache
+            .stock_info()
+            .iter()
+            .flatten()
+            .map(|(_, v)| v.into())
+            .collect(),
+        DataType::Index => cache
+            .index_info()
+            .iter()
+            .flatten()
+            .map(|(_, v)| v.into())
+            .collect(),
+        DataType::Fund => cache
+            .fund_info()
+            .iter()
+            .flatten()
+            .map(|(_, v)| v.into())
+            .collect(),
+        DataType::Bond => cache
+            .bond_info()
+            .iter()
+            .flatten()
+            .map(|(_, v)| v.into())
+            .collect(),
+        _ => vec![],
+    };
+    codes.sort_by(|a, b| a.code.cmp(&b.code));
+    codes
+}
+
+/// 保留数据的第一个交易日，更早的数据同步时删除，全部保留时为空
+pub(crate) fn retain_start(cache: &Cache, minute: &MinuteSync) -> Option<NaiveDate> {
+    if minute.retain_days == 0 {
+        return None;
+    }
+    cache.prev_trade_date(&Local::now().date_naive(), minute.retain_days)
+}
+
+/// 同步开始日期，`latest`为已保存最新一条数据的时间，
+/// 最新数据不到收盘时间时当日数据不完整，从当日开始重新获取，且不早于保留的第一个交易日
+pub(crate) fn minute_start(
+    cache: &Cache,
+    code: &str,
+    latest: Option<NaiveDateTime>,
+    minute: &MinuteSync,
+    resync: &Resync,
+) -> NaiveDate {
+    let start = cache.sync_start(code, latest.map(|dt| dt.date()), resync);
+    let close = NaiveTime::from_hms_opt(15, 0, 0).unwrap();
+    let start = match latest {
+        Some(latest) if latest.time() < close && latest.date() < start => latest.date(),
+        _ => start,
+    };
+    match retain_start(cache, minute) {
+        Some(retain) if retain > start => retain,
+        _ => start,
+    }
+}
+
+pub(crate) struct MinuteAsyncFunc<'a> {
+    pub typ: DataType,
+    pub freq: BarFreq,
+    pub code: &'a MinuteCode,
+    pub start: Option<NaiveDate>,
+}
+
+#[async_trait]
+impl<'a> AsyncFunc for MinuteAsyncFunc<'a> {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let (code, name, start) = (self.code.code.as_str(), self.code.name.as_str(), self.start);
+        let freq = Some(self.freq);
+        let bars = match self.typ {
+            DataType::Stock => {
+                rwqfetch::fetch_stock_bar(code, Some(name), freq, start, None, true)
+                    .await?
+                    .bars
+            }
+            DataType::Index => {
+                rwqfetch::fetch_index_bar(code, Some(name), freq, start, None, true)
+                    .await?
+                    .bars
+            }
+            DataType::Fund => {
+                rwqfetch::fetch_fund_bar(code, Some(name), freq, start, None, true)
+                    .await?
+                    .bars
+            }
+            DataType::Bond => {
+                rwqfetch::fetch_bond_bar(
+                    code,
+                    name,
+                    self.code.stock_code.as_str(),
+                    self.code.stock_name.as_str(),
+                    freq,
+                    start,
+                    None,
+                    true,
+                )
+                .await?
+                .bars
+            }
+            _ => None,
+        };
+        Ok(bars.filter(|b| !b.is_empty()).map(SyncData::MinuteBar))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::NaiveDate;
+    use rwqfetch::BarFreq;
+
+    use crate::{
+        store::{Cache, DataType},
+        types::{MinuteSync, Resync},
+    };
+
+    use super::minute_start;
+
+    #[test]
+    fn test_minute_start() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y%m%d").unwrap();
+        let mut cache = Cache::new();
+        let trade_date: BTreeSet<_> = [20230301, 20230302, 20230303, 20230306].into();
+        cache.cache_trade_date(&trade_date);
+
+        let minute = MinuteSync::try_from("stock:5").unwrap();
+        assert_eq!(minute.typ, DataType::Stock);
+        assert_eq!(minute.freq, BarFreq::Min5);
+        assert_eq!(minute.tab().unwrap(), "stock_min5");
+        assert_eq!(MinuteSync::try_from("index:1:20").unwrap().retain_days, 20);
+        assert!(MinuteSync::try_from("stock:101").is_err());
+        assert!(MinuteSync::try_from("concept:5").is_err());
+
+        let resync = Resync::default();
+        let at = |d: &str, t: &str| {
+            Some(date(d).and_time(chrono::NaiveTime::parse_from_str(t, "%H:%M").unwrap()))
+        };
+        let start = |latest| minute_start(&cache, "a", latest, &minute, &resync);
+        assert_eq!(start(None), date("20100101"));
+        // 当日数据完整，从下一个交易日开始
+        assert_eq!(start(at("20230303", "15:00")), date("20230306"));
+        // 当日数据不完整，从当日开始
+        assert_eq!(start(at("20230303", "10:30")), date("20230303"));
+
+        let minute = MinuteSync::try_from("stock:5:2").unwrap();
+        let start = minute_start(&cache, "a", None, &minute, &resync);
+        assert_eq!(start, date("20230303"));
+    }
+}
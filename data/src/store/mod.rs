@@ -1,61 +1,119 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     ops::Add,
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
 use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
-use mongodb::bson::Document;
-use rwqfetch::{BondInfo, FundInfo, StockInfo};
+use rwqfetch::{AdjustFactor, BarFreq, BondInfo, FundInfo, StockInfo};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    syncer::Syncer,
+    syncer::TypedSyncer,
     types::SyncDest,
-    types::{SyncDataType, SyncDestType},
+    types::{Checkpoint, MinuteSync, Resync, SyncDataType, SyncDestType},
     Error, Result,
 };
 
 use async_trait::async_trait;
 
+mod adjust;
+mod file;
+mod minute;
 pub mod mongo;
 mod mysql;
+mod query;
+mod sql;
 mod sqlite;
+mod table;
+
+pub use query::{CmpOp, Cond, Order, Query, QueryValue};
 
 pub(crate) use mongo::MongoStore;
 
+use self::file::FileDb;
 use self::mongo::MongoLoader;
-// pub(crate) use sqlite::SqliteStore;
-// pub(crate) use mysql::MysqlStore;
+use self::mysql::MySqlDb;
+use self::sqlite::SqliteDb;
+use self::table::{TableLoader, TableStore};
 
 /// 获取同步数据store  
 /// `dest`: 目标数据源  
 /// `skip_basic` 初始化数据是否从远程获取，true在从数据库获取, false则从远程获取    
 /// `split_count` 代码切分份数，同一份数据在同一个task里处理  
 /// `funcs` 过滤的同步类型，None则全部同步
+/// `resync` 重新同步选项
+/// `minute` 同步的分钟线，为空则不同步分钟线
 /// `try_init` 是否初始化
 pub async fn get_store(
     dest: &SyncDest,
     skip_basic: bool,
     split_count: usize,
     funcs: &Option<Vec<SyncDataType>>,
+    resync: &Resync,
+    minute: &[MinuteSync],
     try_init: bool,
 ) -> Result<(SyncDestType, Box<dyn Store>)> {
     match dest {
-        SyncDest::File(_) => todo!(),
+        SyncDest::File(path, format) => {
+            let db = Arc::new(FileDb::new(path, *format));
+            let mut store: Box<dyn Store> = Box::new(TableStore::new(
+                db,
+                skip_basic,
+                split_count,
+                funcs,
+                resync,
+                minute,
+            ));
+            if try_init {
+                store.init().await?;
+            }
+            Ok((SyncDestType::File, store))
+        }
         SyncDest::MongoDB(url) => {
             let mut store: Box<dyn Store> = Box::new(MongoStore::new(
                 (*url).clone(),
                 skip_basic,
                 split_count,
                 funcs,
+                resync,
+                minute,
             ));
             if try_init {
                 store.init().await?;
             }
             Ok((SyncDestType::MongoDB, store))
         }
-        SyncDest::MySQL(_) => todo!(),
+        SyncDest::MySQL(url) => {
+            let db = Arc::new(MySqlDb::new(url)?);
+            let mut store: Box<dyn Store> = Box::new(TableStore::new(
+                db,
+                skip_basic,
+                split_count,
+                funcs,
+                resync,
+                minute,
+            ));
+            if try_init {
+                store.init().await?;
+            }
+            Ok((SyncDestType::MySQL, store))
+        }
+        SyncDest::SQLite(path) => {
+            let db = Arc::new(SqliteDb::new(path)?);
+            let mut store: Box<dyn Store> = Box::new(TableStore::new(
+                db,
+                skip_basic,
+                split_count,
+                funcs,
+                resync,
+                minute,
+            ));
+            if try_init {
+                store.init().await?;
+            }
+            Ok((SyncDestType::SQLite, store))
+        }
     }
 }
 
@@ -67,7 +125,13 @@ pub async fn get_loader(
     try_init: bool,
 ) -> Result<(SyncDestType, Box<dyn Loader>)> {
     match dest {
-        SyncDest::File(_) => todo!(),
+        SyncDest::File(path, format) => {
+            let mut loader = TableLoader::new(Arc::new(FileDb::new(path, *format)));
+            if try_init {
+                loader.init().await?;
+            }
+            Ok((SyncDestType::File, Box::new(loader)))
+        }
         SyncDest::MongoDB(url) => {
             let mut loader = MongoLoader::new((*url).clone());
             if try_init {
@@ -75,7 +139,20 @@ pub async fn get_loader(
             }
             Ok((SyncDestType::MongoDB, Box::new(loader)))
         }
-        SyncDest::MySQL(_) => todo!(),
+        SyncDest::MySQL(url) => {
+            let mut loader = TableLoader::new(Arc::new(MySqlDb::new(url)?));
+            if try_init {
+                loader.init().await?;
+            }
+            Ok((SyncDestType::MySQL, Box::new(loader)))
+        }
+        SyncDest::SQLite(path) => {
+            let mut loader = TableLoader::new(Arc::new(SqliteDb::new(path)?));
+            if try_init {
+                loader.init().await?;
+            }
+            Ok((SyncDestType::SQLite, Box::new(loader)))
+        }
     }
 }
 
@@ -90,8 +167,29 @@ pub trait Store: Sync + Send {
     async fn build_index(&self) -> Result<()> {
         Ok(())
     }
-    /// 同步的syncer
-    fn syncer(&self) -> Result<Vec<Arc<Box<dyn Syncer>>>>;
+    /// 同步的syncer及其同步类型
+    fn syncer(&self) -> Result<Vec<TypedSyncer>>;
+    /// 读取同步标识为`run`的同步进度
+    async fn load_checkpoint(&self, _run: &str) -> Result<Vec<Checkpoint>> {
+        Ok(vec![])
+    }
+    /// 保存同步进度，相同(run, name, code)的覆盖
+    async fn save_checkpoint(&self, _data: &[Checkpoint]) -> Result<()> {
+        Ok(())
+    }
+    /// 续传，syncer跳过已完成的代码，需在`init`后调用
+    fn resume(&self, _data: &[Checkpoint]) {}
+    /// 表`tab`中代码`code`最新一条数据的时间，没有数据时为空
+    async fn latest(&self, _tab: &str, _code: &str) -> Result<Option<NaiveDateTime>> {
+        Ok(None)
+    }
+    /// 删除表`tab`中代码`code`的数据，`before`不为空时只删除该日期之前的数据
+    async fn delete(&self, _tab: &str, _code: &str, _before: Option<NaiveDate>) -> Result<()> {
+        Ok(())
+    }
+    /// 多数据源同步时由本数据源获取按代码增量同步的数据，并同时保存到`peers`，
+    /// 开始日期取各数据源中最早的，删除数据时同时删除`peers`中的数据，需在`init`后调用
+    fn set_peers(&self, _peers: Vec<Arc<Box<dyn Store>>>) {}
 }
 
 pub(crate) struct Cache {
@@ -100,6 +198,8 @@ pub(crate) struct Cache {
     stock_info: Option<HashMap<String, StockInfo>>,
     bond_info: Option<HashMap<String, BondInfo>>,
     fund_info: Option<HashMap<String, FundInfo>>,
+    done: HashMap<String, HashSet<String>>,
+    peers: Vec<Arc<Box<dyn Store>>>,
 }
 
 impl Cache {
@@ -110,8 +210,18 @@ impl Cache {
             stock_info: None,
             bond_info: None,
             fund_info: None,
+            done: HashMap::new(),
+            peers: Vec::new(),
         }
     }
+    /// `date`当日或之前的第`n`个交易日，交易日不足`n`个时为最早的交易日，`n`为0或没有交易日数据时为空
+    pub fn prev_trade_date(&self, date: &NaiveDate, n: usize) -> Option<NaiveDate> {
+        let d: i32 = date.format("%Y%m%d").to_string().parse().unwrap();
+        self.trade_date
+            .as_ref()
+            .and_then(|cache| cache.range(..=d).rev().take(n).last())
+            .and_then(|d| NaiveDate::parse_from_str(&d.to_string(), "%Y%m%d").ok())
+    }
     pub fn next_trade_date(&self, date: &NaiveDate) -> NaiveDate {
         let trade_date = if let Some(cache) = &self.trade_date {
             let mut next_date = date.clone();
@@ -128,6 +238,25 @@ impl Cache {
         };
         trade_date
     }
+    /// 同步开始日期，`latest`为已保存的最新数据日期，为空或全量重新同步时从`DATA_DEF_START_DATE`开始，
+    /// `overlap_days`大于0时从`latest`往前数第`overlap_days`个交易日开始，重新获取最近的数据，
+    /// `since`中指定的日期更早时从指定日期开始
+    pub fn sync_start(&self, code: &str, latest: Option<NaiveDate>, resync: &Resync) -> NaiveDate {
+        let latest = match latest {
+            Some(latest) if !resync.is_full(code) => latest,
+            _ => return NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
+        };
+        let start = if resync.overlap_days == 0 {
+            self.next_trade_date(&latest)
+        } else {
+            self.prev_trade_date(&latest, resync.overlap_days)
+                .unwrap_or(latest)
+        };
+        match resync.since.get(code) {
+            Some(since) if *since < start => *since,
+            _ => start,
+        }
+    }
     pub fn cache_trade_date(&mut self, data: &BTreeSet<i32>) {
         let mut cache = BTreeSet::new();
         cache.extend(data.iter());
@@ -168,6 +297,62 @@ impl Cache {
     pub fn fund_info(&self) -> &Option<HashMap<String, FundInfo>> {
         &self.fund_info
     }
+    /// 缓存已完成的代码，续传时跳过
+    pub fn cache_done(&mut self, data: &[Checkpoint]) {
+        for cp in data.iter().filter(|cp| cp.done && !cp.code.is_empty()) {
+            self.done
+                .entry(cp.name.clone())
+                .or_default()
+                .insert(cp.code.clone());
+        }
+    }
+    /// 表`tab`的代码`code`是否已完成
+    pub fn is_done(&self, tab: &str, code: &str) -> bool {
+        self.done.get(tab).is_some_and(|codes| codes.contains(code))
+    }
+    pub fn cache_peers(&mut self, peers: Vec<Arc<Box<dyn Store>>>) {
+        self.peers = peers;
+    }
+    /// 共享获取数据的其他数据源
+    pub fn peers(&self) -> &Vec<Arc<Box<dyn Store>>> {
+        &self.peers
+    }
+}
+
+/// 共享获取数据时各数据源中最早的最新数据时间，`latest`为本数据源的最新数据时间，
+/// 任一数据源没有数据时为空，读取失败的数据源不参与比较
+pub(crate) async fn peer_latest(
+    cache: &RwLock<Cache>,
+    tab: &str,
+    code: &str,
+    latest: Option<NaiveDateTime>,
+) -> Option<NaiveDateTime> {
+    let mut latest = latest?;
+    let peers = cache.read().unwrap().peers().clone();
+    for peer in peers.iter() {
+        match peer.latest(tab, code).await {
+            Ok(Some(dt)) => latest = latest.min(dt),
+            Ok(None) => return None,
+            Err(e) => log::error!("query {}({}) latest error: {:?}", tab, code, e),
+        }
+    }
+    Some(latest)
+}
+
+/// 删除共享获取数据的其他数据源中表`tab`代码`code`的数据，`before`不为空时只删除该日期之前的数据，
+/// 删除失败只记录日志，不影响其他数据源
+pub(crate) async fn peer_delete(
+    cache: &RwLock<Cache>,
+    tab: &str,
+    code: &str,
+    before: Option<NaiveDate>,
+) {
+    let peers = cache.read().unwrap().peers().clone();
+    for peer in peers.iter() {
+        if let Err(e) = peer.delete(tab, code, before).await {
+            log::error!("delete {}({}) error: {:?}", tab, code, e);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -180,171 +365,93 @@ pub enum DataType {
     Industry,
 }
 
+/// 读取本地数据trait接口，查询条件由各存储转换为具体的查询
 #[async_trait]
 pub trait Loader: Sync + Send {
     async fn init(&mut self) -> Result<()> {
         Ok(())
     }
-    async fn load_bond_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::BondInfo>>;
-    async fn load_bond_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
+    /// 按表名读取原始数据，可配合`Query::project`只返回部分字段
+    async fn load_raw(&self, tab: &str, query: Query) -> Result<Vec<serde_json::Value>>;
+    async fn load_trade_date(&self, query: Query) -> Result<Vec<rwqfetch::TradeDate>>;
+    async fn load_bond_info(&self, query: Query) -> Result<Vec<rwqfetch::BondInfo>>;
+    async fn load_bond_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
 
-    async fn load_fund_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::FundInfo>>;
-    async fn load_fund_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
-    async fn load_fund_net(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::FundNet>>;
+    async fn load_fund_info(&self, query: Query) -> Result<Vec<rwqfetch::FundInfo>>;
+    async fn load_fund_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
+    async fn load_fund_net(&self, query: Query) -> Result<Vec<rwqfetch::FundNet>>;
 
-    async fn load_index_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockInfo>>;
+    async fn load_index_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>>;
 
-    async fn load_index_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
+    async fn load_index_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
 
-    async fn load_stock_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockInfo>>;
+    async fn load_stock_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>>;
 
-    async fn load_stock_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
+    async fn load_stock_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
 
-    async fn load_stock_index(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockIndex>>;
-    async fn load_stock_industry(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockIndustry>>;
+    async fn load_stock_index(&self, query: Query) -> Result<Vec<rwqfetch::StockIndex>>;
+    async fn load_stock_industry(&self, query: Query) -> Result<Vec<rwqfetch::StockIndustry>>;
 
-    async fn load_stock_industry_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
+    async fn load_stock_industry_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
     async fn load_stock_industry_detail(
         &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
+        query: Query,
     ) -> Result<Vec<rwqfetch::StockIndustryDetail>>;
 
-    async fn load_stock_concept(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockConcept>>;
+    async fn load_stock_concept(&self, query: Query) -> Result<Vec<rwqfetch::StockConcept>>;
 
-    async fn load_stock_concept_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>>;
+    async fn load_stock_concept_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>>;
     async fn load_stock_concept_detail(
         &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
+        query: Query,
     ) -> Result<Vec<rwqfetch::StockConceptDetail>>;
 
-    async fn load_stock_yjbb(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockYJBB>>;
+    async fn load_stock_yjbb(&self, query: Query) -> Result<Vec<rwqfetch::StockYJBB>>;
 
-    async fn load_stock_margin(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockMargin>>;
+    async fn load_stock_margin(&self, query: Query) -> Result<Vec<rwqfetch::StockMargin>>;
 
-    async fn load_info(
+    /// 读取分钟线，`typ`为品种，`freq`为频率，对应的表见`MINUTE_TABLES`
+    async fn load_minute(
         &self,
         typ: DataType,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<(String, String)>> {
+        freq: BarFreq,
+        query: Query,
+    ) -> Result<Vec<rwqfetch::Bar>>;
+
+    async fn load_info(&self, typ: DataType, query: Query) -> Result<Vec<(String, String)>> {
         let data: Vec<_> = match typ {
             DataType::Bond => self
-                .load_bond_info(filter, sort, limit)
+                .load_bond_info(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
                 .collect(),
             DataType::Fund => self
-                .load_fund_info(filter, sort, limit)
+                .load_fund_info(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
                 .collect(),
             DataType::Stock => self
-                .load_stock_info(filter, sort, limit)
+                .load_stock_info(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
                 .collect(),
             DataType::Index => self
-                .load_index_info(filter, sort, limit)
+                .load_index_info(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
                 .collect(),
             DataType::Concept => self
-                .load_stock_concept(filter, sort, limit)
+                .load_stock_concept(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
                 .collect(),
             DataType::Industry => self
-                .load_stock_industry(filter, sort, limit)
+                .load_stock_industry(query)
                 .await?
                 .into_iter()
                 .map(|e| (e.code, e.name))
@@ -352,21 +459,54 @@ pub trait Loader: Sync + Send {
         };
         Ok(data)
     }
-    async fn load_daily(
+    async fn load_daily(&self, typ: DataType, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        let data: Vec<_> = match typ {
+            DataType::Bond => self.load_bond_daily(query).await?,
+            DataType::Fund => self.load_fund_daily(query).await?,
+            DataType::Stock => self.load_stock_daily(query).await?,
+            DataType::Index => self.load_index_daily(query).await?,
+            DataType::Concept => self.load_stock_concept_daily(query).await?,
+            DataType::Industry => self.load_stock_industry_daily(query).await?,
+        };
+        Ok(data)
+    }
+    /// 读取复权后的日线，保存的日线为不复权价格，按保存的`hfq_factor`计算：
+    /// `fq`: 复权方式，不复权/后复权/前复权
+    /// `anchor`: 前复权基准日，取当日或之前最近一条数据的复权因子，为空则以最新一条数据为基准
+    async fn load_daily_adjusted(
         &self,
         typ: DataType,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
+        query: Query,
+        fq: AdjustFactor,
+        anchor: Option<NaiveDate>,
     ) -> Result<Vec<rwqfetch::Bar>> {
-        let data: Vec<_> = match typ {
-            DataType::Bond => self.load_bond_daily(filter, sort, limit).await?,
-            DataType::Fund => self.load_fund_daily(filter, sort, limit).await?,
-            DataType::Stock => self.load_stock_daily(filter, sort, limit).await?,
-            DataType::Index => self.load_index_daily(filter, sort, limit).await?,
-            DataType::Concept => self.load_stock_concept_daily(filter, sort, limit).await?,
-            DataType::Industry => self.load_stock_industry_daily(filter, sort, limit).await?,
-        };
+        let mut data = self.load_daily(typ, query).await?;
+        let mut anchors = HashMap::new();
+        if fq == AdjustFactor::QFQ {
+            let codes: BTreeSet<_> = data.iter().map(|e| e.code.clone()).collect();
+            for code in codes {
+                let mut query = Query::new()
+                    .code(&code)
+                    .sort("trade_date", Order::Desc)
+                    .limit(1);
+                if let Some(anchor) = anchor {
+                    query = query.filter(Cond::lte("trade_date", anchor));
+                }
+                let mut base = self.load_daily(typ, query).await?;
+                if base.is_empty() {
+                    // 基准日早于最早一条数据时以最早一条数据为基准
+                    let query = Query::new()
+                        .code(&code)
+                        .sort("trade_date", Order::Asc)
+                        .limit(1);
+                    base = self.load_daily(typ, query).await?;
+                }
+                if let Some(bar) = base.first() {
+                    anchors.insert(code, adjust::factor(bar));
+                }
+            }
+        }
+        adjust::adjust_bars(&mut data, fq, &anchors);
         Ok(data)
     }
     #[inline]
@@ -393,6 +533,8 @@ pub const DATABASE: &'static str = "winq";
 
 pub const TAB_TRADE_DATE: &'static str = "trade_date";
 
+pub const TAB_SYNC_CHECKPOINT: &str = "sync_checkpoint";
+
 pub const TAB_BOND_INFO: &'static str = "bond_info";
 pub const TAB_BOND_DAILY: &'static str = "bond_daily";
 
@@ -414,3 +556,98 @@ pub const TAB_STOCK_CONCEPT_DAILY: &'static str = "stock_concept_daily";
 pub const TAB_STOCK_CONCEPT_DETAIL: &'static str = "stock_concept_detail";
 pub const TAB_STOCK_YJBB: &'static str = "stock_yjbb";
 pub const TAB_STOCK_MARGIN: &'static str = "stock_margin";
+
+pub const TAB_STOCK_MIN1: &str = "stock_min1";
+pub const TAB_STOCK_MIN5: &str = "stock_min5";
+pub const TAB_STOCK_MIN15: &str = "stock_min15";
+pub const TAB_STOCK_MIN30: &str = "stock_min30";
+pub const TAB_STOCK_MIN60: &str = "stock_min60";
+pub const TAB_INDEX_MIN1: &str = "index_min1";
+pub const TAB_INDEX_MIN5: &str = "index_min5";
+pub const TAB_INDEX_MIN15: &str = "index_min15";
+pub const TAB_INDEX_MIN30: &str = "index_min30";
+pub const TAB_INDEX_MIN60: &str = "index_min60";
+pub const TAB_FUND_MIN1: &str = "fund_min1";
+pub const TAB_FUND_MIN5: &str = "fund_min5";
+pub const TAB_FUND_MIN15: &str = "fund_min15";
+pub const TAB_FUND_MIN30: &str = "fund_min30";
+pub const TAB_FUND_MIN60: &str = "fund_min60";
+pub const TAB_BOND_MIN1: &str = "bond_min1";
+pub const TAB_BOND_MIN5: &str = "bond_min5";
+pub const TAB_BOND_MIN15: &str = "bond_min15";
+pub const TAB_BOND_MIN30: &str = "bond_min30";
+pub const TAB_BOND_MIN60: &str = "bond_min60";
+
+/// 分钟线的表，按品种和频率分表保存
+pub const MINUTE_TABLES: &[(DataType, BarFreq, &str)] = &[
+    (DataType::Stock, BarFreq::Min1, TAB_STOCK_MIN1),
+    (DataType::Stock, BarFreq::Min5, TAB_STOCK_MIN5),
+    (DataType::Stock, BarFreq::Min15, TAB_STOCK_MIN15),
+    (DataType::Stock, BarFreq::Min30, TAB_STOCK_MIN30),
+    (DataType::Stock, BarFreq::Min60, TAB_STOCK_MIN60),
+    (DataType::Index, BarFreq::Min1, TAB_INDEX_MIN1),
+    (DataType::Index, BarFreq::Min5, TAB_INDEX_MIN5),
+    (DataType::Index, BarFreq::Min15, TAB_INDEX_MIN15),
+    (DataType::Index, BarFreq::Min30, TAB_INDEX_MIN30),
+    (DataType::Index, BarFreq::Min60, TAB_INDEX_MIN60),
+    (DataType::Fund, BarFreq::Min1, TAB_FUND_MIN1),
+    (DataType::Fund, BarFreq::Min5, TAB_FUND_MIN5),
+    (DataType::Fund, BarFreq::Min15, TAB_FUND_MIN15),
+    (DataType::Fund, BarFreq::Min30, TAB_FUND_MIN30),
+    (DataType::Fund, BarFreq::Min60, TAB_FUND_MIN60),
+    (DataType::Bond, BarFreq::Min1, TAB_BOND_MIN1),
+    (DataType::Bond, BarFreq::Min5, TAB_BOND_MIN5),
+    (DataType::Bond, BarFreq::Min15, TAB_BOND_MIN15),
+    (DataType::Bond, BarFreq::Min30, TAB_BOND_MIN30),
+    (DataType::Bond, BarFreq::Min60, TAB_BOND_MIN60),
+];
+
+/// 分钟线的表名，不支持的品种或频率返回错误
+pub fn minute_tab(typ: DataType, freq: BarFreq) -> Result<&'static str> {
+    MINUTE_TABLES
+        .iter()
+        .find(|(t, f, _)| *t == typ && *f == freq)
+        .map(|(_, _, tab)| *tab)
+        .ok_or_else(|| Error::Custom(format!("unsupported minute bar: {:?} {:?}", typ, freq)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::NaiveDate;
+
+    use crate::types::Resync;
+
+    use super::Cache;
+
+    #[test]
+    fn test_sync_start() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y%m%d").unwrap();
+        let mut cache = Cache::new();
+        let trade_date: BTreeSet<_> = [20230301, 20230302, 20230303, 20230306, 20230307].into();
+        cache.cache_trade_date(&trade_date);
+
+        let mut resync = Resync::default();
+        let latest = Some(date("20230306"));
+        assert_eq!(cache.sync_start("a", None, &resync), date("20100101"));
+        assert_eq!(
+            cache.sync_start("a", Some(date("20230303")), &resync),
+            date("20230306")
+        );
+        resync.overlap_days = 1;
+        assert_eq!(cache.sync_start("a", latest, &resync), date("20230306"));
+        resync.overlap_days = 3;
+        assert_eq!(cache.sync_start("a", latest, &resync), date("20230302"));
+        resync.overlap_days = 10;
+        assert_eq!(cache.sync_start("a", latest, &resync), date("20230301"));
+
+        resync.overlap_days = 0;
+        resync.since.insert("b".into(), date("20230302"));
+        resync.since.insert("c".into(), date("20230310"));
+        resync.full_codes.insert("d".into());
+        assert_eq!(cache.sync_start("b", latest, &resync), date("20230302"));
+        assert_eq!(cache.sync_start("c", latest, &resync), date("20230307"));
+        assert_eq!(cache.sync_start("d", latest, &resync), date("20100101"));
+    }
+}
@@ -7,13 +7,13 @@ use rwqfetch::BarFreq;
 use tokio::sync::mpsc;
 
 use crate::{
-    store::{mongo::service::query_one, Cache, DATA_DEF_START_DATE, TAB_BOND_DAILY},
+    store::{mongo::service::query_one, peer_latest, Cache, TAB_BOND_DAILY},
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
-use super::service::insert_many;
+use super::service::save_codes;
 
 struct BondDailyAsyncFunc<'a> {
     code: &'a str,
@@ -51,17 +51,22 @@ impl<'a> AsyncFunc for BondDailyAsyncFunc<'a> {
 pub(crate) struct BondDailySyncer {
     cache: Arc<RwLock<Cache>>,
     client: Client,
+    resync: Resync,
 }
 
 impl BondDailySyncer {
-    pub fn new(client: Client, cache: Arc<RwLock<Cache>>) -> Self {
-        Self { client, cache }
+    pub fn new(client: Client, cache: Arc<RwLock<Cache>>, resync: Resync) -> Self {
+        Self {
+            client,
+            cache,
+            resync,
+        }
     }
 }
 
 #[async_trait]
 impl Syncer for BondDailySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let data = {
             let mut data = Vec::new();
             let cache_info = self.cache.read().unwrap();
@@ -73,6 +78,20 @@ impl Syncer for BondDailySyncer {
             data
         };
         for info in data.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_BOND_DAILY, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_BOND_DAILY
+                );
+                continue;
+            }
             log::info!(
                 "prepare sync {}({}) {}",
                 info.name.as_str(),
@@ -89,14 +108,13 @@ impl Syncer for BondDailySyncer {
                     .build(),
             )
             .await?;
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(&self.cache, TAB_BOND_DAILY, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
             if !need_to_start(&start) {
                 log::info!(
                     "{}({}) {} is the newest",
@@ -125,7 +143,7 @@ impl Syncer for BondDailySyncer {
 
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -153,7 +171,14 @@ impl Syncer for BondDailySyncer {
                 TAB_BOND_DAILY,
                 len
             );
-            insert_many(self.client.clone(), TAB_BOND_DAILY, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_BOND_DAILY,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -164,4 +189,8 @@ impl Syncer for BondDailySyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_BOND_DAILY)
+    }
 }
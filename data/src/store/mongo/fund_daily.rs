@@ -7,13 +7,13 @@ use rwqfetch::BarFreq;
 use tokio::sync::mpsc;
 
 use crate::{
-    store::{mongo::service::query_one, Cache, DATA_DEF_START_DATE, TAB_FUND_DAILY},
+    store::{mongo::service::query_one, peer_latest, Cache, TAB_FUND_DAILY},
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
-use super::service::insert_many;
+use super::service::save_codes;
 
 struct FundDailyAsyncFunc<'a> {
     code: &'a str,
@@ -47,17 +47,22 @@ impl<'a> AsyncFunc for FundDailyAsyncFunc<'a> {
 pub(crate) struct FundDailySyncer {
     cache: Arc<RwLock<Cache>>,
     client: Client,
+    resync: Resync,
 }
 
 impl FundDailySyncer {
-    pub fn new(client: Client, cache: Arc<RwLock<Cache>>) -> Self {
-        Self { client, cache }
+    pub fn new(client: Client, cache: Arc<RwLock<Cache>>, resync: Resync) -> Self {
+        Self {
+            client,
+            cache,
+            resync,
+        }
     }
 }
 
 #[async_trait]
 impl Syncer for FundDailySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let data = {
             let mut data = Vec::new();
             let cache_info = self.cache.read().unwrap();
@@ -69,6 +74,20 @@ impl Syncer for FundDailySyncer {
             data
         };
         for info in data.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_FUND_DAILY, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_FUND_DAILY
+                );
+                continue;
+            }
             log::info!(
                 "prepare sync {}({}) {}",
                 info.name.as_str(),
@@ -85,14 +104,13 @@ impl Syncer for FundDailySyncer {
                     .build(),
             )
             .await?;
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(&self.cache, TAB_FUND_DAILY, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
             if !need_to_start(&start) {
                 log::info!(
                     "{}({}) {} is the newest",
@@ -119,7 +137,7 @@ impl Syncer for FundDailySyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -147,7 +165,14 @@ impl Syncer for FundDailySyncer {
                 TAB_FUND_DAILY,
                 len
             );
-            insert_many(self.client.clone(), TAB_FUND_DAILY, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_FUND_DAILY,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -158,4 +183,8 @@ impl Syncer for FundDailySyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_FUND_DAILY)
+    }
 }
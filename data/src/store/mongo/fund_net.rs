@@ -7,11 +7,11 @@ use tokio::sync::mpsc;
 
 use crate::{
     store::{
-        mongo::service::{insert_many, query_one},
-        Cache, DATA_DEF_START_DATE, TAB_FUND_NET,
+        mongo::service::{query_one, save_codes},
+        peer_latest, Cache, TAB_FUND_NET,
     },
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
@@ -38,17 +38,22 @@ impl<'a> AsyncFunc for FundNetAsyncFunc<'a> {
 pub(crate) struct FundNetSyncer {
     cache: Arc<RwLock<Cache>>,
     client: Client,
+    resync: Resync,
 }
 
 impl FundNetSyncer {
-    pub fn new(client: Client, cache: Arc<RwLock<Cache>>) -> Self {
-        Self { client, cache }
+    pub fn new(client: Client, cache: Arc<RwLock<Cache>>, resync: Resync) -> Self {
+        Self {
+            client,
+            cache,
+            resync,
+        }
     }
 }
 
 #[async_trait]
 impl Syncer for FundNetSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let data = {
             let mut data = Vec::new();
             let cache_info = self.cache.read().unwrap();
@@ -60,6 +65,20 @@ impl Syncer for FundNetSyncer {
             data
         };
         for info in data.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_FUND_NET, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_FUND_NET
+                );
+                continue;
+            }
             let bar: Option<rwqfetch::FundNet> = query_one(
                 self.client.clone(),
                 TAB_FUND_NET,
@@ -70,14 +89,13 @@ impl Syncer for FundNetSyncer {
                     .build(),
             )
             .await?;
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(&self.cache, TAB_FUND_NET, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
             if !need_to_start(&start) {
                 log::info!(
                     "{}({}) {} is the newest",
@@ -103,7 +121,7 @@ impl Syncer for FundNetSyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -131,7 +149,14 @@ impl Syncer for FundNetSyncer {
                 TAB_FUND_NET,
                 len
             );
-            insert_many(self.client.clone(), TAB_FUND_NET, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_FUND_NET,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 elm.name.as_str(),
@@ -142,4 +167,8 @@ impl Syncer for FundNetSyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_FUND_NET)
+    }
 }
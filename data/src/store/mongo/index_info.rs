@@ -47,13 +47,13 @@ impl IndexInfoSyncer {
 
 #[async_trait]
 impl Syncer for IndexInfoSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let func = IndexInfoAsyncFunc {
             cache: self.cache.clone(),
         };
         let data = retry(func).await?;
         if let Some(data) = data {
-            tx.send(data).map_err(|e| {
+            tx.send(data).await.map_err(|e| {
                 log::error!("send data error {:?}", e);
                 Error::Custom(format!("send data error {:?}", e))
             })?;
@@ -1,22 +1,23 @@
 use crate::{
     store::{
-        Loader, TAB_BOND_DAILY, TAB_BOND_INFO, TAB_FUND_DAILY, TAB_FUND_INFO, TAB_FUND_NET,
-        TAB_INDEX_DAILY, TAB_INDEX_INFO, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
-        TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_DAILY, TAB_STOCK_INDEX, TAB_STOCK_INDUSTRY,
-        TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_INDUSTRY_DETAIL, TAB_STOCK_INFO, TAB_STOCK_MARGIN,
-        TAB_STOCK_YJBB,
+        minute_tab, Cond, DataType, Loader, Order, Query, TAB_BOND_DAILY, TAB_BOND_INFO,
+        TAB_FUND_DAILY, TAB_FUND_INFO, TAB_FUND_NET, TAB_INDEX_DAILY, TAB_INDEX_INFO,
+        TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY, TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_DAILY,
+        TAB_STOCK_INDEX, TAB_STOCK_INDUSTRY, TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_INDUSTRY_DETAIL,
+        TAB_STOCK_INFO, TAB_STOCK_MARGIN, TAB_STOCK_YJBB, TAB_TRADE_DATE,
     },
     Error, Result,
 };
 use async_trait::async_trait;
 use mongodb::{
-    bson::Document,
+    bson::{doc, Bson, Document},
     options::{ClientOptions, FindOptions},
     Client,
 };
+use rwqfetch::BarFreq;
 use serde::de::DeserializeOwned;
 
-use super::{query, query_one};
+use super::query_one;
 
 pub(crate) struct MongoLoader {
     client: Option<Client>,
@@ -54,220 +55,195 @@ impl MongoLoader {
             .ok_or(Error::Custom("mongodb not connected!".to_owned()))?;
         Ok(client.clone())
     }
-    async fn query<T>(
-        &self,
-        tab: &str,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<T>>
+    async fn query<T>(&self, tab: &str, query: Query) -> Result<Vec<T>>
     where
         T: DeserializeOwned + Unpin + Send + Sync + Clone,
     {
-        if let Some(limit) = limit {
+        let client = self.get_client()?;
+        let filter = filter_document(&query);
+        let mut options = FindOptions::builder()
+            .sort(sort_document(&query))
+            .projection(projection_document(&query))
+            .build();
+        if let Some(limit) = query.get_limit() {
             if limit == 1 {
-                let data: Option<T> = self.query_one(tab, filter, sort).await?;
+                let data: Option<T> = query_one(client, tab, filter, options).await?;
                 return data.map_or(Ok(Vec::new()), |e| Ok(vec![e]));
             }
+            options.limit = Some(limit);
         }
+        let data: Vec<T> = super::query(client, tab, filter, options).await?;
+        Ok(data)
+    }
+}
 
-        self.query_all(tab, filter, sort, limit).await
+/// 查询条件转换为MongoDB的查询文档，同一字段的操作符合并在一起，以便使用索引
+fn filter_document(query: &Query) -> Document {
+    let mut filter = Document::new();
+    let mut rest = Vec::new();
+    for cond in query.typed_conds() {
+        for (field, value) in cond_document(cond) {
+            match (filter.get_mut(&field), value) {
+                (None, value) if !field.starts_with('$') => {
+                    filter.insert(field, value);
+                }
+                (Some(Bson::Document(ops)), Bson::Document(new_ops))
+                    if new_ops.keys().all(|k| !ops.contains_key(k)) =>
+                {
+                    ops.extend(new_ops);
+                }
+                (_, value) => rest.push(doc! {field: value}),
+            }
+        }
     }
-    async fn query_all<T>(
-        &self,
-        tab: &str,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<T>>
-    where
-        T: DeserializeOwned + Unpin + Send + Sync + Clone,
-    {
-        let client = self.get_client()?;
-        let options = if let Some(limit) = limit {
-            FindOptions::builder().sort(sort).limit(limit).build()
-        } else {
-            FindOptions::builder().sort(sort).build()
-        };
-        let data: Vec<T> = query(client, tab, filter, options).await?;
-        Ok(data)
+    if !rest.is_empty() {
+        filter.insert("$and", rest);
     }
-    async fn query_one<T>(&self, tab: &str, filter: Document, sort: Document) -> Result<Option<T>>
-    where
-        T: DeserializeOwned + Unpin + Send + Sync + Clone,
-    {
-        let client = self.get_client()?;
-        let options = FindOptions::builder().sort(sort).build();
+    match query.raw_filter() {
+        Some(raw) if filter.is_empty() => raw.clone(),
+        Some(raw) => doc! {"$and": [filter, raw.clone()]},
+        None => filter,
+    }
+}
 
-        let data: Option<T> = query_one(client, tab, filter, options).await?;
-        Ok(data)
+fn cond_document(cond: &Cond) -> Document {
+    let values = |values: &Vec<_>| values.iter().map(Bson::from).collect::<Vec<_>>();
+    match cond {
+        Cond::Cmp(field, op, value) => doc! {field: {op.operator(): Bson::from(value)}},
+        Cond::In(field, v) => doc! {field: {"$in": values(v)}},
+        // MongoDB中$nin匹配空值，与其他存储保持一致
+        Cond::NotIn(field, v) => doc! {field: {"$nin": values(v), "$ne": Bson::Null}},
+        Cond::And(conds) | Cond::Or(conds) => {
+            let is_or = matches!(cond, Cond::Or(_));
+            let docs: Vec<_> = conds.iter().map(cond_document).collect();
+            // 空的条件恒真
+            if docs.is_empty() || (is_or && docs.iter().any(|d| d.is_empty())) {
+                return Document::new();
+            }
+            doc! {if is_or { "$or" } else { "$and" }: docs}
+        }
     }
 }
 
+fn sort_document(query: &Query) -> Document {
+    let mut sort = Document::new();
+    for (field, order) in query.typed_sort() {
+        sort.insert(field, if *order == Order::Desc { -1 } else { 1 });
+    }
+    if let Some(raw) = query.raw_sort() {
+        sort.extend(raw.clone());
+    }
+    sort
+}
+
+fn projection_document(query: &Query) -> Option<Document> {
+    query.get_projection().map(|fields| {
+        let mut projection: Document = fields.iter().map(|f| (f.clone(), Bson::Int32(1))).collect();
+        if !fields.iter().any(|f| f == "_id") {
+            projection.insert("_id", 0);
+        }
+        projection
+    })
+}
+
 #[async_trait]
 impl Loader for MongoLoader {
     async fn init(&mut self) -> Result<()> {
         self.build_client().await?;
         Ok(())
     }
-    async fn load_bond_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::BondInfo>> {
-        self.query(TAB_BOND_INFO, filter, sort, limit).await
+    async fn load_raw(&self, tab: &str, query: Query) -> Result<Vec<serde_json::Value>> {
+        let data: Vec<Document> = self.query(tab, query).await?;
+        Ok(data
+            .into_iter()
+            .map(|mut e| {
+                e.remove("_id");
+                Bson::Document(e).into_relaxed_extjson()
+            })
+            .collect())
     }
-    async fn load_bond_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_BOND_DAILY, filter, sort, limit).await
+    async fn load_trade_date(&self, query: Query) -> Result<Vec<rwqfetch::TradeDate>> {
+        self.query(TAB_TRADE_DATE, query).await
+    }
+    async fn load_bond_info(&self, query: Query) -> Result<Vec<rwqfetch::BondInfo>> {
+        self.query(TAB_BOND_INFO, query).await
+    }
+    async fn load_bond_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_BOND_DAILY, query).await
     }
 
-    async fn load_fund_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::FundInfo>> {
-        self.query(TAB_FUND_INFO, filter, sort, limit).await
+    async fn load_fund_info(&self, query: Query) -> Result<Vec<rwqfetch::FundInfo>> {
+        self.query(TAB_FUND_INFO, query).await
     }
-    async fn load_fund_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_FUND_DAILY, filter, sort, limit).await
+    async fn load_fund_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_FUND_DAILY, query).await
     }
-    async fn load_fund_net(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::FundNet>> {
-        self.query(TAB_FUND_NET, filter, sort, limit).await
+    async fn load_fund_net(&self, query: Query) -> Result<Vec<rwqfetch::FundNet>> {
+        self.query(TAB_FUND_NET, query).await
     }
 
-    async fn load_index_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockInfo>> {
-        self.query(TAB_INDEX_INFO, filter, sort, limit).await
+    async fn load_index_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>> {
+        self.query(TAB_INDEX_INFO, query).await
     }
 
-    async fn load_index_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_INDEX_DAILY, filter, sort, limit).await
+    async fn load_index_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_INDEX_DAILY, query).await
     }
 
-    async fn load_stock_info(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockInfo>> {
-        self.query(TAB_STOCK_INFO, filter, sort, limit).await
+    async fn load_stock_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>> {
+        self.query(TAB_STOCK_INFO, query).await
     }
 
-    async fn load_stock_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_STOCK_DAILY, filter, sort, limit).await
+    async fn load_stock_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_STOCK_DAILY, query).await
     }
 
-    async fn load_stock_index(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockIndex>> {
-        self.query(TAB_STOCK_INDEX, filter, sort, limit).await
+    async fn load_stock_index(&self, query: Query) -> Result<Vec<rwqfetch::StockIndex>> {
+        self.query(TAB_STOCK_INDEX, query).await
     }
-    async fn load_stock_industry(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockIndustry>> {
-        self.query(TAB_STOCK_INDUSTRY, filter, sort, limit).await
+    async fn load_stock_industry(&self, query: Query) -> Result<Vec<rwqfetch::StockIndustry>> {
+        self.query(TAB_STOCK_INDUSTRY, query).await
     }
 
-    async fn load_stock_industry_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_STOCK_INDUSTRY_DAILY, filter, sort, limit)
-            .await
+    async fn load_stock_industry_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_STOCK_INDUSTRY_DAILY, query).await
     }
     async fn load_stock_industry_detail(
         &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
+        query: Query,
     ) -> Result<Vec<rwqfetch::StockIndustryDetail>> {
-        self.query(TAB_STOCK_INDUSTRY_DETAIL, filter, sort, limit)
-            .await
+        self.query(TAB_STOCK_INDUSTRY_DETAIL, query).await
     }
 
-    async fn load_stock_concept(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockConcept>> {
-        self.query(TAB_STOCK_CONCEPT, filter, sort, limit).await
+    async fn load_stock_concept(&self, query: Query) -> Result<Vec<rwqfetch::StockConcept>> {
+        self.query(TAB_STOCK_CONCEPT, query).await
     }
 
-    async fn load_stock_concept_daily(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::Bar>> {
-        self.query(TAB_STOCK_CONCEPT_DAILY, filter, sort, limit)
-            .await
+    async fn load_stock_concept_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(TAB_STOCK_CONCEPT_DAILY, query).await
     }
     async fn load_stock_concept_detail(
         &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
+        query: Query,
     ) -> Result<Vec<rwqfetch::StockConceptDetail>> {
-        self.query(TAB_STOCK_CONCEPT_DETAIL, filter, sort, limit)
-            .await
+        self.query(TAB_STOCK_CONCEPT_DETAIL, query).await
     }
 
-    async fn load_stock_yjbb(
-        &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockYJBB>> {
-        self.query(TAB_STOCK_YJBB, filter, sort, limit).await
+    async fn load_stock_yjbb(&self, query: Query) -> Result<Vec<rwqfetch::StockYJBB>> {
+        self.query(TAB_STOCK_YJBB, query).await
+    }
+
+    async fn load_stock_margin(&self, query: Query) -> Result<Vec<rwqfetch::StockMargin>> {
+        self.query(TAB_STOCK_MARGIN, query).await
     }
 
-    async fn load_stock_margin(
+    async fn load_minute(
         &self,
-        filter: Document,
-        sort: Document,
-        limit: Option<i64>,
-    ) -> Result<Vec<rwqfetch::StockMargin>> {
-        self.query(TAB_STOCK_MARGIN, filter, sort, limit).await
+        typ: DataType,
+        freq: BarFreq,
+        query: Query,
+    ) -> Result<Vec<rwqfetch::Bar>> {
+        self.query(minute_tab(typ, freq)?, query).await
     }
 }
 
@@ -278,7 +254,9 @@ mod tests {
     use mongodb::bson::doc;
 
     use crate::store::mongo::MongoLoader;
-    use crate::store::Loader;
+    use crate::store::{Cond, Loader, Order, Query};
+
+    use super::{filter_document, projection_document, sort_document};
 
     #[test]
     fn test_loader_async() {
@@ -298,19 +276,60 @@ mod tests {
         let mut loader = MongoLoader::new("mongodb://localhost:27017".to_owned());
         loader.init().await?;
 
-        let info = loader.load_stock_info(doc! {}, doc! {}, Some(1)).await?;
+        let info = loader.load_stock_info(Query::new().limit(1)).await?;
 
         println!("info: {:?}", info);
 
         let nd = NaiveDate::parse_from_str("2022-12-12", "%Y-%m-%d").unwrap();
         let data = loader
             .load_stock_daily(
-                doc! {"code": "sz001219", "trade_date": {"$lte": loader.naive_date_to_datetime_str(&nd)?}},
-                doc! {"trade_date": -1},
-                Some(2),
+                Query::new()
+                    .code("sz001219")
+                    .filter(Cond::lte("trade_date", nd))
+                    .sort("trade_date", Order::Desc)
+                    .limit(2),
             )
             .await?;
         println!("data: {:?}", data);
         Ok(())
     }
+
+    #[test]
+    fn test_filter_document() {
+        let query = Query::new()
+            .code("sz001219")
+            .filter(Cond::gte("trade_date", 100))
+            .filter(Cond::lte("trade_date", 200))
+            .filter(Cond::gt("trade_date", 150))
+            .filter(Cond::Or(vec![
+                Cond::not_in("name", ["ST"]),
+                Cond::eq("close", None::<f64>),
+            ]))
+            .sort("trade_date", Order::Desc)
+            .project(["code", "close"]);
+        assert_eq!(
+            filter_document(&query),
+            doc! {
+                "code": {"$eq": "sz001219"},
+                "trade_date": {"$gte": 100_i64, "$lte": 200_i64, "$gt": 150_i64},
+                "$and": [
+                    {"$or": [{"name": {"$nin": ["ST"], "$ne": null}}, {"close": {"$eq": null}}]},
+                ],
+            }
+        );
+        assert_eq!(sort_document(&query), doc! {"trade_date": -1});
+        assert_eq!(
+            projection_document(&query),
+            Some(doc! {"code": 1, "close": 1, "_id": 0})
+        );
+
+        let raw = doc! {"name": {"$regex": "ST"}};
+        let query = Query::new().mongo_filter(raw.clone());
+        assert_eq!(filter_document(&query), raw);
+        let query = query.code("sz001219");
+        assert_eq!(
+            filter_document(&query),
+            doc! {"$and": [{"code": {"$eq": "sz001219"}}, raw]}
+        );
+    }
 }
@@ -0,0 +1,189 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use mongodb::{bson::doc, options::FindOptions, Client};
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{
+        minute::{minute_start, retain_start, MinuteAsyncFunc, MinuteCode},
+        mongo::service::query_one,
+        peer_delete, peer_latest, Cache,
+    },
+    syncer::{need_to_start, retry, Syncer},
+    types::{MinuteSync, Resync, SyncData},
+    Error, Result,
+};
+
+use super::service::{delete_many, save_codes};
+
+/// 分钟线增量同步，按品种和频率保存到不同的集合，同步时删除保留交易日之前的数据
+pub(crate) struct MinuteSyncer {
+    cache: Arc<RwLock<Cache>>,
+    client: Client,
+    tab: &'static str,
+    minute: MinuteSync,
+    resync: Resync,
+    codes: Vec<MinuteCode>,
+    task_n: usize,
+}
+
+impl MinuteSyncer {
+    pub fn new(
+        client: Client,
+        cache: Arc<RwLock<Cache>>,
+        tab: &'static str,
+        minute: MinuteSync,
+        resync: Resync,
+        codes: Vec<MinuteCode>,
+        task_n: usize,
+    ) -> Self {
+        Self {
+            cache,
+            client,
+            tab,
+            minute,
+            resync,
+            codes,
+            task_n,
+        }
+    }
+}
+
+#[async_trait]
+impl Syncer for MinuteSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let tab = self.tab;
+        let retain = {
+            let cache = self.cache.read().unwrap();
+            retain_start(&cache, &self.minute)
+        };
+        for info in self.codes.iter() {
+            if self.cache.read().unwrap().is_done(tab, info.code.as_str()) {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab
+                );
+                continue;
+            }
+            log::info!(
+                "prepare sync {}({}) {}, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                self.task_n
+            );
+            if let Some(retain) = retain {
+                let ts = retain.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+                delete_many(
+                    self.client.clone(),
+                    tab,
+                    doc! {"code": info.code.as_str(), "trade_date": {"$lt": ts}},
+                )
+                .await?;
+                peer_delete(&self.cache, tab, info.code.as_str(), Some(retain)).await;
+            }
+            let bar: Option<rwqfetch::Bar> = query_one(
+                self.client.clone(),
+                tab,
+                doc! {"code": info.code.as_str()},
+                FindOptions::builder()
+                    .sort(doc! {"trade_date": -1})
+                    .limit(1)
+                    .build(),
+            )
+            .await?;
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(&self.cache, tab, info.code.as_str(), latest).await;
+            let start = {
+                let cache = self.cache.read().unwrap();
+                Some(minute_start(
+                    &cache,
+                    info.code.as_str(),
+                    latest,
+                    &self.minute,
+                    &self.resync,
+                ))
+            };
+            if !need_to_start(&start) {
+                log::info!(
+                    "{}({}) {} is the newest, task#{}",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab,
+                    self.task_n
+                );
+                continue;
+            }
+
+            log::info!(
+                "start sync {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+            let func = MinuteAsyncFunc {
+                typ: self.minute.typ,
+                freq: self.minute.freq,
+                code: info,
+                start,
+            };
+            let data = retry(func).await?;
+            if let Some(data) = data {
+                tx.send(data).await.map_err(|e| {
+                    log::error!("send data error {:?}", e);
+                    Error::Custom(format!("send data error {:?}", e))
+                })?;
+            };
+            log::info!(
+                "end fetch {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        if let SyncData::MinuteBar(info) = data {
+            let bar = match info.first() {
+                Some(bar) => bar,
+                None => return Ok(()),
+            };
+            let len = info.len();
+            log::info!(
+                "start save {}({}) {}, size={}, task#{}",
+                bar.name.as_str(),
+                bar.code.as_str(),
+                self.tab,
+                len,
+                self.task_n
+            );
+            save_codes(self.client.clone(), self.tab, &self.resync, &info, |e| {
+                e.code.as_str()
+            })
+            .await?;
+            log::info!(
+                "done save {}({}) {}, size={}, task#{}",
+                bar.name.as_str(),
+                bar.code.as_str(),
+                self.tab,
+                len,
+                self.task_n
+            );
+        }
+        Ok(())
+    }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(self.tab)
+    }
+}
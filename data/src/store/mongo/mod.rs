@@ -28,6 +28,8 @@ mod stock_concept_detail;
 mod stock_yjbb;
 mod stock_margin;
 
+mod minute;
+
 mod loader;
 
 mod service;
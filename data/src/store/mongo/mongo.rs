@@ -4,37 +4,55 @@ use std::{
 };
 
 use async_trait::async_trait;
-use mongodb::{bson::doc, options::ClientOptions, Client};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use mongodb::{
+    bson::doc,
+    options::{ClientOptions, FindOptions},
+    Client,
+};
+use serde::Deserialize;
 
 use crate::{
     store::{
-        mongo::service::query, Cache, Store, TAB_BOND_INFO, TAB_FUND_INFO, TAB_INDEX_INFO,
-        TAB_STOCK_INFO, TAB_TRADE_DATE,
+        minute::minute_codes,
+        mongo::service::{delete_many, query, query_one, upsert_many},
+        Cache, Store, TAB_BOND_INFO, TAB_FUND_INFO, TAB_INDEX_INFO, TAB_STOCK_INFO,
+        TAB_SYNC_CHECKPOINT, TAB_TRADE_DATE,
     },
-    syncer::Syncer,
-    types::SyncDataType,
+    syncer::{Syncer, TypedSyncer},
+    types::{Checkpoint, MinuteSync, Resync, SyncDataType},
     Error, Result,
 };
 
 use super::{
     bond_daily::BondDailySyncer, bond_info::BondInfoSyncer, fund_daily::FundDailySyncer,
     fund_info::FundInfoSyncer, fund_net::FundNetSyncer, index_daily::IndexDailySyncer,
-    index_info::IndexInfoSyncer, mongo_index::build_index, stock_concept::StockConceptSyncer,
-    stock_concept_daily::StockConceptDailySyncer, stock_concept_detail::StockConceptDetailSyncer,
-    stock_daily::StockDailySyncer, stock_index::StockIndexSyncer,
-    stock_industry::StockIndustrySyncer, stock_industry_daily::StockIndustryDailySyncer,
+    index_info::IndexInfoSyncer, minute::MinuteSyncer, mongo_index::build_index,
+    stock_concept::StockConceptSyncer, stock_concept_daily::StockConceptDailySyncer,
+    stock_concept_detail::StockConceptDetailSyncer, stock_daily::StockDailySyncer,
+    stock_index::StockIndexSyncer, stock_industry::StockIndustrySyncer,
+    stock_industry_daily::StockIndustryDailySyncer,
     stock_industry_detail::StockIndustryDetailSyncer, stock_info::StockInfoSyncer,
     stock_margin::StockMarginSyncer, stock_yjbb::StockYJBBSyncer, trade_date::TradeDateSyncer,
 };
 
+/// 最新一条数据的时间
+#[derive(Clone, Deserialize)]
+struct Latest {
+    trade_date: i64,
+}
+
 pub(crate) struct MongoStore {
-    syncer_vec: Vec<Arc<Box<dyn Syncer>>>,
+    syncer_vec: Vec<TypedSyncer>,
     cache: Arc<RwLock<Cache>>,
+    client: Option<Client>,
 
     url: String,
     skip_basic: bool,
     split_count: usize,
     funcs: Option<Vec<SyncDataType>>,
+    resync: Resync,
+    minute: Vec<MinuteSync>,
 }
 
 impl MongoStore {
@@ -43,6 +61,8 @@ impl MongoStore {
         skip_basic: bool,
         split_count: usize,
         funcs: &Option<Vec<SyncDataType>>,
+        resync: &Resync,
+        minute: &[MinuteSync],
     ) -> Self {
         let syncer_vec = Vec::new();
 
@@ -57,10 +77,13 @@ impl MongoStore {
         Self {
             syncer_vec,
             cache,
+            client: None,
             url,
             skip_basic,
             split_count,
             funcs: t_funcs,
+            resync: resync.clone(),
+            minute: minute.to_vec(),
         }
     }
     async fn prepare_cache(&mut self, client: Client) -> Result<()> {
@@ -140,7 +163,7 @@ impl MongoStore {
     fn add_syncer(&mut self, typ: &SyncDataType, syncer: Arc<Box<dyn Syncer>>) {
         if self.contains(typ) {
             // log::info!("add syncer: {:?}", typ);
-            self.syncer_vec.push(syncer);
+            self.syncer_vec.push((typ.clone(), syncer));
         }
     }
     fn prepare_heavy_syncer(&mut self, client: Client, split_count: usize) {
@@ -174,6 +197,7 @@ impl MongoStore {
                     Arc::new(Box::new(StockDailySyncer::new(
                         client.clone(),
                         self.cache.clone(),
+                        self.resync.clone(),
                         sub_codes,
                         task_n,
                     ))),
@@ -184,6 +208,7 @@ impl MongoStore {
                     Arc::new(Box::new(StockMarginSyncer::new(
                         client.clone(),
                         self.cache.clone(),
+                        self.resync.clone(),
                         margin_sub_codes,
                         task_n,
                     ))),
@@ -200,6 +225,7 @@ impl MongoStore {
                 Arc::new(Box::new(StockDailySyncer::new(
                     client.clone(),
                     self.cache.clone(),
+                    self.resync.clone(),
                     sub_codes.clone(),
                     task_n,
                 ))),
@@ -212,12 +238,47 @@ impl MongoStore {
                 Arc::new(Box::new(StockMarginSyncer::new(
                     client.clone(),
                     self.cache.clone(),
+                    self.resync.clone(),
                     margin_sub_codes,
                     task_n,
                 ))),
             );
         }
     }
+    /// 分钟线，每个品种和频率的代码切分为`split_count`份，每份一个syncer
+    fn prepare_minute_syncer(&mut self, client: Client, split_count: usize) {
+        for minute in self.minute.clone() {
+            let tab = match minute.tab() {
+                Ok(tab) => tab,
+                Err(e) => {
+                    log::error!("skip minute syncer: {}", e);
+                    continue;
+                }
+            };
+            let codes = {
+                let cache = self.cache.read().unwrap();
+                minute_codes(&cache, minute.typ)
+            };
+            if codes.is_empty() {
+                continue;
+            }
+            let len = codes.len().div_ceil(split_count.max(1));
+            for (i, sub_codes) in codes.chunks(len).enumerate() {
+                self.add_syncer(
+                    &SyncDataType::MinuteBar,
+                    Arc::new(Box::new(MinuteSyncer::new(
+                        client.clone(),
+                        self.cache.clone(),
+                        tab,
+                        minute.clone(),
+                        self.resync.clone(),
+                        sub_codes.to_vec(),
+                        i + 1,
+                    ))),
+                );
+            }
+        }
+    }
     fn prepare_syncer(&mut self, client: Client, split_count: usize) {
         if !self.skip_basic {
             // bond
@@ -270,6 +331,7 @@ impl MongoStore {
             Arc::new(Box::new(BondDailySyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
 
@@ -279,6 +341,7 @@ impl MongoStore {
             Arc::new(Box::new(FundDailySyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
 
@@ -287,6 +350,7 @@ impl MongoStore {
             Arc::new(Box::new(FundNetSyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
 
@@ -296,6 +360,7 @@ impl MongoStore {
             Arc::new(Box::new(IndexDailySyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
 
@@ -313,6 +378,7 @@ impl MongoStore {
             Arc::new(Box::new(StockIndustryDailySyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
         self.add_syncer(
@@ -329,6 +395,7 @@ impl MongoStore {
             Arc::new(Box::new(StockConceptDailySyncer::new(
                 client.clone(),
                 self.cache.clone(),
+                self.resync.clone(),
             ))),
         );
         self.add_syncer(
@@ -339,6 +406,7 @@ impl MongoStore {
             &SyncDataType::StockYJBB,
             Arc::new(Box::new(StockYJBBSyncer::new(client.clone()))),
         );
+        self.prepare_minute_syncer(client.clone(), split_count);
         self.prepare_heavy_syncer(client, split_count);
     }
 
@@ -356,6 +424,13 @@ impl MongoStore {
         })?;
         Ok(client)
     }
+    /// 已初始化时复用连接，否则新建连接
+    async fn client(&self) -> Result<Client> {
+        match self.client.as_ref() {
+            Some(client) => Ok(client.clone()),
+            None => self.build_client().await,
+        }
+    }
 }
 
 #[async_trait]
@@ -365,6 +440,7 @@ impl Store for MongoStore {
 
         self.prepare_cache(client.clone()).await?;
         self.prepare_syncer(client.clone(), self.split_count);
+        self.client = Some(client);
 
         Ok(())
     }
@@ -373,9 +449,55 @@ impl Store for MongoStore {
         build_index(client).await
     }
 
-    fn syncer(&self) -> Result<Vec<Arc<Box<dyn Syncer>>>> {
+    fn syncer(&self) -> Result<Vec<TypedSyncer>> {
         Ok(self.syncer_vec.iter().map(|e| e.clone()).collect())
     }
+
+    async fn load_checkpoint(&self, run: &str) -> Result<Vec<Checkpoint>> {
+        let client = self.client().await?;
+        query(client, TAB_SYNC_CHECKPOINT, doc! {"run": run}, None).await
+    }
+
+    async fn save_checkpoint(&self, data: &[Checkpoint]) -> Result<()> {
+        let client = self.client().await?;
+        upsert_many(client, TAB_SYNC_CHECKPOINT, data, &["run", "name", "code"]).await
+    }
+
+    fn resume(&self, data: &[Checkpoint]) {
+        self.cache.write().unwrap().cache_done(data);
+    }
+
+    async fn latest(&self, tab: &str, code: &str) -> Result<Option<NaiveDateTime>> {
+        let latest: Option<Latest> = query_one(
+            self.client().await?,
+            tab,
+            doc! {"code": code},
+            FindOptions::builder()
+                .sort(doc! {"trade_date": -1})
+                .limit(1)
+                .build(),
+        )
+        .await?;
+        Ok(latest
+            .and_then(|l| DateTime::from_timestamp(l.trade_date, 0))
+            .map(|dt| dt.naive_utc()))
+    }
+
+    async fn delete(&self, tab: &str, code: &str, before: Option<NaiveDate>) -> Result<()> {
+        let filter = match before {
+            Some(before) => {
+                let ts = before.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+                doc! {"code": code, "trade_date": {"$lt": ts}}
+            }
+            None => doc! {"code": code},
+        };
+        delete_many(self.client().await?, tab, filter).await?;
+        Ok(())
+    }
+
+    fn set_peers(&self, peers: Vec<Arc<Box<dyn Store>>>) {
+        self.cache.write().unwrap().cache_peers(peers);
+    }
 }
 
 #[cfg(test)]
@@ -1,13 +1,13 @@
 use mongodb::{bson::doc, Client, IndexModel};
 
 use crate::store::{
-    DATABASE, TAB_BOND_DAILY, TAB_BOND_INFO, TAB_FUND_DAILY, TAB_FUND_INFO, TAB_FUND_NET,
-    TAB_INDEX_DAILY, TAB_INDEX_INFO, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
+    DATABASE, MINUTE_TABLES, TAB_BOND_DAILY, TAB_BOND_INFO, TAB_FUND_DAILY, TAB_FUND_INFO,
+    TAB_FUND_NET, TAB_INDEX_DAILY, TAB_INDEX_INFO, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
     TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_DAILY, TAB_STOCK_INDEX, TAB_STOCK_INDUSTRY,
     TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_INDUSTRY_DETAIL, TAB_STOCK_INFO, TAB_STOCK_MARGIN,
-    TAB_STOCK_YJBB, TAB_TRADE_DATE,
+    TAB_STOCK_YJBB, TAB_SYNC_CHECKPOINT, TAB_TRADE_DATE,
 };
-use crate::{Error, Result};
+use crate::{types::Checkpoint, Error, Result};
 
 pub(crate) async fn build_index(client: Client) -> Result<()> {
     let db = client.database(DATABASE);
@@ -223,6 +223,33 @@ pub(crate) async fn build_index(client: Client) -> Result<()> {
             }
         }
     }
+    // minute
+    for (_, _, tab) in MINUTE_TABLES.iter() {
+        log::info!("start build {} index!", tab);
+        let coll = db.collection::<rwqfetch::Bar>(tab);
+        coll.create_indexes(indexes.clone(), None)
+            .await
+            .map_err(|e| {
+                log::error!("create index err: {}", e);
+                Error::Custom(format!("create index err: {}", e))
+            })?;
+    }
+    // checkpoint
+    {
+        log::info!("start build {} index!", TAB_SYNC_CHECKPOINT);
+        let coll = db.collection::<Checkpoint>(TAB_SYNC_CHECKPOINT);
+        coll.create_index(
+            IndexModel::builder()
+                .keys(doc! {"run": 1, "name": 1, "code": 1})
+                .build(),
+            None,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("create index err: {}", e);
+            Error::Custom(format!("create index err: {}", e))
+        })?;
+    }
 
     Ok(())
 }
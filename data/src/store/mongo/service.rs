@@ -1,12 +1,14 @@
+use std::collections::BTreeMap;
+
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, Document},
+    bson::{self, doc, Bson, Document},
     options::FindOptions,
     Client,
 };
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{store::DATABASE, Error, Result};
+use crate::{store::DATABASE, types::Resync, Error, Result};
 
 pub(crate) async fn insert_many<T>(
     client: Client,
@@ -56,6 +58,139 @@ where
     Ok(())
 }
 
+/// 每条update命令的数据条数
+const UPSERT_CHUNK: usize = 1000;
+
+/// 写入数据，`keys`字段相同的覆盖旧数据，不存在则插入，重复写入不会产生重复数据
+pub(crate) async fn upsert_many<T>(
+    client: Client,
+    collection: &str,
+    info: &[T],
+    keys: &[&str],
+) -> Result<()>
+where
+    T: Serialize,
+{
+    let db = client.database(DATABASE);
+
+    log::info!("upsert into {}, {} items", collection, info.len());
+    for chunk in info.chunks(UPSERT_CHUNK) {
+        let updates = chunk
+            .iter()
+            .map(|item| {
+                let data = bson::to_document(item).map_err(|e| {
+                    log::error!("serialize {} data error: {}", collection, e);
+                    Error::Custom(format!("serialize {} data error: {}", collection, e))
+                })?;
+                let filter: Document = keys
+                    .iter()
+                    .map(|k| (k.to_string(), data.get(k).cloned().unwrap_or(Bson::Null)))
+                    .collect();
+                Ok(doc! {"q": filter, "u": data, "upsert": true})
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let res = db
+            .run_command(
+                doc! {"update": collection, "updates": updates, "ordered": false},
+                None,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("upsert collection {} failed: {}", collection, e);
+                Error::Custom(format!("upsert collection {} failed: {}", collection, e))
+            })?;
+        if let Ok(errors) = res.get_array("writeErrors") {
+            log::error!("upsert collection {} failed: {:?}", collection, errors);
+            return Err(Error::Custom(format!(
+                "upsert collection {} failed: {:?}",
+                collection, errors
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 删除满足条件的数据，返回删除的条数
+pub(crate) async fn delete_many(client: Client, collection: &str, filter: Document) -> Result<u64> {
+    let db = client.database(DATABASE);
+    let coll = db.collection::<Document>(collection);
+
+    let res = coll.delete_many(filter, None).await.map_err(|e| {
+        log::error!("delete collection {} failed: {}", collection, e);
+        Error::Custom(format!("delete collection {} failed: {}", collection, e))
+    })?;
+    log::info!("delete {}, {} items", collection, res.deleted_count);
+    Ok(res.deleted_count)
+}
+
+/// 全量重新同步时替换代码的数据：先写入新数据，写入成功后再删除该代码原有的数据，
+/// 原有的重复数据一并删除，写入失败时保留原有数据
+pub(crate) async fn replace_code<T>(
+    client: Client,
+    collection: &str,
+    code: &str,
+    info: &Vec<T>,
+) -> Result<()>
+where
+    T: Serialize,
+{
+    let old: Vec<Document> = query(
+        client.clone(),
+        collection,
+        doc! {"code": code},
+        FindOptions::builder().projection(doc! {"_id": 1}).build(),
+    )
+    .await?;
+    let old: Vec<_> = old
+        .into_iter()
+        .filter_map(|d| d.get("_id").cloned())
+        .collect();
+    if !info.is_empty() {
+        insert_many(client.clone(), collection, info, false).await?;
+    }
+    for chunk in old.chunks(UPSERT_CHUNK) {
+        delete_many(
+            client.clone(),
+            collection,
+            doc! {"_id": {"$in": chunk.to_vec()}},
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// 保存数据，同一批可包含多个代码，全量重新同步的代码按代码分别替换原有数据，
+/// 其他按代码及交易日覆盖写入
+pub(crate) async fn save_codes<T, F>(
+    client: Client,
+    collection: &str,
+    resync: &Resync,
+    info: &[T],
+    code: F,
+) -> Result<()>
+where
+    T: Serialize,
+    F: Fn(&T) -> &str,
+{
+    let mut full: BTreeMap<&str, Vec<&T>> = BTreeMap::new();
+    let mut rest = Vec::new();
+    for item in info.iter() {
+        let c = code(item);
+        if resync.is_full(c) {
+            full.entry(c).or_default().push(item);
+        } else {
+            rest.push(item);
+        }
+    }
+    if !rest.is_empty() {
+        upsert_many(client.clone(), collection, &rest, &["code", "trade_date"]).await?;
+    }
+    for (code, info) in full.iter() {
+        replace_code(client.clone(), collection, code, info).await?;
+    }
+    Ok(())
+}
+
 pub async fn query<T>(
     client: Client,
     collection: &str,
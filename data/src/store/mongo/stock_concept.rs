@@ -11,7 +11,7 @@ use crate::{
     Error, Result,
 };
 
-use super::service::{insert_many, query};
+use super::service::{query, upsert_many};
 
 struct StockConceptAsyncFunc;
 
@@ -36,7 +36,7 @@ impl StockConceptSyncer {
 
 #[async_trait]
 impl Syncer for StockConceptSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         log::info!("start fetch {}", TAB_STOCK_CONCEPT);
         let func = StockConceptAsyncFunc {};
         let data = retry(func).await?;
@@ -56,7 +56,7 @@ impl Syncer for StockConceptSyncer {
                 };
 
                 if data.len() > 0 {
-                    tx.send(SyncData::StockConcept(data)).map_err(|e| {
+                    tx.send(SyncData::StockConcept(data)).await.map_err(|e| {
                         log::error!("send data error {:?}", e);
                         Error::Custom(format!("send data error {:?}", e))
                     })?;
@@ -77,7 +77,7 @@ impl Syncer for StockConceptSyncer {
                 TAB_STOCK_CONCEPT,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_CONCEPT, &info, false).await?;
+            upsert_many(self.client.clone(), TAB_STOCK_CONCEPT, &info, &["code"]).await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 elm.name.as_str(),
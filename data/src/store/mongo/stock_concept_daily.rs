@@ -8,11 +8,11 @@ use tokio::sync::mpsc;
 
 use crate::{
     store::{
-        mongo::service::{insert_many, query, query_one},
-        Cache, DATA_DEF_START_DATE, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
+        mongo::service::{query, query_one, save_codes},
+        peer_latest, Cache, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
     },
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
@@ -46,17 +46,22 @@ impl<'a> AsyncFunc for StockConceptDailyAsyncFunc<'a> {
 pub(crate) struct StockConceptDailySyncer {
     client: Client,
     cache: Arc<RwLock<Cache>>,
+    resync: Resync,
 }
 
 impl StockConceptDailySyncer {
-    pub fn new(client: Client, cache: Arc<RwLock<Cache>>) -> Self {
-        Self { client, cache }
+    pub fn new(client: Client, cache: Arc<RwLock<Cache>>, resync: Resync) -> Self {
+        Self {
+            client,
+            cache,
+            resync,
+        }
     }
 }
 
 #[async_trait]
 impl Syncer for StockConceptDailySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let mut concept: Vec<rwqfetch::StockConcept> =
             query(self.client.clone(), TAB_STOCK_CONCEPT, doc! {}, None).await?;
         if concept.is_empty() {
@@ -64,6 +69,20 @@ impl Syncer for StockConceptDailySyncer {
         }
 
         for info in concept.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_STOCK_CONCEPT_DAILY, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_STOCK_CONCEPT_DAILY
+                );
+                continue;
+            }
             log::info!(
                 "prepare sync {}({}) {}",
                 info.name.as_str(),
@@ -80,14 +99,19 @@ impl Syncer for StockConceptDailySyncer {
                     .build(),
             )
             .await?;
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(
+                &self.cache,
+                TAB_STOCK_CONCEPT_DAILY,
+                info.code.as_str(),
+                latest,
+            )
+            .await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
             if !need_to_start(&start) {
                 log::info!(
                     "{}({}) {} is the newest",
@@ -113,7 +137,7 @@ impl Syncer for StockConceptDailySyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -141,7 +165,14 @@ impl Syncer for StockConceptDailySyncer {
                 TAB_STOCK_CONCEPT_DAILY,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_CONCEPT_DAILY, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_STOCK_CONCEPT_DAILY,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -152,4 +183,8 @@ impl Syncer for StockConceptDailySyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_STOCK_CONCEPT_DAILY)
+    }
 }
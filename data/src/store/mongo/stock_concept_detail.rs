@@ -5,7 +5,7 @@ use mongodb::{bson::doc, Client};
 use tokio::sync::mpsc;
 
 use crate::{
-    store::mongo::service::{insert_many, query},
+    store::mongo::service::{query, upsert_many},
     syncer::{retry, AsyncFunc, Syncer},
     types::SyncData,
     Error, Result,
@@ -39,7 +39,7 @@ impl StockConceptDetailSyncer {
 
 #[async_trait]
 impl Syncer for StockConceptDetailSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let mut concept: Vec<rwqfetch::StockConcept> =
             query(self.client.clone(), TAB_STOCK_CONCEPT, doc! {}, None).await?;
         if concept.is_empty() {
@@ -80,10 +80,12 @@ impl Syncer for StockConceptDetailSyncer {
                     };
 
                     if data.len() > 0 {
-                        tx.send(SyncData::StockConceptDetail(data)).map_err(|e| {
-                            log::error!("send data error {:?}", e);
-                            Error::Custom(format!("send data error {:?}", e))
-                        })?;
+                        tx.send(SyncData::StockConceptDetail(data))
+                            .await
+                            .map_err(|e| {
+                                log::error!("send data error {:?}", e);
+                                Error::Custom(format!("send data error {:?}", e))
+                            })?;
                     }
                 }
             }
@@ -109,7 +111,13 @@ impl Syncer for StockConceptDetailSyncer {
                 TAB_STOCK_CONCEPT_DETAIL,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_CONCEPT_DETAIL, &info, false).await?;
+            upsert_many(
+                self.client.clone(),
+                TAB_STOCK_CONCEPT_DETAIL,
+                &info,
+                &["code", "stock_code"],
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 elm.name.as_str(),
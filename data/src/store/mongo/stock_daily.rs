@@ -7,13 +7,13 @@ use rwqfetch::{BarFreq, StockInfo};
 use tokio::sync::mpsc;
 
 use crate::{
-    store::{mongo::service::query_one, Cache, DATA_DEF_START_DATE, TAB_STOCK_DAILY},
+    store::{mongo::service::query_one, peer_latest, Cache, TAB_STOCK_DAILY},
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
-use super::service::insert_many;
+use super::service::save_codes;
 
 struct StockDailyAsyncFunc<'a> {
     code: &'a str,
@@ -49,18 +49,21 @@ pub(crate) struct StockDailySyncer {
     client: Client,
     codes: Vec<StockInfo>,
     task_n: usize,
+    resync: Resync,
 }
 
 impl StockDailySyncer {
     pub fn new(
         client: Client,
         cache: Arc<RwLock<Cache>>,
+        resync: Resync,
         codes: Vec<StockInfo>,
         task_n: usize,
     ) -> Self {
         Self {
             client,
             cache,
+            resync,
             codes,
             task_n,
         }
@@ -69,8 +72,22 @@ impl StockDailySyncer {
 
 #[async_trait]
 impl Syncer for StockDailySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         for info in self.codes.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_STOCK_DAILY, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_STOCK_DAILY
+                );
+                continue;
+            }
             log::info!(
                 "prepare sync {}({}) {}, task#{}",
                 info.name.as_str(),
@@ -88,14 +105,14 @@ impl Syncer for StockDailySyncer {
                     .build(),
             )
             .await?;
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest =
+                peer_latest(&self.cache, TAB_STOCK_DAILY, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
             if !need_to_start(&start) {
                 log::info!(
                     "{}({}) {} is the newest, task#{}",
@@ -124,7 +141,7 @@ impl Syncer for StockDailySyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -154,7 +171,14 @@ impl Syncer for StockDailySyncer {
                 len,
                 self.task_n
             );
-            insert_many(self.client.clone(), TAB_STOCK_DAILY, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_STOCK_DAILY,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}, task#{}",
                 bar.name.as_str(),
@@ -166,4 +190,8 @@ impl Syncer for StockDailySyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_STOCK_DAILY)
+    }
 }
@@ -40,12 +40,12 @@ impl StockIndexSyncer {
 
 #[async_trait]
 impl Syncer for StockIndexSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         log::info!("start sync {}", TAB_STOCK_INDEX);
         let func = StockIndexAsyncFunc {};
         let data = retry(func).await?;
         if let Some(data) = data {
-            tx.send(data).map_err(|e| {
+            tx.send(data).await.map_err(|e| {
                 log::error!("send data error {:?}", e);
                 Error::Custom(format!("send data error {:?}", e))
             })?;
@@ -36,7 +36,7 @@ impl StockIndustrySyncer {
 
 #[async_trait]
 impl Syncer for StockIndustrySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         log::info!("start fetch {}", TAB_STOCK_INDUSTRY);
         let func = StockIndustryAsyncFunc {};
         let data = retry(func).await?;
@@ -56,7 +56,7 @@ impl Syncer for StockIndustrySyncer {
                 };
 
                 if data.len() > 0 {
-                    tx.send(SyncData::StockIndustry(data)).map_err(|e| {
+                    tx.send(SyncData::StockIndustry(data)).await.map_err(|e| {
                         log::error!("send data error {:?}", e);
                         Error::Custom(format!("send data error {:?}", e))
                     })?;
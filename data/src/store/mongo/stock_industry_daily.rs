@@ -7,11 +7,11 @@ use tokio::sync::mpsc;
 
 use crate::{
     store::{
-        mongo::service::{insert_many, query, query_one},
-        Cache, DATA_DEF_START_DATE,
+        mongo::service::{query, query_one, save_codes},
+        peer_latest, Cache,
     },
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
@@ -47,17 +47,22 @@ impl<'a> AsyncFunc for StockIndustryDailyAsyncFunc<'a> {
 pub(crate) struct StockIndustryDailySyncer {
     client: Client,
     cache: Arc<RwLock<Cache>>,
+    resync: Resync,
 }
 
 impl StockIndustryDailySyncer {
-    pub fn new(client: Client, cache: Arc<RwLock<Cache>>) -> Self {
-        Self { client, cache }
+    pub fn new(client: Client, cache: Arc<RwLock<Cache>>, resync: Resync) -> Self {
+        Self {
+            client,
+            cache,
+            resync,
+        }
     }
 }
 
 #[async_trait]
 impl Syncer for StockIndustryDailySyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let mut industry: Vec<rwqfetch::StockIndustry> =
             query(self.client.clone(), TAB_STOCK_INDUSTRY, doc! {}, None).await?;
         if industry.is_empty() {
@@ -65,6 +70,20 @@ impl Syncer for StockIndustryDailySyncer {
         }
 
         for info in industry.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_STOCK_INDUSTRY_DAILY, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_STOCK_INDUSTRY_DAILY
+                );
+                continue;
+            }
             log::info!(
                 "prepare sync {}({}) {}",
                 info.name.as_str(),
@@ -88,14 +107,19 @@ impl Syncer for StockIndustryDailySyncer {
             )
             .await?;
 
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest = peer_latest(
+                &self.cache,
+                TAB_STOCK_INDUSTRY_DAILY,
+                info.code.as_str(),
+                latest,
+            )
+            .await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
 
             if !need_to_start(&start) {
                 log::info!(
@@ -122,7 +146,7 @@ impl Syncer for StockIndustryDailySyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -150,7 +174,14 @@ impl Syncer for StockIndustryDailySyncer {
                 TAB_STOCK_INDUSTRY_DAILY,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_INDUSTRY_DAILY, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_STOCK_INDUSTRY_DAILY,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -161,4 +192,8 @@ impl Syncer for StockIndustryDailySyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_STOCK_INDUSTRY_DAILY)
+    }
 }
@@ -5,7 +5,7 @@ use mongodb::{bson::doc, Client};
 use tokio::sync::mpsc;
 
 use crate::{
-    store::mongo::service::{insert_many, query},
+    store::mongo::service::{query, upsert_many},
     syncer::{retry, AsyncFunc, Syncer},
     types::SyncData,
     Error, Result,
@@ -39,7 +39,7 @@ impl StockIndustryDetailSyncer {
 
 #[async_trait]
 impl Syncer for StockIndustryDetailSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let mut industry: Vec<rwqfetch::StockIndustry> =
             query(self.client.clone(), TAB_STOCK_INDUSTRY, doc! {}, None).await?;
         if industry.is_empty() {
@@ -80,10 +80,12 @@ impl Syncer for StockIndustryDetailSyncer {
                     };
 
                     if data.len() > 0 {
-                        tx.send(SyncData::StockIndustryDetail(data)).map_err(|e| {
-                            log::error!("send data error {:?}", e);
-                            Error::Custom(format!("send data error {:?}", e))
-                        })?;
+                        tx.send(SyncData::StockIndustryDetail(data))
+                            .await
+                            .map_err(|e| {
+                                log::error!("send data error {:?}", e);
+                                Error::Custom(format!("send data error {:?}", e))
+                            })?;
                     }
                 }
             }
@@ -109,7 +111,13 @@ impl Syncer for StockIndustryDetailSyncer {
                 TAB_STOCK_INDUSTRY_DETAIL,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_INDUSTRY_DETAIL, &info, false).await?;
+            upsert_many(
+                self.client.clone(),
+                TAB_STOCK_INDUSTRY_DETAIL,
+                &info,
+                &["code", "stock_code"],
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 elm.name.as_str(),
@@ -7,13 +7,13 @@ use rwqfetch::StockInfo;
 use tokio::sync::mpsc;
 
 use crate::{
-    store::{mongo::service::query_one, Cache, DATA_DEF_START_DATE, TAB_STOCK_MARGIN},
+    store::{mongo::service::query_one, peer_latest, Cache, TAB_STOCK_MARGIN},
     syncer::{need_to_start, retry, AsyncFunc, Syncer},
-    types::SyncData,
+    types::{Resync, SyncData},
     Error, Result,
 };
 
-use super::service::insert_many;
+use super::service::save_codes;
 
 struct StockMarginAsyncFunc<'a> {
     code: &'a str,
@@ -39,18 +39,21 @@ pub(crate) struct StockMarginSyncer {
     client: Client,
     codes: Vec<StockInfo>,
     task_n: usize,
+    resync: Resync,
 }
 
 impl StockMarginSyncer {
     pub fn new(
         client: Client,
         cache: Arc<RwLock<Cache>>,
+        resync: Resync,
         codes: Vec<StockInfo>,
         task_n: usize,
     ) -> Self {
         Self {
             client,
             cache,
+            resync,
             codes,
             task_n,
         }
@@ -59,8 +62,22 @@ impl StockMarginSyncer {
 
 #[async_trait]
 impl Syncer for StockMarginSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         for info in self.codes.iter() {
+            if self
+                .cache
+                .read()
+                .unwrap()
+                .is_done(TAB_STOCK_MARGIN, info.code.as_str())
+            {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    TAB_STOCK_MARGIN
+                );
+                continue;
+            }
             log::info!(
                 "start sync {}({}) {}, task#{}",
                 info.name.as_str(),
@@ -80,14 +97,14 @@ impl Syncer for StockMarginSyncer {
             )
             .await?;
 
-            let start = bar
-                .map(|b| {
-                    let cache = self.cache.read().unwrap();
-                    Some(cache.next_trade_date(&(b.trade_date.date())))
-                })
-                .unwrap_or(Some(
-                    NaiveDate::parse_from_str(DATA_DEF_START_DATE, "%Y-%m-%d").unwrap(),
-                ));
+            let latest = bar.map(|b| b.trade_date);
+            let latest =
+                peer_latest(&self.cache, TAB_STOCK_MARGIN, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
 
             if !need_to_start(&start) {
                 log::info!(
@@ -114,7 +131,7 @@ impl Syncer for StockMarginSyncer {
             };
             let data = retry(func).await?;
             if let Some(data) = data {
-                tx.send(data).map_err(|e| {
+                tx.send(data).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -142,7 +159,14 @@ impl Syncer for StockMarginSyncer {
                 TAB_STOCK_MARGIN,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_MARGIN, &info, false).await?;
+            save_codes(
+                self.client.clone(),
+                TAB_STOCK_MARGIN,
+                &self.resync,
+                &info,
+                |e| e.code.as_str(),
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -153,4 +177,8 @@ impl Syncer for StockMarginSyncer {
         }
         Ok(())
     }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(TAB_STOCK_MARGIN)
+    }
 }
@@ -7,7 +7,7 @@ use tokio::sync::mpsc;
 
 use crate::{
     store::{
-        mongo::service::{insert_many, query, query_one},
+        mongo::service::{query, query_one, upsert_many},
         TAB_STOCK_YJBB,
     },
     syncer::{retry, AsyncFunc, Syncer},
@@ -44,7 +44,7 @@ impl StockYJBBSyncer {
 
 #[async_trait]
 impl Syncer for StockYJBBSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let yjbb: Option<rwqfetch::StockYJBB> = query_one(
             self.client.clone(),
             TAB_STOCK_YJBB,
@@ -112,7 +112,7 @@ impl Syncer for StockYJBBSyncer {
                     };
 
                     if data.len() > 0 {
-                        tx.send(SyncData::StockYJBB(data)).map_err(|e| {
+                        tx.send(SyncData::StockYJBB(data)).await.map_err(|e| {
                             log::error!("send data error {:?}", e);
                             Error::Custom(format!("send data error {:?}", e))
                         })?;
@@ -141,7 +141,13 @@ impl Syncer for StockYJBBSyncer {
                 TAB_STOCK_YJBB,
                 len
             );
-            insert_many(self.client.clone(), TAB_STOCK_YJBB, &info, false).await?;
+            upsert_many(
+                self.client.clone(),
+                TAB_STOCK_YJBB,
+                &info,
+                &["code", "year", "season"],
+            )
+            .await?;
             log::info!(
                 "done save {}({}) {}, size={}",
                 bar.name.as_str(),
@@ -11,7 +11,7 @@ use crate::{
     Error, Result,
 };
 
-use super::service::{insert_many, query_one};
+use super::service::{query_one, upsert_many};
 
 struct TradeDateAsyncFunc {
     cache: Arc<RwLock<Cache>>,
@@ -47,7 +47,7 @@ impl TradeDateSyncer {
 
 #[async_trait]
 impl Syncer for TradeDateSyncer {
-    async fn fetch(&self, tx: mpsc::UnboundedSender<SyncData>) -> Result<()> {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
         let func = TradeDateAsyncFunc {
             cache: self.cache.clone(),
         };
@@ -72,7 +72,7 @@ impl Syncer for TradeDateSyncer {
                 .filter(|e| e.trade_date > latest.trade_date)
                 .collect();
             if new_data.len() > 0 {
-                tx.send(SyncData::TradeDate(new_data)).map_err(|e| {
+                tx.send(SyncData::TradeDate(new_data)).await.map_err(|e| {
                     log::error!("send data error {:?}", e);
                     Error::Custom(format!("send data error {:?}", e))
                 })?;
@@ -86,7 +86,7 @@ impl Syncer for TradeDateSyncer {
         if let SyncData::TradeDate(info) = data {
             let len = info.len();
             log::info!("start save {}, size={}", TAB_TRADE_DATE, len);
-            insert_many(self.client.clone(), TAB_TRADE_DATE, &info, false).await?;
+            upsert_many(self.client.clone(), TAB_TRADE_DATE, &info, &["trade_date"]).await?;
             log::info!("done save {}, size={}", TAB_TRADE_DATE, len);
         }
         Ok(())
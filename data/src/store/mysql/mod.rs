@@ -1,3 +1,3 @@
 mod mysql;
 
-// pub(crate) use mysql::MysqlStore;
\ No newline at end of file
+pub(crate) use mysql::MySqlDb;
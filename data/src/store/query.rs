@@ -0,0 +1,473 @@
+//! 与存储无关的查询条件，由各存储转换为具体的查询
+//!
+//! ```ignore
+//! let query = Query::new()
+//!     .code("sh600000")
+//!     .date_range("trade_date", Some(start), None)
+//!     .filter(Cond::gte("close", 10.0))
+//!     .sort("trade_date", Order::Desc)
+//!     .limit(60);
+//! ```
+//!
+//! 复杂的查询可以通过`mongo_filter`/`mongo_sort`直接使用MongoDB风格的`Document`，
+//! MongoDB原样使用，其他存储仅支持`$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`, `$in`, `$nin`,
+//! `$and`, `$or`。
+
+use chrono::{NaiveDate, NaiveDateTime};
+use mongodb::bson::{Bson, Document};
+
+use crate::{Error, Result};
+
+/// 查询值，日期按秒级时间戳表示，与存储的格式一致
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<bool> for QueryValue {
+    fn from(v: bool) -> Self {
+        QueryValue::Bool(v)
+    }
+}
+
+impl From<i32> for QueryValue {
+    fn from(v: i32) -> Self {
+        QueryValue::Int(v as i64)
+    }
+}
+
+impl From<i64> for QueryValue {
+    fn from(v: i64) -> Self {
+        QueryValue::Int(v)
+    }
+}
+
+impl From<f32> for QueryValue {
+    fn from(v: f32) -> Self {
+        QueryValue::Float(v as f64)
+    }
+}
+
+impl From<f64> for QueryValue {
+    fn from(v: f64) -> Self {
+        QueryValue::Float(v)
+    }
+}
+
+impl From<&str> for QueryValue {
+    fn from(v: &str) -> Self {
+        QueryValue::Text(v.to_owned())
+    }
+}
+
+impl From<String> for QueryValue {
+    fn from(v: String) -> Self {
+        QueryValue::Text(v)
+    }
+}
+
+impl From<&String> for QueryValue {
+    fn from(v: &String) -> Self {
+        QueryValue::Text(v.clone())
+    }
+}
+
+impl From<NaiveDateTime> for QueryValue {
+    fn from(v: NaiveDateTime) -> Self {
+        QueryValue::Int(v.and_utc().timestamp())
+    }
+}
+
+impl From<NaiveDate> for QueryValue {
+    fn from(v: NaiveDate) -> Self {
+        QueryValue::from(v.and_hms_opt(0, 0, 0).unwrap())
+    }
+}
+
+impl<T: Into<QueryValue>> From<Option<T>> for QueryValue {
+    fn from(v: Option<T>) -> Self {
+        v.map(Into::into).unwrap_or(QueryValue::Null)
+    }
+}
+
+impl TryFrom<&Bson> for QueryValue {
+    type Error = Error;
+
+    fn try_from(value: &Bson) -> Result<Self> {
+        match value {
+            Bson::Null => Ok(QueryValue::Null),
+            Bson::Boolean(v) => Ok(QueryValue::Bool(*v)),
+            Bson::Int32(v) => Ok(QueryValue::Int(*v as i64)),
+            Bson::Int64(v) => Ok(QueryValue::Int(*v)),
+            Bson::Double(v) => Ok(QueryValue::Float(*v)),
+            Bson::String(v) => Ok(QueryValue::Text(v.clone())),
+            // 日期字段存储的是秒级时间戳
+            Bson::DateTime(v) => Ok(QueryValue::Int(v.timestamp_millis() / 1000)),
+            _ => Err(Error::Custom(format!(
+                "unsupported query value: {:?}",
+                value
+            ))),
+        }
+    }
+}
+
+impl From<&QueryValue> for Bson {
+    fn from(value: &QueryValue) -> Self {
+        match value {
+            QueryValue::Null => Bson::Null,
+            QueryValue::Bool(v) => Bson::Boolean(*v),
+            QueryValue::Int(v) => Bson::Int64(*v),
+            QueryValue::Float(v) => Bson::Double(*v),
+            QueryValue::Text(v) => Bson::String(v.clone()),
+        }
+    }
+}
+
+/// 比较操作符，与空值比较时只支持`Eq`及`Ne`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CmpOp {
+    /// 对应的MongoDB操作符
+    pub fn operator(&self) -> &'static str {
+        match self {
+            CmpOp::Eq => "$eq",
+            CmpOp::Ne => "$ne",
+            CmpOp::Gt => "$gt",
+            CmpOp::Gte => "$gte",
+            CmpOp::Lt => "$lt",
+            CmpOp::Lte => "$lte",
+        }
+    }
+}
+
+/// 查询条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cond {
+    /// 字段比较
+    Cmp(String, CmpOp, QueryValue),
+    /// 字段取值在集合中
+    In(String, Vec<QueryValue>),
+    /// 字段取值不在集合中，空值不满足
+    NotIn(String, Vec<QueryValue>),
+    /// 全部满足，为空时恒真
+    And(Vec<Cond>),
+    /// 任一满足，为空时恒真
+    Or(Vec<Cond>),
+}
+
+impl Cond {
+    pub fn eq(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Eq, value.into())
+    }
+    pub fn ne(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Ne, value.into())
+    }
+    pub fn gt(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Gt, value.into())
+    }
+    pub fn gte(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Gte, value.into())
+    }
+    pub fn lt(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Lt, value.into())
+    }
+    pub fn lte(field: &str, value: impl Into<QueryValue>) -> Self {
+        Cond::Cmp(field.to_owned(), CmpOp::Lte, value.into())
+    }
+    pub fn is_in<T: Into<QueryValue>>(field: &str, values: impl IntoIterator<Item = T>) -> Self {
+        Cond::In(
+            field.to_owned(),
+            values.into_iter().map(Into::into).collect(),
+        )
+    }
+    pub fn not_in<T: Into<QueryValue>>(field: &str, values: impl IntoIterator<Item = T>) -> Self {
+        Cond::NotIn(
+            field.to_owned(),
+            values.into_iter().map(Into::into).collect(),
+        )
+    }
+
+    /// 解析MongoDB风格的查询条件，只支持模块说明中列出的操作符
+    pub fn from_mongo(filter: &Document) -> Result<Self> {
+        let mut conds = Vec::new();
+        for (key, value) in filter.iter() {
+            match key.as_str() {
+                "$and" | "$or" => {
+                    let docs = value.as_array().ok_or_else(|| {
+                        Error::Custom(format!("{} expect array, got: {:?}", key, value))
+                    })?;
+                    let mut sub = Vec::with_capacity(docs.len());
+                    for doc in docs {
+                        let doc = doc.as_document().ok_or_else(|| {
+                            Error::Custom(format!("{} expect document, got: {:?}", key, doc))
+                        })?;
+                        sub.push(Cond::from_mongo(doc)?);
+                    }
+                    conds.push(if key == "$and" {
+                        Cond::And(sub)
+                    } else {
+                        Cond::Or(sub)
+                    });
+                }
+                _ => match value {
+                    Bson::Document(ops) if is_operator(ops) => {
+                        for (op, v) in ops.iter() {
+                            conds.push(op_cond(key, op, v)?);
+                        }
+                    }
+                    _ => conds.push(op_cond(key, "$eq", value)?),
+                },
+            }
+        }
+        Ok(if conds.len() == 1 {
+            conds.remove(0)
+        } else {
+            Cond::And(conds)
+        })
+    }
+}
+
+fn is_operator(doc: &Document) -> bool {
+    doc.keys()
+        .next()
+        .map(|k| k.starts_with('$'))
+        .unwrap_or(false)
+}
+
+fn op_cond(field: &str, op: &str, value: &Bson) -> Result<Cond> {
+    let op = match op {
+        "$eq" => CmpOp::Eq,
+        "$ne" => CmpOp::Ne,
+        "$gt" => CmpOp::Gt,
+        "$gte" => CmpOp::Gte,
+        "$lt" => CmpOp::Lt,
+        "$lte" => CmpOp::Lte,
+        "$in" | "$nin" => {
+            let values = value
+                .as_array()
+                .ok_or_else(|| Error::Custom(format!("{} expect array, got: {:?}", op, value)))?
+                .iter()
+                .map(QueryValue::try_from)
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(if op == "$in" {
+                Cond::In(field.to_owned(), values)
+            } else {
+                Cond::NotIn(field.to_owned(), values)
+            });
+        }
+        _ => return Err(Error::NotImpl(format!("query operator {}", op))),
+    };
+    Ok(Cond::Cmp(
+        field.to_owned(),
+        op,
+        QueryValue::try_from(value)?,
+    ))
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// 查询，条件之间为且的关系
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    conds: Vec<Cond>,
+    mongo_filter: Option<Document>,
+    sort: Vec<(String, Order)>,
+    mongo_sort: Option<Document>,
+    limit: Option<i64>,
+    projection: Option<Vec<String>>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 增加条件
+    pub fn filter(mut self, cond: Cond) -> Self {
+        self.conds.push(cond);
+        self
+    }
+
+    /// 代码等于`code`
+    pub fn code(self, code: &str) -> Self {
+        self.filter(Cond::eq("code", code))
+    }
+
+    /// 代码为`codes`之一
+    pub fn codes<T: Into<QueryValue>>(self, codes: impl IntoIterator<Item = T>) -> Self {
+        self.filter(Cond::is_in("code", codes))
+    }
+
+    /// 日期在`[start, end]`之间，None表示不限
+    pub fn date_range(
+        mut self,
+        field: &str,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Self {
+        if let Some(start) = start {
+            self = self.filter(Cond::gte(field, start));
+        }
+        if let Some(end) = end {
+            self = self.filter(Cond::lte(field, end));
+        }
+        self
+    }
+
+    /// 增加排序字段，先增加的优先
+    pub fn sort(mut self, field: &str, order: Order) -> Self {
+        self.sort.push((field.to_owned(), order));
+        self
+    }
+
+    /// 最多返回的数量
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 只返回部分字段，一般用于`Loader::load_raw`，类型化的读取接口需要完整的字段
+    pub fn project<T: AsRef<str>>(mut self, fields: impl IntoIterator<Item = T>) -> Self {
+        self.projection = Some(fields.into_iter().map(|f| f.as_ref().to_owned()).collect());
+        self
+    }
+
+    /// 直接使用MongoDB风格的查询条件，与其他条件为且的关系
+    pub fn mongo_filter(mut self, filter: Document) -> Self {
+        self.mongo_filter = Some(filter);
+        self
+    }
+
+    /// 直接使用MongoDB风格的排序，排在`sort`增加的字段之后
+    pub fn mongo_sort(mut self, sort: Document) -> Self {
+        self.mongo_sort = Some(sort);
+        self
+    }
+
+    pub fn get_limit(&self) -> Option<i64> {
+        self.limit.filter(|l| *l > 0)
+    }
+
+    pub fn get_projection(&self) -> Option<&[String]> {
+        self.projection.as_deref()
+    }
+
+    /// 全部条件，MongoDB风格的条件解析后放在最后
+    pub fn conds(&self) -> Result<Vec<Cond>> {
+        let mut conds = self.conds.clone();
+        if let Some(filter) = &self.mongo_filter {
+            match Cond::from_mongo(filter)? {
+                Cond::And(sub) => conds.extend(sub),
+                cond => conds.push(cond),
+            }
+        }
+        Ok(conds)
+    }
+
+    /// 全部排序字段
+    pub fn orders(&self) -> Result<Vec<(String, Order)>> {
+        let mut orders = self.sort.clone();
+        if let Some(sort) = &self.mongo_sort {
+            for (key, value) in sort.iter() {
+                let order = match value {
+                    Bson::Int32(v) => *v as i64,
+                    Bson::Int64(v) => *v,
+                    Bson::Double(v) => *v as i64,
+                    _ => return Err(Error::Custom(format!("invalid sort value: {:?}", value))),
+                };
+                let order = if order < 0 { Order::Desc } else { Order::Asc };
+                orders.push((key.clone(), order));
+            }
+        }
+        Ok(orders)
+    }
+
+    /// 类型化的条件
+    pub(crate) fn typed_conds(&self) -> &[Cond] {
+        &self.conds
+    }
+
+    pub(crate) fn raw_filter(&self) -> Option<&Document> {
+        self.mongo_filter.as_ref()
+    }
+
+    pub(crate) fn typed_sort(&self) -> &[(String, Order)] {
+        &self.sort
+    }
+
+    pub(crate) fn raw_sort(&self) -> Option<&Document> {
+        self.mongo_sort.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use mongodb::bson::doc;
+
+    use super::{CmpOp, Cond, Order, Query, QueryValue};
+
+    #[test]
+    fn test_query() {
+        let start = NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+        let query = Query::new()
+            .codes(["sh600000", "sz000001"])
+            .date_range(
+                "trade_date",
+                Some(start.and_hms_opt(0, 0, 0).unwrap()),
+                None,
+            )
+            .mongo_filter(doc! {"close": {"$gt": 1.0, "$lte": 2}, "name": null})
+            .sort("trade_date", Order::Desc)
+            .mongo_sort(doc! {"code": 1})
+            .limit(0);
+        assert_eq!(
+            query.conds().unwrap(),
+            vec![
+                Cond::is_in("code", ["sh600000", "sz000001"]),
+                Cond::Cmp("trade_date".into(), CmpOp::Gte, QueryValue::Int(1677628800)),
+                Cond::gt("close", 1.0),
+                Cond::lte("close", 2),
+                Cond::eq("name", None::<i64>),
+            ]
+        );
+        assert_eq!(
+            query.orders().unwrap(),
+            vec![
+                ("trade_date".to_owned(), Order::Desc),
+                ("code".to_owned(), Order::Asc)
+            ]
+        );
+        assert_eq!(query.get_limit(), None);
+
+        assert_eq!(
+            Cond::from_mongo(&doc! {"$or": [{"code": "a"}, {"code": {"$nin": []}}]}).unwrap(),
+            Cond::Or(vec![
+                Cond::eq("code", "a"),
+                Cond::NotIn("code".into(), vec![])
+            ])
+        );
+        assert!(Cond::from_mongo(&doc! {"name": {"$regex": "ST"}}).is_err());
+        assert!(Query::new()
+            .mongo_sort(doc! {"code": "asc"})
+            .orders()
+            .is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! SQL数据库的按表存储实现，SQLite，MySQL共用
+//!
+//! 每张表保存json格式的原始数据(`data`列)，查询及索引用到的字段(`code`, `trade_date`等)
+//! 作为`data`的生成列。具体数据库只需实现`SqlDb`执行SQL语句。
+
+use async_trait::async_trait;
+
+use crate::{
+    store::{
+        query::Query,
+        table::{ColumnType, TableDb, TableDef, TABLES},
+    },
+    Error, Result,
+};
+
+mod query;
+pub(crate) use query::{delete_sql, select_sql};
+
+/// SQL方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Sqlite,
+    MySql,
+}
+
+/// SQL参数
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SqlValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl Dialect {
+    /// 从`data`中提取字段的表达式
+    fn extract(&self, name: &str) -> String {
+        match self {
+            Dialect::Sqlite => format!("(data ->> '$.{}')", name),
+            Dialect::MySql => format!("JSON_UNQUOTE(JSON_EXTRACT(data, '$.{}'))", name),
+        }
+    }
+
+    /// 建表及索引语句
+    pub fn create_table(&self, def: &TableDef) -> Vec<String> {
+        let mut sqls = Vec::new();
+        let index_keys = |keys: &[(&str, i32)]| {
+            let name: Vec<_> = keys.iter().map(|(k, _)| *k).collect();
+            let keys: Vec<_> = keys
+                .iter()
+                .map(|(k, o)| format!("{} {}", k, if *o < 0 { "DESC" } else { "ASC" }))
+                .collect();
+            (name.join("_"), keys.join(", "))
+        };
+        match self {
+            Dialect::Sqlite => {
+                let mut columns = vec![
+                    String::from("id INTEGER PRIMARY KEY AUTOINCREMENT"),
+                    String::from("data TEXT NOT NULL"),
+                ];
+                for (col, typ) in def.columns.iter() {
+                    let typ = match typ {
+                        ColumnType::Int => "INTEGER",
+                        ColumnType::Text => "TEXT",
+                    };
+                    columns.push(format!(
+                        "{} {} GENERATED ALWAYS AS {} VIRTUAL",
+                        col,
+                        typ,
+                        self.extract(col)
+                    ));
+                }
+                sqls.push(format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    def.name,
+                    columns.join(", ")
+                ));
+                sqls.push(format!(
+                    "CREATE UNIQUE INDEX IF NOT EXISTS uk_{} ON {} ({})",
+                    def.name,
+                    def.name,
+                    def.keys.join(", ")
+                ));
+                for keys in def.indexes.iter() {
+                    let (name, keys) = index_keys(keys);
+                    sqls.push(format!(
+                        "CREATE INDEX IF NOT EXISTS idx_{}_{} ON {} ({})",
+                        def.name, name, def.name, keys
+                    ));
+                }
+            }
+            Dialect::MySql => {
+                // MySQL没有CREATE INDEX IF NOT EXISTS，索引随表一起创建
+                let mut columns = vec![
+                    String::from("id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY"),
+                    String::from("data JSON NOT NULL"),
+                ];
+                for (col, typ) in def.columns.iter() {
+                    let typ = match typ {
+                        ColumnType::Int => "BIGINT",
+                        ColumnType::Text => "VARCHAR(64)",
+                    };
+                    columns.push(format!("{} {} AS ({}) STORED", col, typ, self.extract(col)));
+                }
+                columns.push(format!(
+                    "UNIQUE KEY uk_{} ({})",
+                    def.name,
+                    def.keys.join(", ")
+                ));
+                for keys in def.indexes.iter() {
+                    let (name, keys) = index_keys(keys);
+                    columns.push(format!("KEY idx_{}_{} ({})", def.name, name, keys));
+                }
+                sqls.push(format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({}) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4",
+                    def.name,
+                    columns.join(", ")
+                ));
+            }
+        }
+        sqls
+    }
+
+    /// 批量写入语句，唯一键冲突时覆盖旧数据
+    pub fn upsert(&self, def: &TableDef, rows: usize) -> String {
+        let holders = vec!["(?)"; rows].join(", ");
+        match self {
+            Dialect::Sqlite => format!(
+                "INSERT INTO {} (data) VALUES {} ON CONFLICT ({}) DO UPDATE SET data = excluded.data",
+                def.name,
+                holders,
+                def.keys.join(", ")
+            ),
+            Dialect::MySql => format!(
+                "INSERT INTO {} (data) VALUES {} ON DUPLICATE KEY UPDATE data = VALUES(data)",
+                def.name, holders
+            ),
+        }
+    }
+
+    /// 字段表达式，生成列直接使用列名，其他字段从`data`中提取
+    pub fn field(&self, def: &TableDef, name: &str) -> Result<String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::Custom(format!("invalid field name: {}", name)));
+        }
+        if def.columns.iter().any(|(c, _)| *c == name) {
+            return Ok(name.to_owned());
+        }
+        Ok(self.extract(name))
+    }
+}
+
+/// SQL数据库执行接口
+#[async_trait]
+pub(crate) trait SqlDb: Sync + Send {
+    fn dialect(&self) -> Dialect;
+
+    /// 执行语句，返回影响的行数
+    async fn execute(&self, sql: &str, params: Vec<SqlValue>) -> Result<u64>;
+
+    /// 查询，返回第一列的文本
+    async fn fetch(&self, sql: &str, params: Vec<SqlValue>) -> Result<Vec<String>>;
+
+    /// 在同一个事务中执行多条语句
+    async fn transaction(&self, stmts: Vec<(String, Vec<SqlValue>)>) -> Result<()>;
+}
+
+/// 数据库结构迁移，按版本顺序执行，已执行的版本记录在`rwq_schema`表中，不会重复执行
+const MIGRATIONS: &[fn(Dialect) -> Vec<String>] = &[
+    // 1: 创建全部表及索引
+    |dialect| {
+        TABLES
+            .iter()
+            .flat_map(|def| dialect.create_table(def))
+            .collect()
+    },
+];
+
+/// 创建或升级全部表及索引
+async fn create_schema<D: SqlDb + ?Sized>(db: &D) -> Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS rwq_schema (version INTEGER NOT NULL PRIMARY KEY)",
+        vec![],
+    )
+    .await?;
+    let version = db
+        .fetch(
+            "SELECT CAST(COALESCE(MAX(version), 0) AS CHAR) FROM rwq_schema",
+            vec![],
+        )
+        .await?
+        .first()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or_default();
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let version = i + 1;
+        log::info!("migrate schema to version {}", version);
+        let mut stmts: Vec<_> = migration(db.dialect())
+            .into_iter()
+            .map(|sql| (sql, vec![]))
+            .collect();
+        stmts.push((
+            String::from("INSERT INTO rwq_schema (version) VALUES (?)"),
+            vec![SqlValue::Int(version as i64)],
+        ));
+        db.transaction(stmts).await?;
+    }
+    Ok(())
+}
+
+/// 每条插入语句的行数
+const INSERT_CHUNK: usize = 500;
+
+#[async_trait]
+impl<D: SqlDb> TableDb for D {
+    async fn create_schema(&self) -> Result<()> {
+        create_schema(self).await
+    }
+
+    async fn find(&self, def: &'static TableDef, query: &Query) -> Result<Vec<String>> {
+        let (sql, params) = select_sql(self.dialect(), def, query)?;
+        self.fetch(&sql, params).await
+    }
+
+    async fn upsert(&self, def: &'static TableDef, rows: Vec<String>, del_old: bool) -> Result<()> {
+        let mut stmts = Vec::new();
+        if del_old {
+            stmts.push((format!("DELETE FROM {}", def.name), vec![]));
+        }
+        for chunk in rows.chunks(INSERT_CHUNK) {
+            let params = chunk.iter().map(|s| SqlValue::Text(s.clone())).collect();
+            stmts.push((self.dialect().upsert(def, chunk.len()), params));
+        }
+        self.transaction(stmts).await
+    }
+
+    async fn delete(&self, def: &'static TableDef, query: &Query) -> Result<u64> {
+        let (sql, params) = delete_sql(self.dialect(), def, query)?;
+        self.execute(&sql, params).await
+    }
+}
@@ -0,0 +1,252 @@
+//! 查询条件转换为SQL语句
+
+use crate::{
+    store::query::{CmpOp, Cond, Order, Query, QueryValue},
+    Error, Result,
+};
+
+use super::{Dialect, SqlValue, TableDef};
+
+/// 查询语句及参数
+pub(crate) fn select_sql(
+    dialect: Dialect,
+    def: &TableDef,
+    query: &Query,
+) -> Result<(String, Vec<SqlValue>)> {
+    let mut params = Vec::new();
+    let mut sql = format!("SELECT data FROM {}", def.name);
+    let cond = and_sql(dialect, def, &query.conds()?, &mut params)?;
+    if !cond.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&cond);
+    }
+    let order = order_sql(dialect, def, &query.orders()?)?;
+    if !order.is_empty() {
+        sql.push_str(" ORDER BY ");
+        sql.push_str(&order);
+    }
+    if let Some(limit) = query.get_limit() {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+    Ok((sql, params))
+}
+
+/// 删除语句及参数，排序及条数限制忽略
+pub(crate) fn delete_sql(
+    dialect: Dialect,
+    def: &TableDef,
+    query: &Query,
+) -> Result<(String, Vec<SqlValue>)> {
+    let mut params = Vec::new();
+    let mut sql = format!("DELETE FROM {}", def.name);
+    let cond = and_sql(dialect, def, &query.conds()?, &mut params)?;
+    if !cond.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&cond);
+    }
+    Ok((sql, params))
+}
+
+fn and_sql(
+    dialect: Dialect,
+    def: &TableDef,
+    conds: &[Cond],
+    params: &mut Vec<SqlValue>,
+) -> Result<String> {
+    let mut sqls = Vec::new();
+    for cond in conds {
+        let sql = cond_sql(dialect, def, cond, params)?;
+        if !sql.is_empty() {
+            sqls.push(sql);
+        }
+    }
+    Ok(sqls.join(" AND "))
+}
+
+fn cond_sql(
+    dialect: Dialect,
+    def: &TableDef,
+    cond: &Cond,
+    params: &mut Vec<SqlValue>,
+) -> Result<String> {
+    match cond {
+        Cond::And(conds) | Cond::Or(conds) => {
+            let is_and = matches!(cond, Cond::And(_));
+            let mut sub = Vec::new();
+            for cond in conds {
+                let sql = cond_sql(dialect, def, cond, params)?;
+                if !sql.is_empty() {
+                    sub.push(format!("({})", sql));
+                } else if !is_and {
+                    // 恒真的条件使OR恒真
+                    return Ok(String::new());
+                }
+            }
+            if sub.is_empty() {
+                return Ok(String::new());
+            }
+            let op = if is_and { " AND " } else { " OR " };
+            Ok(format!("({})", sub.join(op)))
+        }
+        Cond::In(field, values) | Cond::NotIn(field, values) => {
+            let is_in = matches!(cond, Cond::In(..));
+            if values.is_empty() {
+                // 空集合: IN 恒假，NOT IN 恒真
+                return Ok(String::from(if is_in { "1 = 0" } else { "1 = 1" }));
+            }
+            params.extend(values.iter().map(SqlValue::from));
+            let holders = vec!["?"; values.len()].join(", ");
+            let not = if is_in { "" } else { "NOT " };
+            Ok(format!(
+                "{} {}IN ({})",
+                dialect.field(def, field)?,
+                not,
+                holders
+            ))
+        }
+        Cond::Cmp(field, op, value) => {
+            let field = dialect.field(def, field)?;
+            if let QueryValue::Null = value {
+                return match op {
+                    CmpOp::Eq => Ok(format!("{} IS NULL", field)),
+                    CmpOp::Ne => Ok(format!("{} IS NOT NULL", field)),
+                    _ => Err(Error::Custom(format!(
+                        "{} null is not supported",
+                        op.operator()
+                    ))),
+                };
+            }
+            let cmp = match op {
+                CmpOp::Eq => "=",
+                CmpOp::Ne => "<>",
+                CmpOp::Gt => ">",
+                CmpOp::Gte => ">=",
+                CmpOp::Lt => "<",
+                CmpOp::Lte => "<=",
+            };
+            params.push(SqlValue::from(value));
+            Ok(format!("{} {} ?", field, cmp))
+        }
+    }
+}
+
+fn order_sql(dialect: Dialect, def: &TableDef, orders: &[(String, Order)]) -> Result<String> {
+    let mut sqls = Vec::new();
+    for (key, order) in orders.iter() {
+        let field = dialect.field(def, key)?;
+        sqls.push(format!(
+            "{} {}",
+            field,
+            if *order == Order::Desc { "DESC" } else { "ASC" }
+        ));
+    }
+    Ok(sqls.join(", "))
+}
+
+impl From<&QueryValue> for SqlValue {
+    fn from(value: &QueryValue) -> Self {
+        match value {
+            QueryValue::Null => SqlValue::Null,
+            QueryValue::Bool(v) => SqlValue::Int(*v as i64),
+            QueryValue::Int(v) => SqlValue::Int(*v),
+            QueryValue::Float(v) => SqlValue::Float(*v),
+            QueryValue::Text(v) => SqlValue::Text(v.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use mongodb::bson::doc;
+
+    use crate::store::{
+        query::{Cond, Order, Query},
+        sql::{Dialect, SqlValue},
+        table::table,
+        TAB_STOCK_DAILY,
+    };
+
+    use super::{delete_sql, select_sql};
+
+    #[test]
+    fn test_select_sql() {
+        let def = table(TAB_STOCK_DAILY).unwrap();
+        let query = Query::new()
+            .mongo_filter(doc! {"code": "sz000001", "trade_date": {"$gte": 100, "$lt": 200}, "close": {"$in": [1.0, 2.0]}})
+            .mongo_sort(doc! {"trade_date": -1})
+            .limit(10);
+        let (sql, params) = select_sql(Dialect::Sqlite, def, &query).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT data FROM stock_daily WHERE code = ? AND trade_date >= ? AND trade_date < ? \
+             AND (data ->> '$.close') IN (?, ?) ORDER BY trade_date DESC LIMIT 10"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlValue::Text("sz000001".into()),
+                SqlValue::Int(100),
+                SqlValue::Int(200),
+                SqlValue::Float(1.0),
+                SqlValue::Float(2.0),
+            ]
+        );
+
+        let query = Query::new().mongo_filter(
+            doc! {"$or": [{"code": "sz000001"}, {"code": {"$in": []}}], "name": null},
+        );
+        let (sql, params) = select_sql(Dialect::Sqlite, def, &query).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT data FROM stock_daily WHERE ((code = ?) OR (1 = 0)) AND (data ->> '$.name') IS NULL"
+        );
+        assert_eq!(params.len(), 1);
+
+        let query = Query::new().filter(Cond::Or(vec![
+            Cond::eq("code", "sz000001"),
+            Cond::not_in("code", Vec::<String>::new()),
+        ]));
+        let (sql, _) = select_sql(Dialect::Sqlite, def, &query).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT data FROM stock_daily WHERE ((code = ?) OR (1 = 1))"
+        );
+
+        let start = NaiveDate::from_ymd_opt(2023, 3, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let query = Query::new()
+            .code("sz000001")
+            .date_range("trade_date", Some(start), None)
+            .filter(Cond::gt("close", 1.0))
+            .sort("trade_date", Order::Asc)
+            .limit(1);
+        let (sql, params) = select_sql(Dialect::MySql, def, &query).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT data FROM stock_daily WHERE code = ? AND trade_date >= ? AND \
+             JSON_UNQUOTE(JSON_EXTRACT(data, '$.close')) > ? ORDER BY trade_date ASC LIMIT 1"
+        );
+        assert_eq!(params[1], SqlValue::Int(1677628800));
+
+        assert!(select_sql(
+            Dialect::Sqlite,
+            def,
+            &Query::new().filter(Cond::eq("code;", 1))
+        )
+        .is_err());
+        assert!(select_sql(
+            Dialect::Sqlite,
+            def,
+            &Query::new().mongo_filter(doc! {"name": {"$regex": "ST"}}),
+        )
+        .is_err());
+
+        let (sql, params) =
+            delete_sql(Dialect::Sqlite, def, &Query::new().code("sz000001")).unwrap();
+        assert_eq!(sql, "DELETE FROM stock_daily WHERE code = ?");
+        assert_eq!(params, vec![SqlValue::Text("sz000001".into())]);
+    }
+}
@@ -1,3 +1,3 @@
 mod sqlite;
 
-// pub(crate) use sqlite::SqliteStore;
\ No newline at end of file
+pub(crate) use sqlite::SqliteDb;
@@ -1,22 +1,184 @@
-// use std::path::PathBuf;
+use std::{path::Path, time::Duration};
 
-// use crate::store::Store;
-// use async_trait::async_trait;
+use async_trait::async_trait;
+use sqlx::{
+    query::Query,
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    Row, Sqlite, SqlitePool,
+};
 
+use crate::{
+    store::sql::{Dialect, SqlDb, SqlValue},
+    Error, Result,
+};
 
-// pub(crate) struct SqliteStore {
+/// SQLite数据库，文件不存在时自动创建
+pub(crate) struct SqliteDb {
+    pool: SqlitePool,
+}
 
-// }
+impl SqliteDb {
+    pub fn new(path: &Path) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(30));
+        let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+        Ok(Self { pool })
+    }
+}
 
-// impl SqliteStore {
-//     pub fn new(path: PathBuf) -> Self {
-//         Self {  }
-//     }
-// }
+fn bind(sql: &str, params: Vec<SqlValue>) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+    let mut query = sqlx::query(sql);
+    for param in params.into_iter() {
+        query = match param {
+            SqlValue::Null => query.bind(None::<String>),
+            SqlValue::Int(v) => query.bind(v),
+            SqlValue::Float(v) => query.bind(v),
+            SqlValue::Text(v) => query.bind(v),
+        };
+    }
+    query
+}
 
-// #[async_trait]
-// impl Store for SqliteStore {
-//     async fn syncer(&self) -> std::sync::Arc<Vec<Box<dyn crate::syncer::Syncer>>> {
-//         todo!()
-//     }
-// }
\ No newline at end of file
+fn sqlx_error(e: sqlx::Error) -> Error {
+    log::error!("sqlite error: {}", e);
+    Error::Custom(format!("sqlite error: {}", e))
+}
+
+#[async_trait]
+impl SqlDb for SqliteDb {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    async fn execute(&self, sql: &str, params: Vec<SqlValue>) -> Result<u64> {
+        let res = bind(sql, params)
+            .execute(&self.pool)
+            .await
+            .map_err(sqlx_error)?;
+        Ok(res.rows_affected())
+    }
+
+    async fn fetch(&self, sql: &str, params: Vec<SqlValue>) -> Result<Vec<String>> {
+        let rows = bind(sql, params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(sqlx_error)?;
+        rows.iter()
+            .map(|row| row.try_get::<String, _>(0).map_err(sqlx_error))
+            .collect()
+    }
+
+    async fn transaction(&self, stmts: Vec<(String, Vec<SqlValue>)>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(sqlx_error)?;
+        for (sql, params) in stmts.iter() {
+            bind(sql, params.clone())
+                .execute(&mut *tx)
+                .await
+                .map_err(sqlx_error)?;
+        }
+        tx.commit().await.map_err(sqlx_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::NaiveDate;
+    use mongodb::bson::doc;
+
+    use crate::store::{
+        table::{insert_many, query_one, TableDb, TableLoader},
+        Cond, Loader, Order, Query, TAB_STOCK_DAILY,
+    };
+
+    use super::SqliteDb;
+
+    fn bar(code: &str, day: u32, close: f32) -> rwqfetch::Bar {
+        rwqfetch::Bar {
+            code: code.to_owned(),
+            name: code.to_owned(),
+            trade_date: NaiveDate::from_ymd_opt(2023, 3, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            close,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_round_trip() {
+        let path = std::env::temp_dir().join(format!("rwqdata-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let db = Arc::new(SqliteDb::new(&path).unwrap());
+        db.create_schema().await.unwrap();
+        // 重复建表不报错
+        db.create_schema().await.unwrap();
+
+        let bars: Vec<_> = (1..=5)
+            .map(|d| bar("sh600000", d, d as f32))
+            .chain([bar("sz000001", 1, 10.0)])
+            .collect();
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars, false)
+            .await
+            .unwrap();
+        // 重复写入覆盖旧数据
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars[3..], false)
+            .await
+            .unwrap();
+
+        let latest: Option<rwqfetch::Bar> = query_one(
+            db.as_ref(),
+            TAB_STOCK_DAILY,
+            Query::new()
+                .code("sh600000")
+                .sort("trade_date", Order::Desc),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            latest.unwrap().trade_date.format("%Y%m%d").to_string(),
+            "20230305"
+        );
+
+        let start = bar("", 2, 0.0).trade_date;
+        let loader = TableLoader::new(db.clone());
+        let data = loader
+            .load_stock_daily(
+                Query::new()
+                    .code("sh600000")
+                    .date_range("trade_date", Some(start), None)
+                    .filter(Cond::lt("close", 5.0))
+                    .sort("trade_date", Order::Asc)
+                    .limit(2),
+            )
+            .await
+            .unwrap();
+        let close: Vec<_> = data.iter().map(|e| e.close).collect();
+        assert_eq!(close, vec![2.0, 3.0]);
+
+        let data = loader.load_stock_daily(Query::new()).await.unwrap();
+        assert_eq!(data.len(), 6);
+
+        let data = loader
+            .load_raw(
+                TAB_STOCK_DAILY,
+                Query::new()
+                    .mongo_filter(doc! {"code": "sz000001"})
+                    .project(["code", "close"]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            data,
+            vec![serde_json::json!({"code": "sz000001", "close": 10.0})]
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
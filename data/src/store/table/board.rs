@@ -0,0 +1,343 @@
+use std::{collections::HashSet, sync::Arc};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{
+        Query, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_INDEX, TAB_STOCK_INDUSTRY,
+        TAB_STOCK_INDUSTRY_DETAIL,
+    },
+    syncer::{retry, AsyncFunc, Syncer},
+    types::SyncData,
+    Error, Result,
+};
+
+use super::{insert_many, query, TableDb};
+
+/// 板块类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BoardType {
+    Industry,
+    Concept,
+}
+
+impl BoardType {
+    fn tab(&self) -> &'static str {
+        match self {
+            BoardType::Industry => TAB_STOCK_INDUSTRY,
+            BoardType::Concept => TAB_STOCK_CONCEPT,
+        }
+    }
+    fn detail_tab(&self) -> &'static str {
+        match self {
+            BoardType::Industry => TAB_STOCK_INDUSTRY_DETAIL,
+            BoardType::Concept => TAB_STOCK_CONCEPT_DETAIL,
+        }
+    }
+    /// 板块列表，(代码，名称)
+    async fn fetch_board(&self) -> Result<Vec<(String, String)>> {
+        let data = match self {
+            BoardType::Industry => rwqfetch::fetch_stock_industry()
+                .await?
+                .into_iter()
+                .map(|e| (e.code, e.name))
+                .collect(),
+            BoardType::Concept => rwqfetch::fetch_stock_concept()
+                .await?
+                .into_iter()
+                .map(|e| (e.code, e.name))
+                .collect(),
+        };
+        Ok(data)
+    }
+}
+
+async fn send(tx: &mpsc::Sender<SyncData>, data: SyncData) -> Result<()> {
+    tx.send(data).await.map_err(|e| {
+        log::error!("send data error {:?}", e);
+        Error::Custom(format!("send data error {:?}", e))
+    })
+}
+
+struct StockIndexAsyncFunc {}
+
+#[async_trait]
+impl AsyncFunc for StockIndexAsyncFunc {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let data = rwqfetch::fetch_stock_index(None).await?;
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let data: Vec<_> = data.into_values().collect();
+        Ok(Some(SyncData::StockIndex(data)))
+    }
+}
+
+/// 股票指标同步，每次全量覆盖
+pub(crate) struct StockIndexSyncer {
+    db: Arc<dyn TableDb>,
+}
+
+impl StockIndexSyncer {
+    pub fn new(db: Arc<dyn TableDb>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl Syncer for StockIndexSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        log::info!("start sync {}", TAB_STOCK_INDEX);
+        let data = retry(StockIndexAsyncFunc {}).await?;
+        if let Some(data) = data {
+            send(&tx, data).await?;
+        };
+        log::info!("done fetch {}", TAB_STOCK_INDEX);
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        if let SyncData::StockIndex(info) = data {
+            let len = info.len();
+            log::info!("start save {}, size={}", TAB_STOCK_INDEX, len);
+            insert_many(self.db.as_ref(), TAB_STOCK_INDEX, &info, true).await?;
+            log::info!("done save {}, size={}", TAB_STOCK_INDEX, len);
+        }
+        Ok(())
+    }
+}
+
+struct BoardAsyncFunc {
+    typ: BoardType,
+}
+
+#[async_trait]
+impl AsyncFunc for BoardAsyncFunc {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let data = match self.typ {
+            BoardType::Industry => SyncData::StockIndustry(rwqfetch::fetch_stock_industry().await?),
+            BoardType::Concept => SyncData::StockConcept(rwqfetch::fetch_stock_concept().await?),
+        };
+        Ok(Some(data))
+    }
+}
+
+/// 板块列表同步，只增加新的板块
+pub(crate) struct BoardSyncer {
+    db: Arc<dyn TableDb>,
+    typ: BoardType,
+}
+
+impl BoardSyncer {
+    pub fn new(db: Arc<dyn TableDb>, typ: BoardType) -> Self {
+        Self { db, typ }
+    }
+    async fn db_codes(&self) -> Result<HashSet<String>> {
+        let db = self.db.as_ref();
+        let tab = self.typ.tab();
+        let codes = match self.typ {
+            BoardType::Industry => query::<rwqfetch::StockIndustry>(db, tab, &Query::new())
+                .await?
+                .into_iter()
+                .map(|e| e.code)
+                .collect(),
+            BoardType::Concept => query::<rwqfetch::StockConcept>(db, tab, &Query::new())
+                .await?
+                .into_iter()
+                .map(|e| e.code)
+                .collect(),
+        };
+        Ok(codes)
+    }
+}
+
+#[async_trait]
+impl Syncer for BoardSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let tab = self.typ.tab();
+        log::info!("start fetch {}", tab);
+        let data = retry(BoardAsyncFunc { typ: self.typ }).await?;
+        if let Some(data) = data {
+            let set = self.db_codes().await?;
+            match data {
+                SyncData::StockIndustry(info) => {
+                    let data: Vec<_> = info
+                        .into_iter()
+                        .filter(|e| !set.contains(&e.code))
+                        .collect();
+                    if !data.is_empty() {
+                        send(&tx, SyncData::StockIndustry(data)).await?;
+                    }
+                }
+                SyncData::StockConcept(info) => {
+                    let data: Vec<_> = info
+                        .into_iter()
+                        .filter(|e| !set.contains(&e.code))
+                        .collect();
+                    if !data.is_empty() {
+                        send(&tx, SyncData::StockConcept(data)).await?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        log::info!("end fetch {}", tab);
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        let tab = self.typ.tab();
+        let db = self.db.as_ref();
+        match data {
+            SyncData::StockIndustry(info) => {
+                log::info!("start save {}, size={}", tab, info.len());
+                insert_many(db, tab, &info, false).await?;
+                log::info!("done save {}, size={}", tab, info.len());
+            }
+            SyncData::StockConcept(info) => {
+                log::info!("start save {}, size={}", tab, info.len());
+                insert_many(db, tab, &info, false).await?;
+                log::info!("done save {}, size={}", tab, info.len());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+struct BoardDetailAsyncFunc<'a> {
+    typ: BoardType,
+    code: &'a str,
+    name: &'a str,
+}
+
+#[async_trait]
+impl<'a> AsyncFunc for BoardDetailAsyncFunc<'a> {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let (code, name) = (Some(self.code), Some(self.name));
+        let data = match self.typ {
+            BoardType::Industry => SyncData::StockIndustryDetail(
+                rwqfetch::fetch_stock_industry_detail(code, name).await?,
+            ),
+            BoardType::Concept => SyncData::StockConceptDetail(
+                rwqfetch::fetch_stock_concept_detail(code, name).await?,
+            ),
+        };
+        Ok(Some(data))
+    }
+}
+
+/// 板块成分股同步，只增加新的成分股
+pub(crate) struct BoardDetailSyncer {
+    db: Arc<dyn TableDb>,
+    typ: BoardType,
+}
+
+impl BoardDetailSyncer {
+    pub fn new(db: Arc<dyn TableDb>, typ: BoardType) -> Self {
+        Self { db, typ }
+    }
+    async fn boards(&self) -> Result<Vec<(String, String)>> {
+        let db = self.db.as_ref();
+        let tab = self.typ.tab();
+        let boards: Vec<_> = match self.typ {
+            BoardType::Industry => query::<rwqfetch::StockIndustry>(db, tab, &Query::new())
+                .await?
+                .into_iter()
+                .map(|e| (e.code, e.name))
+                .collect(),
+            BoardType::Concept => query::<rwqfetch::StockConcept>(db, tab, &Query::new())
+                .await?
+                .into_iter()
+                .map(|e| (e.code, e.name))
+                .collect(),
+        };
+        if boards.is_empty() {
+            return self.typ.fetch_board().await;
+        }
+        Ok(boards)
+    }
+    async fn db_stock_codes(&self, code: &str) -> Result<HashSet<String>> {
+        let db = self.db.as_ref();
+        let tab = self.typ.detail_tab();
+        let codes = match self.typ {
+            BoardType::Industry => {
+                query::<rwqfetch::StockIndustryDetail>(db, tab, &Query::new().code(code))
+                    .await?
+                    .into_iter()
+                    .map(|e| e.stock_code)
+                    .collect()
+            }
+            BoardType::Concept => {
+                query::<rwqfetch::StockConceptDetail>(db, tab, &Query::new().code(code))
+                    .await?
+                    .into_iter()
+                    .map(|e| e.stock_code)
+                    .collect()
+            }
+        };
+        Ok(codes)
+    }
+}
+
+#[async_trait]
+impl Syncer for BoardDetailSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let tab = self.typ.detail_tab();
+        for (code, name) in self.boards().await?.iter() {
+            log::info!("start sync {}({}) {}", name, code, tab);
+            let func = BoardDetailAsyncFunc {
+                typ: self.typ,
+                code,
+                name,
+            };
+            let data = retry(func).await?;
+            if let Some(data) = data {
+                let set = self.db_stock_codes(code).await?;
+                match data {
+                    SyncData::StockIndustryDetail(info) => {
+                        let data: Vec<_> = info
+                            .into_iter()
+                            .filter(|e| !set.contains(&e.stock_code))
+                            .collect();
+                        if !data.is_empty() {
+                            send(&tx, SyncData::StockIndustryDetail(data)).await?;
+                        }
+                    }
+                    SyncData::StockConceptDetail(info) => {
+                        let data: Vec<_> = info
+                            .into_iter()
+                            .filter(|e| !set.contains(&e.stock_code))
+                            .collect();
+                        if !data.is_empty() {
+                            send(&tx, SyncData::StockConceptDetail(data)).await?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            log::info!("end fetch {}({}) {}", name, code, tab);
+        }
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        let tab = self.typ.detail_tab();
+        let db = self.db.as_ref();
+        match data {
+            SyncData::StockIndustryDetail(info) => {
+                log::info!("start save {}, size={}", tab, info.len());
+                insert_many(db, tab, &info, false).await?;
+                log::info!("done save {}, size={}", tab, info.len());
+            }
+            SyncData::StockConceptDetail(info) => {
+                log::info!("start save {}, size={}", tab, info.len());
+                insert_many(db, tab, &info, false).await?;
+                log::info!("done save {}, size={}", tab, info.len());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
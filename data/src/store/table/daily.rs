@@ -0,0 +1,397 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use rwqfetch::{BarFreq, StockInfo};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{
+        peer_latest, Cache, Order, Query, TAB_BOND_DAILY, TAB_FUND_DAILY, TAB_FUND_NET,
+        TAB_INDEX_DAILY, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY, TAB_STOCK_DAILY,
+        TAB_STOCK_INDUSTRY, TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_MARGIN,
+    },
+    syncer::{need_to_start, retry, AsyncFunc, Syncer},
+    types::{Resync, SyncData},
+    Error, Result,
+};
+
+use super::{query, query_one, save_codes, TableDb};
+
+/// 按日增量同步的数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DailyType {
+    Bond,
+    Fund,
+    FundNet,
+    Index,
+    Stock,
+    StockMargin,
+    Concept,
+    Industry,
+}
+
+impl DailyType {
+    fn tab(&self) -> &'static str {
+        match self {
+            DailyType::Bond => TAB_BOND_DAILY,
+            DailyType::Fund => TAB_FUND_DAILY,
+            DailyType::FundNet => TAB_FUND_NET,
+            DailyType::Index => TAB_INDEX_DAILY,
+            DailyType::Stock => TAB_STOCK_DAILY,
+            DailyType::StockMargin => TAB_STOCK_MARGIN,
+            DailyType::Concept => TAB_STOCK_CONCEPT_DAILY,
+            DailyType::Industry => TAB_STOCK_INDUSTRY_DAILY,
+        }
+    }
+}
+
+/// 同步的代码，可转债需要正股信息
+#[derive(Debug, Clone, Default)]
+struct DailyCode {
+    code: String,
+    name: String,
+    stock_code: String,
+    stock_name: String,
+}
+
+impl From<&StockInfo> for DailyCode {
+    fn from(info: &StockInfo) -> Self {
+        Self {
+            code: info.code.clone(),
+            name: info.name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 最新一条数据的日期
+#[derive(Deserialize)]
+struct Latest {
+    trade_date: i64,
+}
+
+struct DailyAsyncFunc<'a> {
+    typ: DailyType,
+    code: &'a DailyCode,
+    start: Option<NaiveDate>,
+}
+
+#[async_trait]
+impl<'a> AsyncFunc for DailyAsyncFunc<'a> {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let (code, name, start) = (self.code.code.as_str(), self.code.name.as_str(), self.start);
+        let freq = Some(BarFreq::Daily);
+        let data = match self.typ {
+            DailyType::Bond => rwqfetch::fetch_bond_bar(
+                code,
+                name,
+                self.code.stock_code.as_str(),
+                self.code.stock_name.as_str(),
+                freq,
+                start,
+                None,
+                true,
+            )
+            .await?
+            .bars
+            .map(SyncData::BondBar),
+            DailyType::Fund => rwqfetch::fetch_fund_bar(code, Some(name), freq, start, None, true)
+                .await?
+                .bars
+                .map(SyncData::FundBar),
+            DailyType::FundNet => {
+                let data = rwqfetch::fetch_fund_net(code, Some(name), start, None).await?;
+                Some(data).filter(|d| !d.is_empty()).map(SyncData::FundNet)
+            }
+            DailyType::Index => {
+                rwqfetch::fetch_index_bar(code, Some(name), freq, start, None, true)
+                    .await?
+                    .bars
+                    .map(SyncData::IndexBar)
+            }
+            DailyType::Stock => {
+                rwqfetch::fetch_stock_bar(code, Some(name), freq, start, None, true)
+                    .await?
+                    .bars
+                    .map(SyncData::StockBar)
+            }
+            DailyType::StockMargin => {
+                let data = rwqfetch::fetch_stock_margin(code, start, None).await?;
+                Some(data)
+                    .filter(|d| !d.is_empty())
+                    .map(SyncData::StockMargin)
+            }
+            DailyType::Concept => {
+                rwqfetch::fetch_stock_concept_daily(code, Some(name), start, None, true)
+                    .await?
+                    .bars
+                    .map(SyncData::StockConceptBar)
+            }
+            DailyType::Industry => {
+                rwqfetch::fetch_stock_industry_daily(code, Some(name), start, None, true)
+                    .await?
+                    .bars
+                    .map(SyncData::StockIndustryBar)
+            }
+        };
+        Ok(data)
+    }
+}
+
+/// 按日增量同步，从数据库中最新一条数据的下一个交易日开始获取，按`resync`重新获取最近的数据
+pub(crate) struct DailySyncer {
+    cache: Arc<RwLock<Cache>>,
+    db: Arc<dyn TableDb>,
+    typ: DailyType,
+    resync: Resync,
+    /// 股票，融资融券按代码拆分到多个任务，其他类型为空
+    codes: Vec<StockInfo>,
+    task_n: usize,
+}
+
+impl DailySyncer {
+    pub fn new(
+        db: Arc<dyn TableDb>,
+        cache: Arc<RwLock<Cache>>,
+        typ: DailyType,
+        resync: Resync,
+    ) -> Self {
+        Self::with_codes(db, cache, typ, resync, vec![], 0)
+    }
+
+    pub fn with_codes(
+        db: Arc<dyn TableDb>,
+        cache: Arc<RwLock<Cache>>,
+        typ: DailyType,
+        resync: Resync,
+        codes: Vec<StockInfo>,
+        task_n: usize,
+    ) -> Self {
+        Self {
+            cache,
+            db,
+            typ,
+            resync,
+            codes,
+            task_n,
+        }
+    }
+
+    async fn codes(&self) -> Result<Vec<DailyCode>> {
+        let codes = match self.typ {
+            DailyType::Bond => {
+                let cache = self.cache.read().unwrap();
+                cache
+                    .bond_info()
+                    .iter()
+                    .flatten()
+                    .map(|(_, v)| DailyCode {
+                        code: v.code.clone(),
+                        name: v.name.clone(),
+                        stock_code: v.stock_code.clone(),
+                        stock_name: v.stock_name.clone(),
+                    })
+                    .collect()
+            }
+            DailyType::Fund | DailyType::FundNet => {
+                let cache = self.cache.read().unwrap();
+                cache
+                    .fund_info()
+                    .iter()
+                    .flatten()
+                    .map(|(_, v)| DailyCode {
+                        code: v.code.clone(),
+                        name: v.name.clone(),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            DailyType::Index => {
+                let cache = self.cache.read().unwrap();
+                cache
+                    .index_info()
+                    .iter()
+                    .flatten()
+                    .map(|(_, v)| v.into())
+                    .collect()
+            }
+            DailyType::Stock | DailyType::StockMargin => {
+                self.codes.iter().map(|v| v.into()).collect()
+            }
+            DailyType::Concept => {
+                let mut concept: Vec<rwqfetch::StockConcept> =
+                    query(self.db.as_ref(), TAB_STOCK_CONCEPT, &Query::new()).await?;
+                if concept.is_empty() {
+                    concept = rwqfetch::fetch_stock_concept().await?;
+                }
+                concept
+                    .into_iter()
+                    .map(|v| DailyCode {
+                        code: v.code,
+                        name: v.name,
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            DailyType::Industry => {
+                let mut industry: Vec<rwqfetch::StockIndustry> =
+                    query(self.db.as_ref(), TAB_STOCK_INDUSTRY, &Query::new()).await?;
+                if industry.is_empty() {
+                    industry = rwqfetch::fetch_stock_industry().await?;
+                }
+                industry
+                    .into_iter()
+                    .map(|v| DailyCode {
+                        code: v.code,
+                        name: v.name,
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+        };
+        Ok(codes)
+    }
+}
+
+#[async_trait]
+impl Syncer for DailySyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let tab = self.typ.tab();
+        for info in self.codes().await?.iter() {
+            if self.cache.read().unwrap().is_done(tab, info.code.as_str()) {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab
+                );
+                continue;
+            }
+            log::info!(
+                "prepare sync {}({}) {}, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                self.task_n
+            );
+            let latest: Option<Latest> = query_one(
+                self.db.as_ref(),
+                tab,
+                Query::new()
+                    .code(&info.code)
+                    .sort("trade_date", Order::Desc),
+            )
+            .await?;
+            let latest = latest
+                .and_then(|b| DateTime::from_timestamp(b.trade_date, 0))
+                .map(|dt| dt.naive_utc());
+            let latest = peer_latest(&self.cache, tab, info.code.as_str(), latest).await;
+            let start = {
+                let latest = latest.map(|dt| dt.date());
+                let cache = self.cache.read().unwrap();
+                Some(cache.sync_start(info.code.as_str(), latest, &self.resync))
+            };
+            if !need_to_start(&start) {
+                log::info!(
+                    "{}({}) {} is the newest, task#{}",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab,
+                    self.task_n
+                );
+                continue;
+            }
+
+            log::info!(
+                "start fetch {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+            let func = DailyAsyncFunc {
+                typ: self.typ,
+                code: info,
+                start,
+            };
+            let data = retry(func).await?;
+            if let Some(data) = data {
+                tx.send(data).await.map_err(|e| {
+                    log::error!("send data error {:?}", e);
+                    Error::Custom(format!("send data error {:?}", e))
+                })?;
+            };
+            log::info!(
+                "end fetch {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        let tab = self.typ.tab();
+        let (code, len) = match &data {
+            SyncData::BondBar(info)
+            | SyncData::FundBar(info)
+            | SyncData::IndexBar(info)
+            | SyncData::StockBar(info)
+            | SyncData::StockConceptBar(info)
+            | SyncData::StockIndustryBar(info) => {
+                (info.first().map(|e| e.code.clone()), info.len())
+            }
+            SyncData::FundNet(info) => (info.first().map(|e| e.code.clone()), info.len()),
+            SyncData::StockMargin(info) => (info.first().map(|e| e.code.clone()), info.len()),
+            _ => return Ok(()),
+        };
+        let code = match code {
+            Some(code) => code,
+            None => return Ok(()),
+        };
+        log::info!(
+            "start save {} {}, size={}, task#{}",
+            code,
+            tab,
+            len,
+            self.task_n
+        );
+        let (db, resync) = (self.db.as_ref(), &self.resync);
+        match data {
+            SyncData::BondBar(info)
+            | SyncData::FundBar(info)
+            | SyncData::IndexBar(info)
+            | SyncData::StockBar(info)
+            | SyncData::StockConceptBar(info)
+            | SyncData::StockIndustryBar(info) => {
+                save_codes(db, tab, resync, &info, |e| e.code.as_str()).await?
+            }
+            SyncData::FundNet(info) => {
+                save_codes(db, tab, resync, &info, |e| e.code.as_str()).await?
+            }
+            SyncData::StockMargin(info) => {
+                save_codes(db, tab, resync, &info, |e| e.code.as_str()).await?
+            }
+            _ => {}
+        }
+        log::info!(
+            "done save {} {}, size={}, task#{}",
+            code,
+            tab,
+            len,
+            self.task_n
+        );
+        Ok(())
+    }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(self.typ.tab())
+    }
+}
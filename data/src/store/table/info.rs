@@ -0,0 +1,119 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{Cache, TAB_BOND_INFO, TAB_FUND_INFO, TAB_INDEX_INFO, TAB_STOCK_INFO},
+    syncer::Syncer,
+    types::SyncData,
+    Error, Result,
+};
+
+use super::{insert_many, TableDb};
+
+/// 基本信息类型，数据来自初始化时的缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InfoType {
+    Bond,
+    Fund,
+    Index,
+    Stock,
+}
+
+impl InfoType {
+    fn tab(&self) -> &'static str {
+        match self {
+            InfoType::Bond => TAB_BOND_INFO,
+            InfoType::Fund => TAB_FUND_INFO,
+            InfoType::Index => TAB_INDEX_INFO,
+            InfoType::Stock => TAB_STOCK_INFO,
+        }
+    }
+}
+
+/// 基本信息同步，每次全量覆盖
+pub(crate) struct InfoSyncer {
+    cache: Arc<RwLock<Cache>>,
+    db: Arc<dyn TableDb>,
+    typ: InfoType,
+}
+
+impl InfoSyncer {
+    pub fn new(db: Arc<dyn TableDb>, cache: Arc<RwLock<Cache>>, typ: InfoType) -> Self {
+        Self { db, cache, typ }
+    }
+}
+
+#[async_trait]
+impl Syncer for InfoSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let data = {
+            let cache = self.cache.read().unwrap();
+            match self.typ {
+                InfoType::Bond => SyncData::BondInfo(
+                    cache
+                        .bond_info()
+                        .iter()
+                        .flatten()
+                        .map(|(_, v)| v.clone())
+                        .collect(),
+                ),
+                InfoType::Fund => SyncData::FundInfo(
+                    cache
+                        .fund_info()
+                        .iter()
+                        .flatten()
+                        .map(|(_, v)| v.clone())
+                        .collect(),
+                ),
+                InfoType::Index => SyncData::IndexInfo(
+                    cache
+                        .index_info()
+                        .iter()
+                        .flatten()
+                        .map(|(_, v)| v.clone())
+                        .collect(),
+                ),
+                InfoType::Stock => SyncData::StockInfo(
+                    cache
+                        .stock_info()
+                        .iter()
+                        .flatten()
+                        .map(|(_, v)| v.clone())
+                        .collect(),
+                ),
+            }
+        };
+        tx.send(data).await.map_err(|e| {
+            log::error!("send data error {:?}", e);
+            Error::Custom(format!("send data error {:?}", e))
+        })?;
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        let tab = self.typ.tab();
+        let db = self.db.as_ref();
+        let len = match &data {
+            SyncData::BondInfo(info) => info.len(),
+            SyncData::FundInfo(info) => info.len(),
+            SyncData::IndexInfo(info) | SyncData::StockInfo(info) => info.len(),
+            _ => return Ok(()),
+        };
+        if len == 0 {
+            return Ok(());
+        }
+        log::info!("start save {}, size={}", tab, len);
+        match data {
+            SyncData::BondInfo(info) => insert_many(db, tab, &info, true).await?,
+            SyncData::FundInfo(info) => insert_many(db, tab, &info, true).await?,
+            SyncData::IndexInfo(info) | SyncData::StockInfo(info) => {
+                insert_many(db, tab, &info, true).await?
+            }
+            _ => {}
+        }
+        log::info!("done save {}, size={}", tab, len);
+        Ok(())
+    }
+}
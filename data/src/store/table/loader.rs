@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rwqfetch::BarFreq;
+
+use crate::{
+    store::{
+        minute_tab, DataType, Loader, Query, TAB_BOND_DAILY, TAB_BOND_INFO, TAB_FUND_DAILY,
+        TAB_FUND_INFO, TAB_FUND_NET, TAB_INDEX_DAILY, TAB_INDEX_INFO, TAB_STOCK_CONCEPT,
+        TAB_STOCK_CONCEPT_DAILY, TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_DAILY, TAB_STOCK_INDEX,
+        TAB_STOCK_INDUSTRY, TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_INDUSTRY_DETAIL, TAB_STOCK_INFO,
+        TAB_STOCK_MARGIN, TAB_STOCK_YJBB, TAB_TRADE_DATE,
+    },
+    Result,
+};
+
+use super::TableDb;
+
+pub(crate) struct TableLoader {
+    db: Arc<dyn TableDb>,
+}
+
+impl TableLoader {
+    pub fn new(db: Arc<dyn TableDb>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl Loader for TableLoader {
+    async fn init(&mut self) -> Result<()> {
+        self.db.create_schema().await
+    }
+    async fn load_raw(&self, tab: &str, query: Query) -> Result<Vec<serde_json::Value>> {
+        super::query(self.db.as_ref(), tab, &query).await
+    }
+    async fn load_trade_date(&self, query: Query) -> Result<Vec<rwqfetch::TradeDate>> {
+        super::query(self.db.as_ref(), TAB_TRADE_DATE, &query).await
+    }
+    async fn load_bond_info(&self, query: Query) -> Result<Vec<rwqfetch::BondInfo>> {
+        super::query(self.db.as_ref(), TAB_BOND_INFO, &query).await
+    }
+    async fn load_bond_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_BOND_DAILY, &query).await
+    }
+
+    async fn load_fund_info(&self, query: Query) -> Result<Vec<rwqfetch::FundInfo>> {
+        super::query(self.db.as_ref(), TAB_FUND_INFO, &query).await
+    }
+    async fn load_fund_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_FUND_DAILY, &query).await
+    }
+    async fn load_fund_net(&self, query: Query) -> Result<Vec<rwqfetch::FundNet>> {
+        super::query(self.db.as_ref(), TAB_FUND_NET, &query).await
+    }
+
+    async fn load_index_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>> {
+        super::query(self.db.as_ref(), TAB_INDEX_INFO, &query).await
+    }
+
+    async fn load_index_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_INDEX_DAILY, &query).await
+    }
+
+    async fn load_stock_info(&self, query: Query) -> Result<Vec<rwqfetch::StockInfo>> {
+        super::query(self.db.as_ref(), TAB_STOCK_INFO, &query).await
+    }
+
+    async fn load_stock_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_STOCK_DAILY, &query).await
+    }
+
+    async fn load_stock_index(&self, query: Query) -> Result<Vec<rwqfetch::StockIndex>> {
+        super::query(self.db.as_ref(), TAB_STOCK_INDEX, &query).await
+    }
+    async fn load_stock_industry(&self, query: Query) -> Result<Vec<rwqfetch::StockIndustry>> {
+        super::query(self.db.as_ref(), TAB_STOCK_INDUSTRY, &query).await
+    }
+
+    async fn load_stock_industry_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_STOCK_INDUSTRY_DAILY, &query).await
+    }
+    async fn load_stock_industry_detail(
+        &self,
+        query: Query,
+    ) -> Result<Vec<rwqfetch::StockIndustryDetail>> {
+        super::query(self.db.as_ref(), TAB_STOCK_INDUSTRY_DETAIL, &query).await
+    }
+
+    async fn load_stock_concept(&self, query: Query) -> Result<Vec<rwqfetch::StockConcept>> {
+        super::query(self.db.as_ref(), TAB_STOCK_CONCEPT, &query).await
+    }
+
+    async fn load_stock_concept_daily(&self, query: Query) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), TAB_STOCK_CONCEPT_DAILY, &query).await
+    }
+    async fn load_stock_concept_detail(
+        &self,
+        query: Query,
+    ) -> Result<Vec<rwqfetch::StockConceptDetail>> {
+        super::query(self.db.as_ref(), TAB_STOCK_CONCEPT_DETAIL, &query).await
+    }
+
+    async fn load_stock_yjbb(&self, query: Query) -> Result<Vec<rwqfetch::StockYJBB>> {
+        super::query(self.db.as_ref(), TAB_STOCK_YJBB, &query).await
+    }
+
+    async fn load_stock_margin(&self, query: Query) -> Result<Vec<rwqfetch::StockMargin>> {
+        super::query(self.db.as_ref(), TAB_STOCK_MARGIN, &query).await
+    }
+
+    async fn load_minute(
+        &self,
+        typ: DataType,
+        freq: BarFreq,
+        query: Query,
+    ) -> Result<Vec<rwqfetch::Bar>> {
+        super::query(self.db.as_ref(), minute_tab(typ, freq)?, &query).await
+    }
+}
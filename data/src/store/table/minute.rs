@@ -0,0 +1,190 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{
+        minute::{minute_start, retain_start, MinuteAsyncFunc, MinuteCode},
+        peer_delete, peer_latest, Cache, Cond, Order, Query,
+    },
+    syncer::{need_to_start, retry, Syncer},
+    types::{MinuteSync, Resync, SyncData},
+    Error, Result,
+};
+
+use super::{delete_many, query_one, save_codes, TableDb};
+
+/// 最新一条数据的时间
+#[derive(Deserialize)]
+struct Latest {
+    trade_date: i64,
+}
+
+/// 分钟线增量同步，按品种和频率保存到不同的表，同步时删除保留交易日之前的数据
+pub(crate) struct MinuteSyncer {
+    cache: Arc<RwLock<Cache>>,
+    db: Arc<dyn TableDb>,
+    tab: &'static str,
+    minute: MinuteSync,
+    resync: Resync,
+    codes: Vec<MinuteCode>,
+    task_n: usize,
+}
+
+impl MinuteSyncer {
+    pub fn new(
+        db: Arc<dyn TableDb>,
+        cache: Arc<RwLock<Cache>>,
+        tab: &'static str,
+        minute: MinuteSync,
+        resync: Resync,
+        codes: Vec<MinuteCode>,
+        task_n: usize,
+    ) -> Self {
+        Self {
+            cache,
+            db,
+            tab,
+            minute,
+            resync,
+            codes,
+            task_n,
+        }
+    }
+}
+
+#[async_trait]
+impl Syncer for MinuteSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let (db, tab) = (self.db.as_ref(), self.tab);
+        let retain = {
+            let cache = self.cache.read().unwrap();
+            retain_start(&cache, &self.minute)
+        };
+        for info in self.codes.iter() {
+            if self.cache.read().unwrap().is_done(tab, info.code.as_str()) {
+                log::info!(
+                    "{}({}) {} is done, skip",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab
+                );
+                continue;
+            }
+            log::info!(
+                "prepare sync {}({}) {}, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                self.task_n
+            );
+            if let Some(retain) = retain {
+                let query = Query::new()
+                    .code(&info.code)
+                    .filter(Cond::lt("trade_date", retain));
+                delete_many(db, tab, &query).await?;
+                peer_delete(&self.cache, tab, info.code.as_str(), Some(retain)).await;
+            }
+            let latest: Option<Latest> = query_one(
+                db,
+                tab,
+                Query::new()
+                    .code(&info.code)
+                    .sort("trade_date", Order::Desc),
+            )
+            .await?;
+            let latest = latest
+                .and_then(|b| DateTime::from_timestamp(b.trade_date, 0))
+                .map(|dt| dt.naive_utc());
+            let latest = peer_latest(&self.cache, tab, info.code.as_str(), latest).await;
+            let start = {
+                let cache = self.cache.read().unwrap();
+                Some(minute_start(
+                    &cache,
+                    info.code.as_str(),
+                    latest,
+                    &self.minute,
+                    &self.resync,
+                ))
+            };
+            if !need_to_start(&start) {
+                log::info!(
+                    "{}({}) {} is the newest, task#{}",
+                    info.name.as_str(),
+                    info.code.as_str(),
+                    tab,
+                    self.task_n
+                );
+                continue;
+            }
+
+            log::info!(
+                "start fetch {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+            let func = MinuteAsyncFunc {
+                typ: self.minute.typ,
+                freq: self.minute.freq,
+                code: info,
+                start,
+            };
+            let data = retry(func).await?;
+            if let Some(data) = data {
+                tx.send(data).await.map_err(|e| {
+                    log::error!("send data error {:?}", e);
+                    Error::Custom(format!("send data error {:?}", e))
+                })?;
+            };
+            log::info!(
+                "end fetch {}({}) {}, start={:?}, end=None, task#{}",
+                info.name.as_str(),
+                info.code.as_str(),
+                tab,
+                &start,
+                self.task_n
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        if let SyncData::MinuteBar(info) = data {
+            let code = match info.first() {
+                Some(bar) => bar.code.clone(),
+                None => return Ok(()),
+            };
+            let len = info.len();
+            log::info!(
+                "start save {} {}, size={}, task#{}",
+                code,
+                self.tab,
+                len,
+                self.task_n
+            );
+            save_codes(self.db.as_ref(), self.tab, &self.resync, &info, |e| {
+                e.code.as_str()
+            })
+            .await?;
+            log::info!(
+                "done save {} {}, size={}, task#{}",
+                code,
+                self.tab,
+                len,
+                self.task_n
+            );
+        }
+        Ok(())
+    }
+
+    fn progress_tab(&self) -> Option<&'static str> {
+        Some(self.tab)
+    }
+}
@@ -0,0 +1,344 @@
+//! 按表存储的通用同步及读取实现，SQL数据库及文件存储共用
+//!
+//! 每条数据以json格式保存，与MongoDB存储的文档一一对应，查询条件为`Query`。
+//! 具体存储只需实现`TableDb`。
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    store::{
+        query::{Cond, Query},
+        TAB_BOND_DAILY, TAB_BOND_INFO, TAB_BOND_MIN1, TAB_BOND_MIN15, TAB_BOND_MIN30,
+        TAB_BOND_MIN5, TAB_BOND_MIN60, TAB_FUND_DAILY, TAB_FUND_INFO, TAB_FUND_MIN1,
+        TAB_FUND_MIN15, TAB_FUND_MIN30, TAB_FUND_MIN5, TAB_FUND_MIN60, TAB_FUND_NET,
+        TAB_INDEX_DAILY, TAB_INDEX_INFO, TAB_INDEX_MIN1, TAB_INDEX_MIN15, TAB_INDEX_MIN30,
+        TAB_INDEX_MIN5, TAB_INDEX_MIN60, TAB_STOCK_CONCEPT, TAB_STOCK_CONCEPT_DAILY,
+        TAB_STOCK_CONCEPT_DETAIL, TAB_STOCK_DAILY, TAB_STOCK_INDEX, TAB_STOCK_INDUSTRY,
+        TAB_STOCK_INDUSTRY_DAILY, TAB_STOCK_INDUSTRY_DETAIL, TAB_STOCK_INFO, TAB_STOCK_MARGIN,
+        TAB_STOCK_MIN1, TAB_STOCK_MIN15, TAB_STOCK_MIN30, TAB_STOCK_MIN5, TAB_STOCK_MIN60,
+        TAB_STOCK_YJBB, TAB_SYNC_CHECKPOINT, TAB_TRADE_DATE,
+    },
+    types::Resync,
+    Error, Result,
+};
+
+mod board;
+mod daily;
+mod info;
+mod minute;
+mod stock_yjbb;
+mod trade_date;
+
+mod loader;
+mod store;
+
+pub(crate) use loader::TableLoader;
+pub(crate) use store::TableStore;
+
+/// 生成列类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnType {
+    Int,
+    Text,
+}
+
+/// 表定义，`columns`为从`data`生成的列，`indexes`与`mongo_index.rs`的索引对应，-1为降序，
+/// `keys`为唯一键，重复写入时覆盖旧数据，`partition`为文件存储时的分区字段
+pub(crate) struct TableDef {
+    pub name: &'static str,
+    pub columns: &'static [(&'static str, ColumnType)],
+    pub indexes: &'static [&'static [(&'static str, i32)]],
+    pub keys: &'static [&'static str],
+    pub partition: Option<&'static str>,
+}
+
+const INFO_COLUMNS: &[(&str, ColumnType)] = &[("code", ColumnType::Text)];
+const INFO_INDEXES: &[&[(&str, i32)]] = &[&[("code", 1)]];
+const INFO_KEYS: &[&str] = &["code"];
+
+const DAILY_COLUMNS: &[(&str, ColumnType)] =
+    &[("code", ColumnType::Text), ("trade_date", ColumnType::Int)];
+const DAILY_INDEXES: &[&[(&str, i32)]] = &[
+    &[("trade_date", -1)],
+    &[("code", 1)],
+    &[("trade_date", -1), ("code", 1)],
+];
+const DAILY_KEYS: &[&str] = &["code", "trade_date"];
+
+const DETAIL_COLUMNS: &[(&str, ColumnType)] =
+    &[("code", ColumnType::Text), ("stock_code", ColumnType::Text)];
+const DETAIL_INDEXES: &[&[(&str, i32)]] = &[&[("code", 1), ("stock_code", 1)]];
+const DETAIL_KEYS: &[&str] = &["code", "stock_code"];
+
+const fn info_table(name: &'static str) -> TableDef {
+    TableDef {
+        name,
+        columns: INFO_COLUMNS,
+        indexes: INFO_INDEXES,
+        keys: INFO_KEYS,
+        partition: None,
+    }
+}
+
+const fn daily_table(name: &'static str) -> TableDef {
+    TableDef {
+        name,
+        columns: DAILY_COLUMNS,
+        indexes: DAILY_INDEXES,
+        keys: DAILY_KEYS,
+        partition: Some("code"),
+    }
+}
+
+const fn detail_table(name: &'static str) -> TableDef {
+    TableDef {
+        name,
+        columns: DETAIL_COLUMNS,
+        indexes: DETAIL_INDEXES,
+        keys: DETAIL_KEYS,
+        partition: None,
+    }
+}
+
+pub(crate) const TABLES: &[TableDef] = &[
+    TableDef {
+        name: TAB_TRADE_DATE,
+        columns: &[("trade_date", ColumnType::Int)],
+        indexes: &[&[("trade_date", -1)]],
+        keys: &["trade_date"],
+        partition: None,
+    },
+    TableDef {
+        name: TAB_SYNC_CHECKPOINT,
+        columns: &[
+            ("run", ColumnType::Text),
+            ("name", ColumnType::Text),
+            ("code", ColumnType::Text),
+        ],
+        indexes: &[&[("run", 1), ("name", 1), ("code", 1)]],
+        keys: &["run", "name", "code"],
+        partition: None,
+    },
+    // bond
+    info_table(TAB_BOND_INFO),
+    daily_table(TAB_BOND_DAILY),
+    // fund
+    info_table(TAB_FUND_INFO),
+    daily_table(TAB_FUND_DAILY),
+    daily_table(TAB_FUND_NET),
+    // index
+    info_table(TAB_INDEX_INFO),
+    daily_table(TAB_INDEX_DAILY),
+    // stock
+    info_table(TAB_STOCK_INFO),
+    daily_table(TAB_STOCK_DAILY),
+    daily_table(TAB_STOCK_INDEX),
+    daily_table(TAB_STOCK_MARGIN),
+    TableDef {
+        name: TAB_STOCK_YJBB,
+        columns: &[
+            ("code", ColumnType::Text),
+            ("year", ColumnType::Int),
+            ("season", ColumnType::Int),
+            ("season_date", ColumnType::Int),
+        ],
+        indexes: &[&[("code", 1)], &[("season_date", -1)]],
+        keys: &["code", "year", "season"],
+        partition: Some("year"),
+    },
+    info_table(TAB_STOCK_INDUSTRY),
+    daily_table(TAB_STOCK_INDUSTRY_DAILY),
+    detail_table(TAB_STOCK_INDUSTRY_DETAIL),
+    info_table(TAB_STOCK_CONCEPT),
+    daily_table(TAB_STOCK_CONCEPT_DAILY),
+    detail_table(TAB_STOCK_CONCEPT_DETAIL),
+    // minute，与日线的结构相同
+    daily_table(TAB_STOCK_MIN1),
+    daily_table(TAB_STOCK_MIN5),
+    daily_table(TAB_STOCK_MIN15),
+    daily_table(TAB_STOCK_MIN30),
+    daily_table(TAB_STOCK_MIN60),
+    daily_table(TAB_INDEX_MIN1),
+    daily_table(TAB_INDEX_MIN5),
+    daily_table(TAB_INDEX_MIN15),
+    daily_table(TAB_INDEX_MIN30),
+    daily_table(TAB_INDEX_MIN60),
+    daily_table(TAB_FUND_MIN1),
+    daily_table(TAB_FUND_MIN5),
+    daily_table(TAB_FUND_MIN15),
+    daily_table(TAB_FUND_MIN30),
+    daily_table(TAB_FUND_MIN60),
+    daily_table(TAB_BOND_MIN1),
+    daily_table(TAB_BOND_MIN5),
+    daily_table(TAB_BOND_MIN15),
+    daily_table(TAB_BOND_MIN30),
+    daily_table(TAB_BOND_MIN60),
+];
+
+/// 表定义
+pub(crate) fn table(tab: &str) -> Result<&'static TableDef> {
+    TABLES
+        .iter()
+        .find(|t| t.name == tab)
+        .ok_or_else(|| Error::Custom(format!("unknown table: {}", tab)))
+}
+
+/// 按表存储的数据库接口
+#[async_trait]
+pub(crate) trait TableDb: Sync + Send {
+    /// 创建或升级全部表及索引
+    async fn create_schema(&self) -> Result<()>;
+
+    /// 按条件查询，返回json格式的完整数据，投影由调用方处理
+    async fn find(&self, def: &'static TableDef, query: &Query) -> Result<Vec<String>>;
+
+    /// 写入json格式的数据，唯一键相同的覆盖旧数据，`del_old`为true时先清空表
+    async fn upsert(&self, def: &'static TableDef, rows: Vec<String>, del_old: bool) -> Result<()>;
+
+    /// 删除满足条件的数据，返回删除的条数
+    async fn delete(&self, def: &'static TableDef, query: &Query) -> Result<u64>;
+}
+
+pub(crate) async fn query<T>(db: &dyn TableDb, tab: &str, query: &Query) -> Result<Vec<T>>
+where
+    T: DeserializeOwned,
+{
+    let def = table(tab)?;
+    let rows = db.find(def, query).await?;
+    rows.iter()
+        .map(|s| {
+            let parsed = match query.get_projection() {
+                Some(fields) => {
+                    serde_json::from_str::<Map<String, Value>>(s).and_then(|mut row| {
+                        row.retain(|k, _| fields.contains(k));
+                        serde_json::from_value(Value::Object(row))
+                    })
+                }
+                None => serde_json::from_str(s),
+            };
+            parsed.map_err(|e| {
+                log::error!("parse {} data error: {}", tab, e);
+                Error::Custom(format!("parse {} data error: {}", tab, e))
+            })
+        })
+        .collect()
+}
+
+pub(crate) async fn query_one<T>(db: &dyn TableDb, tab: &str, query: Query) -> Result<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    let data = self::query(db, tab, &query.limit(1)).await?;
+    Ok(data.into_iter().next())
+}
+
+pub(crate) async fn insert_many<T>(
+    db: &dyn TableDb,
+    tab: &str,
+    info: &[T],
+    del_old: bool,
+) -> Result<()>
+where
+    T: Serialize,
+{
+    let def = table(tab)?;
+    let rows = info
+        .iter()
+        .map(|item| {
+            serde_json::to_string(item).map_err(|e| {
+                log::error!("serialize {} data error: {}", tab, e);
+                Error::Custom(format!("serialize {} data error: {}", tab, e))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    log::info!("insert into {}, {} items", tab, info.len());
+    db.upsert(def, rows, del_old).await
+}
+
+pub(crate) async fn delete_many(db: &dyn TableDb, tab: &str, query: &Query) -> Result<u64> {
+    let def = table(tab)?;
+    let count = db.delete(def, query).await?;
+    log::info!("delete {}, {} items", tab, count);
+    Ok(count)
+}
+
+#[derive(Deserialize)]
+struct TradeDate {
+    trade_date: i64,
+}
+
+/// 每次删除的交易日数
+const DELETE_CHUNK: usize = 500;
+
+/// 全量重新同步时替换代码的数据：先写入新数据，写入成功后再删除该代码不在新数据中的旧数据，
+/// 写入失败时保留原有数据
+pub(crate) async fn replace_code<T>(
+    db: &dyn TableDb,
+    tab: &str,
+    code: &str,
+    info: &[T],
+) -> Result<()>
+where
+    T: Serialize,
+{
+    insert_many(db, tab, info, false).await?;
+    let dates = info
+        .iter()
+        .map(|item| {
+            let date = serde_json::to_value(item)
+                .ok()
+                .and_then(|v| v.get("trade_date").and_then(Value::as_i64));
+            date.ok_or_else(|| Error::Custom(format!("{} data has no trade_date", tab)))
+        })
+        .collect::<Result<BTreeSet<_>>>()?;
+    let old: Vec<TradeDate> =
+        query(db, tab, &Query::new().code(code).project(["trade_date"])).await?;
+    let stale: Vec<_> = old
+        .into_iter()
+        .map(|d| d.trade_date)
+        .filter(|d| !dates.contains(d))
+        .collect();
+    for chunk in stale.chunks(DELETE_CHUNK) {
+        let query = Query::new()
+            .code(code)
+            .filter(Cond::is_in("trade_date", chunk.iter().copied()));
+        delete_many(db, tab, &query).await?;
+    }
+    Ok(())
+}
+
+/// 保存数据，同一批可包含多个代码，全量重新同步的代码按代码分别替换原有数据，
+/// 其他按代码及交易日覆盖写入
+pub(crate) async fn save_codes<T, F>(
+    db: &dyn TableDb,
+    tab: &str,
+    resync: &Resync,
+    info: &[T],
+    code: F,
+) -> Result<()>
+where
+    T: Serialize,
+    F: Fn(&T) -> &str,
+{
+    let mut full: BTreeMap<&str, Vec<&T>> = BTreeMap::new();
+    let mut rest = Vec::new();
+    for item in info.iter() {
+        let c = code(item);
+        if resync.is_full(c) {
+            full.entry(c).or_default().push(item);
+        } else {
+            rest.push(item);
+        }
+    }
+    if !rest.is_empty() {
+        insert_many(db, tab, &rest, false).await?;
+    }
+    for (code, info) in full.iter() {
+        replace_code(db, tab, code, info).await?;
+    }
+    Ok(())
+}
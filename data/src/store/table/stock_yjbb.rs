@@ -0,0 +1,153 @@
+use std::{collections::HashSet, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{Datelike, Local};
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{Cond, Order, Query, TAB_STOCK_YJBB},
+    syncer::{retry, AsyncFunc, Syncer},
+    types::SyncData,
+    Error, Result,
+};
+
+use super::{insert_many, query, query_one, TableDb};
+
+struct StockYJBBAsyncFunc {
+    year: u16,
+    season: u16,
+}
+
+#[async_trait]
+impl AsyncFunc for StockYJBBAsyncFunc {
+    async fn call(&self) -> Result<Option<SyncData>> {
+        let data = rwqfetch::fetch_stock_yjbb(self.year, self.season).await?;
+        if data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(SyncData::StockYJBB(data)))
+        }
+    }
+}
+
+/// 业绩报表同步，从数据库中最新的季度开始获取
+pub(crate) struct StockYJBBSyncer {
+    db: Arc<dyn TableDb>,
+}
+
+impl StockYJBBSyncer {
+    pub fn new(db: Arc<dyn TableDb>) -> Self {
+        Self { db }
+    }
+}
+
+/// 需要同步的(年份，季度)
+fn seasons(latest: Option<(u16, u16)>, n_year: u16) -> Vec<(u16, u16)> {
+    let mut s_vec = Vec::new();
+    match latest {
+        Some((year, season)) if year == n_year => {
+            for s in season..=4 {
+                s_vec.push((year, s));
+            }
+        }
+        Some((year, season)) => {
+            for y in year..=n_year {
+                for s in 1..=4 {
+                    if y == year && s < season {
+                        continue;
+                    }
+                    s_vec.push((y, s));
+                }
+            }
+        }
+        None => {
+            for y in 1991..=n_year {
+                for s in 1..=4 {
+                    s_vec.push((y, s));
+                }
+            }
+        }
+    }
+    s_vec
+}
+
+#[async_trait]
+impl Syncer for StockYJBBSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let yjbb: Option<rwqfetch::StockYJBB> = query_one(
+            self.db.as_ref(),
+            TAB_STOCK_YJBB,
+            Query::new().sort("season_date", Order::Desc),
+        )
+        .await?;
+
+        let n_year = Local::now().naive_local().year() as u16;
+        let s_vec = seasons(yjbb.map(|e| (e.year, e.season)), n_year);
+        for (year, season) in s_vec.into_iter() {
+            log::info!(
+                "start sync {} year={}, season={}",
+                TAB_STOCK_YJBB,
+                year,
+                season
+            );
+
+            let func = StockYJBBAsyncFunc { year, season };
+            if let Some(SyncData::StockYJBB(info)) = retry(func).await? {
+                let db_data: Vec<rwqfetch::StockYJBB> = query(
+                    self.db.as_ref(),
+                    TAB_STOCK_YJBB,
+                    &Query::new()
+                        .filter(Cond::eq("year", year as i32))
+                        .filter(Cond::eq("season", season as i32)),
+                )
+                .await?;
+
+                let set: HashSet<_> = db_data.into_iter().map(|e| e.code).collect();
+                let data: Vec<_> = info
+                    .into_iter()
+                    .filter(|e| !set.contains(&e.code))
+                    .collect();
+
+                if !data.is_empty() {
+                    tx.send(SyncData::StockYJBB(data)).await.map_err(|e| {
+                        log::error!("send data error {:?}", e);
+                        Error::Custom(format!("send data error {:?}", e))
+                    })?;
+                }
+            }
+            log::info!(
+                "end fetch {} year={}, season={}",
+                TAB_STOCK_YJBB,
+                year,
+                season
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        if let SyncData::StockYJBB(info) = data {
+            let len = info.len();
+            log::info!("start save {}, size={}", TAB_STOCK_YJBB, len);
+            insert_many(self.db.as_ref(), TAB_STOCK_YJBB, &info, false).await?;
+            log::info!("done save {}, size={}", TAB_STOCK_YJBB, len);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::seasons;
+
+    #[test]
+    fn test_seasons() {
+        assert_eq!(seasons(Some((2023, 3)), 2023), vec![(2023, 3), (2023, 4)]);
+        assert_eq!(
+            seasons(Some((2022, 4)), 2023),
+            vec![(2022, 4), (2023, 1), (2023, 2), (2023, 3), (2023, 4)]
+        );
+        assert_eq!(seasons(None, 1992).len(), 8);
+    }
+}
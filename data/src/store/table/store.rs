@@ -0,0 +1,693 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::{
+    store::{
+        minute::minute_codes, Cache, Cond, Order, Query, Store, TAB_BOND_INFO, TAB_FUND_INFO,
+        TAB_INDEX_INFO, TAB_STOCK_INFO, TAB_SYNC_CHECKPOINT, TAB_TRADE_DATE,
+    },
+    syncer::{Syncer, TypedSyncer},
+    types::{Checkpoint, MinuteSync, Resync, SyncDataType},
+    Error, Result,
+};
+
+use super::{
+    board::{BoardDetailSyncer, BoardSyncer, BoardType, StockIndexSyncer},
+    daily::{DailySyncer, DailyType},
+    delete_many,
+    info::{InfoSyncer, InfoType},
+    insert_many,
+    minute::MinuteSyncer,
+    query, query_one,
+    stock_yjbb::StockYJBBSyncer,
+    trade_date::TradeDateSyncer,
+    TableDb,
+};
+
+/// 最新一条数据的时间
+#[derive(Deserialize)]
+struct Latest {
+    trade_date: i64,
+}
+
+pub(crate) struct TableStore {
+    syncer_vec: Vec<TypedSyncer>,
+    cache: Arc<RwLock<Cache>>,
+
+    db: Arc<dyn TableDb>,
+    skip_basic: bool,
+    split_count: usize,
+    funcs: Option<Vec<SyncDataType>>,
+    resync: Resync,
+    minute: Vec<MinuteSync>,
+}
+
+impl TableStore {
+    pub fn new(
+        db: Arc<dyn TableDb>,
+        skip_basic: bool,
+        split_count: usize,
+        funcs: &Option<Vec<SyncDataType>>,
+        resync: &Resync,
+        minute: &[MinuteSync],
+    ) -> Self {
+        Self {
+            syncer_vec: Vec::new(),
+            cache: Arc::new(RwLock::new(Cache::new())),
+            db,
+            skip_basic,
+            split_count,
+            funcs: funcs.clone(),
+            resync: resync.clone(),
+            minute: minute.to_vec(),
+        }
+    }
+    async fn prepare_cache(&mut self) -> Result<()> {
+        let (bond_info, index_info, stock_info, fund_info, trade_date) = if !self.skip_basic {
+            log::info!("prepare cache data from remote");
+
+            log::info!("prepare cache bond_info");
+            let bond_info = rwqfetch::fetch_bond_info().await?;
+
+            log::info!("prepare cache index_info");
+            let index_info = rwqfetch::fetch_index_info().await?;
+
+            log::info!("prepare cache stock_info");
+            let stock_info = rwqfetch::fetch_stock_info(None).await?;
+
+            log::info!("prepare cache fund_info");
+            let fund_info = rwqfetch::fetch_fund_info().await?;
+
+            log::info!("prepare cache trade_date");
+            let trade_date = rwqfetch::fetch_trade_date().await?;
+
+            (bond_info, index_info, stock_info, fund_info, trade_date)
+        } else {
+            log::info!("prepare cache data from database");
+            let db = self.db.as_ref();
+
+            log::info!("prepare cache bond_info");
+            let bond_info = query(db, TAB_BOND_INFO, &Query::new()).await?;
+
+            log::info!("prepare cache index_info");
+            let index_info = query(db, TAB_INDEX_INFO, &Query::new()).await?;
+
+            log::info!("prepare cache stock_info");
+            let stock_info = query(db, TAB_STOCK_INFO, &Query::new()).await?;
+
+            log::info!("prepare cache fund_info");
+            let fund_info = query(db, TAB_FUND_INFO, &Query::new()).await?;
+
+            log::info!("prepare cache trade_date");
+            let trade_date_v: Vec<rwqfetch::TradeDate> =
+                query(db, TAB_TRADE_DATE, &Query::new()).await?;
+
+            let trade_date: BTreeSet<_> = trade_date_v.iter().map(|t| t.trade_date).collect();
+            (bond_info, index_info, stock_info, fund_info, trade_date)
+        };
+
+        {
+            let mut cache = self.cache.write().map_err(|e| {
+                log::error!("get cache write log error: {}", e);
+                Error::Custom(format!("get cache write log error: {}", e))
+            })?;
+            cache.cache_bond_info(&bond_info);
+            cache.cache_index_info(&index_info);
+            cache.cache_stock_info(&stock_info);
+            cache.cache_fund_info(&fund_info);
+            cache.cache_trade_date(&trade_date);
+        }
+        if bond_info.is_empty()
+            || index_info.is_empty()
+            || stock_info.is_empty()
+            || fund_info.is_empty()
+            || trade_date.is_empty()
+        {
+            Err(Error::Custom(
+                "cache info is empty, try not skip basic".to_owned(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+    fn add_syncer(&mut self, typ: &SyncDataType, syncer: impl Syncer + 'static) {
+        if self.funcs.as_ref().is_none_or(|funcs| funcs.contains(typ)) {
+            self.syncer_vec
+                .push((typ.clone(), Arc::new(Box::new(syncer))));
+        }
+    }
+    /// 股票日线，融资融券按代码切分为`split_count`份，每份一个syncer
+    fn prepare_heavy_syncer(&mut self) {
+        let mut stock_codes: Vec<_> = {
+            let cache = self.cache.read().unwrap();
+            cache
+                .stock_info()
+                .iter()
+                .flatten()
+                .map(|(_, v)| v.clone())
+                .collect()
+        };
+        if stock_codes.is_empty() {
+            return;
+        }
+        stock_codes.sort_by(|a, b| a.code.cmp(&b.code));
+        let split_count = self.split_count.max(1);
+        let len = stock_codes.len().div_ceil(split_count);
+        for (i, sub_codes) in stock_codes.chunks(len).enumerate() {
+            let task_n = i + 1;
+            let margin_sub_codes: Vec<_> =
+                sub_codes.iter().filter(|e| e.is_margin).cloned().collect();
+            self.add_syncer(
+                &SyncDataType::StockBar,
+                DailySyncer::with_codes(
+                    self.db.clone(),
+                    self.cache.clone(),
+                    DailyType::Stock,
+                    self.resync.clone(),
+                    sub_codes.to_vec(),
+                    task_n,
+                ),
+            );
+            if !margin_sub_codes.is_empty() {
+                self.add_syncer(
+                    &SyncDataType::StockMargin,
+                    DailySyncer::with_codes(
+                        self.db.clone(),
+                        self.cache.clone(),
+                        DailyType::StockMargin,
+                        self.resync.clone(),
+                        margin_sub_codes,
+                        task_n,
+                    ),
+                );
+            }
+        }
+    }
+    /// 分钟线，每个品种和频率的代码切分为`split_count`份，每份一个syncer
+    fn prepare_minute_syncer(&mut self) {
+        for minute in self.minute.clone() {
+            let tab = match minute.tab() {
+                Ok(tab) => tab,
+                Err(e) => {
+                    log::error!("skip minute syncer: {}", e);
+                    continue;
+                }
+            };
+            let codes = {
+                let cache = self.cache.read().unwrap();
+                minute_codes(&cache, minute.typ)
+            };
+            if codes.is_empty() {
+                continue;
+            }
+            let len = codes.len().div_ceil(self.split_count.max(1));
+            for (i, sub_codes) in codes.chunks(len).enumerate() {
+                self.add_syncer(
+                    &SyncDataType::MinuteBar,
+                    MinuteSyncer::new(
+                        self.db.clone(),
+                        self.cache.clone(),
+                        tab,
+                        minute.clone(),
+                        self.resync.clone(),
+                        sub_codes.to_vec(),
+                        i + 1,
+                    ),
+                );
+            }
+        }
+    }
+    fn prepare_syncer(&mut self) {
+        let (db, cache) = (self.db.clone(), self.cache.clone());
+        if !self.skip_basic {
+            for (typ, info) in [
+                (SyncDataType::BondInfo, InfoType::Bond),
+                (SyncDataType::IndexInfo, InfoType::Index),
+                (SyncDataType::StockInfo, InfoType::Stock),
+                (SyncDataType::FundInfo, InfoType::Fund),
+            ] {
+                self.add_syncer(&typ, InfoSyncer::new(db.clone(), cache.clone(), info));
+            }
+            self.add_syncer(
+                &SyncDataType::TradeDate,
+                TradeDateSyncer::new(db.clone(), cache.clone()),
+            );
+        }
+
+        for (typ, daily) in [
+            (SyncDataType::BondBar, DailyType::Bond),
+            (SyncDataType::FundBar, DailyType::Fund),
+            (SyncDataType::FundNet, DailyType::FundNet),
+            (SyncDataType::IndexBar, DailyType::Index),
+        ] {
+            self.add_syncer(
+                &typ,
+                DailySyncer::new(db.clone(), cache.clone(), daily, self.resync.clone()),
+            );
+        }
+
+        self.add_syncer(&SyncDataType::StockIndex, StockIndexSyncer::new(db.clone()));
+
+        self.add_syncer(
+            &SyncDataType::StockIndustry,
+            BoardSyncer::new(db.clone(), BoardType::Industry),
+        );
+        self.add_syncer(
+            &SyncDataType::StockIndustryBar,
+            DailySyncer::new(
+                db.clone(),
+                cache.clone(),
+                DailyType::Industry,
+                self.resync.clone(),
+            ),
+        );
+        self.add_syncer(
+            &SyncDataType::StockIndustryDetail,
+            BoardDetailSyncer::new(db.clone(), BoardType::Industry),
+        );
+
+        self.add_syncer(
+            &SyncDataType::StockConcept,
+            BoardSyncer::new(db.clone(), BoardType::Concept),
+        );
+        self.add_syncer(
+            &SyncDataType::StockConceptBar,
+            DailySyncer::new(db.clone(), cache, DailyType::Concept, self.resync.clone()),
+        );
+        self.add_syncer(
+            &SyncDataType::StockConceptDetail,
+            BoardDetailSyncer::new(db.clone(), BoardType::Concept),
+        );
+
+        self.add_syncer(&SyncDataType::StockYJBB, StockYJBBSyncer::new(db));
+
+        self.prepare_heavy_syncer();
+        self.prepare_minute_syncer();
+    }
+}
+
+#[async_trait]
+impl Store for TableStore {
+    async fn init(&mut self) -> Result<()> {
+        self.db.create_schema().await?;
+
+        self.prepare_cache().await?;
+        self.prepare_syncer();
+
+        Ok(())
+    }
+    async fn build_index(&self) -> Result<()> {
+        self.db.create_schema().await
+    }
+
+    fn syncer(&self) -> Result<Vec<TypedSyncer>> {
+        Ok(self.syncer_vec.to_vec())
+    }
+
+    async fn load_checkpoint(&self, run: &str) -> Result<Vec<Checkpoint>> {
+        query(
+            self.db.as_ref(),
+            TAB_SYNC_CHECKPOINT,
+            &Query::new().filter(Cond::eq("run", run)),
+        )
+        .await
+    }
+
+    async fn save_checkpoint(&self, data: &[Checkpoint]) -> Result<()> {
+        insert_many(self.db.as_ref(), TAB_SYNC_CHECKPOINT, data, false).await
+    }
+
+    fn resume(&self, data: &[Checkpoint]) {
+        self.cache.write().unwrap().cache_done(data);
+    }
+
+    async fn latest(&self, tab: &str, code: &str) -> Result<Option<NaiveDateTime>> {
+        let latest: Option<Latest> = query_one(
+            self.db.as_ref(),
+            tab,
+            Query::new().code(code).sort("trade_date", Order::Desc),
+        )
+        .await?;
+        Ok(latest
+            .and_then(|l| DateTime::from_timestamp(l.trade_date, 0))
+            .map(|dt| dt.naive_utc()))
+    }
+
+    async fn delete(&self, tab: &str, code: &str, before: Option<NaiveDate>) -> Result<()> {
+        let mut query = Query::new().code(code);
+        if let Some(before) = before {
+            query = query.filter(Cond::lt("trade_date", before));
+        }
+        delete_many(self.db.as_ref(), tab, &query).await?;
+        Ok(())
+    }
+
+    fn set_peers(&self, peers: Vec<Arc<Box<dyn Store>>>) {
+        self.cache.write().unwrap().cache_peers(peers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use rwqfetch::{with_http_mode, HttpMode, StockInfo};
+    use tokio::sync::mpsc;
+
+    use crate::{
+        store::{
+            file::FileDb, peer_delete, peer_latest, Cache, Query, Store, TAB_STOCK_DAILY,
+            TAB_STOCK_MARGIN,
+        },
+        syncer::Syncer,
+        types::{Checkpoint, FileFormat, Resync, SyncData},
+    };
+
+    use super::{
+        super::daily::{DailySyncer, DailyType},
+        insert_many, query, TableDb, TableStore,
+    };
+
+    #[tokio::test]
+    async fn test_checkpoint() {
+        let root = std::env::temp_dir().join(format!("rwqdata-checkpoint-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let db = Arc::new(FileDb::new(&root, FileFormat::default()));
+        db.create_schema().await.unwrap();
+        let store = TableStore::new(db, true, 1, &None, &Resync::default(), &[]);
+        let cp = |run: &str, code: &str, done| Checkpoint {
+            run: run.to_owned(),
+            name: "stock_daily".to_owned(),
+            code: code.to_owned(),
+            done,
+            ..Default::default()
+        };
+        store
+            .save_checkpoint(&[cp("a", "sh600000", false), cp("b", "sh600000", true)])
+            .await
+            .unwrap();
+        store
+            .save_checkpoint(&[cp("a", "sh600000", true), cp("a", "sh600001", true)])
+            .await
+            .unwrap();
+        let mut data = store.load_checkpoint("a").await.unwrap();
+        data.sort_by(|a, b| a.code.cmp(&b.code));
+        assert_eq!(
+            data,
+            vec![cp("a", "sh600000", true), cp("a", "sh600001", true)]
+        );
+
+        store.resume(&data);
+        let cache = store.cache.read().unwrap();
+        assert!(cache.is_done("stock_daily", "sh600001"));
+        assert!(!cache.is_done("stock_margin", "sh600001"));
+        drop(cache);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_peer() {
+        let root = std::env::temp_dir().join(format!("rwqdata-peer-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let dt = |s: &str| {
+            NaiveDate::parse_from_str(s, "%Y%m%d")
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        let bar = |s: &str| rwqfetch::Bar {
+            code: "sh600000".to_owned(),
+            trade_date: dt(s),
+            ..Default::default()
+        };
+        let store = |name: &str| {
+            let db = Arc::new(FileDb::new(&root.join(name), FileFormat::default()));
+            (
+                db.clone(),
+                TableStore::new(db, true, 1, &None, &Resync::default(), &[]),
+            )
+        };
+        let (db, primary) = store("primary");
+        let (peer_db, peer) = store("peer");
+        db.create_schema().await.unwrap();
+        peer_db.create_schema().await.unwrap();
+        let bars = vec![bar("20230301"), bar("20230302"), bar("20230303")];
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &bars, false)
+            .await
+            .unwrap();
+        insert_many(peer_db.as_ref(), TAB_STOCK_DAILY, &bars[..2], false)
+            .await
+            .unwrap();
+
+        let peer: Arc<Box<dyn Store>> = Arc::new(Box::new(peer));
+        let latest = primary.latest(TAB_STOCK_DAILY, "sh600000").await.unwrap();
+        assert_eq!(latest, Some(dt("20230303")));
+        assert_eq!(
+            peer.latest(TAB_STOCK_DAILY, "sh600001").await.unwrap(),
+            None
+        );
+
+        // 没有设置其他数据源时为本数据源的最新时间
+        let cache = primary.cache.clone();
+        let latest_of = |code: &'static str, latest: Option<NaiveDateTime>| {
+            let cache = cache.clone();
+            async move { peer_latest(&cache, TAB_STOCK_DAILY, code, latest).await }
+        };
+        assert_eq!(latest_of("sh600000", latest).await, latest);
+        primary.set_peers(vec![peer.clone()]);
+        assert_eq!(latest_of("sh600000", latest).await, Some(dt("20230302")));
+        assert_eq!(latest_of("sh600000", None).await, None);
+        assert_eq!(latest_of("sh600001", latest).await, None);
+
+        let before = NaiveDate::parse_from_str("20230302", "%Y%m%d").ok();
+        peer_delete(&cache, TAB_STOCK_DAILY, "sh600000", before).await;
+        let data: Vec<rwqfetch::Bar> = query(peer_db.as_ref(), TAB_STOCK_DAILY, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(latest_of("sh600000", latest).await, Some(dt("20230302")));
+        peer_delete(&cache, TAB_STOCK_DAILY, "sh600000", None).await;
+        assert_eq!(latest_of("sh600000", latest).await, None);
+        assert_eq!(
+            primary.latest(TAB_STOCK_DAILY, "sh600000").await.unwrap(),
+            latest
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_full_resync() {
+        let root = std::env::temp_dir().join(format!("rwqdata-full-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let bar = |code: &str, s: &str| rwqfetch::Bar {
+            code: code.to_owned(),
+            trade_date: NaiveDate::parse_from_str(s, "%Y%m%d")
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            ..Default::default()
+        };
+        let dates = |data: Vec<rwqfetch::Bar>, code: &str| {
+            let mut dates: Vec<_> = data
+                .iter()
+                .filter(|b| b.code == code)
+                .map(|b| b.trade_date.format("%Y%m%d").to_string())
+                .collect();
+            dates.sort();
+            dates
+        };
+        let db: Arc<dyn TableDb> = Arc::new(FileDb::new(&root, FileFormat::default()));
+        db.create_schema().await.unwrap();
+        // 20230304为非交易日的错误数据
+        let old = vec![
+            bar("sh600000", "20230228"),
+            bar("sh600000", "20230301"),
+            bar("sh600000", "20230304"),
+            bar("sh600001", "20230301"),
+        ];
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &old, false)
+            .await
+            .unwrap();
+        let cache = Arc::new(RwLock::new(Cache::new()));
+        let syncer =
+            |resync: Resync| DailySyncer::new(db.clone(), cache.clone(), DailyType::Stock, resync);
+        let new = vec![
+            bar("sh600000", "20230301"),
+            bar("sh600000", "20230302"),
+            bar("sh600000", "20230303"),
+        ];
+
+        // 增量同步只覆盖相同交易日的数据
+        syncer(Resync::default())
+            .save(SyncData::StockBar(new.clone()))
+            .await
+            .unwrap();
+        let data = query(db.as_ref(), TAB_STOCK_DAILY, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            dates(data, "sh600000"),
+            vec!["20230228", "20230301", "20230302", "20230303", "20230304"]
+        );
+
+        // 全量重新同步保存后删除不在新数据中的旧数据，不影响其他代码
+        let mut resync = Resync::default();
+        resync.full_codes.insert("sh600000".into());
+        syncer(resync).save(SyncData::StockBar(new)).await.unwrap();
+        let data = query(db.as_ref(), TAB_STOCK_DAILY, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            dates(data.clone(), "sh600000"),
+            vec!["20230301", "20230302", "20230303"]
+        );
+        assert_eq!(dates(data, "sh600001"), vec!["20230301"]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_merged_full_resync() {
+        let root = std::env::temp_dir().join(format!("rwqdata-merged-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let bar = |code: &str, s: &str| rwqfetch::Bar {
+            code: code.to_owned(),
+            trade_date: NaiveDate::parse_from_str(s, "%Y%m%d")
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            ..Default::default()
+        };
+        let dates = |data: &[rwqfetch::Bar], code: &str| {
+            let mut dates: Vec<_> = data
+                .iter()
+                .filter(|b| b.code == code)
+                .map(|b| b.trade_date.format("%Y%m%d").to_string())
+                .collect();
+            dates.sort();
+            dates
+        };
+        let db: Arc<dyn TableDb> = Arc::new(FileDb::new(&root, FileFormat::default()));
+        db.create_schema().await.unwrap();
+        let old = vec![
+            bar("sh600000", "20230228"),
+            bar("sh600000", "20230301"),
+            bar("sh600001", "20230228"),
+            bar("sh600001", "20230301"),
+            bar("sh600002", "20230228"),
+            bar("sh600002", "20230304"),
+        ];
+        insert_many(db.as_ref(), TAB_STOCK_DAILY, &old, false)
+            .await
+            .unwrap();
+
+        // 保存队列合并多个代码的数据，第一个代码为增量同步
+        let mut batch = SyncData::StockBar(vec![bar("sh600000", "20230302")]);
+        for code in ["sh600001", "sh600002"] {
+            batch
+                .merge(SyncData::StockBar(vec![
+                    bar(code, "20230301"),
+                    bar(code, "20230302"),
+                ]))
+                .unwrap();
+        }
+        let mut resync = Resync::default();
+        resync.full_codes.insert("sh600001".into());
+        resync.full_codes.insert("sh600002".into());
+        let syncer = DailySyncer::new(
+            db.clone(),
+            Arc::new(RwLock::new(Cache::new())),
+            DailyType::Stock,
+            resync,
+        );
+        syncer.save(batch).await.unwrap();
+
+        let data: Vec<rwqfetch::Bar> = query(db.as_ref(), TAB_STOCK_DAILY, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            dates(&data, "sh600000"),
+            vec!["20230228", "20230301", "20230302"]
+        );
+        for code in ["sh600001", "sh600002"] {
+            assert_eq!(dates(&data, code), vec!["20230301", "20230302"]);
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_replay_sync() {
+        let root = std::env::temp_dir().join(format!("rwqdata-replay-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let db: Arc<dyn TableDb> = Arc::new(FileDb::new(&root, FileFormat::default()));
+        db.create_schema().await.unwrap();
+        let codes = vec![StockInfo {
+            code: "sz000001".to_owned(),
+            name: "平安银行".to_owned(),
+            block: "主板".to_owned(),
+            is_margin: true,
+            listing_date: NaiveDate::from_ymd_opt(1991, 4, 3)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        }];
+        let syncer = DailySyncer::with_codes(
+            db.clone(),
+            Arc::new(RwLock::new(Cache::new())),
+            DailyType::StockMargin,
+            Resync::default(),
+            codes,
+            0,
+        );
+
+        // 使用rwqfetch记录的响应，不请求网络
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../fetch/tests/fixtures");
+        with_http_mode(HttpMode::Replay(fixtures), async {
+            let (tx, mut rx) = mpsc::channel(8);
+            syncer.fetch(tx).await.unwrap();
+            while let Some(data) = rx.recv().await {
+                syncer.save(data).await.unwrap();
+            }
+        })
+        .await;
+
+        let mut data: Vec<rwqfetch::StockMargin> = query(
+            db.as_ref(),
+            TAB_STOCK_MARGIN,
+            &Query::new().code("sz000001"),
+        )
+        .await
+        .unwrap();
+        data.sort_by_key(|m| m.trade_date);
+        let close: Vec<_> = data
+            .iter()
+            .map(|m| (m.trade_date.format("%Y%m%d").to_string(), m.close))
+            .collect();
+        assert_eq!(
+            close,
+            vec![
+                ("20230301".to_owned(), 14.2),
+                ("20230302".to_owned(), 14.1),
+                ("20230303".to_owned(), 14.15)
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
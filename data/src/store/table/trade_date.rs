@@ -0,0 +1,69 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::{
+    store::{Cache, Order, Query, TAB_TRADE_DATE},
+    syncer::Syncer,
+    types::SyncData,
+    Error, Result,
+};
+
+use super::{insert_many, query_one, TableDb};
+
+pub(crate) struct TradeDateSyncer {
+    cache: Arc<RwLock<Cache>>,
+    db: Arc<dyn TableDb>,
+}
+
+impl TradeDateSyncer {
+    pub fn new(db: Arc<dyn TableDb>, cache: Arc<RwLock<Cache>>) -> Self {
+        Self { db, cache }
+    }
+}
+
+#[async_trait]
+impl Syncer for TradeDateSyncer {
+    async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+        let trade_date: Vec<_> = {
+            let cache = self.cache.read().unwrap();
+            cache
+                .trade_date()
+                .iter()
+                .flatten()
+                .map(|v| rwqfetch::TradeDate { trade_date: *v })
+                .collect()
+        };
+        let latest: Option<rwqfetch::TradeDate> = query_one(
+            self.db.as_ref(),
+            TAB_TRADE_DATE,
+            Query::new().sort("trade_date", Order::Desc),
+        )
+        .await?;
+
+        let latest = latest.map(|t| t.trade_date).unwrap_or(19700101);
+        let new_data: Vec<_> = trade_date
+            .into_iter()
+            .filter(|e| e.trade_date > latest)
+            .collect();
+        if !new_data.is_empty() {
+            tx.send(SyncData::TradeDate(new_data)).await.map_err(|e| {
+                log::error!("send data error {:?}", e);
+                Error::Custom(format!("send data error {:?}", e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, data: SyncData) -> Result<()> {
+        if let SyncData::TradeDate(info) = data {
+            let len = info.len();
+            log::info!("start save {}, size={}", TAB_TRADE_DATE, len);
+            insert_many(self.db.as_ref(), TAB_TRADE_DATE, &info, false).await?;
+            log::info!("done save {}, size={}", TAB_TRADE_DATE, len);
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,29 @@
 //! 数据同步，支持同时同步到多个数据源
-use std::{collections::HashMap, sync::Arc};
+//!
+//! 按代码增量同步的数据只由第一个数据源获取一次，开始日期取各数据源中最早的，
+//! 获取的数据发送到各数据源分别保存，某个数据源保存失败不影响其他数据源；
+//! 其他数据获取时需与已有数据比较，由各数据源分别获取。
+//!
+//! 同步进度按同步标识记录到各数据源，按代码同步的数据每个代码保存后记录，
+//! 同步类型全部完成或失败时记录，续传时跳过已完成的同步类型及代码。
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
+use chrono::Local;
 use futures::future::join_all;
 
-use tokio::sync::mpsc::UnboundedSender;
-use tokio::sync::{broadcast, mpsc};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinError,
+};
 
 use crate::store::get_store;
-use crate::types::SyncDataType;
+use crate::types::{
+    Checkpoint, MinuteSync, Resync, SyncDataType, SyncQueue, SyncReport, TypeReport,
+};
 use crate::{
     store::Store,
     syncer::Syncer,
@@ -15,18 +31,26 @@ use crate::{
     Error, Result,
 };
 
+/// 数据源类型及其store
+type TypedStore = (SyncDestType, Arc<Box<dyn Store>>);
+
 /// 数据同步
 pub struct Sync {
     dest: Vec<SyncDest>,
     shutdown: broadcast::Receiver<()>,
     funcs: Option<Vec<SyncDataType>>,
-    store: Option<HashMap<SyncDestType, Arc<Box<dyn Store>>>>,
+    resync: Resync,
+    minute: Vec<MinuteSync>,
+    store: Option<Vec<TypedStore>>,
     is_init: bool,
+    run: String,
+    resume: bool,
+    queue: SyncQueue,
 }
 
 impl Sync {
     /// 构造对象  
-    /// `dest` 数据源，需保证数据源正确，否则后续`init`会报错  
+    /// `dest` 数据源，需保证数据源正确，否则后续`init`会报错，按代码增量同步的数据由第一个数据源获取  
     /// `shutdown·` 停止信号  
     /// `funcs` 同步的条目类型，如果为`None`，则全部同步
     pub fn new(
@@ -38,19 +62,55 @@ impl Sync {
             dest,
             shutdown,
             funcs,
+            resync: Resync::default(),
+            minute: Vec::new(),
             store: None,
             is_init: false,
+            run: Local::now().format("%Y%m%d").to_string(),
+            resume: false,
+            queue: SyncQueue::default(),
         }
     }
+    /// 设置重新同步选项，需在`init`前设置，默认从最新数据的下一个交易日开始同步
+    pub fn with_resync(mut self, resync: Resync) -> Self {
+        self.resync = resync;
+        self
+    }
+    /// 设置同步的分钟线，需在`init`前设置，默认不同步分钟线
+    pub fn with_minute(mut self, minute: Vec<MinuteSync>) -> Self {
+        self.minute = minute;
+        self
+    }
+    /// 设置同步标识及是否续传，需在`sync`前设置，默认标识为当日日期(YYYYMMDD)，不续传  
+    /// 同步进度按标识记录，续传时跳过相同标识已完成的同步类型及代码
+    pub fn with_run(mut self, run: impl Into<String>, resume: bool) -> Self {
+        self.run = run.into();
+        self.resume = resume;
+        self
+    }
+    /// 设置同步队列选项，需在`sync`前设置，限制获取与保存之间缓存的数据量
+    pub fn with_queue(mut self, queue: SyncQueue) -> Self {
+        self.queue = queue;
+        self
+    }
     /// 初始化
     /// `skip_basic` 初始化数据是否从远程获取，true在从数据库获取, false则从远程获取    
     /// `split_count` 代码切分份数，同一份数据在同一个task里处理  
     pub async fn init(&mut self, skip_basic: bool, split_count: usize) -> Result<()> {
         if !self.is_init {
-            let mut store = HashMap::new();
+            let mut store = Vec::new();
             for (i, dest) in self.dest.iter().enumerate() {
-                let (t, s) = get_store(dest, skip_basic, split_count, &self.funcs, true).await?;
-                store.insert(t.clone(), Arc::new(s));
+                let (t, s) = get_store(
+                    dest,
+                    skip_basic,
+                    split_count,
+                    &self.funcs,
+                    &self.resync,
+                    &self.minute,
+                    true,
+                )
+                .await?;
+                store.push((t.clone(), Arc::new(s)));
                 log::debug!("store#{}{:?}-{:?} inited ", i, dest, &t);
             }
             self.store = Some(store);
@@ -59,32 +119,40 @@ impl Sync {
         }
         Ok(())
     }
-    /// 初始化
+    /// 同步
     /// `skip_basic` 初始化数据是否从远程获取，true在从数据库获取, false则从远程获取    
     /// `split_count` 代码切分份数，同一份数据在同一个task里处理  
-    /// `task_count` 远程获取数据启动的task数量，代表并发获取， task_count不宜过大，可能被封
+    /// `task_count` 远程获取数据启动的task数量，代表并发获取， task_count不宜过大，可能被封  
+    /// 返回各数据源各同步类型的同步结果，被停止信号中断时为已完成部分的结果，
+    /// 同步任务出错或panic时返回错误
     pub async fn sync(
         &mut self,
         skip_basic: bool,
         task_count: usize,
         split_count: usize,
-    ) -> Result<()> {
+    ) -> Result<SyncReport> {
+        let start = Local::now().naive_local();
         self.init(skip_basic, split_count).await?;
+        let mut progress = Vec::new();
+        let mut interrupted = false;
         if let Some(ref store) = self.store {
-            let mut fut = Vec::new();
-
             let (tx, _) = broadcast::channel(1);
             for (typ, store) in store.iter() {
-                let store = (*store).clone();
-                let rx = tx.subscribe();
-                let h = tokio::spawn(sync_task((*typ).clone(), store, rx, task_count));
-                fut.push(h);
+                progress.push(Arc::new(Progress::new(&self.run, typ, store.clone())));
             }
+            let h = tokio::spawn(sync_task(
+                progress.clone(),
+                tx.subscribe(),
+                task_count,
+                self.resume,
+                self.queue,
+            ));
 
             tokio::select! {
-                _ = join_all(fut) => {},
+                res = h => task_result(res)?,
                 _ = self.shutdown.recv() => {
                     log::info!("stop sync received");
+                    interrupted = true;
                     tx.send(()).map_err(|e| {
                         log::error!("send data error {:?}", e);
                         Error::Custom(format!("send data error {:?}", e))
@@ -93,37 +161,305 @@ impl Sync {
             }
         }
         log::info!("done sync");
-        Ok(())
+        Ok(SyncReport {
+            run: self.run.clone(),
+            resume: self.resume,
+            start,
+            end: Local::now().naive_local(),
+            interrupted,
+            types: progress.iter().flat_map(|p| p.report()).collect(),
+        })
     }
 }
 
-async fn sync_task(
-    store_typ: SyncDestType,
+/// 同步任务的结果，任务出错或panic时返回错误，不能当作同步成功
+fn task_result(res: std::result::Result<Result<()>, JoinError>) -> Result<()> {
+    match res {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            log::error!("sync task error: {:?}", e);
+            Err(e)
+        }
+        Err(e) => {
+            log::error!("sync task panic: {:?}", e);
+            Err(Error::Custom(format!("sync task error: {}", e)))
+        }
+    }
+}
+
+/// 报告中保留的错误条数
+const MAX_ERRORS: usize = 10;
+
+/// 一个数据源的同步统计，并记录同步进度
+struct Progress {
+    run: String,
+    dest: String,
     store: Arc<Box<dyn Store>>,
+    types: Mutex<BTreeMap<&'static str, TypeReport>>,
+}
+
+impl Progress {
+    fn new(run: &str, dest: &SyncDestType, store: Arc<Box<dyn Store>>) -> Self {
+        Self {
+            run: run.to_owned(),
+            dest: format!("{:?}", dest),
+            store,
+            types: Mutex::new(BTreeMap::new()),
+        }
+    }
+    fn update(&self, typ: &SyncDataType, f: impl FnOnce(&mut TypeReport)) {
+        let mut types = self.types.lock().unwrap();
+        let report = types.entry(typ.name()).or_insert_with(|| TypeReport {
+            dest: self.dest.clone(),
+            typ: typ.name().to_owned(),
+            ..Default::default()
+        });
+        f(report);
+    }
+    fn failed(&self, typ: &SyncDataType) -> usize {
+        let types = self.types.lock().unwrap();
+        types.get(typ.name()).map(|r| r.failed).unwrap_or_default()
+    }
+    async fn checkpoint(&self, name: &str, code: &str, error: Option<String>) {
+        let cp = Checkpoint {
+            run: self.run.clone(),
+            name: name.to_owned(),
+            code: code.to_owned(),
+            done: error.is_none(),
+            error,
+            update_time: Local::now().timestamp(),
+        };
+        if let Err(e) = self.store.save_checkpoint(&[cp]).await {
+            log::error!("save checkpoint {}({}) error: {:?}", name, code, e);
+        }
+    }
+    /// 记录失败，`tab`及`code`为空时记录为同步类型失败
+    async fn fail(&self, typ: &SyncDataType, tab: Option<&str>, code: Option<&str>, e: &Error) {
+        let error = e.to_string();
+        self.update(typ, |r| {
+            r.failed += 1;
+            if r.errors.len() < MAX_ERRORS {
+                r.errors.push(error.clone());
+            }
+        });
+        match (tab, code) {
+            (Some(tab), Some(code)) => self.checkpoint(tab, code, Some(error)).await,
+            _ => self.checkpoint(typ.name(), "", Some(error)).await,
+        }
+    }
+    fn report(&self) -> Vec<TypeReport> {
+        self.types.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// 一个获取单元，获取的数据发送到各数据源的保存task
+#[derive(Clone)]
+struct FetchJob {
+    typ: SyncDataType,
+    syncer: Arc<Box<dyn Syncer>>,
+    dest: Vec<(Arc<Progress>, mpsc::Sender<SyncData>)>,
+    capacity: usize,
+}
+
+impl FetchJob {
+    /// 获取数据，边获取边发送，保存队列满时获取等待
+    async fn fetch(&self) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel(self.capacity);
+        let forward = async {
+            while let Some(data) = rx.recv().await {
+                self.send(data).await;
+            }
+        };
+        let (res, _) = tokio::join!(self.syncer.fetch(tx), forward);
+        res
+    }
+    /// 发送到各数据源，保存task已停止的数据源跳过
+    async fn send(&self, data: SyncData) {
+        if let Some(((p, tx), rest)) = self.dest.split_last() {
+            for (p, tx) in rest.iter() {
+                self.send_to(p, tx, data.clone()).await;
+            }
+            self.send_to(p, tx, data).await;
+        }
+    }
+    /// 发送到一个数据源，记录队列长度及等待时间
+    async fn send_to(&self, p: &Progress, tx: &mpsc::Sender<SyncData>, data: SyncData) {
+        let full = tx.capacity() == 0;
+        let start = Instant::now();
+        if let Err(e) = tx.send(data).await {
+            log::error!("send data to {} error {:?}", &p.dest, e);
+            return;
+        }
+        let wait = if full {
+            start.elapsed().as_secs_f64()
+        } else {
+            0.0
+        };
+        let depth = tx.max_capacity() - tx.capacity();
+        p.update(&self.typ, |r| {
+            r.queue_peak = r.queue_peak.max(depth);
+            r.queue_wait += wait;
+        });
+    }
+}
+
+/// 已完成的(name, code)，`code`为空的为已完成的同步类型
+fn done_set(checkpoint: &[Checkpoint]) -> HashSet<(&str, &str)> {
+    checkpoint
+        .iter()
+        .filter(|cp| cp.done)
+        .map(|cp| (cp.name.as_str(), cp.code.as_str()))
+        .collect()
+}
+
+async fn sync_task(
+    dest: Vec<Arc<Progress>>,
     mut shutdown_rx: broadcast::Receiver<()>,
     task_count: usize,
+    resume: bool,
+    queue: SyncQueue,
 ) -> Result<()> {
-    log::info!("start sync {:?}", &store_typ);
-
     let (shutdown_tx, _) = broadcast::channel(1);
 
+    // 获取syncer失败的数据源不参与同步，读取同步进度失败的不续传
+    let mut prepared = Vec::new();
+    for p in dest.into_iter() {
+        log::info!("start sync {}", &p.dest);
+        let syncer = match p.store.syncer() {
+            Ok(syncer) => syncer,
+            Err(e) => {
+                log::error!("prepare {} syncer error: {:?}", &p.dest, e);
+                continue;
+            }
+        };
+        let checkpoint = if resume {
+            match p.store.load_checkpoint(&p.run).await {
+                Ok(checkpoint) => {
+                    log::info!(
+                        "resume {} of {}, {} checkpoints",
+                        &p.run,
+                        &p.dest,
+                        checkpoint.len()
+                    );
+                    checkpoint
+                }
+                Err(e) => {
+                    log::error!("load {} checkpoint error, not resume: {:?}", &p.dest, e);
+                    vec![]
+                }
+            }
+        } else {
+            vec![]
+        };
+        prepared.push((p, syncer, checkpoint));
+    }
+    if prepared.is_empty() {
+        return Ok(());
+    }
+
+    // 共享获取的数据续传时跳过各数据源都已完成的同步类型及代码
+    let done: Vec<_> = prepared.iter().map(|(_, _, cp)| done_set(cp)).collect();
+    let shared_done: HashSet<_> = done[0]
+        .iter()
+        .filter(|e| done[1..].iter().all(|d| d.contains(*e)))
+        .cloned()
+        .collect();
+
+    let (primary, primary_syncer, checkpoint) = &prepared[0];
+    primary.store.set_peers(
+        prepared[1..]
+            .iter()
+            .map(|(p, ..)| p.store.clone())
+            .collect(),
+    );
+    if resume {
+        let checkpoint: Vec<_> = checkpoint
+            .iter()
+            .filter(|cp| shared_done.contains(&(cp.name.as_str(), cp.code.as_str())))
+            .cloned()
+            .collect();
+        primary.store.resume(&checkpoint);
+    }
+
+    // (同步类型, 获取的syncer, 各数据源保存的syncer)
+    let mut jobs = Vec::new();
+    let mut tabs = HashMap::new();
+    for (typ, s) in primary_syncer.iter() {
+        let tab = match s.progress_tab() {
+            Some(tab) => tab,
+            None => {
+                if done[0].contains(&(typ.name(), "")) {
+                    primary.update(typ, |r| r.skipped += 1);
+                } else {
+                    jobs.push((typ.clone(), s.clone(), vec![(primary.clone(), s.clone())]));
+                }
+                continue;
+            }
+        };
+        tabs.insert(tab, typ.clone());
+        if shared_done.contains(&(typ.name(), "")) {
+            for (p, ..) in prepared.iter() {
+                p.update(typ, |r| r.skipped += 1);
+            }
+            continue;
+        }
+        let mut saver = Vec::new();
+        for (p, syncer, _) in prepared.iter() {
+            match syncer
+                .iter()
+                .find(|(t, s)| t == typ && s.progress_tab() == Some(tab))
+            {
+                Some((_, s)) => saver.push((p.clone(), s.clone())),
+                None => log::warn!("{} has no syncer of {}({})", &p.dest, typ.name(), tab),
+            }
+        }
+        jobs.push((typ.clone(), s.clone(), saver));
+    }
+    for ((p, syncer, _), done) in prepared.iter().zip(done.iter()).skip(1) {
+        for (typ, s) in syncer.iter().filter(|(_, s)| s.progress_tab().is_none()) {
+            if done.contains(&(typ.name(), "")) {
+                p.update(typ, |r| r.skipped += 1);
+            } else {
+                jobs.push((typ.clone(), s.clone(), vec![(p.clone(), s.clone())]));
+            }
+        }
+    }
+    for (name, _) in shared_done.iter().filter(|(_, code)| !code.is_empty()) {
+        if let Some(typ) = tabs.get(name) {
+            if !shared_done.contains(&(typ.name(), "")) {
+                for (p, ..) in prepared.iter() {
+                    p.update(typ, |r| r.skipped += 1);
+                }
+            }
+        }
+    }
+
     let mut fut = Vec::new();
-    let mut tx_map = HashMap::new();
-    let syncer = store.syncer()?;
-    for (i, s) in syncer.iter().enumerate() {
-        let (tx, rx) = mpsc::unbounded_channel();
-        tx_map.insert(i, tx);
-        let syncer = s.clone();
-        let h = tokio::spawn(save_task(
-            store_typ.clone(),
-            i,
+    let mut fetch_jobs = Vec::new();
+    for (i, (typ, syncer, saver)) in jobs.into_iter().enumerate() {
+        let mut dest = Vec::new();
+        for (p, s) in saver.into_iter() {
+            let (tx, rx) = mpsc::channel(queue.capacity.max(1));
+            let h = tokio::spawn(save_task(
+                i,
+                typ.clone(),
+                s,
+                rx,
+                shutdown_tx.subscribe(),
+                p.clone(),
+                queue.batch_size,
+            ));
+            fut.push(h);
+            dest.push((p, tx));
+        }
+        fetch_jobs.push(FetchJob {
+            typ,
             syncer,
-            rx,
-            shutdown_tx.subscribe(),
-        ));
-        fut.push(h);
+            dest,
+            capacity: queue.capacity.max(1),
+        });
     }
-    let syncer_len = syncer.len();
+    let syncer_len = fetch_jobs.len();
     log::info!("syncer counts: {}", syncer_len);
 
     let mut task_n = 0;
@@ -140,8 +476,7 @@ async fn sync_task(
             log::info!("start fetch task#{}", task_n);
             let h = tokio::spawn(fetch_task(
                 sub_types,
-                store.clone(),
-                tx_map.clone(),
+                fetch_jobs.clone(),
                 shutdown_tx.subscribe(),
             ));
             fut.push(h);
@@ -153,15 +488,28 @@ async fn sync_task(
         log::info!("start fetch task#{}", task_n);
         let h = tokio::spawn(fetch_task(
             sub_types,
-            store.clone(),
-            tx_map,
+            fetch_jobs.clone(),
             shutdown_tx.subscribe(),
         ));
         fut.push(h);
     }
 
     tokio::select! {
-        _ = join_all(fut) => {},
+        _ = join_all(fut) => {
+            // 没有失败的同步类型记录为已完成，续传时跳过
+            for (p, ..) in prepared.iter() {
+                let types: BTreeMap<_, _> = fetch_jobs
+                    .iter()
+                    .filter(|job| job.dest.iter().any(|(d, _)| Arc::ptr_eq(d, p)))
+                    .map(|job| (job.typ.name(), &job.typ))
+                    .collect();
+                for typ in types.values() {
+                    if p.failed(typ) == 0 {
+                        p.checkpoint(typ.name(), "", None).await;
+                    }
+                }
+            }
+        },
         _ = shutdown_rx.recv() => {
             log::info!("sync_task shutdown recv");
             shutdown_tx.send(()).map_err(|e| {
@@ -176,67 +524,471 @@ async fn sync_task(
 
 async fn fetch_task(
     syncer_index: Vec<usize>,
-    store: Arc<Box<dyn Store>>,
-    tx_map: HashMap<usize, UnboundedSender<SyncData>>,
+    jobs: Vec<FetchJob>,
     mut shutdown_rx: broadcast::Receiver<()>,
 ) -> Result<()> {
-    let syncer = store.syncer()?;
     for index in syncer_index.into_iter() {
-        let s = syncer.get(index).unwrap();
-        let tx = tx_map.get(&index).unwrap();
+        let job = jobs.get(index).unwrap();
+        let start = Instant::now();
         tokio::select! {
-            _ = s.fetch(tx.clone()) => {},
+            res = job.fetch() => {
+                let elapsed = start.elapsed().as_secs_f64();
+                for (p, _) in job.dest.iter() {
+                    p.update(&job.typ, |r| r.duration += elapsed);
+                }
+                if let Err(e) = res {
+                    log::error!("fetch {} error: {:?}", job.typ.name(), e);
+                    for (p, _) in job.dest.iter() {
+                        p.fail(&job.typ, None, None, &e).await;
+                    }
+                }
+            },
             _ = shutdown_rx.recv() => {
                 log::info!("fetch_task shutdown recv");
                 break;
             }
         }
-        tx.send(SyncData::Done).map_err(|e| {
-            log::error!("send data error {:?}", e);
-            Error::Custom(format!("send data error {:?}", e))
-        })?;
+        job.send(SyncData::Done).await;
     }
     Ok(())
 }
 
 async fn save_task(
-    store_typ: SyncDestType,
     index: usize,
+    typ: SyncDataType,
     syncer: Arc<Box<dyn Syncer>>,
-    mut rx: mpsc::UnboundedReceiver<SyncData>,
+    mut rx: mpsc::Receiver<SyncData>,
     mut shutdown_rx: broadcast::Receiver<()>,
+    progress: Arc<Progress>,
+    batch_size: usize,
 ) -> Result<()> {
-    log::info!("store({:?})#{} save task start", &store_typ, index);
+    log::info!("store({})#{} save task start", &progress.dest, index);
 
-    loop {
-        let data = rx.recv().await;
-        if let Some(d) = data {
-            if matches!(d, SyncData::Done) {
+    let tab = syncer.progress_tab();
+    let mut pending = None;
+    let mut done = false;
+    while !done {
+        let mut batch = match pending.take() {
+            Some(d) => d,
+            None => match rx.recv().await {
+                Some(SyncData::Done) | None => break,
+                Some(d) => d,
+            },
+        };
+        let mut codes = vec![batch.code().map(|c| c.to_owned())];
+        progress.update(&typ, |r| r.fetched += batch.len());
+        // 按代码同步的数据合并队列中已有的数据，到`batch_size`条或队列为空时保存
+        while tab.is_some() && batch.len() < batch_size {
+            let d = match rx.try_recv() {
+                Ok(SyncData::Done) => {
+                    done = true;
+                    break;
+                }
+                Ok(d) => d,
+                Err(_) => break,
+            };
+            let (code, len) = (d.code().map(|c| c.to_owned()), d.len());
+            if let Err(d) = batch.merge(d) {
+                pending = Some(d);
                 break;
             }
-            tokio::select! {
-                _ = syncer.save(d) => {},
-                _ = shutdown_rx.recv() => {
-                    log::info!("save_task shutdown recv");
-                    break;
+            codes.push(code);
+            progress.update(&typ, |r| r.fetched += len);
+        }
+        let len = batch.len();
+        tokio::select! {
+            res = syncer.save(batch) => {
+                progress.update(&typ, |r| r.batches += 1);
+                match res {
+                    Ok(_) => {
+                        progress.update(&typ, |r| r.saved += len);
+                        if let Some(tab) = tab {
+                            for code in codes.iter().flatten() {
+                                progress.checkpoint(tab, code, None).await;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("save {} to {} error: {:?}", typ.name(), &progress.dest, e);
+                        for code in codes.iter() {
+                            progress.fail(&typ, tab, code.as_deref(), &e).await;
+                        }
+                    }
                 }
+            },
+            _ = shutdown_rx.recv() => {
+                log::info!("save_task shutdown recv");
+                break;
             }
-        } else {
-            break;
         }
     }
 
-    log::info!("store({:?})#{} save task done", &store_typ, index);
+    log::info!("store({})#{} save task done", &progress.dest, index);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::types::{SyncDataType, SyncDest};
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use rwqfetch::Bar;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        store::Store,
+        syncer::{Syncer, TypedSyncer},
+        types::{
+            Checkpoint, SyncData, SyncDataType, SyncDest, SyncDestType, SyncQueue, TypeReport,
+        },
+        Error, Result,
+    };
 
-    use super::Sync;
+    use super::{sync_task, task_result, Progress, Sync};
     use tokio::sync::broadcast;
 
+    /// 按代码同步，跳过已完成的代码
+    struct CodeSyncer {
+        codes: Vec<&'static str>,
+        done: Arc<Mutex<HashSet<String>>>,
+        fetched: Arc<Mutex<Vec<String>>>,
+        fail_save: bool,
+    }
+
+    #[async_trait]
+    impl Syncer for CodeSyncer {
+        async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+            for code in self.codes.iter() {
+                if self.done.lock().unwrap().contains(*code) {
+                    continue;
+                }
+                self.fetched.lock().unwrap().push(code.to_string());
+                let bar = Bar {
+                    code: code.to_string(),
+                    ..Default::default()
+                };
+                tx.send(SyncData::StockBar(vec![bar.clone(), bar]))
+                    .await
+                    .unwrap();
+            }
+            Ok(())
+        }
+        async fn save(&self, _data: SyncData) -> Result<()> {
+            if self.fail_save {
+                return Err(Error::Custom("db error".to_owned()));
+            }
+            Ok(())
+        }
+        fn progress_tab(&self) -> Option<&'static str> {
+            Some("stock_daily")
+        }
+    }
+
+    /// 保存慢的syncer，每个代码两条数据
+    struct SlowSyncer {
+        codes: Vec<String>,
+    }
+
+    #[async_trait]
+    impl Syncer for SlowSyncer {
+        async fn fetch(&self, tx: mpsc::Sender<SyncData>) -> Result<()> {
+            for code in self.codes.iter() {
+                let bar = Bar {
+                    code: code.clone(),
+                    ..Default::default()
+                };
+                tx.send(SyncData::StockBar(vec![bar.clone(), bar]))
+                    .await
+                    .unwrap();
+            }
+            Ok(())
+        }
+        async fn save(&self, _data: SyncData) -> Result<()> {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(())
+        }
+        fn progress_tab(&self) -> Option<&'static str> {
+            Some("stock_daily")
+        }
+    }
+
+    struct FailSyncer;
+
+    #[async_trait]
+    impl Syncer for FailSyncer {
+        async fn fetch(&self, _tx: mpsc::Sender<SyncData>) -> Result<()> {
+            Err(Error::Custom("remote error".to_owned()))
+        }
+        async fn save(&self, _data: SyncData) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct MemStore {
+        syncer: Vec<TypedSyncer>,
+        checkpoint: Mutex<Vec<Checkpoint>>,
+        done: Arc<Mutex<HashSet<String>>>,
+    }
+
+    #[async_trait]
+    impl Store for MemStore {
+        fn syncer(&self) -> Result<Vec<TypedSyncer>> {
+            Ok(self.syncer.clone())
+        }
+        async fn load_checkpoint(&self, run: &str) -> Result<Vec<Checkpoint>> {
+            let data = self.checkpoint.lock().unwrap();
+            Ok(data.iter().filter(|cp| cp.run == run).cloned().collect())
+        }
+        async fn save_checkpoint(&self, data: &[Checkpoint]) -> Result<()> {
+            let mut checkpoint = self.checkpoint.lock().unwrap();
+            for cp in data.iter() {
+                checkpoint.retain(|e| (&e.run, &e.name, &e.code) != (&cp.run, &cp.name, &cp.code));
+                checkpoint.push(cp.clone());
+            }
+            Ok(())
+        }
+        fn resume(&self, data: &[Checkpoint]) {
+            let mut done = self.done.lock().unwrap();
+            for cp in data.iter().filter(|cp| cp.done && cp.name == "stock_daily") {
+                done.insert(cp.code.clone());
+            }
+        }
+    }
+
+    async fn run(store: &Arc<Box<dyn Store>>, resume: bool) -> Vec<TypeReport> {
+        let progress = Arc::new(Progress::new(
+            "20230301",
+            &SyncDestType::File,
+            store.clone(),
+        ));
+        let (_tx, rx) = broadcast::channel(1);
+        sync_task(vec![progress.clone()], rx, 2, resume, SyncQueue::default())
+            .await
+            .unwrap();
+        progress.report()
+    }
+
+    #[tokio::test]
+    async fn test_sync_resume() {
+        let done = Arc::new(Mutex::new(HashSet::new()));
+        let fetched = Arc::new(Mutex::new(Vec::new()));
+        let mem = MemStore {
+            syncer: vec![
+                (
+                    SyncDataType::StockBar,
+                    Arc::new(Box::new(CodeSyncer {
+                        codes: vec!["sh600000", "sh600001"],
+                        done: done.clone(),
+                        fetched: fetched.clone(),
+                        fail_save: false,
+                    })),
+                ),
+                (SyncDataType::StockYJBB, Arc::new(Box::new(FailSyncer))),
+            ],
+            checkpoint: Mutex::new(vec![Checkpoint {
+                run: "20230301".to_owned(),
+                name: "stock_daily".to_owned(),
+                code: "sh600000".to_owned(),
+                done: true,
+                ..Default::default()
+            }]),
+            done: done.clone(),
+        };
+        let store: Arc<Box<dyn Store>> = Arc::new(Box::new(mem));
+
+        // 续传跳过已完成的代码
+        let report = run(&store, true).await;
+        assert_eq!(*fetched.lock().unwrap(), vec!["sh600001".to_owned()]);
+        assert_eq!(report.len(), 2);
+        let (daily, yjbb) = (&report[0], &report[1]);
+        assert_eq!(daily.typ, "stock_daily");
+        assert_eq!((daily.fetched, daily.saved, daily.skipped), (2, 2, 1));
+        assert_eq!(daily.failed, 0);
+        assert_eq!(yjbb.typ, "stock_yjbb");
+        assert_eq!(yjbb.failed, 1);
+        assert_eq!(yjbb.errors, vec!["remote error".to_owned()]);
+
+        let checkpoint = store.load_checkpoint("20230301").await.unwrap();
+        let find = |name: &str, code: &str| {
+            checkpoint
+                .iter()
+                .find(|cp| cp.name == name && cp.code == code)
+                .cloned()
+        };
+        assert!(find("stock_daily", "sh600001").unwrap().done);
+        assert!(find("stock_daily", "").unwrap().done);
+        let failed = find("stock_yjbb", "").unwrap();
+        assert!(!failed.done);
+        assert_eq!(failed.error.as_deref(), Some("remote error"));
+        assert!(store.load_checkpoint("20230302").await.unwrap().is_empty());
+
+        // 已完成的同步类型整体跳过
+        fetched.lock().unwrap().clear();
+        let report = run(&store, true).await;
+        assert!(fetched.lock().unwrap().is_empty());
+        assert_eq!((report[0].fetched, report[0].skipped), (0, 1));
+        assert_eq!(report[1].failed, 1);
+    }
+
+    fn mem_store(fetched: &Arc<Mutex<Vec<String>>>, fail_save: bool) -> Arc<Box<dyn Store>> {
+        let done = Arc::new(Mutex::new(HashSet::new()));
+        let mem = MemStore {
+            syncer: vec![
+                (
+                    SyncDataType::StockBar,
+                    Arc::new(Box::new(CodeSyncer {
+                        codes: vec!["sh600000", "sh600001"],
+                        done: done.clone(),
+                        fetched: fetched.clone(),
+                        fail_save,
+                    })),
+                ),
+                (SyncDataType::StockYJBB, Arc::new(Box::new(FailSyncer))),
+            ],
+            checkpoint: Mutex::new(vec![]),
+            done,
+        };
+        Arc::new(Box::new(mem))
+    }
+
+    #[tokio::test]
+    async fn test_sync_fan_out() {
+        let (fetched, peer_fetched) = (
+            Arc::new(Mutex::new(Vec::new())),
+            Arc::new(Mutex::new(Vec::new())),
+        );
+        let store = mem_store(&fetched, false);
+        let peer = mem_store(&peer_fetched, true);
+        let run = |resume: bool| {
+            let progress = vec![
+                Arc::new(Progress::new(
+                    "20230301",
+                    &SyncDestType::File,
+                    store.clone(),
+                )),
+                Arc::new(Progress::new(
+                    "20230301",
+                    &SyncDestType::SQLite,
+                    peer.clone(),
+                )),
+            ];
+            async move {
+                let (_tx, rx) = broadcast::channel(1);
+                sync_task(progress.clone(), rx, 2, resume, SyncQueue::default())
+                    .await
+                    .unwrap();
+                (progress[0].report(), progress[1].report())
+            }
+        };
+
+        // 按代码同步的数据只获取一次，保存失败不影响其他数据源，其他数据各数据源分别获取
+        let (report, peer_report) = run(false).await;
+        let codes = vec!["sh600000".to_owned(), "sh600001".to_owned()];
+        assert_eq!(*fetched.lock().unwrap(), codes);
+        assert!(peer_fetched.lock().unwrap().is_empty());
+        assert_eq!(
+            (report[0].fetched, report[0].saved, report[0].failed),
+            (4, 4, 0)
+        );
+        assert_eq!(report[0].dest, "File");
+        assert_eq!(
+            (
+                peer_report[0].fetched,
+                peer_report[0].saved,
+                peer_report[0].failed
+            ),
+            (4, 0, 2)
+        );
+        assert_eq!(peer_report[0].dest, "SQLite");
+        assert_eq!(peer_report[0].errors, vec!["db error".to_owned(); 2]);
+        assert_eq!((report[1].failed, peer_report[1].failed), (1, 1));
+
+        let checkpoint = store.load_checkpoint("20230301").await.unwrap();
+        assert!(checkpoint
+            .iter()
+            .any(|cp| cp.name == "stock_daily" && cp.code.is_empty() && cp.done));
+        let checkpoint = peer.load_checkpoint("20230301").await.unwrap();
+        assert!(checkpoint
+            .iter()
+            .filter(|cp| cp.name == "stock_daily")
+            .all(|cp| !cp.done));
+
+        // 续传只跳过各数据源都已完成的代码
+        fetched.lock().unwrap().clear();
+        let (report, _) = run(true).await;
+        assert_eq!(*fetched.lock().unwrap(), codes);
+        assert_eq!((report[0].fetched, report[0].skipped), (4, 0));
+    }
+
+    #[tokio::test]
+    async fn test_sync_queue() {
+        let codes: Vec<_> = (0..6).map(|i| format!("sh60000{}", i)).collect();
+        let run = |capacity: usize| {
+            let mem = MemStore {
+                syncer: vec![(
+                    SyncDataType::StockBar,
+                    Arc::new(Box::new(SlowSyncer {
+                        codes: codes.clone(),
+                    })),
+                )],
+                checkpoint: Mutex::new(vec![]),
+                done: Arc::new(Mutex::new(HashSet::new())),
+            };
+            let store: Arc<Box<dyn Store>> = Arc::new(Box::new(mem));
+            let progress = Arc::new(Progress::new("20230301", &SyncDestType::File, store));
+            let queue = SyncQueue {
+                capacity,
+                batch_size: 4,
+            };
+            async move {
+                let (_tx, rx) = broadcast::channel(1);
+                sync_task(vec![progress.clone()], rx, 1, false, queue)
+                    .await
+                    .unwrap();
+                let checkpoint = progress.store.load_checkpoint("20230301").await.unwrap();
+                (progress.report().remove(0), checkpoint)
+            }
+        };
+
+        // 队列满时获取等待保存
+        let (report, checkpoint) = run(1).await;
+        assert_eq!((report.fetched, report.saved, report.failed), (12, 12, 0));
+        assert_eq!(report.queue_peak, 1);
+        assert!(report.queue_wait > 0.0);
+        for code in codes.iter() {
+            assert!(checkpoint.iter().any(|cp| &cp.code == code && cp.done));
+        }
+
+        // 队列中的数据合并到batch_size条后保存
+        let (report, checkpoint) = run(8).await;
+        assert_eq!((report.fetched, report.saved, report.failed), (12, 12, 0));
+        assert!(report.queue_peak > 1);
+        assert!(report.batches < codes.len());
+        for code in codes.iter() {
+            assert!(checkpoint.iter().any(|cp| &cp.code == code && cp.done));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_result() {
+        assert!(task_result(tokio::spawn(async { Ok(()) }).await).is_ok());
+        let res = tokio::spawn(async { Err(Error::Custom("store error".to_owned())) }).await;
+        assert_eq!(task_result(res).unwrap_err().to_string(), "store error");
+        let res = tokio::spawn(async {
+            if true {
+                panic!("sync task crashed");
+            }
+            Ok(())
+        })
+        .await;
+        let e = task_result(res).unwrap_err().to_string();
+        assert!(e.contains("panic"), "{}", e);
+    }
+
     #[test]
     fn test() {
         fern::Dispatch::new()
@@ -1,10 +1,16 @@
-use std::time::Duration;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use chrono::{Local, NaiveDate, Timelike};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
 use tokio::sync::mpsc;
 
-use crate::{types::SyncData, Result};
+use crate::{
+    types::{SyncData, SyncDataType},
+    Result,
+};
 
 /// 封装获取数据函数，方便无参数调用，出错时重试等
 #[async_trait]
@@ -31,34 +37,77 @@ pub async fn retry(func: impl AsyncFunc) -> Result<Option<SyncData>> {
     }
 }
 
+/// 当日数据可同步的时间，未设置时为15:05
+static SYNC_CUTOFF: RwLock<Option<NaiveTime>> = RwLock::new(None);
+
+/// 设置当日数据可同步的时间，该时间之前不同步当日数据
+pub fn set_sync_cutoff(cutoff: NaiveTime) {
+    *SYNC_CUTOFF.write().unwrap() = Some(cutoff);
+}
+
+/// 当日数据可同步的时间
+pub fn sync_cutoff() -> NaiveTime {
+    SYNC_CUTOFF
+        .read()
+        .unwrap()
+        .unwrap_or_else(|| NaiveTime::from_hms_opt(15, 5, 0).unwrap())
+}
+
 /// 判断是否可以同步
 pub fn need_to_start(start: &Option<NaiveDate>) -> bool {
+    need_to_start_at(start, &Local::now().naive_local(), &sync_cutoff())
+}
+
+/// 判断`now`时是否可以同步，开始日期为当日时需过了`cutoff`
+fn need_to_start_at(start: &Option<NaiveDate>, now: &NaiveDateTime, cutoff: &NaiveTime) -> bool {
     if let Some(s) = start {
-        let now = Local::now().naive_local();
-        let hour = now.hour();
-        let min = now.minute();
         let n = now.date();
-        if s == &n {
-            if hour <= 15 {
-                if hour == 15 && min > 5 {
-                    return true;
-                }
-                return false;
-            }
+        if s == &n && &now.time() <= cutoff {
+            return false;
         }
         if s > &n {
             return false;
         }
     }
-    return true;
+    true
 }
 
+/// syncer及其同步类型
+pub type TypedSyncer = (SyncDataType, Arc<Box<dyn Syncer>>);
+
 /// 同步接口
 #[async_trait]
 pub trait Syncer: Sync + Send {
-    /// 获取远程数据，如果有数据，则塞进队列
-    async fn fetch(&self, _tx: mpsc::UnboundedSender<SyncData>) -> Result<()>;
+    /// 获取远程数据，如果有数据，则塞进队列，队列满时等待保存
+    async fn fetch(&self, _tx: mpsc::Sender<SyncData>) -> Result<()>;
 
     /// 保存远程数据，独立任务保存
     async fn save(&self, data: SyncData) -> Result<()>;
+
+    /// 按代码记录同步进度的表名，保存后记录代码已完成，续传时跳过，None则不按代码记录，
+    /// 不为None时保存的一批数据可能合并了多个代码
+    fn progress_tab(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    use super::need_to_start_at;
+
+    #[test]
+    fn test_need_to_start_at() {
+        let now = |s: &str| NaiveDateTime::parse_from_str(s, "%Y%m%d %H:%M").unwrap();
+        let cutoff = NaiveTime::from_hms_opt(15, 5, 0).unwrap();
+        let today = NaiveDate::from_ymd_opt(2023, 3, 1);
+        assert!(need_to_start_at(&None, &now("20230301 10:00"), &cutoff));
+        assert!(!need_to_start_at(&today, &now("20230301 15:00"), &cutoff));
+        assert!(need_to_start_at(&today, &now("20230301 15:06"), &cutoff));
+        assert!(!need_to_start_at(&today, &now("20230228 20:00"), &cutoff));
+
+        let cutoff = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        assert!(!need_to_start_at(&today, &now("20230301 15:06"), &cutoff));
+    }
 }
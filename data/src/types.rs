@@ -1,23 +1,42 @@
 //! 公共基本数据类型
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
 
 use rwqfetch::{
-    Bar, BondInfo, FundInfo, FundNet, StockConcept, StockConceptDetail, StockIndex, StockIndustry,
-    StockIndustryDetail, StockInfo, StockMargin, StockYJBB, TradeDate,
+    Bar, BarFreq, BondInfo, FundInfo, FundNet, StockConcept, StockConceptDetail, StockIndex,
+    StockIndustry, StockIndustryDetail, StockInfo, StockMargin, StockYJBB, TradeDate,
 };
 
-use crate::Error;
+use crate::{
+    store::{minute_tab, DataType},
+    Error,
+};
+
+/// 文件存储的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    #[default]
+    Parquet,
+    Csv,
+}
 
 /// 目的数据源
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SyncDest {
-    File(PathBuf),
+    File(PathBuf, FileFormat),
     MongoDB(String),
     MySQL(String),
+    SQLite(PathBuf),
 }
 
 /// 转换为`SyncDest`  
-/// 格式为(file, path), (mongodb, url), (mysql, url)
+/// 格式为(file, path), (csv, path), (mongodb, url), (mysql, url), (sqlite, path)，
+/// file为Parquet格式的文件存储，csv为CSV格式的文件存储
 impl TryFrom<(String, String)> for SyncDest {
     type Error = Error;
 
@@ -25,9 +44,11 @@ impl TryFrom<(String, String)> for SyncDest {
         let typ = value.0.to_lowercase();
         let val = value.1;
         match typ.as_str() {
-            "file" => Ok(SyncDest::File(PathBuf::from(val))),
+            "file" => Ok(SyncDest::File(PathBuf::from(val), FileFormat::Parquet)),
+            "csv" => Ok(SyncDest::File(PathBuf::from(val), FileFormat::Csv)),
             "mongodb" => Ok(SyncDest::MongoDB(val)),
             "mysql" => Ok(SyncDest::MySQL(val)),
+            "sqlite" => Ok(SyncDest::SQLite(PathBuf::from(val))),
             _ => Err(Error::Custom(format!("Invalid SyncDest: {}", typ.as_str()))),
         }
     }
@@ -39,6 +60,7 @@ pub enum SyncDestType {
     File = 1,
     MongoDB,
     MySQL,
+    SQLite,
 }
 
 impl TryFrom<i32> for SyncDestType {
@@ -49,6 +71,7 @@ impl TryFrom<i32> for SyncDestType {
             1 => Ok(SyncDestType::File),
             2 => Ok(SyncDestType::MongoDB),
             3 => Ok(SyncDestType::MySQL),
+            4 => Ok(SyncDestType::SQLite),
             _ => Err(Error::Custom(format!("Invalid SyncDestType: {}", v))),
         }
     }
@@ -81,10 +104,75 @@ pub enum SyncData {
     BondInfo(Vec<BondInfo>),
     BondBar(Vec<Bar>),
 
+    // minute
+    MinuteBar(Vec<Bar>),
+
     // tag
     Done,
 }
 
+impl SyncData {
+    /// 按代码同步的数据的代码，同一批数据的代码相同，其他数据为空
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            SyncData::IndexBar(data)
+            | SyncData::StockBar(data)
+            | SyncData::StockIndustryBar(data)
+            | SyncData::StockConceptBar(data)
+            | SyncData::FundBar(data)
+            | SyncData::BondBar(data)
+            | SyncData::MinuteBar(data) => data.first().map(|e| e.code.as_str()),
+            SyncData::FundNet(data) => data.first().map(|e| e.code.as_str()),
+            SyncData::StockMargin(data) => data.first().map(|e| e.code.as_str()),
+            _ => None,
+        }
+    }
+    /// 数据条数
+    pub fn len(&self) -> usize {
+        match self {
+            SyncData::TradeDate(data) => data.len(),
+            SyncData::IndexInfo(data) | SyncData::StockInfo(data) => data.len(),
+            SyncData::IndexBar(data)
+            | SyncData::StockBar(data)
+            | SyncData::StockIndustryBar(data)
+            | SyncData::StockConceptBar(data)
+            | SyncData::FundBar(data)
+            | SyncData::BondBar(data)
+            | SyncData::MinuteBar(data) => data.len(),
+            SyncData::StockIndex(data) => data.len(),
+            SyncData::StockIndustry(data) => data.len(),
+            SyncData::StockIndustryDetail(data) => data.len(),
+            SyncData::StockConcept(data) => data.len(),
+            SyncData::StockConceptDetail(data) => data.len(),
+            SyncData::StockYJBB(data) => data.len(),
+            SyncData::StockMargin(data) => data.len(),
+            SyncData::FundInfo(data) => data.len(),
+            SyncData::FundNet(data) => data.len(),
+            SyncData::BondInfo(data) => data.len(),
+            SyncData::Done => 0,
+        }
+    }
+    /// 合并按代码同步的同类型数据，类型不同或不是按代码同步的数据时返回`other`
+    pub fn merge(&mut self, other: SyncData) -> std::result::Result<(), SyncData> {
+        match (self, other) {
+            (SyncData::IndexBar(data), SyncData::IndexBar(other))
+            | (SyncData::StockBar(data), SyncData::StockBar(other))
+            | (SyncData::StockIndustryBar(data), SyncData::StockIndustryBar(other))
+            | (SyncData::StockConceptBar(data), SyncData::StockConceptBar(other))
+            | (SyncData::FundBar(data), SyncData::FundBar(other))
+            | (SyncData::BondBar(data), SyncData::BondBar(other))
+            | (SyncData::MinuteBar(data), SyncData::MinuteBar(other)) => data.extend(other),
+            (SyncData::FundNet(data), SyncData::FundNet(other)) => data.extend(other),
+            (SyncData::StockMargin(data), SyncData::StockMargin(other)) => data.extend(other),
+            (_, other) => return Err(other),
+        }
+        Ok(())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum SyncDataType {
     TradeDate = 1,
@@ -111,6 +199,9 @@ pub enum SyncDataType {
     // bond
     BondInfo,
     BondBar,
+
+    // minute
+    MinuteBar,
 }
 
 impl TryFrom<i32> for SyncDataType {
@@ -142,11 +233,49 @@ impl TryFrom<i32> for SyncDataType {
             // bond
             18 => Ok(SyncDataType::BondInfo),
             19 => Ok(SyncDataType::BondBar),
+
+            // minute
+            20 => Ok(SyncDataType::MinuteBar),
             _ => Err(Error::Custom(format!("Invalid SyncDataType: {}", v))),
         }
     }
 }
 
+impl SyncDataType {
+    /// 名称，与`TryFrom<&str>`对应
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncDataType::TradeDate => "trade_date",
+            // stock
+            SyncDataType::IndexInfo => "index_info",
+            SyncDataType::IndexBar => "index_daily",
+            SyncDataType::StockInfo => "stock_info",
+            SyncDataType::StockBar => "stock_daily",
+            SyncDataType::StockIndex => "stock_index",
+            SyncDataType::StockIndustry => "stock_industry",
+            SyncDataType::StockIndustryDetail => "stock_industry_detail",
+            SyncDataType::StockIndustryBar => "stock_industry_daily",
+            SyncDataType::StockConcept => "stock_concept",
+            SyncDataType::StockConceptDetail => "stock_concept_detail",
+            SyncDataType::StockConceptBar => "stock_concept_daily",
+            SyncDataType::StockYJBB => "stock_yjbb",
+            SyncDataType::StockMargin => "stock_margin",
+
+            // fund
+            SyncDataType::FundInfo => "fund_info",
+            SyncDataType::FundNet => "fund_net",
+            SyncDataType::FundBar => "fund_daily",
+
+            // bond
+            SyncDataType::BondInfo => "bond_info",
+            SyncDataType::BondBar => "bond_daily",
+
+            // minute
+            SyncDataType::MinuteBar => "minute",
+        }
+    }
+}
+
 impl TryFrom<&str> for SyncDataType {
     type Error = Error;
 
@@ -178,6 +307,9 @@ impl TryFrom<&str> for SyncDataType {
             // bond
             "bond_info" => Ok(SyncDataType::BondInfo),
             "bond_daily" => Ok(SyncDataType::BondBar),
+
+            // minute
+            "minute" => Ok(SyncDataType::MinuteBar),
             _ => Err(Error::Custom(format!(
                 "Invalid SyncDataType: {}",
                 v.as_str()
@@ -185,3 +317,210 @@ impl TryFrom<&str> for SyncDataType {
         }
     }
 }
+
+/// 代码范围，包含首尾，None表示不限
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeRange {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl CodeRange {
+    pub fn contains(&self, code: &str) -> bool {
+        self.start.as_deref().is_none_or(|s| code >= s)
+            && self.end.as_deref().is_none_or(|e| code <= e)
+    }
+}
+
+/// 转换为`CodeRange`  
+/// 格式为`sh600000..sh600999`，`sh600000..`，`..sz000100`，`..`(全部) 或单个代码`sh600000`
+impl TryFrom<&str> for CodeRange {
+    type Error = Error;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        let bound = |s: &str| {
+            let s = s.trim();
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        };
+        let range = match v.split_once("..") {
+            Some((start, end)) => CodeRange {
+                start: bound(start),
+                end: bound(end),
+            },
+            None => match bound(v) {
+                Some(code) => CodeRange {
+                    start: Some(code.clone()),
+                    end: Some(code),
+                },
+                None => return Err(Error::Custom(format!("Invalid CodeRange: {}", v))),
+            },
+        };
+        Ok(range)
+    }
+}
+
+/// 重新同步选项，按日同步的数据按(code, trade_date)覆盖写入，重复同步不会产生重复数据
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resync {
+    /// 重新获取最近的交易日数，覆盖数据源修正过的数据，0表示从最新数据的下一个交易日开始
+    pub overlap_days: usize,
+    /// 全量重新同步的代码，从头同步，保存成功后再删除原有数据
+    pub full: Option<CodeRange>,
+    /// 全量重新同步的代码列表，如数据检查发现重复数据的代码
+    pub full_codes: BTreeSet<String>,
+    /// 从指定日期开始重新同步的代码，如数据检查发现缺失数据的代码
+    pub since: BTreeMap<String, NaiveDate>,
+}
+
+impl Resync {
+    /// 代码是否需要全量重新同步
+    pub fn is_full(&self, code: &str) -> bool {
+        self.full.as_ref().is_some_and(|r| r.contains(code)) || self.full_codes.contains(code)
+    }
+}
+
+/// 分钟线同步配置，按品种和频率保存到不同的表
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinuteSync {
+    /// 品种，支持股票，指数，基金，可转债
+    pub typ: DataType,
+    /// 频率，支持1，5，15，30，60分钟
+    pub freq: BarFreq,
+    /// 保留最近的交易日数，同步时删除更早的数据，0表示全部保留
+    pub retain_days: usize,
+}
+
+impl MinuteSync {
+    /// 保存的表
+    pub fn tab(&self) -> Result<&'static str, Error> {
+        minute_tab(self.typ, self.freq)
+    }
+}
+
+/// 转换为`MinuteSync`  
+/// 格式为`品种:分钟[:保留交易日数]`，品种为stock, index, fund, bond，如：stock:5, index:1:20
+impl TryFrom<&str> for MinuteSync {
+    type Error = Error;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        let invalid = || Error::Custom(format!("Invalid MinuteSync: {}", v));
+        let mut parts = v.split(':').map(|s| s.trim());
+        let typ = match parts.next().map(|s| s.to_lowercase()).as_deref() {
+            Some("stock") => DataType::Stock,
+            Some("index") => DataType::Index,
+            Some("fund") => DataType::Fund,
+            Some("bond") => DataType::Bond,
+            _ => return Err(invalid()),
+        };
+        let freq = parts
+            .next()
+            .and_then(|s| s.parse::<i32>().ok())
+            .map(BarFreq::from)
+            .ok_or_else(invalid)?;
+        let retain_days = match parts.next() {
+            Some(s) => s.parse().map_err(|_| invalid())?,
+            None => 0,
+        };
+        if parts.next().is_some() || minute_tab(typ, freq).is_err() {
+            return Err(invalid());
+        }
+        Ok(MinuteSync {
+            typ,
+            freq,
+            retain_days,
+        })
+    }
+}
+
+/// 同步队列选项，获取与保存之间为有界队列，队列满时获取等待保存，
+/// 缓存的数据最多约为 获取task数 × (数据源数 + 1) × `capacity` 批
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncQueue {
+    /// 每个队列最多缓存的批数，一批为一次获取的数据，如一个代码的日线
+    pub capacity: usize,
+    /// 按代码同步的数据合并保存的条数，队列中已有的数据合并到该条数后一次保存
+    pub batch_size: usize,
+}
+
+impl Default for SyncQueue {
+    fn default() -> Self {
+        Self {
+            capacity: 8,
+            batch_size: 5000,
+        }
+    }
+}
+
+/// 同步进度，按代码同步的数据每个代码保存后记录一条，`name`为表名，
+/// 同步类型全部完成或获取失败时记录一条`code`为空的，`name`为同步类型名称
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// 同步标识，相同标识的同步可续传
+    pub run: String,
+    /// 表名或同步类型名称
+    pub name: String,
+    /// 代码
+    pub code: String,
+    /// 是否完成
+    pub done: bool,
+    /// 失败的错误
+    pub error: Option<String>,
+    /// 更新时间，unix时间戳
+    pub update_time: i64,
+}
+
+/// 每种同步类型的同步结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TypeReport {
+    /// 目的数据源
+    pub dest: String,
+    /// 同步类型名称
+    pub typ: String,
+    /// 获取的数据条数
+    pub fetched: usize,
+    /// 保存的数据条数
+    pub saved: usize,
+    /// 续传时跳过的syncer及代码数
+    pub skipped: usize,
+    /// 获取或保存失败次数
+    pub failed: usize,
+    /// 获取数据耗时秒数，多个syncer的累计
+    pub duration: f64,
+    /// 保存队列的最大长度(批数)
+    #[serde(default)]
+    pub queue_peak: usize,
+    /// 获取时因保存队列满等待的秒数
+    #[serde(default)]
+    pub queue_wait: f64,
+    /// 保存的次数，合并保存的计为一次
+    #[serde(default)]
+    pub batches: usize,
+    /// 部分错误信息
+    pub errors: Vec<String>,
+}
+
+/// 一次同步的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// 同步标识
+    pub run: String,
+    /// 是否续传
+    pub resume: bool,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// 是否被停止信号中断
+    pub interrupted: bool,
+    pub types: Vec<TypeReport>,
+}
+
+impl SyncReport {
+    /// 获取或保存失败的总次数
+    pub fn failed(&self) -> usize {
+        self.types.iter().map(|t| t.failed).sum()
+    }
+}
@@ -1,10 +1,11 @@
 use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
-use bson::doc;
 use rwqstrategy::{
-    stat_result, store::Loader, strategy_to_data_type, Error, Result, Strategy, StrategyResult,
-    StrategyType,
+    market_to_data_type,
+    select::{stat_result, Strategy, StrategyResult},
+    store::{Loader, Order, Query},
+    Error, MarketType, Result,
 };
 
 #[derive(Debug, Clone)]
@@ -20,11 +21,11 @@ impl Strategy for TestStrategy {
     async fn test(
         &self,
         loader: Arc<Box<dyn Loader>>,
-        typ: StrategyType,
+        typ: MarketType,
         code: String,
         name: String,
     ) -> Result<Option<StrategyResult>> {
-        let codes = vec![
+        let codes = [
             "sz002805".to_string(),
             "sz300827".to_string(),
             "sz000762".to_string(),
@@ -32,18 +33,19 @@ impl Strategy for TestStrategy {
         if codes.contains(&code) {
             let data = loader
                 .load_daily(
-                    strategy_to_data_type(typ),
-                    doc! {"code": &code},
-                    doc! {"trade_date": -1},
-                    Some(60),
+                    market_to_data_type(typ),
+                    Query::new()
+                        .code(&code)
+                        .sort("trade_date", Order::Desc)
+                        .limit(60),
                 )
                 .await
-                .map_err(|e| Error::Custom(format!("load_daily error: {}", e.to_string())))?;
+                .map_err(|e| Error::Custom(format!("load_daily error: {}", e)))?;
             let stat = stat_result(&data, 3, 15)
-                .map_err(|e| Error::Custom(format!("stat result error: {}", e.to_string())))?;
+                .map_err(|e| Error::Custom(format!("stat result error: {}", e)))?;
 
             let mut mark = HashMap::new();
-            let data0 = data.get(0).unwrap();
+            let data0 = data.first().unwrap();
             let data1 = data.get(1).unwrap();
             mark.insert(
                 data0.trade_date.date(),
@@ -1,7 +1,7 @@
 use crate::bond::trans_info::EastBondInfo;
-use crate::comm::{async_client, fetch_bar};
+use crate::comm::{async_client, LimitedSend};
 use crate::util::to_std_code;
-use crate::{Market, MarketType, Result, HTTP_CMM_HEADER};
+use crate::{providers, MarketType, Result, HTTP_CMM_HEADER};
 use chrono::naive::NaiveDate;
 use chrono::NaiveDateTime;
 use rwqcmm::{BarFreq, BondBar, BondInfo};
@@ -28,7 +28,7 @@ pub async fn fetch_bond_info() -> Result<Vec<BondInfo>> {
         let resp = async_client()
             .get(req_url)
             .headers(HTTP_CMM_HEADER.to_owned())
-            .send()
+            .send_limited()
             .await?
             .text()
             .await?;
@@ -73,6 +73,8 @@ pub async fn fetch_bond_info() -> Result<Vec<BondInfo>> {
 /// 获取可转债基本
 ///
 /// *code* 可转债代码，其中11开头的为深市，12开头的为沪市。
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个
 pub async fn fetch_bond_bar(
     code: &str,
     name: &str,
@@ -83,19 +85,14 @@ pub async fn fetch_bond_bar(
     end: Option<NaiveDate>,
     skip_rt: bool,
 ) -> Result<BondBar> {
-    let market_code = if code.starts_with("sz") {
-        // 深圳市场
-        format!("{}.{}", Market::SZ as i32, &code[2..])
-    } else {
-        format!("{}.{}", Market::SH as i32, &code[2..])
-    };
     let freq = if freq.is_none() {
         BarFreq::Daily
     } else {
         freq.unwrap()
     };
-    let client = async_client();
-    let bars = fetch_bar(&client, &market_code, code, freq, start, end, skip_rt).await?;
+    let bars = providers()
+        .fetch_bar(code, freq, start, end, skip_rt)
+        .await?;
     let bond_bar = BondBar {
         code: code.to_owned(),
         name: name.to_owned(),
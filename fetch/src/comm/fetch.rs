@@ -1,8 +1,9 @@
-use crate::comm::EastBar;
-use crate::{AdjustFactor, Error, Result, XuQiuRtQuot, HTTP_CMM_HEADER};
+use crate::comm::{EastBar, LimitedSend, XueQiuBar};
+use crate::{providers, AdjustFactor, Error, Result, XuQiuRtQuot, HTTP_CMM_HEADER};
 use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use once_cell::sync::Lazy;
 use regex::Regex;
-use rwqcmm::{Bar, BarFreq, Quot, QuotSn, QuotXq, RtQuot, RtQuotSn, RtQuotXq};
+use rwqcmm::{Bar, BarFreq, QuotSn, QuotXq, RtQuot, RtQuotSn, RtQuotXq};
 use std::ops::Add;
 use tracing::{debug, instrument};
 
@@ -16,12 +17,17 @@ use super::trade_date::fetch_prev_trade_date;
 //         .unwrap()
 // }
 
-pub(crate) fn async_client() -> reqwest::Client {
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
     reqwest::ClientBuilder::new()
         .cookie_store(true)
         .default_headers(HTTP_CMM_HEADER.to_owned())
         .build()
         .unwrap()
+});
+
+/// 共用的http客户端，复用连接和cookie，请求需通过`send_limited`按站点限流
+pub(crate) fn async_client() -> reqwest::Client {
+    HTTP_CLIENT.clone()
 }
 
 pub(crate) fn to_bar_ds(name: Option<&str>, bars: Vec<Bar>) -> (String, Option<Vec<Bar>>) {
@@ -110,7 +116,7 @@ pub(crate) async fn fetch_bar(
 
         debug!(request = req_url);
 
-        let resp = client.get(req_url).send().await?.text().await?;
+        let resp = client.get(req_url).send_limited().await?.text().await?;
         let mut pre_item: Option<Bar> = None;
         let json: EastBar = serde_json::from_str(&resp)?;
         let tmp_bars: Option<Vec<_>> = if let Some(data) = json.data {
@@ -210,6 +216,126 @@ pub(crate) async fn fetch_bar(
     Ok(data)
 }
 
+/// 雪球日k线数据，`fq`为复权方式: normal不复权，before前复权，after后复权
+pub(crate) async fn fetch_bar_xq(
+    code: &str,
+    name: &str,
+    fq: &str,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> Result<Vec<Bar>> {
+    let mut first_date: Option<i32> = None;
+    let code = code.to_uppercase();
+
+    let mut start = if let Some(st) = &start {
+        let prev = fetch_prev_trade_date(&st).await?;
+        first_date = Some(prev);
+        NaiveDateTime::parse_from_str(&format!("{} 00:00:00", prev), "%Y%m%d %H:%M:%S").unwrap()
+    } else {
+        NaiveDateTime::parse_from_str("2010-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    };
+
+    let end = end.map_or(Local::now().naive_local(), |d| {
+        d.and_hms_opt(0, 0, 0).unwrap()
+    });
+
+    let mut data = Vec::new();
+
+    let mut pre_item: Option<Bar> = None;
+    // prepare cookie
+    async_client()
+        .get("https://xueqiu.com/hq")
+        .send_limited()
+        .await?;
+    while start <= end {
+        let timestamp = start.timestamp() * 1000;
+        let req_url = format!(
+            "https://stock.xueqiu.com/v5/stock/chart/kline.json?\
+            symbol={code}&begin={timestamp}&period=day&type={fq}&count=100&indicator=kline",
+            code = code,
+            timestamp = timestamp,
+            fq = fq
+        );
+
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
+        let json: XueQiuBar = serde_json::from_str(&resp)?;
+
+        if let Some(result) = json.data {
+            let tmp_vec: Vec<_> = result
+                .item
+                .iter()
+                .map(|item| {
+                    // ["timestamp","volume","open","high","low","close","chg","percent","turnoverrate","amount","volume_post","amount_post"]
+                    let trade_date: NaiveDateTime =
+                        Local.timestamp_opt(item.0 / 1000, 0).unwrap().naive_local();
+
+                    let volume = item.1.unwrap_or(0);
+                    let amount = item.9.unwrap_or(0.0);
+                    let (volume_chg_pct, amount_chg_pct) = if let Some(item) = &pre_item {
+                        (
+                            (((volume as i64 - item.volume as i64) * 100) as f64
+                                / item.volume as f64) as f32,
+                            ((amount - item.amount) * 100.0 / item.amount) as f32,
+                        )
+                    } else {
+                        (0.0, 0.0)
+                    };
+
+                    let bar = Bar {
+                        code: result.code[2..].to_owned(),
+                        name: name.to_owned(),
+                        trade_date,
+                        open: item.2.unwrap_or(0.0),
+                        close: item.5.unwrap_or(0.0),
+                        high: item.3.unwrap_or(0.0),
+                        low: item.4.unwrap_or(0.0),
+                        volume,
+                        amount,
+                        volume_chg_pct,
+                        amount_chg_pct,
+                        turnover: item.8.unwrap_or(0.0),
+                        chg_pct: item.6.unwrap_or(0.0),
+                        hfq_factor: 1.0,
+                    };
+                    pre_item = Some(bar.clone());
+                    bar
+                })
+                .filter(|item| item.trade_date <= end)
+                .collect();
+            if tmp_vec.is_empty() {
+                break;
+            }
+            let last = tmp_vec[tmp_vec.len() - 1].trade_date.clone();
+
+            start = last.add(Duration::days(1));
+            data.extend(tmp_vec.into_iter());
+        } else {
+            break;
+        }
+    }
+    if let Some(first_date) = first_date {
+        if data.len() > 0 {
+            let first = data.get(0).unwrap();
+            let (y, m, d) = (
+                first.trade_date.year(),
+                first.trade_date.month(),
+                first.trade_date.day(),
+            );
+            let date = y * 10000 + m as i32 * 100 + d as i32;
+            if first_date == date {
+                data = data.into_iter().skip(1).collect();
+            }
+        }
+    }
+
+    Ok(data)
+}
+
 /// 雪球实时行情
 pub async fn fetch_rt_quot_xq(code: &Vec<String>) -> Result<RtQuotXq> {
     let codes = code
@@ -225,7 +351,7 @@ pub async fn fetch_rt_quot_xq(code: &Vec<String>) -> Result<RtQuotXq> {
     );
     let client = async_client();
 
-    let resp = client.get(req_url).send().await?.text().await?;
+    let resp = client.get(req_url).send_limited().await?.text().await?;
 
     let json: XuQiuRtQuot = serde_json::from_str(&resp)?;
     let data = json
@@ -283,7 +409,7 @@ pub async fn fetch_rt_quot_sn(code: &Vec<String>) -> Result<RtQuotSn> {
     let resp = client
         .get(req_url)
         .header("Referer", "https://finance.sina.com.cn/")
-        .send()
+        .send_limited()
         .await?
         .text()
         .await?;
@@ -433,55 +559,19 @@ pub async fn fetch_rt_quot_sn(code: &Vec<String>) -> Result<RtQuotSn> {
     Ok(rq)
 }
 
+/// 实时行情
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个，
+/// 后一个可用来源的行情用于补充缺少的字段，如新浪的行情补充雪球的换手率及市值
 pub async fn fetch_rt_quot(code: &Vec<String>) -> Result<RtQuot> {
-    let (xq, sn) = tokio::join!(fetch_rt_quot_xq(code), fetch_rt_quot_sn(code));
-    let (mxq, msn) = (xq?, sn?);
-
-    if mxq.len() != msn.len() {
-        return Err(Error::Custom(String::from("fail to fetch quot")));
-    }
-
-    let rt_quot = msn
-        .into_iter()
-        .map(|(k, sn)| {
-            let xq = mxq.get(&k).unwrap();
-            let quot = Quot {
-                chg: xq.chg,
-                chg_pct: xq.chg_pct,
-                turnover: xq.turnover,
-                total_value: xq.total_value,
-                currency_value: xq.currency_value,
-                is_trading: xq.is_trading,
-                code: sn.code,
-                name: sn.name,
-                open: sn.open,
-                last_close: sn.last_close,
-                now: sn.now,
-                high: sn.high,
-                low: sn.low,
-                buy: sn.buy,
-                sell: sn.sell,
-                volume: sn.volume,
-                amount: sn.amount,
-                bid: sn.bid,
-                ask: sn.ask,
-                time: sn.time,
-                freq_open: 0.0,
-                freq_high: 0.0,
-                freq_low: 0.0,
-                freq_chg: 0.0,
-                freq_chg_pct: 0.0,
-                freq_time: Default::default()
-            };
-            (k.clone(), quot)
-        })
-        .collect();
-    Ok(rt_quot)
+    providers().fetch_rt_quot(code).await
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::fetch_rt_quot;
+    use super::*;
+    use crate::comm::fixtures;
+    use crate::{fetch_rt_quot, with_http_mode};
 
     #[test]
     fn test_fetch_rt_quot() {
@@ -535,4 +625,72 @@ mod tests {
                 });
             })
     }
+
+    /// 离线数据
+    fn replay<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(with_http_mode(fixtures(), f))
+    }
+
+    #[test]
+    fn test_parse_east_bar() {
+        let end = NaiveDate::from_ymd_opt(2023, 3, 3);
+        let client = async_client();
+        let bars = replay(fetch_bar(
+            &client,
+            "0.000001",
+            "sz000001",
+            BarFreq::Daily,
+            None,
+            end,
+            false,
+        ))
+        .unwrap();
+        assert_eq!(bars.len(), 3);
+        let bar = &bars[1];
+        assert_eq!(bar.code, "sz000001");
+        assert_eq!(bar.name, "平安银行");
+        assert_eq!(bar.trade_date.format("%Y%m%d").to_string(), "20230302");
+        assert_eq!(
+            (bar.open, bar.close, bar.high, bar.low),
+            (14.2, 14.1, 14.25, 14.0)
+        );
+        assert_eq!((bar.volume, bar.amount), (1000000, 1412345678.0));
+        assert_eq!((bar.chg_pct, bar.turnover), (-0.7, 0.52));
+        assert_eq!(bar.volume_chg_pct, -20.0);
+        assert!((bar.hfq_factor - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_rt_quot_xq() {
+        let codes = vec!["sh600887".to_owned(), "sz000001".to_owned()];
+        let data = replay(fetch_rt_quot_xq(&codes)).unwrap();
+        assert_eq!(data.len(), 2);
+        let quot = &data["sh600887"];
+        assert_eq!((quot.last_close, quot.now, quot.chg), (29.0, 29.35, 0.35));
+        assert_eq!((quot.volume, quot.turnover), (12345600, 0.19));
+        assert_eq!(data["sz000001"].total_value, 272900000000.0);
+    }
+
+    #[test]
+    fn test_parse_rt_quot_sn() {
+        let codes = vec!["sh600887".to_owned(), "bj832089".to_owned()];
+        let data = replay(fetch_rt_quot_sn(&codes)).unwrap();
+        assert_eq!(data.len(), 2);
+        let quot = &data["sh600887"];
+        assert_eq!(quot.name, "伊利股份");
+        assert_eq!((quot.open, quot.last_close, quot.now), (29.1, 29.0, 29.35));
+        assert_eq!((quot.volume, quot.amount), (12345600, 362345678.0));
+        assert_eq!(quot.bid.0, (100, 29.34));
+        assert_eq!(quot.ask.4, (2500, 29.39));
+        assert_eq!(quot.time.to_string(), "2023-03-01 15:00:00");
+
+        // 被反爬拦截的响应
+        let codes = vec!["sh600000".to_owned()];
+        let e = replay(fetch_rt_quot_sn(&codes)).unwrap_err();
+        assert!(e.to_string().contains("Kinsoku jikou desu"));
+    }
 }
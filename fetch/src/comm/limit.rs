@@ -0,0 +1,426 @@
+use super::record::{record, replay};
+use crate::{http_mode, Error, HttpMode, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// 被限流后，速率最低降到配置速率的比例
+const MIN_FACTOR: f64 = 1.0 / 16.0;
+/// 每次请求成功后恢复的速率比例
+const RECOVER_STEP: f64 = 0.05;
+
+/// 数据来源站点，同一站点的请求共用一个限流器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Site {
+    /// 东方财富
+    EastMoney,
+    /// 新浪
+    Sina,
+    /// 雪球
+    XueQiu,
+    /// 交易所(上交所，深交所，北交所)
+    Exchange,
+    /// 其他
+    Other,
+}
+
+impl Site {
+    /// 根据请求的域名判断所属站点
+    ///
+    /// # Examples
+    /// ```
+    /// use rwqfetch::Site;
+    /// assert_eq!(Site::from_host("push2his.eastmoney.com"), Site::EastMoney);
+    /// assert_eq!(Site::from_host("query.sse.com.cn"), Site::Exchange);
+    /// ```
+    pub fn from_host(host: &str) -> Site {
+        let is = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+        if is("eastmoney.com") {
+            Site::EastMoney
+        } else if is("sina.com.cn") || is("sinajs.cn") {
+            Site::Sina
+        } else if is("xueqiu.com") {
+            Site::XueQiu
+        } else if is("sse.com.cn") || is("szse.cn") || is("bse.cn") {
+            Site::Exchange
+        } else {
+            Site::Other
+        }
+    }
+
+    /// 站点被反爬拦截时，响应内容里出现的特征
+    fn block_marks(&self) -> &'static [&'static str] {
+        match self {
+            Site::Sina => &["Kinsoku jikou desu"],
+            Site::XueQiu => &["aliyun_waf"],
+            _ => &[],
+        }
+    }
+}
+
+/// 站点限流配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// 每秒请求数
+    pub rate: f64,
+    /// 令牌桶容量，即允许的突发请求数
+    pub burst: u32,
+    /// 最大同时请求数
+    pub max_inflight: usize,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            rate: 5.0,
+            burst: 5,
+            max_inflight: 4,
+        }
+    }
+}
+
+impl RateLimit {
+    /// 站点默认的限流配置
+    pub fn of(site: Site) -> Self {
+        match site {
+            Site::XueQiu => Self {
+                rate: 2.0,
+                burst: 2,
+                max_inflight: 2,
+            },
+            Site::Exchange => Self {
+                rate: 1.0,
+                burst: 1,
+                max_inflight: 1,
+            },
+            _ => Self::default(),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+    factor: f64,
+}
+
+struct Limiter {
+    site: Site,
+    limit: RateLimit,
+    inflight: Arc<Semaphore>,
+    bucket: Mutex<Bucket>,
+}
+
+impl Limiter {
+    fn new(site: Site, limit: RateLimit) -> Self {
+        Self {
+            site,
+            limit,
+            inflight: Arc::new(Semaphore::new(limit.max_inflight.max(1))),
+            bucket: Mutex::new(Bucket {
+                tokens: limit.burst.max(1) as f64,
+                last: Instant::now(),
+                factor: 1.0,
+            }),
+        }
+    }
+
+    /// 等待可以发起请求，返回的许可在请求完成前不能释放
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        let permit = self.inflight.clone().acquire_owned().await.unwrap();
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let rate = self.limit.rate.max(f64::EPSILON) * bucket.factor;
+                let elapsed = now.duration_since(bucket.last).as_secs_f64();
+                bucket.tokens =
+                    (bucket.tokens + elapsed * rate).min(self.limit.burst.max(1) as f64);
+                bucket.last = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    break;
+                }
+                (1.0 - bucket.tokens) / rate
+            };
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+        permit
+    }
+
+    /// 被限流，速率减半并清空令牌
+    fn throttle(&self, reason: &str) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.factor = (bucket.factor / 2.0).max(MIN_FACTOR);
+        bucket.tokens = 0.0;
+        warn!(
+            "{:?} throttled ({}), slow down to {:.2} req/s",
+            self.site,
+            reason,
+            self.limit.rate * bucket.factor
+        );
+    }
+
+    /// 请求成功，逐步恢复速率
+    fn recover(&self) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.factor = (bucket.factor + RECOVER_STEP).min(1.0);
+    }
+
+    fn factor(&self) -> f64 {
+        self.bucket.lock().unwrap().factor
+    }
+}
+
+static LIMITERS: Lazy<RwLock<HashMap<Site, Arc<Limiter>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn limiter(site: Site) -> Arc<Limiter> {
+    if let Some(limiter) = LIMITERS.read().unwrap().get(&site) {
+        return limiter.clone();
+    }
+    LIMITERS
+        .write()
+        .unwrap()
+        .entry(site)
+        .or_insert_with(|| Arc::new(Limiter::new(site, RateLimit::of(site))))
+        .clone()
+}
+
+/// 设置站点的限流配置，对之后发起的请求生效
+///
+/// # Examples
+/// ```
+/// use rwqfetch::{rate_limit, set_rate_limit, RateLimit, Site};
+/// let limit = RateLimit {
+///     rate: 2.0,
+///     burst: 2,
+///     max_inflight: 1,
+/// };
+/// set_rate_limit(Site::Sina, limit);
+/// assert_eq!(rate_limit(Site::Sina), limit);
+/// ```
+pub fn set_rate_limit(site: Site, limit: RateLimit) {
+    LIMITERS
+        .write()
+        .unwrap()
+        .insert(site, Arc::new(Limiter::new(site, limit)));
+}
+
+/// 站点当前的限流配置
+pub fn rate_limit(site: Site) -> RateLimit {
+    limiter(site).limit
+}
+
+/// 站点当前的速率比例，被限流后小于1，请求成功后逐步恢复到1
+pub fn rate_factor(site: Site) -> f64 {
+    limiter(site).factor()
+}
+
+enum Body {
+    Live(reqwest::Response),
+    Replay(Vec<u8>),
+}
+
+/// 限流后的响应，读取完内容后才释放并发许可
+pub(crate) struct LimitedResponse {
+    body: Body,
+    site: Site,
+    /// 回放时为空
+    limiter: Option<Arc<Limiter>>,
+    /// 记录时保存响应内容的文件
+    record: Option<PathBuf>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl LimitedResponse {
+    pub(crate) async fn text(self) -> Result<String> {
+        let LimitedResponse {
+            body,
+            site,
+            limiter,
+            record,
+            _permit,
+        } = self;
+        let text = match body {
+            Body::Live(resp) => resp.text().await?,
+            Body::Replay(body) => String::from_utf8_lossy(&body).into_owned(),
+        };
+        save_body(record, text.as_bytes());
+        if let Some(mark) = site.block_marks().iter().find(|mark| text.contains(*mark)) {
+            if let Some(limiter) = limiter {
+                limiter.throttle(mark);
+            }
+            return Err(Error::Custom(format!(
+                "Request blocked by {:?}: {}!",
+                site, mark
+            )));
+        }
+        Ok(text)
+    }
+
+    pub(crate) async fn bytes(self) -> Result<Vec<u8>> {
+        let LimitedResponse {
+            body,
+            record,
+            _permit,
+            ..
+        } = self;
+        let bytes = match body {
+            Body::Live(resp) => resp.bytes().await?.to_vec(),
+            Body::Replay(body) => body,
+        };
+        save_body(record, &bytes);
+        Ok(bytes)
+    }
+}
+
+fn save_body(record: Option<PathBuf>, body: &[u8]) {
+    if let Some(path) = record {
+        if let Err(e) = std::fs::write(&path, body) {
+            warn!("record response to {} error: {}", path.display(), e);
+        }
+    }
+}
+
+/// 429，403及新浪的456，一般是请求太频繁被拦截
+fn is_blocked(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::FORBIDDEN
+        || status.as_u16() == 456
+}
+
+/// 按站点限流发送请求，并按[`http_mode`]记录或回放响应
+#[async_trait]
+pub(crate) trait LimitedSend {
+    async fn send_limited(self) -> Result<LimitedResponse>;
+}
+
+#[async_trait]
+impl LimitedSend for reqwest::RequestBuilder {
+    async fn send_limited(self) -> Result<LimitedResponse> {
+        let (client, req) = self.build_split();
+        let req = req?;
+        let site = Site::from_host(req.url().host_str().unwrap_or_default());
+        let mode = http_mode();
+        if let HttpMode::Replay(dir) = &mode {
+            let (status, body) = replay(dir, &req)?;
+            if let Ok(status) = StatusCode::from_u16(status) {
+                if is_blocked(status) {
+                    return Err(Error::Custom(format!(
+                        "Request blocked by {:?}: status {}!",
+                        site, status
+                    )));
+                }
+            }
+            return Ok(LimitedResponse {
+                body: Body::Replay(body),
+                site,
+                limiter: None,
+                record: None,
+                _permit: None,
+            });
+        }
+        let record_req = match &mode {
+            HttpMode::Record(_) => req.try_clone(),
+            _ => None,
+        };
+        let limiter = limiter(site);
+        let permit = limiter.acquire().await;
+        match client.execute(req).await {
+            Ok(resp) => {
+                let status = resp.status();
+                let record = match (&mode, record_req) {
+                    (HttpMode::Record(dir), Some(req)) => record(dir, &req, status.as_u16())
+                        .map_err(|e| warn!("{}", e))
+                        .ok(),
+                    _ => None,
+                };
+                if is_blocked(status) {
+                    limiter.throttle(status.as_str());
+                    return Err(Error::Custom(format!(
+                        "Request blocked by {:?}: status {}!",
+                        site, status
+                    )));
+                }
+                limiter.recover();
+                Ok(LimitedResponse {
+                    body: Body::Live(resp),
+                    site,
+                    limiter: Some(limiter),
+                    record,
+                    _permit: Some(permit),
+                })
+            }
+            Err(e) => {
+                // 连接被拒绝或超时，多半是请求太频繁
+                if e.is_connect() || e.is_timeout() {
+                    limiter.throttle(&e.to_string());
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_from_host() {
+        assert_eq!(Site::from_host("push2.eastmoney.com"), Site::EastMoney);
+        assert_eq!(Site::from_host("hq.sinajs.cn"), Site::Sina);
+        assert_eq!(Site::from_host("finance.sina.com.cn"), Site::Sina);
+        assert_eq!(Site::from_host("stock.xueqiu.com"), Site::XueQiu);
+        assert_eq!(Site::from_host("www.szse.cn"), Site::Exchange);
+        assert_eq!(Site::from_host("www.bse.cn"), Site::Exchange);
+        assert_eq!(Site::from_host("noteastmoney.com"), Site::Other);
+    }
+
+    #[test]
+    fn test_limiter() {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let limiter = Limiter::new(
+                    Site::Other,
+                    RateLimit {
+                        rate: 20.0,
+                        burst: 2,
+                        max_inflight: 1,
+                    },
+                );
+                let start = Instant::now();
+                for _ in 0..4 {
+                    let _permit = limiter.acquire().await;
+                }
+                // 2个突发，另外2个按20req/s
+                assert!(start.elapsed() >= Duration::from_millis(90));
+
+                limiter.throttle("test");
+                limiter.throttle("test");
+                assert_eq!(limiter.factor(), 0.25);
+                let start = Instant::now();
+                let _permit = limiter.acquire().await;
+                assert!(start.elapsed() >= Duration::from_millis(190));
+                assert_eq!(limiter.inflight.available_permits(), 0);
+
+                for _ in 0..100 {
+                    limiter.recover();
+                }
+                assert_eq!(limiter.factor(), 1.0);
+            })
+    }
+}
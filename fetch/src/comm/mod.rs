@@ -1,8 +1,15 @@
 mod fetch;
+mod limit;
+mod record;
 mod trans_info;
 mod trade_date;
 
 pub use self::fetch::*;
+pub(crate) use limit::LimitedSend;
+pub use limit::{rate_factor, rate_limit, set_rate_limit, RateLimit, Site};
+#[cfg(test)]
+pub(crate) use record::fixtures;
+pub use record::{http_mode, set_http_mode, with_http_mode, HttpMode};
 pub(crate) use trans_info::*;
 
 pub use trade_date::*;
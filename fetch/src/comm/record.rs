@@ -0,0 +1,233 @@
+use crate::{Error, Result};
+use futures::Future;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// http请求方式
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HttpMode {
+    /// 请求网络
+    #[default]
+    Live,
+    /// 请求网络，并将每个请求的原始响应保存到目录
+    Record(PathBuf),
+    /// 从目录读取之前保存的响应，不请求网络
+    Replay(PathBuf),
+}
+
+static HTTP_MODE: Lazy<RwLock<HttpMode>> = Lazy::new(|| RwLock::new(HttpMode::Live));
+
+tokio::task_local! {
+    static SCOPED_HTTP_MODE: HttpMode;
+}
+
+/// 设置全局的http请求方式，如生产环境记录响应，以便在本地重现解释数据出错的问题
+pub fn set_http_mode(mode: HttpMode) {
+    *HTTP_MODE.write().unwrap() = mode;
+}
+
+/// 在*f*内使用指定的http请求方式，不影响其他任务，*f*内新建的任务仍使用全局的方式
+///
+/// # Examples
+/// ```no_run
+/// use rwqfetch::{fetch_stock_yjbb, with_http_mode, HttpMode};
+/// # async fn run() {
+/// let data = with_http_mode(HttpMode::Replay("tests/fixtures".into()), async {
+///     fetch_stock_yjbb(2022, 4).await
+/// })
+/// .await;
+/// # }
+/// ```
+pub async fn with_http_mode<F: Future>(mode: HttpMode, f: F) -> F::Output {
+    SCOPED_HTTP_MODE.scope(mode, f).await
+}
+
+/// 当前的http请求方式
+pub fn http_mode() -> HttpMode {
+    SCOPED_HTTP_MODE
+        .try_with(|mode| mode.clone())
+        .unwrap_or_else(|_| HTTP_MODE.read().unwrap().clone())
+}
+
+/// 保存的请求信息，响应内容保存在同名的`.body`文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    body: Option<String>,
+    status: u16,
+}
+
+/// 请求体规范化，表单和json的字段顺序不固定，排序后再比较
+fn canonical_body(req: &reqwest::Request) -> Option<String> {
+    let body = req.body()?.as_bytes()?;
+    let body = String::from_utf8_lossy(body).to_string();
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
+        return Some(json.to_string());
+    }
+    let mut pairs: Vec<_> = body.split('&').collect();
+    pairs.sort();
+    Some(pairs.join("&"))
+}
+
+/// FNV-1a，保证不同版本下文件名一致
+fn fnv(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 每次请求都会变化的查询参数，如时间戳及防缓存的随机数
+const VOLATILE_PARAMS: [&str; 5] = ["_", "_t", "timestamp", "random", "rnd"];
+
+/// 去掉易变查询参数后的url，保证重放时与记录时一致
+fn stable_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    if let Some(query) = url.query() {
+        let query = query
+            .split('&')
+            .filter(|p| !VOLATILE_PARAMS.contains(&p.split('=').next().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("&");
+        url.set_query((!query.is_empty()).then_some(query.as_str()));
+    }
+    url.to_string()
+}
+
+/// 请求对应的文件，按域名分目录，文件名为请求的hash
+fn fixture_path(dir: &Path, req: &reqwest::Request) -> PathBuf {
+    let body = canonical_body(req);
+    let key = format!(
+        "{} {}\n{}",
+        req.method(),
+        stable_url(req.url()),
+        body.as_deref().unwrap_or_default()
+    );
+    dir.join(req.url().host_str().unwrap_or("unknown"))
+        .join(format!("{:016x}", fnv(&key)))
+}
+
+/// 读取保存的响应状态及内容
+pub(crate) fn replay(dir: &Path, req: &reqwest::Request) -> Result<(u16, Vec<u8>)> {
+    let path = fixture_path(dir, req);
+    let fixture = std::fs::read_to_string(path.with_extension("json")).map_err(|e| {
+        Error::Custom(format!(
+            "No recorded response of {} {} in {}: {}!",
+            req.method(),
+            req.url(),
+            path.display(),
+            e
+        ))
+    })?;
+    let fixture: Fixture = serde_json::from_str(&fixture)?;
+    // 只发送请求未读取内容时不保存内容
+    let body = std::fs::read(path.with_extension("body")).unwrap_or_default();
+    Ok((fixture.status, body))
+}
+
+/// 保存请求信息及响应状态，返回响应内容要保存的文件
+pub(crate) fn record(dir: &Path, req: &reqwest::Request, status: u16) -> Result<PathBuf> {
+    let path = fixture_path(dir, req);
+    let fixture = Fixture {
+        method: req.method().to_string(),
+        url: req.url().to_string(),
+        body: canonical_body(req),
+        status,
+    };
+    let save = || -> std::io::Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(
+            path.with_extension("json"),
+            serde_json::to_string_pretty(&fixture)?,
+        )
+    };
+    save().map_err(|e| {
+        Error::Custom(format!(
+            "Record response to {} error: {}!",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(path.with_extension("body"))
+}
+
+/// 测试用的离线数据，在`tests/fixtures`目录
+#[cfg(test)]
+pub(crate) fn fixtures() -> HttpMode {
+    HttpMode::Replay(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, form: &[(&str, &str)]) -> reqwest::Request {
+        reqwest::Client::new().post(url).form(form).build().unwrap()
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let dir = std::env::temp_dir().join(format!("rwqfetch-record-{}", std::process::id()));
+        let req = request(
+            "https://www.bse.cn/list.do",
+            &[("page", "1"), ("type", "T")],
+        );
+        let body = record(&dir, &req, 200).unwrap();
+        std::fs::write(&body, "[]").unwrap();
+        assert!(body.starts_with(dir.join("www.bse.cn")));
+
+        // 表单字段顺序不同也能找到
+        let req = request(
+            "https://www.bse.cn/list.do",
+            &[("type", "T"), ("page", "1")],
+        );
+        assert_eq!(replay(&dir, &req).unwrap(), (200, b"[]".to_vec()));
+
+        let req = request(
+            "https://www.bse.cn/list.do",
+            &[("page", "2"), ("type", "T")],
+        );
+        assert!(replay(&dir, &req).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_volatile_params() {
+        let dir = std::env::temp_dir().join(format!("rwqfetch-volatile-{}", std::process::id()));
+        let get = |url: &str| reqwest::Client::new().get(url).build().unwrap();
+        let req =
+            get("https://api.fund.eastmoney.com/f10/lsjz?fundCode=000001&pageIndex=1&_=1677650000");
+        let body = record(&dir, &req, 200).unwrap();
+        std::fs::write(&body, "{}").unwrap();
+
+        // 时间戳不同也能找到
+        let req =
+            get("https://api.fund.eastmoney.com/f10/lsjz?fundCode=000001&pageIndex=1&_=1697600000");
+        assert_eq!(replay(&dir, &req).unwrap(), (200, b"{}".to_vec()));
+        let req =
+            get("https://api.fund.eastmoney.com/f10/lsjz?fundCode=000001&pageIndex=2&_=1697600000");
+        assert!(replay(&dir, &req).is_err());
+
+        assert_eq!(
+            stable_url(&"http://hq.sinajs.cn/?random=0.69&_t=1".parse().unwrap()),
+            "http://hq.sinajs.cn/"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_http_mode() {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let mode = HttpMode::Replay("fixtures".into());
+                let scoped = with_http_mode(mode.clone(), async { http_mode() }).await;
+                assert_eq!(scoped, mode);
+                assert_eq!(http_mode(), HttpMode::Live);
+            })
+    }
+}
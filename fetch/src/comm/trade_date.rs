@@ -1,5 +1,5 @@
-use crate::comm::async_client;
-use crate::{Error, Result};
+use crate::comm::{async_client, LimitedSend};
+use crate::{providers, Error, Result};
 use chrono::{Duration, NaiveDate};
 use js_sandbox::Script;
 use once_cell::sync::Lazy;
@@ -13,12 +13,26 @@ static CACHE_TRADE_DATE: Lazy<RwLock<BTreeSet<i32>>> = Lazy::new(|| {
 });
 
 /// 获取全量交易日数据，获取数据后，进行缓存
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个
 pub async fn fetch_trade_date() -> Result<BTreeSet<i32>> {
+    let data = providers().fetch_trade_date().await?;
+
+    {
+        let mut cache = CACHE_TRADE_DATE.write().unwrap();
+        *cache = data.clone();
+    }
+
+    Ok(data)
+}
+
+/// 新浪交易日数据
+pub(crate) async fn fetch_trade_date_sn() -> Result<BTreeSet<i32>> {
     let client = async_client();
 
     let resp = client
         .get("https://finance.sina.com.cn/realstock/company/klc_td_sh.txt")
-        .send()
+        .send_limited()
         .await?
         .text()
         .await?;
@@ -29,11 +43,6 @@ pub async fn fetch_trade_date() -> Result<BTreeSet<i32>> {
         .call("get_trade_date", ())
         .map_err(|e| Error::Custom(format!("Call js function error:{}!", e.to_string())))?;
 
-    {
-        let mut cache = CACHE_TRADE_DATE.write().unwrap();
-        *cache = data.clone();
-    }
-
     Ok(data)
 }
 
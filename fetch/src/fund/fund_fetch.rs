@@ -1,11 +1,10 @@
-use crate::comm::{async_client, fetch_bar, fetch_prev_trade_date, XueQiuBar};
+use crate::comm::{async_client, fetch_bar_xq, LimitedSend};
 use crate::fund::trans_info::EastFundNet;
 use crate::util::to_std_code;
-use crate::{Error, HeaderValue, Market, MarketType, Result, HTTP_CMM_HEADER};
-use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use crate::{providers, Error, HeaderValue, MarketType, Result, HTTP_CMM_HEADER};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
 use reqwest::header::REFERER;
-use rwqcmm::{Bar, BarFreq, FundBar, FundInfo, FundNet};
-use std::ops::Add;
+use rwqcmm::{BarFreq, FundBar, FundInfo, FundNet};
 
 pub async fn fetch_fund_bar_xq(
     code: &str,
@@ -13,106 +12,8 @@ pub async fn fetch_fund_bar_xq(
     start: Option<NaiveDate>,
     end: Option<NaiveDate>,
 ) -> Result<FundBar> {
-    let mut first_date: Option<i32> = None;
-    let code = code.to_uppercase();
     let name = name.unwrap_or("");
-
-    let mut start = if let Some(st) = &start {
-        let prev = fetch_prev_trade_date(&st).await?;
-        first_date = Some(prev);
-        NaiveDateTime::parse_from_str(&format!("{} 00:00:00", prev), "%Y%m%d %H:%M:%S").unwrap()
-    } else {
-        NaiveDateTime::parse_from_str("2010-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
-    };
-
-    let end = end.map_or(Local::now().naive_local(), |d| {
-        d.and_hms_opt(0, 0, 0).unwrap()
-    });
-
-    let mut data = Vec::new();
-
-    let mut pre_item: Option<Bar> = None;
-    // prepare cookie
-    async_client().get("https://xueqiu.com/hq").send().await?;
-    while start <= end {
-        let timestamp = start.timestamp() * 1000;
-        let req_url = format!(
-            "https://stock.xueqiu.com/v5/stock/chart/kline.json?\
-            symbol={code}&begin={timestamp}&period=day&type=before&count=100&indicator=kline",
-            code = code,
-            timestamp = timestamp
-        );
-
-        let resp = async_client().get(req_url).send().await?.text().await?;
-        let json: XueQiuBar = serde_json::from_str(&resp)?;
-
-        if let Some(result) = json.data {
-            let tmp_vec: Vec<_> = result
-                .item
-                .iter()
-                .map(|item| {
-                    // ["timestamp","volume","open","high","low","close","chg","percent","turnoverrate","amount","volume_post","amount_post"]
-                    let trade_date: NaiveDateTime =
-                        Local.timestamp_opt(item.0 / 1000, 0).unwrap().naive_local();
-
-                    let volume = item.1.unwrap_or(0);
-                    let amount = item.9.unwrap_or(0.0);
-                    let (volume_chg_pct, amount_chg_pct) = if let Some(item) = &pre_item {
-                        (
-                            (((volume as i64 - item.volume as i64) * 100) as f64
-                                / item.volume as f64) as f32,
-                            ((amount - item.amount) * 100.0 / item.amount) as f32,
-                        )
-                    } else {
-                        (0.0, 0.0)
-                    };
-
-                    let bar = Bar {
-                        code: result.code[2..].to_owned(),
-                        name: name.to_owned(),
-                        trade_date,
-                        open: item.2.unwrap_or(0.0),
-                        close: item.5.unwrap_or(0.0),
-                        high: item.3.unwrap_or(0.0),
-                        low: item.4.unwrap_or(0.0),
-                        volume,
-                        amount,
-                        volume_chg_pct,
-                        amount_chg_pct,
-                        turnover: item.8.unwrap_or(0.0),
-                        chg_pct: item.6.unwrap_or(0.0),
-                        hfq_factor: 1.0,
-                    };
-                    pre_item = Some(bar.clone());
-                    bar
-                })
-                .filter(|item| item.trade_date <= end)
-                .collect();
-            if tmp_vec.is_empty() {
-                break;
-            }
-            let last = tmp_vec[tmp_vec.len() - 1].trade_date.clone();
-
-            start = last.add(Duration::days(1));
-            data.extend(tmp_vec.into_iter());
-        } else {
-            break;
-        }
-    }
-    if let Some(first_date) = first_date {
-        if data.len() > 0 {
-            let first = data.get(0).unwrap();
-            let (y, m, d) = (
-                first.trade_date.year(),
-                first.trade_date.month(),
-                first.trade_date.day(),
-            );
-            let date = y * 10000 + m as i32 * 100 + d as i32;
-            if first_date == date {
-                data = data.into_iter().skip(1).collect();
-            }
-        }
-    }
+    let data = fetch_bar_xq(code, name, "before", start, end).await?;
 
     Ok(FundBar {
         code: code.to_lowercase(),
@@ -126,7 +27,12 @@ pub async fn fetch_fund_bar_xq(
 pub async fn fetch_fund_info() -> Result<Vec<FundInfo>> {
     let req_url = format!("http://fund.eastmoney.com/js/fundcode_search.js?v=20130718.js");
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let index = resp
         .find("[")
@@ -176,7 +82,7 @@ pub async fn fetch_fund_net(
     let resp = async_client()
         .get(req_url)
         .headers(headers)
-        .send()
+        .send_limited()
         .await?
         .text()
         .await?;
@@ -209,6 +115,8 @@ pub async fn fetch_fund_net(
     Ok(data)
 }
 /// etf基金k线数据
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个
 pub async fn fetch_fund_bar(
     code: &str,
     name: Option<&str>,
@@ -217,27 +125,15 @@ pub async fn fetch_fund_bar(
     end: Option<NaiveDate>,
     skip_rt: bool,
 ) -> Result<FundBar> {
-    let market_code = if code.starts_with("sz") {
-        format!("{}.{}", Market::SZ as i32, &code[2..])
-    } else {
-        format!("{}.{}", Market::SH as i32, &code[2..])
-    };
     let freq = if freq.is_none() {
         BarFreq::Daily
     } else {
         freq.unwrap()
     };
 
-    let bars = fetch_bar(
-        &async_client(),
-        &market_code,
-        code,
-        freq,
-        start,
-        end,
-        skip_rt,
-    )
-    .await?;
+    let bars = providers()
+        .fetch_bar(code, freq, start, end, skip_rt)
+        .await?;
     let bond_bar = FundBar {
         code: code.to_owned(),
         name: name.unwrap_or("").to_owned(),
@@ -1,7 +1,12 @@
 //! 网上获取数据，数据类型包括可转债，ETF基金，股票。  
 //! 这里获取的数据是最基本的数据，不排除以后会新增其他类型的数据。  
-//! 数据的来源不一定是固定一个地方。  
-//! 需要注意的是，获取数据时，如果并发获取，需要要限制并发数量，否则可能会被封ip  
+//! 数据的来源不一定是固定一个地方，k线，交易日历，股票基本信息按[`providers`]的优先级获取，
+//! 某个来源出错或返回的数据不合法时自动切换到下一个来源。  
+//! 需要注意的是，获取数据时，如果并发获取，需要要限制并发数量，否则可能会被封ip。  
+//! 所有请求按站点共用限流器(令牌桶+最大同时请求数)，可通过[`set_rate_limit`]调整，
+//! 遇到429或被反爬拦截时会自动降速，请求成功后逐步恢复。  
+//! 通过[`set_http_mode`]或[`with_http_mode`]可将响应记录到目录，或从目录重放而不请求网络，
+//! 解释数据的测试使用`tests/fixtures`下记录的响应，不依赖网络。  
 use once_cell::sync::Lazy;
 use reqwest::header::{
     HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CACHE_CONTROL, CONNECTION, PRAGMA, USER_AGENT,
@@ -26,6 +31,9 @@ pub use util::to_std_code;
 pub mod ta;
 pub use ta::*;
 
+pub mod provider;
+pub use provider::*;
+
 pub use rwqcmm::*;
 
 /// 模块定义的错误码
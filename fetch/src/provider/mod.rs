@@ -0,0 +1,650 @@
+//! 数据来源
+//!
+//! k线，实时行情，交易日历，基本信息等数据可以从多个来源获取，每个来源实现[`DataProvider`]，
+//! [`Providers`]按优先级依次尝试，某个来源请求失败或返回的数据不合法时，自动切换到下一个来源。
+//! 开启交叉校验后，会再从下一个来源获取一份数据进行比对，不一致时返回错误。
+use crate::{Error, Market, Result};
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use futures::Future;
+use once_cell::sync::Lazy;
+use rwqcmm::{Bar, BarFreq, RtQuot, StockInfo};
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+mod source;
+pub use source::*;
+
+/// 数据来源，未实现的接口返回[`Error::NotImpl`]
+#[async_trait]
+pub trait DataProvider: Send + Sync {
+    /// 来源名称
+    fn name(&self) -> &'static str;
+
+    /// k线数据，*code* 为内部代码格式，如sz000001
+    async fn fetch_bar(
+        &self,
+        _code: &str,
+        _freq: BarFreq,
+        _start: Option<NaiveDate>,
+        _end: Option<NaiveDate>,
+        _skip_rt: bool,
+    ) -> Result<Vec<Bar>> {
+        Err(Error::NotImpl(format!("{}::fetch_bar", self.name())))
+    }
+
+    /// 实时行情
+    async fn fetch_rt_quot(&self, _code: &[String]) -> Result<RtQuot> {
+        Err(Error::NotImpl(format!("{}::fetch_rt_quot", self.name())))
+    }
+
+    /// 全量交易日
+    async fn fetch_trade_date(&self) -> Result<BTreeSet<i32>> {
+        Err(Error::NotImpl(format!("{}::fetch_trade_date", self.name())))
+    }
+
+    /// 股票基本信息
+    async fn fetch_stock_info(&self, _market: Option<Market>) -> Result<Vec<StockInfo>> {
+        Err(Error::NotImpl(format!("{}::fetch_stock_info", self.name())))
+    }
+}
+
+/// 按优先级排列的数据来源
+#[derive(Clone)]
+pub struct Providers {
+    providers: Vec<Arc<dyn DataProvider>>,
+    tolerance: Option<f32>,
+}
+
+impl Default for Providers {
+    /// 新浪，东方财富，雪球，交易所
+    fn default() -> Self {
+        Self::new(vec![
+            Arc::new(Sina),
+            Arc::new(EastMoney),
+            Arc::new(XueQiu),
+            Arc::new(Exchange),
+        ])
+    }
+}
+
+impl Providers {
+    /// 按给定的优先级创建
+    pub fn new(providers: Vec<Arc<dyn DataProvider>>) -> Self {
+        Self {
+            providers,
+            tolerance: None,
+        }
+    }
+
+    /// 开启交叉校验，*tolerance* 为价格允许的相对误差，如0.01
+    pub fn with_check(mut self, tolerance: f32) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// 来源名称，按优先级排列
+    pub fn names(&self) -> Vec<&'static str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+
+    /// k线数据
+    pub async fn fetch_bar(
+        &self,
+        code: &str,
+        freq: BarFreq,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+        skip_rt: bool,
+    ) -> Result<Vec<Bar>> {
+        let what = format!("bar of {}", code);
+        let fetch = |p: &Arc<dyn DataProvider>| {
+            let p = p.clone();
+            async move { p.fetch_bar(code, freq, start, end, skip_rt).await }
+        };
+        let check = |data: &Vec<Bar>| check_bars(data);
+        let (index, data) = self.failover(&what, 0, &fetch, check).await?;
+        if let Some(tolerance) = self.tolerance {
+            if !data.is_empty() {
+                if let Some((other, ref_data)) = self.reference(&what, index, &fetch, check).await {
+                    let diff = diff_bars(&data, &ref_data, tolerance);
+                    self.consistent(&what, index, other, diff)?;
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// 实时行情，后一个可用来源的行情用于补充缺少的字段
+    pub async fn fetch_rt_quot(&self, code: &[String]) -> Result<RtQuot> {
+        let what = "realtime quotation".to_owned();
+        let check = |data: &RtQuot| check_rt_quot(code, data);
+        let fetch = |p: &Arc<dyn DataProvider>| {
+            let p = p.clone();
+            async move { p.fetch_rt_quot(code).await }
+        };
+        let (index, mut data) = self.failover(&what, 0, &fetch, check).await?;
+        if let Some((other, ref_data)) = self.reference(&what, index, &fetch, check).await {
+            if let Some(tolerance) = self.tolerance {
+                let diff = diff_rt_quot(&data, &ref_data, tolerance);
+                self.consistent(&what, index, other, diff)?;
+            }
+            merge_rt_quot(&mut data, &ref_data);
+        }
+        Ok(data)
+    }
+
+    /// 全量交易日
+    pub async fn fetch_trade_date(&self) -> Result<BTreeSet<i32>> {
+        let what = "trade date".to_owned();
+        let fetch = |p: &Arc<dyn DataProvider>| {
+            let p = p.clone();
+            async move { p.fetch_trade_date().await }
+        };
+        let (index, data) = self.failover(&what, 0, &fetch, check_trade_date).await?;
+        if self.tolerance.is_some() {
+            if let Some((other, ref_data)) =
+                self.reference(&what, index, &fetch, check_trade_date).await
+            {
+                let diff = diff_trade_date(&data, &ref_data);
+                self.consistent(&what, index, other, diff)?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// 股票基本信息，目前只有交易所一个来源，不做交叉校验
+    pub async fn fetch_stock_info(&self, market: Option<Market>) -> Result<Vec<StockInfo>> {
+        let fetch = |p: &Arc<dyn DataProvider>| {
+            let p = p.clone();
+            async move { p.fetch_stock_info(market).await }
+        };
+        let (_, data) = self
+            .failover("stock info", 0, &fetch, |data: &Vec<StockInfo>| {
+                check_stock_info(data)
+            })
+            .await?;
+        Ok(data)
+    }
+
+    /// 从*from*开始按优先级获取，返回第一个成功且数据合法的来源及数据
+    async fn failover<T, F, Fut, C>(
+        &self,
+        what: &str,
+        from: usize,
+        fetch: &F,
+        check: C,
+    ) -> Result<(usize, T)>
+    where
+        F: Fn(&Arc<dyn DataProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+        C: Fn(&T) -> Result<()>,
+    {
+        let mut errors = Vec::new();
+        for (index, provider) in self.providers.iter().enumerate().skip(from) {
+            let res = match fetch(provider).await {
+                Ok(data) => check(&data).map(|_| data),
+                Err(Error::NotImpl(_)) => continue,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(data) => return Ok((index, data)),
+                Err(e) => {
+                    warn!("fetch {} from {} error: {}", what, provider.name(), e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                }
+            }
+        }
+        if errors.is_empty() {
+            return Err(Error::NotImpl(format!("fetch {}", what)));
+        }
+        Err(Error::Custom(format!(
+            "Fetch {} error, {}!",
+            what,
+            errors.join("; ")
+        )))
+    }
+
+    /// 交叉校验用的数据，从*index*之后的来源获取，没有可用的来源时只记录日志
+    async fn reference<T, F, Fut, C>(
+        &self,
+        what: &str,
+        index: usize,
+        fetch: &F,
+        check: C,
+    ) -> Option<(usize, T)>
+    where
+        F: Fn(&Arc<dyn DataProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+        C: Fn(&T) -> Result<()>,
+    {
+        match self.failover(what, index + 1, fetch, check).await {
+            Ok(res) => Some(res),
+            Err(e) => {
+                warn!("no reference to check {}: {}", what, e);
+                None
+            }
+        }
+    }
+
+    fn consistent(&self, what: &str, index: usize, other: usize, diff: Vec<String>) -> Result<()> {
+        if diff.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Custom(format!(
+            "Inconsistent {} between {} and {}: {}!",
+            what,
+            self.providers[index].name(),
+            self.providers[other].name(),
+            diff.into_iter().take(5).collect::<Vec<_>>().join(", ")
+        )))
+    }
+}
+
+static PROVIDERS: Lazy<RwLock<Arc<Providers>>> =
+    Lazy::new(|| RwLock::new(Arc::new(Providers::default())));
+
+/// 设置全局的数据来源，`fetch_*_bar`，[`crate::fetch_rt_quot`]，[`crate::fetch_trade_date`]，[`crate::fetch_stock_info`]按其获取数据
+pub fn set_providers(providers: Providers) {
+    *PROVIDERS.write().unwrap() = Arc::new(providers);
+}
+
+/// 全局的数据来源
+pub fn providers() -> Arc<Providers> {
+    PROVIDERS.read().unwrap().clone()
+}
+
+fn ymd(date: &NaiveDateTime) -> i32 {
+    date.year() * 10000 + date.month() as i32 * 100 + date.day() as i32
+}
+
+fn price_diff(a: f32, b: f32) -> f32 {
+    if a == b {
+        0.0
+    } else {
+        (a - b).abs() / a.abs().max(b.abs())
+    }
+}
+
+/// k线价格需为正数，最高最低价包含开盘收盘价，时间递增
+pub(crate) fn check_bars(bars: &[Bar]) -> Result<()> {
+    let mut last: Option<NaiveDateTime> = None;
+    for bar in bars.iter() {
+        let prices = [bar.open, bar.close, bar.high, bar.low];
+        if prices.iter().any(|p| !p.is_finite() || *p <= 0.0) {
+            return Err(Error::Custom(format!(
+                "Invalid bar price of {} at {}!",
+                bar.code, bar.trade_date
+            )));
+        }
+        let eps = bar.high * 1e-3;
+        if bar.high + eps < bar.open.max(bar.close) || bar.low - eps > bar.open.min(bar.close) {
+            return Err(Error::Custom(format!(
+                "Invalid bar high/low of {} at {}!",
+                bar.code, bar.trade_date
+            )));
+        }
+        if let Some(last) = last {
+            if bar.trade_date <= last {
+                return Err(Error::Custom(format!(
+                    "Unordered bar of {} at {}!",
+                    bar.code, bar.trade_date
+                )));
+            }
+        }
+        last = Some(bar.trade_date);
+    }
+    Ok(())
+}
+
+/// 请求的代码都需有行情，价格不能为负数
+pub(crate) fn check_rt_quot(code: &[String], data: &RtQuot) -> Result<()> {
+    if let Some(code) = code.iter().find(|code| !data.contains_key(*code)) {
+        return Err(Error::Custom(format!("Missing quotation of {}!", code)));
+    }
+    if let Some(quot) = data
+        .values()
+        .find(|q| !q.now.is_finite() || q.now < 0.0 || q.last_close < 0.0)
+    {
+        return Err(Error::Custom(format!(
+            "Invalid quotation price of {}!",
+            quot.code
+        )));
+    }
+    Ok(())
+}
+
+/// 交易日需为合法的工作日
+pub(crate) fn check_trade_date(data: &BTreeSet<i32>) -> Result<()> {
+    if data.is_empty() {
+        return Err(Error::Custom("Empty trade date!".to_owned()));
+    }
+    for date in data.iter() {
+        let valid = NaiveDate::parse_from_str(&date.to_string(), "%Y%m%d")
+            .map(|d| !matches!(d.weekday(), Weekday::Sat | Weekday::Sun))
+            .unwrap_or(false);
+        if !valid {
+            return Err(Error::Custom(format!("Invalid trade date {}!", date)));
+        }
+    }
+    Ok(())
+}
+
+/// 股票代码需为内部格式
+pub(crate) fn check_stock_info(data: &[StockInfo]) -> Result<()> {
+    if data.is_empty() {
+        return Err(Error::Custom("Empty stock info!".to_owned()));
+    }
+    let valid = |code: &str| {
+        code.len() == 8
+            && ["sh", "sz", "bj"].contains(&&code[..2])
+            && code[2..].chars().all(|c| c.is_ascii_digit())
+    };
+    if let Some(info) = data.iter().find(|info| !valid(&info.code)) {
+        return Err(Error::Custom(format!("Invalid stock code {}!", info.code)));
+    }
+    Ok(())
+}
+
+/// 同一交易日的收盘价及复权因子比对
+pub(crate) fn diff_bars(bars: &[Bar], other: &[Bar], tolerance: f32) -> Vec<String> {
+    let mut diff = Vec::new();
+    let mut other = other.iter().peekable();
+    for bar in bars.iter() {
+        while other.next_if(|o| o.trade_date < bar.trade_date).is_some() {}
+        if let Some(o) = other.next_if(|o| o.trade_date == bar.trade_date) {
+            if price_diff(bar.close, o.close) > tolerance {
+                diff.push(format!(
+                    "{} close {} != {}",
+                    ymd(&bar.trade_date),
+                    bar.close,
+                    o.close
+                ));
+            } else if price_diff(bar.close * bar.hfq_factor, o.close * o.hfq_factor) > tolerance {
+                diff.push(format!(
+                    "{} hfq_factor {} != {}",
+                    ymd(&bar.trade_date),
+                    bar.hfq_factor,
+                    o.hfq_factor
+                ));
+            }
+        }
+    }
+    diff
+}
+
+/// 同一代码的最新价比对
+pub(crate) fn diff_rt_quot(data: &RtQuot, other: &RtQuot, tolerance: f32) -> Vec<String> {
+    let mut diff: Vec<_> = data
+        .iter()
+        .filter_map(|(code, quot)| {
+            let o = other.get(code)?;
+            if price_diff(quot.now, o.now) > tolerance {
+                Some(format!("{} now {} != {}", code, quot.now, o.now))
+            } else {
+                None
+            }
+        })
+        .collect();
+    diff.sort();
+    diff
+}
+
+/// 用*other*补充*data*缺少的字段，如新浪没有换手率及市值，雪球没有名称及盘口
+pub(crate) fn merge_rt_quot(data: &mut RtQuot, other: &RtQuot) {
+    for (code, quot) in data.iter_mut() {
+        let o = match other.get(code) {
+            Some(o) => o,
+            None => continue,
+        };
+        if quot.name.is_empty() {
+            quot.name = o.name.clone();
+        }
+        if quot.bid == Default::default() && quot.ask == Default::default() {
+            quot.bid = o.bid;
+            quot.ask = o.ask;
+        }
+        if quot.turnover == 0.0 {
+            quot.turnover = o.turnover;
+        }
+        if quot.total_value == 0.0 {
+            quot.total_value = o.total_value;
+        }
+        if quot.currency_value == 0.0 {
+            quot.currency_value = o.currency_value;
+        }
+    }
+}
+
+/// 两个来源都覆盖的日期范围内，交易日需一致
+pub(crate) fn diff_trade_date(data: &BTreeSet<i32>, other: &BTreeSet<i32>) -> Vec<String> {
+    let (first, last) = match (data.first(), data.last(), other.first(), other.last()) {
+        (Some(a), Some(b), Some(c), Some(d)) => (*a.max(c), *b.min(d)),
+        _ => return Vec::new(),
+    };
+    data.symmetric_difference(other)
+        .filter(|d| **d >= first && **d <= last)
+        .map(|d| {
+            format!(
+                "{} only in {}",
+                d,
+                if data.contains(d) { "one" } else { "other" }
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rwqcmm::Quot;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn bar(day: u32, close: f32) -> Bar {
+        Bar {
+            code: "sz000001".to_owned(),
+            trade_date: NaiveDate::from_ymd_opt(2023, 3, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            open: close,
+            close,
+            high: close,
+            low: close,
+            hfq_factor: 1.0,
+            ..Default::default()
+        }
+    }
+
+    struct Fake {
+        name: &'static str,
+        close: Option<f32>,
+        calls: AtomicUsize,
+    }
+
+    impl Fake {
+        fn new(name: &'static str, close: Option<f32>) -> Arc<Fake> {
+            Arc::new(Fake {
+                name,
+                close,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl DataProvider for Fake {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn fetch_bar(
+            &self,
+            _code: &str,
+            _freq: BarFreq,
+            _start: Option<NaiveDate>,
+            _end: Option<NaiveDate>,
+            _skip_rt: bool,
+        ) -> Result<Vec<Bar>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.close {
+                Some(close) => Ok(vec![bar(1, close), bar(2, close)]),
+                None => Err(Error::Custom("format changed".to_owned())),
+            }
+        }
+    }
+
+    /// 只提供实时行情的来源
+    struct FakeQuot {
+        name: &'static str,
+        quot: Option<Quot>,
+    }
+
+    #[async_trait]
+    impl DataProvider for FakeQuot {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn fetch_rt_quot(&self, _code: &[String]) -> Result<RtQuot> {
+            match &self.quot {
+                Some(quot) => Ok([(quot.code.clone(), quot.clone())].into_iter().collect()),
+                None => Err(Error::Custom("blocked".to_owned())),
+            }
+        }
+    }
+
+    fn fetch(providers: &Providers) -> Result<Vec<Bar>> {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(providers.fetch_bar("sz000001", BarFreq::Daily, None, None, false))
+    }
+
+    #[test]
+    fn test_failover() {
+        let (broken, malformed, ok) = (
+            Fake::new("broken", None),
+            Fake::new("malformed", Some(-1.0)),
+            Fake::new("ok", Some(10.0)),
+        );
+        let providers = Providers::new(vec![
+            Arc::new(Exchange),
+            broken.clone(),
+            malformed.clone(),
+            ok.clone(),
+        ]);
+        let data = fetch(&providers).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].close, 10.0);
+        assert_eq!(broken.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(malformed.calls.load(Ordering::SeqCst), 1);
+
+        let providers = Providers::new(vec![broken.clone(), malformed.clone()]);
+        let e = fetch(&providers).unwrap_err().to_string();
+        assert!(e.contains("broken: format changed"));
+        assert!(e.contains("malformed: Invalid bar price"));
+
+        let providers = Providers::new(vec![Arc::new(Exchange)]);
+        assert!(matches!(fetch(&providers), Err(Error::NotImpl(_))));
+    }
+
+    #[test]
+    fn test_rt_quot() {
+        let quot = Quot {
+            code: "sh600887".to_owned(),
+            last_close: 29.0,
+            now: 29.35,
+            ..Default::default()
+        };
+        let broken = Arc::new(FakeQuot {
+            name: "broken",
+            quot: None,
+        });
+        let sina = Arc::new(FakeQuot {
+            name: "sina",
+            quot: Some(Quot {
+                name: "伊利股份".to_owned(),
+                bid: ((100, 29.34), (0, 0.0), (0, 0.0), (0, 0.0), (0, 0.0)),
+                ..quot.clone()
+            }),
+        });
+        let xueqiu = Arc::new(FakeQuot {
+            name: "xueqiu",
+            quot: Some(Quot {
+                now: 29.36,
+                turnover: 0.19,
+                total_value: 186800000000.0,
+                ..quot
+            }),
+        });
+        let fetch = |providers: &Providers| {
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(providers.fetch_rt_quot(&["sh600887".to_owned()]))
+        };
+
+        // 第一个来源失败，切换到下一个，并用其后的来源补充缺少的字段
+        let providers = Providers::new(vec![broken.clone(), sina.clone(), xueqiu.clone()]);
+        let data = fetch(&providers).unwrap();
+        let quot = &data["sh600887"];
+        assert_eq!((quot.name.as_str(), quot.now), ("伊利股份", 29.35));
+        assert_eq!(quot.bid.0, (100, 29.34));
+        assert_eq!((quot.turnover, quot.total_value), (0.19, 186800000000.0));
+
+        let e = fetch(&providers.with_check(0.0001))
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("Inconsistent realtime quotation between sina and xueqiu"));
+
+        let providers = Providers::new(vec![broken, Arc::new(Exchange)]);
+        let e = fetch(&providers).unwrap_err().to_string();
+        assert!(e.contains("broken: blocked"));
+    }
+
+    #[test]
+    fn test_check() {
+        let providers = Providers::new(vec![
+            Fake::new("a", Some(10.0)),
+            Fake::new("b", Some(10.05)),
+        ]);
+        assert!(fetch(&providers.clone().with_check(0.01)).is_ok());
+        let e = fetch(&providers.with_check(0.001)).unwrap_err().to_string();
+        assert!(e.contains("Inconsistent bar of sz000001 between a and b"));
+
+        // 没有其他来源可比对
+        let providers = Providers::new(vec![Fake::new("a", Some(10.0))]).with_check(0.001);
+        assert!(fetch(&providers).is_ok());
+    }
+
+    #[test]
+    fn test_check_data() {
+        let mut bars = vec![bar(1, 10.0), bar(2, 11.0)];
+        assert!(check_bars(&bars).is_ok());
+        bars[1].high = 10.5;
+        assert!(check_bars(&bars).is_err());
+        let bars = vec![bar(2, 10.0), bar(1, 11.0)];
+        assert!(check_bars(&bars).is_err());
+
+        let dates: BTreeSet<i32> = [20230301, 20230302, 20230303].into_iter().collect();
+        assert!(check_trade_date(&dates).is_ok());
+        assert!(check_trade_date(&[20230304].into_iter().collect()).is_err());
+        assert!(check_trade_date(&[20230230].into_iter().collect()).is_err());
+
+        let other: BTreeSet<i32> = [20230227, 20230301, 20230303].into_iter().collect();
+        assert_eq!(
+            diff_trade_date(&dates, &other),
+            vec!["20230302 only in one"]
+        );
+
+        let mut other = vec![bar(2, 11.0), bar(3, 12.0)];
+        assert!(diff_bars(&[bar(1, 10.0), bar(2, 11.0)], &other, 0.001).is_empty());
+        other[0].hfq_factor = 1.1;
+        assert_eq!(
+            diff_bars(&[bar(1, 10.0), bar(2, 11.0)], &other, 0.001),
+            vec!["20230302 hfq_factor 1 != 1.1"]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use super::DataProvider;
+use crate::comm::{async_client, fetch_bar, fetch_bar_xq, fetch_trade_date_sn};
+use crate::stock::fetch_stock_info_exch;
+use crate::{fetch_rt_quot_sn, fetch_rt_quot_xq, Error, Market, Result};
+use async_trait::async_trait;
+use chrono::{Local, NaiveDate, NaiveTime, Timelike};
+use rwqcmm::{Bar, BarFreq, Quot, RtQuot, StockInfo};
+use std::collections::{BTreeSet, HashMap};
+
+/// 东方财富，提供k线，交易日历(由上证指数日线推算，不含未来的交易日)
+pub struct EastMoney;
+
+#[async_trait]
+impl DataProvider for EastMoney {
+    fn name(&self) -> &'static str {
+        "eastmoney"
+    }
+
+    async fn fetch_bar(
+        &self,
+        code: &str,
+        freq: BarFreq,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+        skip_rt: bool,
+    ) -> Result<Vec<Bar>> {
+        let market_code = match code.strip_prefix("sh") {
+            // 上海市场
+            Some(c) => format!("{}.{}", Market::SH as i32, c),
+            // 深圳和北京一样是0
+            None => format!("{}.{}", Market::SZ as i32, &code[2..]),
+        };
+        fetch_bar(
+            &async_client(),
+            &market_code,
+            code,
+            freq,
+            start,
+            end,
+            skip_rt,
+        )
+        .await
+    }
+
+    async fn fetch_trade_date(&self) -> Result<BTreeSet<i32>> {
+        let bars = fetch_bar(
+            &async_client(),
+            "1.000001",
+            "sh000001",
+            BarFreq::Daily,
+            None,
+            None,
+            false,
+        )
+        .await?;
+        Ok(bars
+            .iter()
+            .map(|bar| bar.trade_date.format("%Y%m%d").to_string().parse().unwrap())
+            .collect())
+    }
+}
+
+/// 新浪，提供实时行情，交易日历
+pub struct Sina;
+
+#[async_trait]
+impl DataProvider for Sina {
+    fn name(&self) -> &'static str {
+        "sina"
+    }
+
+    async fn fetch_rt_quot(&self, code: &[String]) -> Result<RtQuot> {
+        let data = fetch_rt_quot_sn(&code.to_vec()).await?;
+        Ok(data
+            .into_iter()
+            .map(|(k, sn)| {
+                let chg = if sn.now > 0.0 {
+                    sn.now - sn.last_close
+                } else {
+                    0.0
+                };
+                let quot = Quot {
+                    chg,
+                    chg_pct: if sn.last_close > 0.0 {
+                        chg * 100.0 / sn.last_close
+                    } else {
+                        0.0
+                    },
+                    is_trading: is_trading(&sn.time.time()),
+                    code: sn.code,
+                    name: sn.name,
+                    open: sn.open,
+                    last_close: sn.last_close,
+                    now: sn.now,
+                    high: sn.high,
+                    low: sn.low,
+                    buy: sn.buy,
+                    sell: sn.sell,
+                    volume: sn.volume,
+                    amount: sn.amount,
+                    bid: sn.bid,
+                    ask: sn.ask,
+                    time: sn.time,
+                    ..Default::default()
+                };
+                (k, quot)
+            })
+            .collect())
+    }
+
+    async fn fetch_trade_date(&self) -> Result<BTreeSet<i32>> {
+        fetch_trade_date_sn().await
+    }
+}
+
+/// 雪球，提供日k线，实时行情(无名称及盘口)
+pub struct XueQiu;
+
+#[async_trait]
+impl DataProvider for XueQiu {
+    fn name(&self) -> &'static str {
+        "xueqiu"
+    }
+
+    async fn fetch_bar(
+        &self,
+        code: &str,
+        freq: BarFreq,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+        skip_rt: bool,
+    ) -> Result<Vec<Bar>> {
+        if !matches!(freq, BarFreq::Daily | BarFreq::LooseDaily) {
+            return Err(Error::NotImpl(format!(
+                "{}::fetch_bar({:?})",
+                self.name(),
+                freq
+            )));
+        }
+        let (bars, hfq) = tokio::join!(
+            fetch_bar_xq(code, "", "normal", start, end),
+            fetch_bar_xq(code, "", "after", start, end)
+        );
+        let (mut bars, hfq) = (bars?, hfq?);
+        if bars.len() != hfq.len() {
+            return Err(Error::Custom(format!(
+                "Mismatch xueqiu hfq bar of {}: {} != {}!",
+                code,
+                bars.len(),
+                hfq.len()
+            )));
+        }
+        // 雪球成交量单位为股(可转债为张)，转换为手
+        let lot = if code.starts_with("sh11") || code.starts_with("sz12") {
+            10
+        } else {
+            100
+        };
+        let n = Local::now().naive_local();
+        bars.iter_mut().zip(hfq.iter()).for_each(|(bar, hfq)| {
+            bar.code = code.to_owned();
+            bar.volume /= lot;
+            bar.hfq_factor = hfq.close / bar.close;
+        });
+        if skip_rt && n.hour() < 15 && matches!(freq, BarFreq::Daily) {
+            // 当日的不准
+            bars.retain(|bar| bar.trade_date.date() < n.date());
+        }
+        Ok(bars)
+    }
+
+    async fn fetch_rt_quot(&self, code: &[String]) -> Result<RtQuot> {
+        let data = fetch_rt_quot_xq(&code.to_vec()).await?;
+        Ok(data
+            .into_iter()
+            .map(|(k, xq)| {
+                let quot = Quot {
+                    code: xq.code,
+                    open: xq.open,
+                    last_close: xq.last_close,
+                    now: xq.now,
+                    high: xq.high,
+                    low: xq.low,
+                    volume: xq.volume.max(0) as u64,
+                    amount: xq.amount,
+                    time: xq.time,
+                    chg: xq.chg,
+                    chg_pct: xq.chg_pct,
+                    turnover: xq.turnover,
+                    total_value: xq.total_value,
+                    currency_value: xq.currency_value,
+                    is_trading: xq.is_trading,
+                    ..Default::default()
+                };
+                (k, quot)
+            })
+            .collect::<HashMap<_, _>>())
+    }
+}
+
+/// 交易所(上交所，深交所，北交所)，提供股票基本信息
+pub struct Exchange;
+
+#[async_trait]
+impl DataProvider for Exchange {
+    fn name(&self) -> &'static str {
+        "exchange"
+    }
+
+    async fn fetch_stock_info(&self, market: Option<Market>) -> Result<Vec<StockInfo>> {
+        fetch_stock_info_exch(market).await
+    }
+}
+
+fn is_trading(t: &NaiveTime) -> bool {
+    let ms = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+    let me = NaiveTime::from_hms_opt(11, 30, 0).unwrap();
+    let ns = NaiveTime::from_hms_opt(13, 0, 0).unwrap();
+    let ne = NaiveTime::from_hms_opt(15, 0, 0).unwrap();
+    (*t > ms && *t < me) || (*t > ns && *t < ne)
+}
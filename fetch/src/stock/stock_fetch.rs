@@ -1,10 +1,10 @@
-use crate::comm::{async_client, fetch_bar, to_bar_ds};
+use crate::comm::{async_client, fetch_bar, to_bar_ds, LimitedSend};
 use crate::stock::trans_info::{
     EastStockHotRankResult, EastStockIndex, EastStockIndustry, EastStockInfoMargin,
     EastStockMargin, EastStockYJBB, ExchSHStockInfo,
 };
 use crate::util::to_std_code;
-use crate::{fetch_trade_date, Error, Market, MarketType, Result, HTTP_CMM_HEADER};
+use crate::{fetch_trade_date, providers, Error, Market, MarketType, Result, HTTP_CMM_HEADER};
 use calamine::{open_workbook_auto_from_rs, DataType, Reader};
 use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
 use reqwest::header::*;
@@ -101,7 +101,7 @@ async fn fetch_stock_info_sh(margin_codes: &HashSet<String>) -> Result<Vec<Stock
         let resp = async_client()
             .get(req_url)
             .headers(header.clone())
-            .send()
+            .send_limited()
             .await?
             .text()
             .await?;
@@ -138,7 +138,12 @@ async fn fetch_stock_info_sz(margin_codes: &HashSet<String>) -> Result<Vec<Stock
     let req_url = "http://www.szse.cn/api/report/ShowReport?SHOWTYPE=xlsx&CATALOGID=1110&\
          TABKEY=tab1&random=0.6935816432433362";
 
-    let resp = async_client().get(req_url).send().await?.bytes().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .bytes()
+        .await?;
     // 板块	公司全称	英文名称	注册地址	A股代码	A股简称	A股上市日期	A股总股本	A股流通股本	B股代码
     // 	B股简称	B股上市日期	B股总股本	B股流通股本	地 区	省    份	城     市	所属行业	公司网址
     // 2712
@@ -202,7 +207,7 @@ async fn fetch_stock_info_bj(margin_codes: &HashSet<String>) -> Result<Vec<Stock
         let resp = async_client()
             .post(req_url)
             .form(&payload)
-            .send()
+            .send_limited()
             .await?
             .text()
             .await?;
@@ -246,7 +251,14 @@ async fn fetch_stock_info_bj(margin_codes: &HashSet<String>) -> Result<Vec<Stock
 }
 
 /// 获取股票基本信息
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个
 pub async fn fetch_stock_info(market: Option<Market>) -> Result<Vec<StockInfo>> {
+    providers().fetch_stock_info(market).await
+}
+
+/// 交易所股票基本信息
+pub(crate) async fn fetch_stock_info_exch(market: Option<Market>) -> Result<Vec<StockInfo>> {
     let margin_codes = fetch_stock_is_margin().await?;
 
     let data = if let Some(m) = market {
@@ -281,7 +293,12 @@ pub async fn fetch_stock_is_margin() -> Result<HashSet<String>> {
             page_size = page_size
         );
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let js_text = &resp[43..resp.len() - 2];
         let json: EastStockInfoMargin = serde_json::from_str(js_text)?;
@@ -306,6 +323,8 @@ pub async fn fetch_stock_is_margin() -> Result<HashSet<String>> {
     Ok(data)
 }
 /// 股票k线数据
+///
+/// 按[`providers`]的优先级获取，前面的来源失败时自动切换到下一个
 pub async fn fetch_stock_bar(
     code: &str,
     name: Option<&str>,
@@ -314,30 +333,15 @@ pub async fn fetch_stock_bar(
     end: Option<NaiveDate>,
     skip_rt: bool,
 ) -> Result<StockBar> {
-    let market_code = if code.starts_with("sh") {
-        // 上海市场
-        format!("{}.{}", Market::SH as i32, &code[2..])
-    } else {
-        // 深圳和北京一样是0
-        format!("{}.{}", Market::SZ as i32, &code[2..])
-    };
-
     let freq = if freq.is_none() {
         BarFreq::Daily
     } else {
         freq.unwrap()
     };
 
-    let bars = fetch_bar(
-        &async_client(),
-        &market_code,
-        code,
-        freq,
-        start,
-        end,
-        skip_rt,
-    )
-    .await?;
+    let bars = providers()
+        .fetch_bar(code, freq, start, end, skip_rt)
+        .await?;
     let (stock_name, bars) = to_bar_ds(name, bars);
     let stock_bar = StockBar {
         code: code.to_owned(),
@@ -373,7 +377,12 @@ pub async fn fetch_stock_index(date: Option<NaiveDate>) -> Result<HashMap<String
             fid=f3&fs=m:0+t:6,m:0+t:13,m:0+t:80,m:1+t:2,m:1+t:23&fields=f2,f9,f12,f14,f20,f21,f23&\
             _=1626075887768", page_num = page_num, page_size = page_size);
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json = serde_json::from_str::<EastStockIndex>(&resp)?;
         if json.data.is_none() {
@@ -426,7 +435,12 @@ pub async fn fetch_stock_industry() -> Result<Vec<StockIndustry>> {
             %2Cf207%2Cf208%2Cf209%2Cf222&_=1626075887768"
     );
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockIndustry = serde_json::from_str(&resp)?;
 
@@ -472,7 +486,12 @@ pub async fn fetch_stock_industry_detail(
             code = &industry.code
         );
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json: EastStockIndustry = serde_json::from_str(&resp)?;
 
@@ -534,7 +553,12 @@ pub async fn fetch_stock_concept() -> Result<Vec<StockConcept>> {
         &_=1626075887768"
     );
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockIndustry = serde_json::from_str(&resp)?;
 
@@ -581,7 +605,12 @@ pub async fn fetch_stock_concept_detail(
             code = &concept.code
         );
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json: EastStockIndustry = serde_json::from_str(&resp)?;
 
@@ -661,7 +690,12 @@ pub async fn fetch_stock_yjbb(year: u16, season: u16) -> Result<Vec<StockYJBB>>
             token=894050c76af8597a853f5b408b759f5d&filter=%28REPORTDATE%3D%27{season_date}%27%29",
                                   page_size = page_size, page = page, season_date = season_date);
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json: EastStockYJBB = serde_json::from_str(&resp)?;
 
@@ -734,7 +768,12 @@ pub async fn fetch_stock_margin(
             code = &code[2..]
         );
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json = serde_json::from_str::<EastStockMargin>(&resp)?;
 
@@ -804,7 +843,7 @@ pub async fn fetch_stock_hot_rank(code: &str) -> Result<StockHotRank> {
     let resp = async_client()
         .post(req_url)
         .json(&map)
-        .send()
+        .send_limited()
         .await?
         .text()
         .await?;
@@ -827,7 +866,12 @@ pub async fn fetch_stock_rt_quot(codes: Option<Vec<String>>) -> Result<Vec<Stock
             "http://82.push2.eastmoney.com/api/qt/clist/get?pn=1&pz=50000&po=1&np=1&ut=bd1d9ddb04089700cf9c27f6f7426281&fltt=2&invt=2&fid=f3&fs=m%3A0+t%3A6%2Cm%3A0+t%3A80%2Cm%3A1+t%3A2%2Cm%3A1+t%3A23%2Cm%3A0+t%3A81+s%3A2048&fields=f1%2Cf2%2Cf3%2Cf4%2Cf5%2Cf6%2Cf7%2Cf8%2Cf9%2Cf10%2Cf12%2Cf13%2Cf14%2Cf15%2Cf16%2Cf17%2Cf18%2Cf20%2Cf21%2Cf23%2Cf24%2Cf25%2Cf22%2Cf11%2Cf62%2Cf128%2Cf136%2Cf115%2Cf152&_=1623833739532"
         );
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockQuot = serde_json::from_str(&resp)?;
 
@@ -887,7 +931,12 @@ pub async fn fetch_stock_comment(codes: Option<Vec<String>>) -> Result<Vec<Stock
                 page = page,
             );
 
-        let resp = async_client().get(req_url).send().await?.text().await?;
+        let resp = async_client()
+            .get(req_url)
+            .send_limited()
+            .await?
+            .text()
+            .await?;
 
         let json: EastStockComment = serde_json::from_str(&resp)?;
 
@@ -956,7 +1005,12 @@ pub async fn fetch_stock_comment_his(code: String) -> Result<Vec<StockComment>>
         code = &code[2..]
     );
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockComment = serde_json::from_str(&resp)?;
 
@@ -980,7 +1034,12 @@ pub async fn fetch_stock_comment_his(code: String) -> Result<Vec<StockComment>>
         code = &code[2..]
     );
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockCommentScore = serde_json::from_str(&resp)?;
 
@@ -1003,7 +1062,12 @@ pub async fn fetch_stock_comment_his(code: String) -> Result<Vec<StockComment>>
             reportName=RPT_STOCK_MARKETFOCUS&sortColumns=TRADE_DATE&sortTypes=-1&pageSize=30&_=1695281367390",
             code = &code[2..]);
 
-    let resp = async_client().get(req_url).send().await?.text().await?;
+    let resp = async_client()
+        .get(req_url)
+        .send_limited()
+        .await?
+        .text()
+        .await?;
 
     let json: EastStockCommentAttention = serde_json::from_str(&resp)?;
 
@@ -1069,6 +1133,8 @@ pub async fn fetch_stock_comment_his(code: String) -> Result<Vec<StockComment>>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::comm::fixtures;
+    use crate::with_http_mode;
     use chrono::NaiveDate;
     use tracing_error::ErrorLayer;
     use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
@@ -1418,4 +1484,70 @@ mod tests {
                 println!("data={:?}", data[1]);
             })
     }
+
+    /// 离线数据
+    fn replay<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(with_http_mode(fixtures(), f))
+    }
+
+    #[test]
+    fn test_parse_stock_info_sh() {
+        let data = replay(fetch_stock_info(Some(Market::SH))).unwrap();
+        assert_eq!(data.len(), 2);
+        let info = data.iter().find(|info| info.code == "sh600887").unwrap();
+        assert_eq!(
+            (info.name.as_str(), info.block.as_str()),
+            ("伊利股份", "主板")
+        );
+        assert!(info.is_margin);
+        assert_eq!(info.listing_date.to_string(), "1996-03-12 00:00:00");
+        let info = data.iter().find(|info| info.code == "sh688001").unwrap();
+        assert_eq!(info.block, "科创板");
+        assert!(!info.is_margin);
+    }
+
+    #[test]
+    fn test_parse_stock_info_bj() {
+        let data = replay(fetch_stock_info(Some(Market::BJ))).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].code, "bj830799");
+        assert_eq!(data[0].name, "艾融软件");
+        assert_eq!(data[0].listing_date.to_string(), "2021-11-15 00:00:00");
+        assert!(data[0].is_margin);
+        assert!(!data[1].is_margin);
+    }
+
+    #[test]
+    fn test_parse_stock_yjbb() {
+        let data = replay(fetch_stock_yjbb(2022, 4)).unwrap();
+        assert_eq!(data.len(), 2);
+        let yjbb = &data[0];
+        assert_eq!((yjbb.year, yjbb.season), (2022, 4));
+        assert_eq!(yjbb.code, "sh600887");
+        assert_eq!(yjbb.season_date.to_string(), "2022-12-31 00:00:00");
+        assert_eq!((yjbb.mg_sy, yjbb.yysr), (1.47, 123171000000.0));
+        assert_eq!((yjbb.jlr, yjbb.xs_mll), (9431000000.0, 32.26));
+        // 缺失的字段为默认值
+        assert_eq!(data[1].code, "bj830799");
+        assert_eq!(data[1].mg_jy_xjl, 0.0);
+    }
+
+    #[test]
+    fn test_parse_stock_margin() {
+        let start = NaiveDate::from_ymd_opt(2023, 3, 1);
+        let end = NaiveDate::from_ymd_opt(2023, 3, 2);
+        let data = replay(fetch_stock_margin("sz000001", start, end)).unwrap();
+        // 结束日期之后的数据被过滤
+        assert_eq!(data.len(), 2);
+        let margin = &data[0];
+        assert_eq!(margin.code, "sz000001");
+        assert_eq!(margin.trade_date.to_string(), "2023-03-02 00:00:00");
+        assert_eq!((margin.close, margin.chg_pct), (14.1, -0.7));
+        assert_eq!((margin.rz_mre, margin.rq_yl), (123456789.0, 2345600));
+        assert_eq!(margin.rz_rq_ye, 5012345678.0);
+    }
 }
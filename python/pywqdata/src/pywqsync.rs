@@ -48,10 +48,11 @@ impl Sync {
         let shutdown_rx = self.shutdown_tx.subscribe();
         pyo3_asyncio::tokio::future_into_py(py, async move {
             let mut rwqsync = rwqdata::Sync::new(dest, shutdown_rx, funcs);
-            Ok(rwqsync
+            rwqsync
                 .sync(skip_basic, task_count, split_count)
                 .await
-                .map_err(|e| PyException::new_err(e.to_string()))?)
+                .map_err(|e| PyException::new_err(e.to_string()))?;
+            Ok(())
         })
     }
     fn shutdown(&self) -> PyResult<()> {
@@ -128,5 +129,6 @@ async fn block_sync(
     rwqsync
         .sync(skip_basic, task_count, split_count)
         .await
+        .map(|_| ())
         .map_err(|e| PyException::new_err(e.to_string()))
 }
@@ -2,6 +2,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use rwqdata::RtQuot;
 use rwqtradecmm::{Entrust, Event, QuotEvent};
 
 use crate::{context::Context, Params, Result};
@@ -44,9 +45,17 @@ pub trait Broker: Sync + Send {
     async fn on_entrust(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
         Ok(())
     }
+    /// 撤销委托，`entrust`为要撤销的活动委托，撤销结果同样以委托结果事件返回
+    async fn on_cancel(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
+        Ok(())
+    }
     async fn on_poll(&self, ctx: Arc<Context>) -> Result<()> {
         Ok(())
     }
+    /// 行情推送，模拟券商据此撮合委托
+    async fn on_quot(&self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+        Ok(())
+    }
 }
 
 // emit(buy, adf, 100)
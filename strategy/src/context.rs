@@ -1,27 +1,87 @@
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use rwqdata::store::Loader;
 use rwqtradecmm::{Account, Entrust, Event, Signal, TradeType};
 use tokio::sync::mpsc;
 
-use crate::Result;
+use crate::{Error, Result};
 
 /// Context 将策略所使用到的功能集合一起供策略库使用。
 pub struct Context {
-    pub loader: Box<dyn Loader>,
-    pub account: Box<RwLock<Account>>,
+    pub loader: Arc<Box<dyn Loader>>,
+    pub account: Arc<Box<RwLock<Account>>>,
     pub event_tx: mpsc::Sender<Event>,
 }
 
 impl Context {
+    pub fn new(
+        loader: Arc<Box<dyn Loader>>,
+        account: Arc<Box<RwLock<Account>>>,
+        event_tx: mpsc::Sender<Event>,
+    ) -> Self {
+        Self {
+            loader,
+            account,
+            event_tx,
+        }
+    }
+    /// 发送事件
+    ///
+    /// - `Signal` 校验资金/可用持仓是否足够
+    /// - `Entrust` 买入冻结资金，卖出冻结可用持仓，并记录委托
     pub async fn emit(&self, event: Event) -> Result<()> {
-        match event {
-            Event::Signal(_) => todo!(),
-            Event::Subscribe(_) => todo!(),
-            Event::Entrust(_) => todo!(),
-            Event::Broker(_) => todo!(),
+        match &event {
+            Event::Signal(signal) => self.check_signal(signal)?,
+            Event::Entrust(entrust) => self.freeze(entrust)?,
+            Event::Subscribe(_) | Event::Broker(_) => {}
+        }
+        self.event_tx
+            .send(event)
+            .await
+            .map_err(|e| Error::Custom(format!("emit event error: {}", e)))
+    }
+    fn check_signal(&self, signal: &Signal) -> Result<()> {
+        match signal.typ {
+            TradeType::Buy => {
+                if signal.volume == 0 || !self.can_buy(signal.price, signal.volume) {
+                    return Err(Error::Custom(format!(
+                        "{}({}) buy signal: price={}, volume={}, cash not enough",
+                        signal.name, signal.code, signal.price, signal.volume
+                    )));
+                }
+            }
+            TradeType::Sell => {
+                if signal.volume == 0 || self.can_sell(&signal.code) < signal.volume {
+                    return Err(Error::Custom(format!(
+                        "{}({}) sell signal: volume={}, position not enough",
+                        signal.name, signal.code, signal.volume
+                    )));
+                }
+            }
+            TradeType::Cancel => {
+                let active = match &signal.entrust_id {
+                    Some(id) => self.account.read().unwrap().entrust.contains_key(id),
+                    None => false,
+                };
+                if !active {
+                    return Err(Error::Custom(format!(
+                        "{}({}) cancel signal: entrust {:?} not active",
+                        signal.name, signal.code, signal.entrust_id
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// 委托冻结资金/可用持仓并记录委托，不足时返回错误
+    pub fn freeze(&self, entrust: &Entrust) -> Result<()> {
+        let mut account = self.account.write().unwrap();
+        if !account.freeze(entrust) {
+            return Err(Error::Custom(format!(
+                "{}({}) {} entrust: price={}, volume={}, cash or position not enough",
+                entrust.name, entrust.code, entrust.typ, entrust.price, entrust.volume
+            )));
         }
-
         Ok(())
     }
     pub fn can_buy(&self, price: f32, volume: u32) -> bool {
@@ -43,6 +103,7 @@ impl Context {
     pub async fn sell(&self, signal: Signal) -> Result<()> {
         self.emit(Event::Signal(signal)).await
     }
+    /// 撤销委托，`signal.entrust_id`为要撤销的委托id(见`can_cancel`)
     pub async fn cancel(&self, signal: Signal) -> Result<()> {
         self.emit(Event::Signal(signal)).await
     }
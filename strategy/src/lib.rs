@@ -8,7 +8,7 @@ pub mod runner;
 pub use runner::*;
 
 pub mod mystrategy;
-// pub use mystrategy::{get_strategy, strategies};
+pub use mystrategy::select::{get_strategy, strategies, strategy_info, StrategyInfo};
 
 pub mod ta;
 
@@ -21,6 +21,9 @@ pub mod trade;
 
 pub mod context;
 
+pub mod plugin;
+pub use plugin::{Plugin, PluginKind, PluginManager};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Function \"{0}\" not implement")]
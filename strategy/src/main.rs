@@ -1,406 +1,501 @@
-// use std::{collections::HashMap, str::FromStr, sync::Arc};
-
-// use anyhow::Context;
-// use argh::FromArgs;
-// use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-// use rwqdata::{
-//     store::{get_loader, Loader},
-//     SyncDest,
-// };
-// use rwqstrategy::{
-//     fit, get_strategy, run, select::CommonParam, select::ProgressFunc, select::Strategy,
-//     strategies, StrategyType, Symbol, SYMBOL_NAME,
-// };
-// use tokio::{signal, sync::broadcast};
-
-// #[tokio::main]
-// async fn main() -> anyhow::Result<()> {
-//     let s: StrategyCli = argh::from_env();
-//     if s.version {
-//         println!("{}", env!("CARGO_PKG_VERSION"));
-//         return Ok(());
-//     }
-
-//     if let Some(h) = s.cmd {
-//         match h {
-//             HelpCommandEnum::Help(s) => {
-//                 if let Some(path) = &s.path {
-//                     let res = build_dll_strategy(path);
-//                     if res.is_err() {
-//                         println!("build_dll_strategy error: {:?}", res.err());
-//                         return Ok(());
-//                     }
-//                     let strategy = res.unwrap();
-//                     println!(
-//                         "dll strategy: {}\nusage:\n{}",
-//                         strategy.name(),
-//                         strategy.help()
-//                     );
-//                 }
-//                 if let Some(name) = &s.builtin {
-//                     let strategy = get_strategy(name);
-//                     if strategy.is_err() {
-//                         println!("builtin strategy {} not found", name);
-//                         return Ok(());
-//                     }
-//                     let strategy = strategy.unwrap();
-//                     println!(
-//                         "builtin strategy: {}\nusage:\n{}",
-//                         strategy.name(),
-//                         strategy.help()
-//                     );
-//                 }
-//                 if s.path.is_none() && s.builtin.is_none() {
-//                     let s = strategies();
-//                     for name in s {
-//                         let strategy = get_strategy(&name);
-//                         if strategy.is_err() {
-//                             println!("builtin strategy {} not found", name);
-//                             return Ok(());
-//                         }
-//                         let strategy = strategy.unwrap();
-//                         println!(
-//                             "builtin strategy: {}\nusage:\n{}",
-//                             strategy.name(),
-//                             strategy.help()
-//                         );
-//                     }
-//                 }
-//             }
-//         }
-
-//         return Ok(());
-//     }
-
-//     let res = set_logger(&s.level);
-//     if res.is_err() {
-//         println!("set up logger error: {:?}", res);
-//         return Ok(());
-//     }
-//     log::info!("logger is ready");
-
-//     let rs = build_app_params(&s.dest, &s.params).await;
-//     if rs.is_err() {
-//         println!("build_app_params error: {:?}", rs.err());
-//         return Ok(());
-//     }
-//     let (loader, cmm_params, params) = rs.unwrap();
-
-//     let (shutdown_tx, _) = broadcast::channel(1);
-//     let mut strategy = if let Some(path) = &s.path {
-//         let res = build_dll_strategy(path);
-//         if res.is_err() {
-//             println!("build_dll_strategy error: {:?}", res.err());
-//             return Ok(());
-//         }
-//         res.unwrap()
-//     } else {
-//         if s.builtin.is_none() {
-//             return Ok(());
-//         }
-//         let name = s.builtin.unwrap();
-
-//         let strategy = get_strategy(&name);
-//         if strategy.is_err() {
-//             println!("get_strategy strategy {} err: {:?}", &name, strategy.err());
-//             return Ok(());
-//         }
-//         strategy.unwrap()
-//     };
-//     strategy
-//         .prepare(loader.clone(), cmm_params.clone(), params.clone())
-//         .await?;
-
-//     if s.function == "run".to_string() {
-//         let func: Option<ProgressFunc> = if s.level.as_str() == "error" {
-//             Some(Box::new(progress))
-//         } else {
-//             None
-//         };
-//         tokio::select! {
-//             rs = run(
-//                 Arc::new(strategy),
-//                 loader,
-//                 s.concurrent,
-//                 shutdown_tx.subscribe(),
-//                 None,
-//                 func
-//             ) => {
-//                 if rs.is_err() {
-//                     println!("run strategy error: {:?}", rs.err());
-//                     return Ok(());
-//                 }
-//                 let rs = rs.unwrap();
-//                 if let Some(rs) = rs {
-//                     println!("\nrun strategy result:");
-//                     for (k, v) in rs {
-//                         println!("type: {:?}", k);
-//                         println!("result: {:?}", v);
-//                     }
-//                 } else {
-//                     println!("\nrun strategy with no result!");
-//                 }
-//             },
-//             _ = signal::ctrl_c() => {
-//                 log::info!("shutdown receive");
-//                 let _ = shutdown_tx.send(());
-//             }
-//         }
-//     } else {
-//         if s.code.is_none() {
-//             println!("test strategy need code!");
-//             return Ok(());
-//         }
-//         let code = s.code.unwrap();
-//         let typ = if let Some(typ) = &s.typ {
-//             StrategyType::from(typ.as_str())
-//         } else {
-//             StrategyType::Stock
-//         };
-//         tokio::select! {
-//             rs = fit(
-//                 code,
-//                 String::from(""),
-//                 typ,
-//                 Arc::new(strategy),
-//                 loader,
-//                 shutdown_tx.subscribe(),
-//             ) => {
-//                 if rs.is_err() {
-//                     println!("test strategy fit error: {:?}", rs.err());
-//                     return Ok(());
-//                 }
-//                 let rs = rs.unwrap();
-//                 if let Some(rs) = rs {
-//                     println!("test strategy fit result: {:?}", rs);
-//                 } else {
-//                     println!("test strategy fit with no result!");
-//                 }
-//             },
-//             _ = signal::ctrl_c() => {
-//                 log::info!("shutdown receive");
-//                 let _ = shutdown_tx.send(());
-//             }
-//         }
-//     }
-
-//     Ok(())
-// }
-
-// fn set_logger(level: &str) -> anyhow::Result<()> {
-//     let level_str = level.to_uppercase();
-//     let level = log::LevelFilter::from_str(level_str.as_str())
-//         .with_context(|| format!("invalid log level {}", level_str))?;
-//     fern::Dispatch::new()
-//         .filter(|f| f.target().starts_with("rwinq"))
-//         .format(|out, message, record| {
-//             out.finish(format_args!(
-//                 "{}[{}] {}",
-//                 chrono::Local::now().format("[%Y-%m-%d %H:%M:%S%.3f]"),
-//                 record.level(),
-//                 message
-//             ))
-//         })
-//         .level(level)
-//         .chain(std::io::stdout())
-//         .apply()?;
-//     Ok(())
-// }
-
-// fn build_dll_strategy(path: &str) -> anyhow::Result<Box<dyn Strategy>> {
-//     unsafe {
-//         let lib = libloading::Library::new(path)
-//             .with_context(|| format!("failed to load dll, path: {}", path))?;
-//         let func: libloading::Symbol<Symbol> = lib
-//             .get(SYMBOL_NAME.as_bytes())
-//             .with_context(|| format!("failed to get dll function name, path: {}", path))?;
-//         let raw = func() as *mut Box<dyn Strategy>;
-//         let strategy = *Box::from_raw(raw);
-//         Ok(strategy)
-//     }
-// }
-
-// async fn build_loader(org_s: &str) -> anyhow::Result<Arc<Box<dyn Loader>>> {
-//     let s: Vec<_> = org_s.split("=").collect();
-//     if s.len() != 2 {
-//         return Err(anyhow::anyhow!("invalid dest format"));
-//     }
-//     let (k, v) = (*s.get(0).unwrap(), *s.get(1).unwrap());
-//     let dest = SyncDest::try_from((String::from(k), String::from(v)))
-//         .with_context(|| format!("try from ({}, {}) error", k, v))?;
-//     let (_, loader) = get_loader(&dest, true)
-//         .await
-//         .with_context(|| format!("get loader failed, params: {}", org_s))?;
-//     Ok(Arc::new(loader))
-// }
-
-// async fn build_app_params(
-//     dest: &String,
-//     args: &Vec<String>,
-// ) -> anyhow::Result<(
-//     Arc<Box<dyn Loader>>,
-//     Option<CommonParam>,
-//     Option<HashMap<String, String>>,
-// )> {
-//     let loader = build_loader(dest).await?;
-//     let params = build_params(args)?;
-//     let cmm_params = build_cmm_params(&params)?;
-//     let params = build_strategy_params(&params);
-//     Ok((loader, cmm_params, params))
-// }
-
-// fn build_params(args: &Vec<String>) -> anyhow::Result<HashMap<String, String>> {
-//     let mut map = HashMap::new();
-//     for e in args {
-//         let s: Vec<_> = e.split("=").collect();
-//         if s.len() != 2 {
-//             return Err(anyhow::anyhow!("invalid dest format"));
-//         }
-//         let (k, v) = (*s.get(0).unwrap(), *s.get(1).unwrap());
-//         if map.contains_key(k) {
-//             return Err(anyhow::anyhow!("duplicate param key: {}", k));
-//         }
-//         map.insert(String::from(k), String::from(v));
-//     }
-//     Ok(map)
-// }
-
-// fn build_cmm_params(params: &HashMap<String, String>) -> anyhow::Result<Option<CommonParam>> {
-//     let mut test_end_date = None;
-//     if params.contains_key(&String::from("test_end_date")) {
-//         let s = params.get(&String::from("test_end_date")).unwrap();
-//         let mut date = NaiveDate::parse_from_str(s, "%Y-%m-%d");
-//         if date.is_err() {
-//             date = NaiveDate::parse_from_str(s, "%Y%m%d");
-//             if date.is_err() {
-//                 return Err(anyhow::anyhow!(
-//                     "test_end_date format is not correct, expect: %Y-%m-%d or %Y%m%d"
-//                 ));
-//             }
-//         }
-//         let date = date.unwrap();
-//         test_end_date = Some(NaiveDateTime::new(
-//             date,
-//             NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-//         ));
-//     }
-//     let mut test_trade_days = Some(60);
-//     if params.contains_key(&String::from("test_trade_days")) {
-//         let s = params.get(&String::from("test_trade_days")).unwrap();
-//         let days = s.parse::<i64>();
-//         if days.is_err() {
-//             return Err(anyhow::anyhow!("test_trade_days is not number"));
-//         }
-//         let days = days.unwrap();
-//         test_trade_days = Some(days);
-//     }
-
-//     if test_end_date.is_none() || test_trade_days.is_none() {
-//         return Ok(None);
-//     }
-//     let cmm_param = CommonParam {
-//         test_end_date,
-//         test_trade_days,
-//     };
-//     Ok(Some(cmm_param))
-// }
-
-// fn build_strategy_params(params: &HashMap<String, String>) -> Option<HashMap<String, String>> {
-//     let mut map = HashMap::new();
-//     for (k, v) in params.iter() {
-//         if k == &String::from("test_end_date") || k == &String::from("test_trade_days") {
-//             continue;
-//         }
-//         map.insert((*k).clone(), (*v).clone());
-//     }
-//     if map.is_empty() {
-//         None
-//     } else {
-//         Some(map)
-//     }
-// }
-
-// fn progress(code: &str, name: &str, total: usize, current: usize, progress: f32) {
-//     print!(
-//         "\r>> processing: {}({}) {}/{}({})%       ",
-//         name, code, current, total, progress
-//     )
-// }
-
-// #[derive(FromArgs, PartialEq, Debug)]
-// /// StrategyCli command.
-// struct StrategyCli {
-//     /// 版本号
-//     #[argh(switch, short = 'v')]
-//     version: bool,
-
-//     /// 日志级别，默认info
-//     #[argh(option, short = 'l', default = "String::from(\"error\")")]
-//     level: String,
-
-//     /// 并发获取数据任务数，默认为1
-//     #[argh(option, short = 'r', default = "4")]
-//     concurrent: usize,
-
-//     /// 如：file=/user/home/app, mongodb=mongodb://localhost:27017
-//     /// 支持的目标有: file, mongodb, mysql
-//     /// 可同时传递多个目标:
-//     /// 如：-d mongodb=mongodb://localhost:27017
-//     #[argh(
-//         option,
-//         short = 'd',
-//         default = "String::from(\"mongodb=mongodb://localhost:27017\")"
-//     )]
-//     dest: String,
-
-//     /// 策略位置
-//     #[argh(option, short = 'p')]
-//     path: Option<String>,
-
-//     /// 内置策略名称
-//     #[argh(option, short = 'b')]
-//     builtin: Option<String>,
-
-//     /// 测试或者运行策略，run/test
-//     #[argh(option, short = 'f', default = "String::from(\"run\")")]
-//     function: String,
-
-//     /// function == test 有效，测试的类型
-//     /// 可选为：bond,fund,stock,index,concept,industry
-//     #[argh(option, short = 't')]
-//     typ: Option<String>,
-
-//     /// function == test 有效，测试的代码
-//     #[argh(option, short = 'c')]
-//     code: Option<String>,
-
-//     /// 策略参数，key=val格式
-//     #[argh(positional)]
-//     params: Vec<String>,
-
-//     #[argh(subcommand)]
-//     cmd: Option<HelpCommandEnum>,
-// }
-// #[derive(FromArgs, PartialEq, Debug)]
-// #[argh(subcommand)]
-// enum HelpCommandEnum {
-//     Help(HelpCommand),
-// }
-
-// #[derive(FromArgs, PartialEq, Debug)]
-// /// 策略帮助信息
-// #[argh(subcommand, name = "usage")]
-// struct HelpCommand {
-//     /// 策略位置
-//     #[argh(option, short = 'p')]
-//     path: Option<String>,
-
-//     /// 内置策略名称
-//     #[argh(option, short = 'b')]
-//     builtin: Option<String>,
-// }
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use anyhow::Context;
+use argh::FromArgs;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rwqdata::{
+    store::{get_loader, Loader},
+    MarketType, SyncDest,
+};
+use rwqstrategy::{
+    fit, get_strategy, run,
+    select::{check_params, CommonParam, ProgressFunc, Strategy, StrategyResult},
+    strategies, strategy_info, Params, Plugin, PluginManager, StrategyInfo,
+};
+use tokio::{signal, sync::broadcast};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let s: StrategyCli = argh::from_env();
+    if s.version {
+        println!("{}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    let res = set_logger(&s.level);
+    if res.is_err() {
+        println!("set up logger error: {:?}", res);
+        return res;
+    }
+    log::info!("logger is ready");
+    if let Some(cmd) = s.cmd {
+        let res = match cmd {
+            StrategySubCommandEnum::List(x) => list_cmd(x),
+            StrategySubCommandEnum::Usage(x) => usage_cmd(x),
+            StrategySubCommandEnum::Run(x) => run_cmd(x).await,
+            StrategySubCommandEnum::Fit(x) => fit_cmd(x).await,
+        };
+        if res.is_err() {
+            log::error!("run cmd error: {:?}", res);
+            return res;
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+fn list_cmd(cmd: ListCommand) -> anyhow::Result<()> {
+    match cmd.plugin_dir {
+        Some(dir) => {
+            let mut manager = PluginManager::new(&dir);
+            let rs = manager.reload()?;
+            for plugin in manager.plugins() {
+                println!(
+                    "{}({:?}): {:?}",
+                    plugin.name(),
+                    plugin.path(),
+                    plugin.kinds()
+                );
+            }
+            for (path, e) in rs.failed {
+                println!("{:?}: {}", path, e);
+            }
+        }
+        None => {
+            for name in strategies() {
+                let info = strategy_info(&name)?;
+                println!("{}: {:?}", info.name, info.accept);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn usage_cmd(cmd: UsageCommand) -> anyhow::Result<()> {
+    let infos = match (cmd.builtin, cmd.plugin) {
+        (_, Some(path)) => {
+            let strategy = Plugin::load(&path)?.new_select()?;
+            vec![StrategyInfo::new(strategy.as_ref())]
+        }
+        (Some(name), None) => vec![strategy_info(&name)?],
+        (None, None) => strategies()
+            .iter()
+            .map(|name| strategy_info(name))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    for info in infos {
+        println!("strategy: {}\nusage:\n{}", info.name, info.help);
+        if !info.params.is_empty() {
+            println!("params:");
+            for p in info.params {
+                println!(
+                    "  {}({:?}) -- {}(默认: {})",
+                    p.name,
+                    p.typ,
+                    p.desc,
+                    p.default.unwrap_or_default()
+                );
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+async fn run_cmd(cmd: RunCommand) -> anyhow::Result<()> {
+    log::info!("run: {:?}", &cmd);
+    let (loader, strategy) =
+        build_strategy(&cmd.dest, &cmd.builtin, &cmd.plugin, &cmd.params).await?;
+
+    let func: Option<ProgressFunc> = if cmd.output.is_some() {
+        Some(Box::new(progress))
+    } else {
+        None
+    };
+    let (shutdown_tx, _) = broadcast::channel(1);
+    tokio::select! {
+        rs = run(
+            Arc::new(strategy),
+            loader,
+            cmd.concurrent,
+            shutdown_tx.subscribe(),
+            None,
+            func
+        ) => {
+            let rs = rs.with_context(|| "run strategy error")?;
+            let rs: Vec<_> = rs
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|(typ, v)| v.into_iter().map(move |r| (typ, r)))
+                .collect();
+            write_result(&rs, &cmd.format, &cmd.output)?;
+        },
+        _ = signal::ctrl_c() => {
+            log::info!("capture ctrl-c to exit");
+            let _ = shutdown_tx.send(());
+        }
+    }
+    Ok(())
+}
+
+async fn fit_cmd(cmd: FitCommand) -> anyhow::Result<()> {
+    log::info!("fit: {:?}", &cmd);
+    let (loader, strategy) =
+        build_strategy(&cmd.dest, &cmd.builtin, &cmd.plugin, &cmd.params).await?;
+    let typ = parse_market_type(&cmd.typ)?;
+
+    let (shutdown_tx, _) = broadcast::channel(1);
+    tokio::select! {
+        rs = fit(
+            cmd.code.clone(),
+            cmd.name.clone().unwrap_or_default(),
+            typ,
+            Arc::new(strategy),
+            loader,
+            shutdown_tx.subscribe(),
+        ) => {
+            let rs = rs.with_context(|| format!("fit {} error", &cmd.code))?;
+            let rs: Vec<_> = rs.into_iter().map(|r| (typ, r)).collect();
+            write_result(&rs, &cmd.format, &cmd.output)?;
+        },
+        _ = signal::ctrl_c() => {
+            log::info!("capture ctrl-c to exit");
+            let _ = shutdown_tx.send(());
+        }
+    }
+    Ok(())
+}
+
+fn set_logger(level: &str) -> anyhow::Result<()> {
+    let level_str = level.to_uppercase();
+    let level = log::LevelFilter::from_str(level_str.as_str())
+        .with_context(|| format!("invalid log level {}", level_str))?;
+    fern::Dispatch::new()
+        .filter(|f| f.target().starts_with("rwq"))
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}] {}",
+                chrono::Local::now().format("[%Y-%m-%d %H:%M:%S%.3f]"),
+                record.level(),
+                message
+            ))
+        })
+        .level(level)
+        .chain(std::io::stderr())
+        .apply()?;
+    Ok(())
+}
+
+fn parse_market_type(s: &str) -> anyhow::Result<MarketType> {
+    match s {
+        "bond" => Ok(MarketType::Bond),
+        "fund" => Ok(MarketType::Fund),
+        "stock" => Ok(MarketType::Stock),
+        _ => Err(anyhow::anyhow!(
+            "invalid type {}, expect: bond, fund, stock",
+            s
+        )),
+    }
+}
+
+async fn build_loader(org_s: &str) -> anyhow::Result<Arc<Box<dyn Loader>>> {
+    let (k, v) = org_s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("invalid dest format"))?;
+    let dest = SyncDest::try_from((String::from(k), String::from(v)))
+        .with_context(|| format!("try from ({}, {}) error", k, v))?;
+    let (_, loader) = get_loader(&dest, true)
+        .await
+        .with_context(|| format!("get loader failed, params: {}", org_s))?;
+    Ok(Arc::new(loader))
+}
+
+/// 加载数据源，创建并准备策略
+async fn build_strategy(
+    dest: &str,
+    builtin: &Option<String>,
+    plugin: &Option<String>,
+    args: &[String],
+) -> anyhow::Result<(Arc<Box<dyn Loader>>, Box<dyn Strategy>)> {
+    let (name, mut strategy) = match (builtin, plugin) {
+        (Some(name), None) => (
+            name.clone(),
+            get_strategy(name).with_context(|| format!("get strategy {}", name))?,
+        ),
+        (None, Some(path)) => (
+            path.clone(),
+            Plugin::load(path)
+                .and_then(|p| p.new_select())
+                .with_context(|| format!("load plugin {}", path))?,
+        ),
+        _ => return Err(anyhow::anyhow!("specify one of builtin(-b) or plugin(-p)")),
+    };
+
+    let params = build_params(args)?;
+    let cmm_params = build_cmm_params(&params)?;
+    let params = build_strategy_params(&params);
+    if let Some(params) = &params {
+        check_params(&strategy.params(), params)
+            .with_context(|| format!("invalid params for strategy {}", name))?;
+    }
+
+    let loader = build_loader(dest).await?;
+    strategy
+        .prepare(loader.clone(), cmm_params, params)
+        .await
+        .with_context(|| format!("prepare strategy {} error", name))?;
+    Ok((loader, strategy))
+}
+
+fn build_params(args: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    let mut map = HashMap::new();
+    for e in args {
+        let (k, v) = e
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("invalid param format: {}, expect: key=val", e))?;
+        if map.contains_key(k) {
+            return Err(anyhow::anyhow!("duplicate param key: {}", k));
+        }
+        map.insert(String::from(k), String::from(v));
+    }
+    Ok(map)
+}
+
+fn build_cmm_params(params: &HashMap<String, String>) -> anyhow::Result<Option<CommonParam>> {
+    let mut cmm_params = CommonParam::default();
+    if let Some(s) = params.get("test_end_date") {
+        let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(s, "%Y%m%d"))
+            .with_context(|| {
+                format!(
+                    "test_end_date {} format is not correct, expect: %Y-%m-%d or %Y%m%d",
+                    s
+                )
+            })?;
+        cmm_params.test_end_date = Some(NaiveDateTime::new(
+            date,
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        ));
+    }
+    if let Some(s) = params.get("test_trade_days") {
+        let days = s
+            .parse::<i64>()
+            .with_context(|| format!("test_trade_days {} is not number", s))?;
+        cmm_params.test_trade_days = Some(days);
+    }
+    Ok(Some(cmm_params))
+}
+
+fn build_strategy_params(params: &HashMap<String, String>) -> Option<Params> {
+    let map: Params = params
+        .iter()
+        .filter(|(k, _)| k.as_str() != "test_end_date" && k.as_str() != "test_trade_days")
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    if map.is_empty() {
+        None
+    } else {
+        Some(map)
+    }
+}
+
+/// 输出结果，`output`为空输出到标准输出
+fn write_result(
+    rs: &[(MarketType, StrategyResult)],
+    format: &str,
+    output: &Option<String>,
+) -> anyhow::Result<()> {
+    let content = match format {
+        "json" => {
+            let rs: Vec<_> = rs
+                .iter()
+                .map(|(typ, r)| serde_json::json!({"type": typ, "result": r}))
+                .collect();
+            serde_json::to_string_pretty(&rs).with_context(|| "to json error")?
+        }
+        "csv" => to_csv(rs),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "invalid format {}, expect: json, csv",
+                format
+            ))
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, content).with_context(|| format!("write {} error", path))?;
+            eprintln!("\n{} results saved to {}", rs.len(), path);
+        }
+        None => println!("{}", content),
+    }
+    Ok(())
+}
+
+fn to_csv(rs: &[(MarketType, StrategyResult)]) -> String {
+    let mut lines = vec![String::from(
+        "type,code,name,hit,hit_max,chg_pct_1,chg_pct_2,chg_pct_4,chg_pct_8,chg_pct_now",
+    )];
+    for (typ, r) in rs {
+        let stat = match &r.stat {
+            Some(stat) => {
+                let chg: Vec<_> = stat.hit_chg_pct.iter().map(|c| c.to_string()).collect();
+                format!("{},{},{}", stat.hit, stat.hit_max, chg.join(","))
+            }
+            None => String::from(",,,,,,"),
+        };
+        lines.push(format!(
+            "{:?},{},{},{}",
+            typ,
+            csv_field(&r.code),
+            csv_field(&r.name),
+            stat
+        ));
+    }
+    lines.join("\n")
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn progress(code: &str, name: &str, total: usize, current: usize, progress: f32) {
+    eprint!(
+        "\r>> processing: {}({}) {}/{}({})%       ",
+        name, code, current, total, progress
+    )
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// StrategyCli command.
+struct StrategyCli {
+    /// 版本号
+    #[argh(switch, short = 'v')]
+    version: bool,
+
+    /// 日志级别，默认error
+    #[argh(option, short = 'l', default = "String::from(\"error\")")]
+    level: String,
+
+    #[argh(subcommand)]
+    cmd: Option<StrategySubCommandEnum>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand)]
+enum StrategySubCommandEnum {
+    List(ListCommand),
+    Usage(UsageCommand),
+    Run(RunCommand),
+    Fit(FitCommand),
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// 列出内置策略
+#[argh(subcommand, name = "list")]
+struct ListCommand {
+    /// 插件目录，列出目录下的插件及其导出的对象
+    #[argh(option, short = 'p')]
+    plugin_dir: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// 策略帮助信息
+#[argh(subcommand, name = "usage")]
+struct UsageCommand {
+    /// 内置策略名称，为空时输出全部内置策略
+    #[argh(option, short = 'b')]
+    builtin: Option<String>,
+
+    /// 插件路径
+    #[argh(option, short = 'p')]
+    plugin: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// 全市场运行选股策略
+#[argh(subcommand, name = "run")]
+struct RunCommand {
+    /// 数据源，“=”分割，前面一部分表示目标，后一部分表示url
+    /// 如：mongodb=mongodb://localhost:27017
+    #[argh(
+        option,
+        short = 'd',
+        default = "String::from(\"mongodb=mongodb://localhost:27017\")"
+    )]
+    dest: String,
+
+    /// 内置策略名称
+    #[argh(option, short = 'b')]
+    builtin: Option<String>,
+
+    /// 插件路径，插件须导出new_select
+    #[argh(option, short = 'p')]
+    plugin: Option<String>,
+
+    /// 并发任务数，默认为4
+    #[argh(option, short = 'r', default = "4")]
+    concurrent: usize,
+
+    /// 输出格式: json, csv，默认json
+    #[argh(option, short = 'f', default = "String::from(\"json\")")]
+    format: String,
+
+    /// 输出文件，默认输出到标准输出
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// 策略参数，key=val格式
+    /// 通用参数: test_end_date(%Y-%m-%d), test_trade_days
+    #[argh(positional)]
+    params: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// 单个代码运行选股策略
+#[argh(subcommand, name = "fit")]
+struct FitCommand {
+    /// 数据源，“=”分割，前面一部分表示目标，后一部分表示url
+    /// 如：mongodb=mongodb://localhost:27017
+    #[argh(
+        option,
+        short = 'd',
+        default = "String::from(\"mongodb=mongodb://localhost:27017\")"
+    )]
+    dest: String,
+
+    /// 内置策略名称
+    #[argh(option, short = 'b')]
+    builtin: Option<String>,
+
+    /// 插件路径，插件须导出new_select
+    #[argh(option, short = 'p')]
+    plugin: Option<String>,
+
+    /// 代码，如: sz000001
+    #[argh(option, short = 'c')]
+    code: String,
+
+    /// 名称
+    #[argh(option, short = 'n')]
+    name: Option<String>,
+
+    /// 类型: bond, fund, stock，默认stock
+    #[argh(option, short = 't', default = "String::from(\"stock\")")]
+    typ: String,
+
+    /// 输出格式: json, csv，默认json
+    #[argh(option, short = 'f', default = "String::from(\"json\")")]
+    format: String,
+
+    /// 输出文件，默认输出到标准输出
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// 策略参数，key=val格式
+    /// 通用参数: test_end_date(%Y-%m-%d), test_trade_days
+    #[argh(positional)]
+    params: Vec<String>,
+}
@@ -1,19 +1,168 @@
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
-use rwqtradecmm::{BrokerEvent, Entrust, EntrustStatus, Event};
+use rwqdata::{Quot, RtQuot};
+use rwqtradecmm::{BrokerEvent, Entrust, EntrustStatus, Event, QuotEvent, TradeType};
 
-use crate::{broker::Broker, context::Context, Result};
+use crate::{broker::Broker, context::Context, Error, Params, Result};
+
+/// 未完成委托
+struct Pending {
+    entrust: Entrust,
+    /// 是否已参与过撮合，收盘时只撤销参与过撮合的委托
+    matched: bool,
+}
 
 pub struct Simulate {
-    pub entrust: RwLock<Vec<Entrust>>,
+    /// 滑点比例，买入价上浮，卖出价下浮，不超过委托价
+    pub slippage: f32,
+    /// 单个行情成交额的最大参与比例，超过部分为部分成交，0为不限制
+    pub volume_ratio: f32,
+    pending: RwLock<Vec<Pending>>,
 }
 
 impl Simulate {
     pub fn new() -> Self {
         Self {
-            entrust: RwLock::new(vec![]),
+            slippage: 0.0,
+            volume_ratio: 0.25,
+            pending: RwLock::new(vec![]),
+        }
+    }
+
+    /// 涨跌停幅度: 北交所30%，科创板/创业板20%，ST 5%，其他10%
+    pub fn limit_rate(code: &str, name: &str) -> f32 {
+        if code.starts_with("bj") {
+            0.3
+        } else if code.starts_with("sh688") || code.starts_with("sz30") {
+            0.2
+        } else if name.contains("ST") {
+            0.05
+        } else {
+            0.1
+        }
+    }
+
+    /// 涨停价，跌停价
+    pub fn limit_price(code: &str, name: &str, last_close: f32) -> (f32, f32) {
+        let rate = Self::limit_rate(code, name);
+        let up = (last_close * (1.0 + rate) * 100.0).round() / 100.0;
+        let down = (last_close * (1.0 - rate) * 100.0).round() / 100.0;
+        (up, down)
+    }
+
+    /// 委托按行情撮合，返回成交后的委托，未成交返回`None`
+    ///
+    /// - 限价: 买入行情最低价不高于委托价，卖出行情最高价不低于委托价，开盘跳空按开盘价成交
+    /// - 涨跌停: 按成交价判断，涨停价不能买入，跌停价不能卖出
+    /// - 滑点: 成交价按`slippage`向不利方向调整，不超过委托价
+    /// - 成交量: 不超过行情成交额的`volume_ratio`，买入按整手成交
+    fn match_entrust(&self, entrust: &Entrust, quot: &Quot) -> Option<Entrust> {
+        let remain = entrust.volume.saturating_sub(entrust.volume_deal);
+        if remain == 0 || !quot.is_trading {
+            return None;
         }
+        let (up, down) = Self::limit_price(&quot.code, &quot.name, quot.last_close);
+        let price = match entrust.typ {
+            TradeType::Buy => {
+                let base = if quot.freq_open <= entrust.price {
+                    quot.freq_open
+                } else if quot.freq_low <= entrust.price {
+                    entrust.price
+                } else {
+                    return None;
+                };
+                if quot.last_close > 0.0 && base >= up {
+                    return None;
+                }
+                (base * (1.0 + self.slippage)).min(entrust.price)
+            }
+            TradeType::Sell => {
+                let base = if quot.freq_open >= entrust.price {
+                    quot.freq_open
+                } else if quot.freq_high >= entrust.price {
+                    entrust.price
+                } else {
+                    return None;
+                };
+                if quot.last_close > 0.0 && base <= down {
+                    return None;
+                }
+                (base * (1.0 - self.slippage)).max(entrust.price)
+            }
+            TradeType::Cancel => return None,
+        };
+        let price = (price * 1000.0).round() / 1000.0;
+
+        let mut volume = remain;
+        if self.volume_ratio > 0.0 {
+            let limit = (quot.amount * self.volume_ratio as f64 / price as f64) as u32;
+            if limit < remain {
+                // 买入按整手成交，卖出可以成交零股
+                volume = match entrust.typ {
+                    TradeType::Buy => limit / 100 * 100,
+                    _ => limit,
+                };
+            }
+        }
+        if volume == 0 {
+            return None;
+        }
+
+        let mut entrust = entrust.clone();
+        let amount = entrust.price_deal * entrust.volume_deal as f32 + price * volume as f32;
+        entrust.volume_deal += volume;
+        entrust.price_deal = amount / entrust.volume_deal as f32;
+        entrust.status = if entrust.volume_deal >= entrust.volume {
+            EntrustStatus::Deal
+        } else {
+            EntrustStatus::PartDeal
+        };
+        Some(entrust)
+    }
+
+    /// 撤销未完成委托，`all`为`false`时只撤销参与过撮合的委托
+    async fn expire(&self, ctx: &Arc<Context>, all: bool) -> Result<()> {
+        let entrusts: Vec<_> = {
+            let mut pending = self.pending.write().unwrap();
+            let (expired, remain) = pending.drain(..).partition(|p| all || p.matched);
+            *pending = remain;
+            expired
+                .into_iter()
+                .map(|p| Self::cancelled(p.entrust))
+                .collect()
+        };
+        if !entrusts.is_empty() {
+            // 模拟委托撤销事件
+            ctx.emit(Event::Broker(BrokerEvent::Entrust(entrusts)))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 撤销未完成委托`id`，已成交部分保留，委托已完成则忽略
+    async fn cancel(&self, ctx: &Arc<Context>, id: &str) -> Result<()> {
+        let entrust = {
+            let mut pending = self.pending.write().unwrap();
+            pending
+                .iter()
+                .position(|p| p.entrust.id.as_str() == id)
+                .map(|i| pending.remove(i).entrust)
+        };
+        if let Some(entrust) = entrust {
+            // 模拟委托撤销事件
+            ctx.emit(Event::Broker(BrokerEvent::Entrust(vec![Self::cancelled(
+                entrust,
+            )])))
+            .await?;
+        }
+        Ok(())
+    }
+
+    fn cancelled(mut entrust: Entrust) -> Entrust {
+        entrust.status = EntrustStatus::Cancel;
+        entrust.volume_cancel = entrust.volume - entrust.volume_deal;
+        entrust
     }
 }
 
@@ -23,43 +172,307 @@ impl Broker for Simulate {
         String::from(
             r#"Simulate 模拟券商
 
-通模拟券商，无条件成交。"#,
+按行情撮合限价委托，委托在下一个行情成交:
+
+- 买入行情最低价不高于委托价，卖出行情最高价不低于委托价成交
+- 涨停不能买入，跌停不能卖出(北交所30%，科创板/创业板20%，ST 5%，其他10%)
+- `slippage` 滑点比例，默认0
+- `volume_ratio` 单个行情成交额的最大参与比例，超过部分为部分成交，默认0.25，0为不限制
+- 收盘时未成交的委托撤销
+- 撤销委托立即撤销未成交部分"#,
         )
     }
     fn name(&self) -> String {
         String::from("Simulate -- 模拟券商")
     }
+    async fn init(&mut self, _ctx: Arc<Context>, params: Option<Params>) -> Result<()> {
+        if let Some(params) = params {
+            if params.contains_key("slippage") {
+                self.slippage = params
+                    .get("slippage")
+                    .unwrap()
+                    .parse()
+                    .map_err(|e| Error::Custom(format!("parse slippage error: {:?}", e)))?;
+            }
+            if params.contains_key("volume_ratio") {
+                self.volume_ratio = params
+                    .get("volume_ratio")
+                    .unwrap()
+                    .parse()
+                    .map_err(|e| Error::Custom(format!("parse volume_ratio error: {:?}", e)))?;
+            }
+        }
+        Ok(())
+    }
+    async fn on_close(&mut self, ctx: Arc<Context>, _event: QuotEvent) -> Result<()> {
+        self.expire(&ctx, false).await
+    }
+    async fn on_end(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.expire(&ctx, true).await
+    }
     async fn on_entrust(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
+        if matches!(entrust.typ, TradeType::Cancel) {
+            return match &entrust.cancel_id {
+                Some(id) => self.cancel(&ctx, id).await,
+                None => Ok(()),
+            };
+        }
         let mut entrust = entrust.clone();
         entrust.broker_entrust_id = Some(entrust.id.to_string());
-        entrust.time = Default::default();
         entrust.status = EntrustStatus::Commit;
 
         // 模拟委托提交事件
         ctx.emit(Event::Broker(BrokerEvent::Entrust(vec![entrust.clone()])))
             .await?;
 
-        entrust.status = EntrustStatus::Deal;
-        entrust.volume_deal = entrust.volume;
         {
-            self.entrust.write().unwrap().push(entrust);
+            self.pending.write().unwrap().push(Pending {
+                entrust,
+                matched: false,
+            });
         }
         Ok(())
     }
-    async fn on_poll(&self, ctx: Arc<Context>) -> Result<()> {
-        let entrust = {
-            let mut entrust = self.entrust.write().unwrap();
-            if entrust.len() > 0 {
-                Some(entrust.remove(0))
-            } else {
-                None
+    async fn on_cancel(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
+        self.cancel(&ctx, entrust.id.as_str()).await
+    }
+    async fn on_quot(&self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+        let entrusts: Vec<_> = {
+            let mut pending = self.pending.write().unwrap();
+            let mut entrusts = vec![];
+            for p in pending.iter_mut() {
+                if let Some(quot) = quots.get(&p.entrust.code) {
+                    p.matched = true;
+                    if let Some(entrust) = self.match_entrust(&p.entrust, quot) {
+                        p.entrust = entrust.clone();
+                        entrusts.push(entrust);
+                    }
+                }
             }
+            pending.retain(|p| !matches!(p.entrust.status, EntrustStatus::Deal));
+            entrusts
         };
-        if let Some(entrust) = entrust {
+        if !entrusts.is_empty() {
             // 模拟委托成交事件
-            ctx.emit(Event::Broker(BrokerEvent::Entrust(vec![entrust.clone()])))
+            ctx.emit(Event::Broker(BrokerEvent::Entrust(entrusts)))
                 .await?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use rwqdata::{store::get_loader, FileFormat, Quot, RtQuot, SyncDest};
+    use rwqtradecmm::{Account, BrokerEvent, Entrust, EntrustStatus, Event, TradeType};
+    use tokio::sync::mpsc;
+
+    use super::Simulate;
+    use crate::{broker::Broker, context::Context};
+
+    fn quot(open: f32, high: f32, low: f32, close: f32) -> Quot {
+        Quot {
+            code: "sz000001".into(),
+            name: "平安银行".into(),
+            last_close: 10.0,
+            now: close,
+            amount: 100000.0,
+            freq_open: open,
+            freq_high: high,
+            freq_low: low,
+            is_trading: true,
+            ..Default::default()
+        }
+    }
+
+    fn entrust(typ: TradeType, price: f32, volume: u32) -> Entrust {
+        Entrust {
+            code: "sz000001".into(),
+            typ,
+            price,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_limit_price() {
+        assert_eq!(
+            Simulate::limit_price("sz000001", "平安银行", 10.0),
+            (11.0, 9.0)
+        );
+        assert_eq!(
+            Simulate::limit_price("sz300750", "宁德时代", 10.0),
+            (12.0, 8.0)
+        );
+        assert_eq!(
+            Simulate::limit_price("sh688981", "中芯国际", 10.0),
+            (12.0, 8.0)
+        );
+        assert_eq!(
+            Simulate::limit_price("bj430047", "诺思兰德", 10.0),
+            (13.0, 7.0)
+        );
+        assert_eq!(
+            Simulate::limit_price("sh600000", "*ST某某", 10.0),
+            (10.5, 9.5)
+        );
+    }
+
+    #[test]
+    fn test_match_price() {
+        let mut broker = Simulate::new();
+        broker.volume_ratio = 0.0;
+
+        // 未触及委托价
+        let buy = entrust(TradeType::Buy, 9.8, 1000);
+        assert!(broker
+            .match_entrust(&buy, &quot(10.0, 10.2, 9.9, 10.1))
+            .is_none());
+        // 触及委托价，按委托价成交
+        let e = broker
+            .match_entrust(&buy, &quot(10.0, 10.2, 9.7, 10.1))
+            .unwrap();
+        assert!(matches!(e.status, EntrustStatus::Deal));
+        assert_eq!(e.price_deal, 9.8);
+        // 低开，按开盘价成交
+        let e = broker
+            .match_entrust(&buy, &quot(9.6, 10.2, 9.5, 10.1))
+            .unwrap();
+        assert_eq!(e.price_deal, 9.6);
+        // 涨停不能买入
+        let buy = entrust(TradeType::Buy, 11.0, 1000);
+        assert!(broker
+            .match_entrust(&buy, &quot(11.0, 11.0, 11.0, 11.0))
+            .is_none());
+        // 跌停不能卖出
+        let sell = entrust(TradeType::Sell, 9.0, 1000);
+        assert!(broker
+            .match_entrust(&sell, &quot(9.0, 9.0, 9.0, 9.0))
+            .is_none());
+        // 涨停开盘后回落，低于涨停价可以买入
+        let q = quot(11.0, 11.0, 10.5, 10.6);
+        assert!(broker.match_entrust(&buy, &q).is_none());
+        let e = broker
+            .match_entrust(&entrust(TradeType::Buy, 10.8, 1000), &q)
+            .unwrap();
+        assert_eq!(e.price_deal, 10.8);
+        // 收盘涨停，开盘价可以买入
+        let e = broker
+            .match_entrust(&buy, &quot(10.2, 11.0, 10.1, 11.0))
+            .unwrap();
+        assert_eq!(e.price_deal, 10.2);
+        // 跌停开盘后回升，高于跌停价可以卖出
+        let e = broker
+            .match_entrust(
+                &entrust(TradeType::Sell, 9.2, 1000),
+                &quot(9.0, 9.5, 9.0, 9.4),
+            )
+            .unwrap();
+        assert_eq!(e.price_deal, 9.2);
+
+        // 滑点不超过委托价
+        broker.slippage = 0.01;
+        let buy = entrust(TradeType::Buy, 10.0, 1000);
+        let e = broker
+            .match_entrust(&buy, &quot(9.5, 10.2, 9.5, 10.1))
+            .unwrap();
+        assert_eq!(e.price_deal, 9.595);
+        let e = broker
+            .match_entrust(&buy, &quot(10.0, 10.2, 9.7, 10.1))
+            .unwrap();
+        assert_eq!(e.price_deal, 10.0);
+    }
+
+    #[test]
+    fn test_part_deal() {
+        let mut broker = Simulate::new();
+        broker.volume_ratio = 0.1;
+
+        // 成交额100000，参与10%，10元最多成交1000股
+        let buy = entrust(TradeType::Buy, 10.0, 2500);
+        let q = quot(10.0, 10.2, 9.9, 10.1);
+        let e = broker.match_entrust(&buy, &q).unwrap();
+        assert!(matches!(e.status, EntrustStatus::PartDeal));
+        assert_eq!(e.volume_deal, 1000);
+        let e = broker.match_entrust(&e, &q).unwrap();
+        assert_eq!(e.volume_deal, 2000);
+        let e = broker.match_entrust(&e, &q).unwrap();
+        assert!(matches!(e.status, EntrustStatus::Deal));
+        assert_eq!(e.volume_deal, 2500);
+        assert_eq!(e.price_deal, 10.0);
+    }
+
+    #[test]
+    fn test_odd_lot_sell() {
+        let mut broker = Simulate::new();
+        broker.volume_ratio = 0.003;
+
+        // 成交额100000，参与0.3%，10元最多成交30股，卖出零股不足一手也能成交
+        let q = quot(10.0, 10.2, 9.9, 10.1);
+        let sell = entrust(TradeType::Sell, 10.0, 50);
+        let e = broker.match_entrust(&sell, &q).unwrap();
+        assert!(matches!(e.status, EntrustStatus::PartDeal));
+        assert_eq!(e.volume_deal, 30);
+        let e = broker.match_entrust(&e, &q).unwrap();
+        assert!(matches!(e.status, EntrustStatus::Deal));
+        assert_eq!(e.volume_deal, 50);
+
+        // 买入不足一手不成交
+        let buy = entrust(TradeType::Buy, 10.0, 100);
+        assert!(broker.match_entrust(&buy, &q).is_none());
+    }
+
+    #[test]
+    fn test_cancel() {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let dest = SyncDest::File(std::env::temp_dir(), FileFormat::default());
+                let (_, loader) = get_loader(&dest, false).await.unwrap();
+                let account = Account {
+                    cash_init: 100000.0,
+                    cash_available: 100000.0,
+                    ..Default::default()
+                };
+                let account = Arc::new(Box::new(RwLock::new(account)));
+                let (tx, mut rx) = mpsc::channel(16);
+                let ctx = Arc::new(Context::new(Arc::new(loader), account.clone(), tx));
+
+                let mut broker = Simulate::new();
+                broker.volume_ratio = 0.1;
+                let buy = entrust(TradeType::Buy, 10.0, 2500);
+                assert!(account.write().unwrap().freeze(&buy));
+                broker.on_entrust(ctx.clone(), buy.clone()).await.unwrap();
+
+                let q = quot(10.0, 10.2, 9.9, 10.1);
+                let quots = [(q.code.clone(), q)].into_iter().collect::<RtQuot>();
+                broker.on_quot(ctx.clone(), quots.clone()).await.unwrap();
+                broker.on_cancel(ctx.clone(), buy.clone()).await.unwrap();
+                // 撤销后不再撮合
+                broker.on_quot(ctx.clone(), quots).await.unwrap();
+
+                let mut entrusts = vec![];
+                while let Ok(event) = rx.try_recv() {
+                    if let Event::Broker(BrokerEvent::Entrust(v)) = event {
+                        entrusts.extend(v);
+                    }
+                }
+                assert_eq!(entrusts.len(), 3);
+                let cancel = entrusts.last().unwrap();
+                assert!(matches!(cancel.status, EntrustStatus::Cancel));
+                assert_eq!((cancel.volume_deal, cancel.volume_cancel), (1000, 1500));
+
+                let mut account = account.write().unwrap();
+                entrusts
+                    .into_iter()
+                    .for_each(|e| account.on_entrust(e, None));
+                assert!(account.entrust.is_empty());
+                assert!(account.cash_frozen.abs() < 0.01);
+                assert_eq!(account.get_position_volume("sz000001"), (1000, 0));
+            })
+    }
+}
@@ -50,6 +50,7 @@ impl StopLostProfit {
             time: Default::default(),
             price,
             volume: position.volume_available,
+            entrust_id: None,
             desc,
         }
     }
@@ -84,6 +85,10 @@ impl Risk for StopLostProfit {
     async fn on_risk(&mut self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
         let positions = { ctx.account.read().unwrap().position.clone() };
         for (_, position) in positions.iter() {
+            // 无可卖持仓(T+1或已冻结)
+            if position.volume_available == 0 {
+                continue;
+            }
             let mut signal = None;
             if let Some(profit) = self.profit {
                 if position.profit >= profit {
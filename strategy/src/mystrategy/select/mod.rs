@@ -1,18 +1,102 @@
-// use crate::{Error, Result, select::Strategy};
+use rwqdata::MarketType;
+use serde::{Deserialize, Serialize};
 
-// use self::{exam_strategy::ExamStrategy, right_side::RightSide};
+use crate::{select::ParamDesc, select::Strategy, Error, Result};
 
-// mod exam_strategy;
-// mod right_side;
+use self::{exam_strategy::ExamStrategy, right_side::RightSide};
 
-// pub fn get_strategy(name: &str) -> Result<Box<dyn Strategy>> {
-//     match name {
-//         "ExamStrategy" => Ok(Box::new(ExamStrategy::default())),
-//         "RightSide" => Ok(Box::new(RightSide::default())),
-//         _ => Err(Error::Custom(format!("strategy {} not found", name))),
-//     }
-// }
+mod exam_strategy;
+mod right_side;
 
-// pub fn strategies() -> Vec<String> {
-//     vec![String::from("ExamStrategy"), String::from("RightSide")]
-// }
+/// 内置选股策略构造函数
+type NewStrategy = fn() -> Box<dyn Strategy>;
+
+/// 内置选股策略，名称及构造函数
+const STRATEGIES: &[(&str, NewStrategy)] = &[
+    ("ExamStrategy", || Box::new(ExamStrategy::default())),
+    ("RightSide", || Box::new(RightSide::default())),
+];
+
+/// 选股策略说明
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrategyInfo {
+    pub name: String,
+    pub help: String,
+    pub accept: Vec<MarketType>,
+    pub params: Vec<ParamDesc>,
+}
+
+impl StrategyInfo {
+    pub fn new(strategy: &dyn Strategy) -> Self {
+        Self {
+            name: strategy.name(),
+            help: strategy.help(),
+            accept: strategy.accept(),
+            params: strategy.params(),
+        }
+    }
+}
+
+pub fn get_strategy(name: &str) -> Result<Box<dyn Strategy>> {
+    STRATEGIES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, new)| new())
+        .ok_or(Error::Custom(format!("strategy {} not found", name)))
+}
+
+pub fn strategies() -> Vec<String> {
+    STRATEGIES.iter().map(|(n, _)| String::from(*n)).collect()
+}
+
+/// 内置选股策略说明
+pub fn strategy_info(name: &str) -> Result<StrategyInfo> {
+    let strategy = get_strategy(name)?;
+    Ok(StrategyInfo::new(strategy.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::select::check_params;
+
+    use super::{get_strategy, strategies, strategy_info};
+
+    #[test]
+    fn test_registry() {
+        let names = strategies();
+        assert!(names.contains(&String::from("RightSide")));
+        for name in names.iter() {
+            let strategy = get_strategy(name).unwrap();
+            assert_eq!(&strategy.name(), name);
+        }
+        assert!(get_strategy("NotExists").is_err());
+
+        let info = strategy_info("RightSide").unwrap();
+        assert_eq!(info.params.len(), 4);
+        // 参数类型以小写文本输出
+        let js = serde_json::to_value(&info).unwrap();
+        assert_eq!(js["name"], "RightSide");
+        let params: Vec<_> = js["params"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["name"].as_str().unwrap(), p["typ"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("min_rise_days", "int"),
+                ("max_shadow_pct", "float"),
+                ("min_volume_chg_pct", "float"),
+                ("min_amount_chg_pct", "float"),
+            ]
+        );
+
+        let params = [("min_rise_days".to_string(), "5".to_string())].into();
+        assert!(check_params(&info.params, &params).is_ok());
+        let params = [("min_rise_days".to_string(), "x".to_string())].into();
+        assert!(check_params(&info.params, &params).is_err());
+        let params = [("unknown".to_string(), "1".to_string())].into();
+        assert!(check_params(&info.params, &params).is_err());
+    }
+}
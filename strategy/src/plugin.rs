@@ -0,0 +1,762 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CStr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use libloading::Library;
+use rwqdata::{store::Loader, MarketType, RtQuot};
+use rwqtradecmm::{Entrust, QuotEvent, Signal};
+use tokio::sync::{broadcast, RwLock};
+
+use crate::{
+    broker::Broker,
+    context::Context,
+    risk::Risk,
+    select::{self, CommonParam, ParamDesc, StrategyResult},
+    trade, Error, Params, Result, Symbol, SYMBOL_BROKER, SYMBOL_RISK, SYMBOL_SELECT, SYMBOL_TRADE,
+};
+
+/// 插件ABI导出的函数名称
+pub const SYMBOL_ABI: &str = "rwq_plugin_abi";
+/// 插件ABI版本，插件与宿主的ABI版本不一致时拒绝加载。
+/// trait对象跨动态库传递没有稳定的ABI，插件须使用相同的编译器及rwqstrategy版本编译，
+/// 版本包含rwqstrategy版本及编译器版本(`rustc --version`)
+pub const PLUGIN_ABI: &str = concat!(
+    "rwqstrategy-",
+    env!("CARGO_PKG_VERSION"),
+    "-",
+    env!("RUSTC_VERSION"),
+    "-abi1\0"
+);
+
+/// 插件ABI导出函数
+pub type AbiSymbol = unsafe extern "C" fn() -> *const c_char;
+
+/// 导出插件，同时导出ABI版本函数
+///
+/// ```ignore
+/// rwqstrategy::export_plugin!(
+///     select => MySelect::new(),
+///     broker => MyBroker::new(),
+/// );
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($($kind:ident => $new:expr),* $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn rwq_plugin_abi() -> *const ::std::ffi::c_char {
+            $crate::plugin::PLUGIN_ABI.as_ptr() as *const ::std::ffi::c_char
+        }
+        $($crate::export_plugin!(@export $kind, $new);)*
+    };
+    (@export select, $new:expr) => {
+        #[no_mangle]
+        pub extern "C" fn new_select() -> *mut ::std::ffi::c_void {
+            let data: Box<Box<dyn $crate::select::Strategy>> = Box::new(Box::new($new));
+            Box::into_raw(data) as *mut ::std::ffi::c_void
+        }
+    };
+    (@export trade, $new:expr) => {
+        #[no_mangle]
+        pub extern "C" fn new_trade() -> *mut ::std::ffi::c_void {
+            let data: Box<Box<dyn $crate::trade::Strategy>> = Box::new(Box::new($new));
+            Box::into_raw(data) as *mut ::std::ffi::c_void
+        }
+    };
+    (@export risk, $new:expr) => {
+        #[no_mangle]
+        pub extern "C" fn new_risk() -> *mut ::std::ffi::c_void {
+            let data: Box<Box<dyn $crate::risk::Risk>> = Box::new(Box::new($new));
+            Box::into_raw(data) as *mut ::std::ffi::c_void
+        }
+    };
+    (@export broker, $new:expr) => {
+        #[no_mangle]
+        pub extern "C" fn new_broker() -> *mut ::std::ffi::c_void {
+            let data: Box<Box<dyn $crate::broker::Broker>> = Box::new(Box::new($new));
+            Box::into_raw(data) as *mut ::std::ffi::c_void
+        }
+    };
+}
+
+/// 插件提供的对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginKind {
+    Select,
+    Trade,
+    Risk,
+    Broker,
+}
+
+impl PluginKind {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PluginKind::Select => SYMBOL_SELECT,
+            PluginKind::Trade => SYMBOL_TRADE,
+            PluginKind::Risk => SYMBOL_RISK,
+            PluginKind::Broker => SYMBOL_BROKER,
+        }
+    }
+}
+
+const PLUGIN_KINDS: [PluginKind; 4] = [
+    PluginKind::Select,
+    PluginKind::Trade,
+    PluginKind::Risk,
+    PluginKind::Broker,
+];
+
+/// 动态库创建的对象，持有动态库的引用，保证对象释放前动态库不被卸载
+pub struct Plugged<T: ?Sized> {
+    // 字段按声明顺序释放，对象须先于动态库释放
+    inner: Box<T>,
+    _lib: Arc<Library>,
+}
+
+impl<T: ?Sized> std::ops::Deref for Plugged<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> std::ops::DerefMut for Plugged<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+/// 已加载的插件
+pub struct Plugin {
+    name: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    kinds: Vec<PluginKind>,
+    lib: Arc<Library>,
+}
+
+impl Plugin {
+    /// 加载插件，校验ABI版本
+    ///
+    /// 动态库先复制到临时目录再加载，同一路径重复加载时不会拿到系统缓存的旧版本，
+    /// 以支持热加载。
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let modified = std::fs::metadata(path)
+            .map_err(|e| Error::Custom(format!("plugin {:?} metadata error: {}", path, e)))?
+            .modified()
+            .ok();
+        let name = plugin_name(path);
+
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let tmp = std::env::temp_dir().join(format!(
+            "rwq-plugin-{}-{}-{}.{}",
+            name,
+            std::process::id(),
+            nanos,
+            std::env::consts::DLL_EXTENSION
+        ));
+        std::fs::copy(path, &tmp)
+            .map_err(|e| Error::Custom(format!("copy plugin {:?} error: {}", path, e)))?;
+        // 安全性: 加载动态库会执行其初始化代码，插件须是可信的
+        let lib = unsafe { Library::new(&tmp) };
+        // 已加载的动态库删除文件不受影响，删除失败(如windows)不影响使用
+        let _ = std::fs::remove_file(&tmp);
+        let lib = lib.map_err(|e| Error::Custom(format!("load plugin {:?} error: {}", path, e)))?;
+
+        check_abi(&lib).map_err(|e| Error::Custom(format!("plugin {:?}: {}", path, e)))?;
+
+        let kinds: Vec<_> = PLUGIN_KINDS
+            .into_iter()
+            .filter(|k| unsafe { lib.get::<Symbol>(k.symbol().as_bytes()).is_ok() })
+            .collect();
+        if kinds.is_empty() {
+            return Err(Error::Custom(format!(
+                "plugin {:?} exports none of: {}, {}, {}, {}",
+                path, SYMBOL_SELECT, SYMBOL_TRADE, SYMBOL_RISK, SYMBOL_BROKER
+            )));
+        }
+
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            modified,
+            kinds,
+            lib: Arc::new(lib),
+        })
+    }
+
+    /// 插件名称，为去掉`lib`前缀的文件名
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 插件提供的对象类型
+    pub fn kinds(&self) -> &[PluginKind] {
+        &self.kinds
+    }
+
+    pub fn has(&self, kind: PluginKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    /// 调用导出函数，取回`Box<Box<T>>`
+    fn create<T: ?Sized>(&self, kind: PluginKind) -> Result<Plugged<T>> {
+        if !self.has(kind) {
+            return Err(Error::Custom(format!(
+                "plugin {} does not export {}",
+                self.name,
+                kind.symbol()
+            )));
+        }
+        // 安全性: ABI版本已校验，导出函数由`export_plugin!`生成，返回`Box<Box<T>>`
+        let inner = unsafe {
+            let func = self
+                .lib
+                .get::<Symbol>(kind.symbol().as_bytes())
+                .map_err(|e| Error::Custom(format!("get symbol error: {}", e)))?;
+            let ptr = func();
+            if ptr.is_null() {
+                return Err(Error::Custom(format!(
+                    "plugin {} {} return null",
+                    self.name,
+                    kind.symbol()
+                )));
+            }
+            *Box::from_raw(ptr as *mut Box<T>)
+        };
+        Ok(Plugged {
+            inner,
+            _lib: self.lib.clone(),
+        })
+    }
+
+    /// 创建选股策略
+    pub fn new_select(&self) -> Result<Box<dyn select::Strategy>> {
+        Ok(Box::new(
+            self.create::<dyn select::Strategy>(PluginKind::Select)?,
+        ))
+    }
+
+    /// 创建交易策略
+    pub fn new_trade(&self) -> Result<Box<dyn trade::Strategy>> {
+        Ok(Box::new(
+            self.create::<dyn trade::Strategy>(PluginKind::Trade)?,
+        ))
+    }
+
+    /// 创建风控策略
+    pub fn new_risk(&self) -> Result<Box<dyn Risk>> {
+        Ok(Box::new(self.create::<dyn Risk>(PluginKind::Risk)?))
+    }
+
+    /// 创建券商
+    pub fn new_broker(&self) -> Result<Box<dyn Broker>> {
+        Ok(Box::new(self.create::<dyn Broker>(PluginKind::Broker)?))
+    }
+}
+
+fn check_abi(lib: &Library) -> Result<()> {
+    let expect = &PLUGIN_ABI[..PLUGIN_ABI.len() - 1];
+    // 安全性: 导出函数返回静态的以'\0'结尾的字符串
+    let abi = unsafe {
+        let func = lib.get::<AbiSymbol>(SYMBOL_ABI.as_bytes()).map_err(|_| {
+            Error::Custom(format!(
+                "missing {}, use rwqstrategy::export_plugin! to export",
+                SYMBOL_ABI
+            ))
+        })?;
+        let ptr = func();
+        if ptr.is_null() {
+            return Err(Error::Custom(format!("{} return null", SYMBOL_ABI)));
+        }
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    };
+    if abi != expect {
+        return Err(Error::Custom(format!(
+            "abi mismatch, plugin: {}, expect: {}",
+            abi, expect
+        )));
+    }
+    Ok(())
+}
+
+fn plugin_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match stem.strip_prefix("lib") {
+        Some(s) if !s.is_empty() => s.to_owned(),
+        _ => stem,
+    }
+}
+
+fn is_plugin_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map(|e| e == std::env::consts::DLL_EXTENSION)
+            .unwrap_or(false)
+}
+
+/// 插件目录扫描结果
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReloadResult {
+    /// 新加载的插件
+    pub added: Vec<String>,
+    /// 文件更新后重新加载的插件
+    pub updated: Vec<String>,
+    /// 文件删除后移除的插件
+    pub removed: Vec<String>,
+    /// 加载失败的插件，及失败原因
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl ReloadResult {
+    pub fn is_changed(&self) -> bool {
+        !(self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty())
+    }
+}
+
+/// 插件管理
+///
+/// 管理目录下的动态库插件，`reload`按文件修改时间热加载。
+/// 重新加载只替换管理器中的插件，已创建的对象继续使用旧版本动态库，直到对象释放。
+pub struct PluginManager {
+    dir: PathBuf,
+    plugins: HashMap<String, Arc<Plugin>>,
+}
+
+impl PluginManager {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            plugins: HashMap::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Plugin>> {
+        self.plugins.get(name).cloned()
+    }
+
+    /// 已加载的插件，按名称排序
+    pub fn plugins(&self) -> Vec<Arc<Plugin>> {
+        let mut plugins: Vec<_> = self.plugins.values().cloned().collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+
+    /// 提供某类对象的插件
+    pub fn find(&self, kind: PluginKind) -> Vec<Arc<Plugin>> {
+        self.plugins().into_iter().filter(|p| p.has(kind)).collect()
+    }
+
+    /// 扫描插件目录，加载新增或修改过的插件，移除已删除的插件
+    ///
+    /// 单个插件加载失败不影响其他插件，失败的插件保留旧版本
+    pub fn reload(&mut self) -> Result<ReloadResult> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| Error::Custom(format!("read plugin dir {:?} error: {}", self.dir, e)))?;
+        let mut result = ReloadResult::default();
+        let mut seen = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !is_plugin_file(&path) {
+                continue;
+            }
+            let name = plugin_name(&path);
+            seen.push(name.clone());
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+            let origin = self.plugins.get(&name);
+            if let Some(origin) = origin {
+                if origin.path == path && origin.modified == modified {
+                    continue;
+                }
+            }
+            let is_update = origin.is_some();
+            match Plugin::load(&path) {
+                Ok(plugin) => {
+                    log::info!("load plugin {}({:?}): {:?}", name, path, plugin.kinds);
+                    self.plugins.insert(name.clone(), Arc::new(plugin));
+                    if is_update {
+                        result.updated.push(name);
+                    } else {
+                        result.added.push(name);
+                    }
+                }
+                Err(e) => {
+                    log::error!("load plugin {:?} error: {}", path, e);
+                    result.failed.push((path, e.to_string()));
+                }
+            }
+        }
+        let removed: Vec<_> = self
+            .plugins
+            .keys()
+            .filter(|k| !seen.contains(k))
+            .cloned()
+            .collect();
+        for name in removed {
+            log::info!("remove plugin {}", name);
+            self.plugins.remove(&name);
+            result.removed.push(name);
+        }
+        result.added.sort();
+        result.updated.sort();
+        result.removed.sort();
+        Ok(result)
+    }
+
+    /// 定时检查插件目录热加载，直到收到`shutdown_rx`信号
+    pub async fn watch(
+        manager: Arc<RwLock<PluginManager>>,
+        interval: Duration,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let rs = manager.write().await.reload();
+                    match rs {
+                        Ok(rs) if rs.is_changed() => log::info!("plugin reload: {:?}", rs),
+                        Ok(_) => {}
+                        Err(e) => log::error!("plugin reload error: {}", e),
+                    }
+                }
+                _ = shutdown_rx.recv() => {
+                    log::info!("plugin watch receive shutdown signal");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl select::Strategy for Plugged<dyn select::Strategy> {
+    fn help(&self) -> String {
+        self.inner.help()
+    }
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+    async fn prepare(
+        &mut self,
+        loader: Arc<Box<dyn Loader>>,
+        cmm_params: Option<CommonParam>,
+        params: Option<Params>,
+    ) -> Result<()> {
+        self.inner.prepare(loader, cmm_params, params).await
+    }
+    fn params(&self) -> Vec<ParamDesc> {
+        self.inner.params()
+    }
+    fn accept(&self) -> Vec<MarketType> {
+        self.inner.accept()
+    }
+    async fn test(
+        &self,
+        loader: Arc<Box<dyn Loader>>,
+        typ: MarketType,
+        code: String,
+        name: String,
+    ) -> Result<Option<StrategyResult>> {
+        self.inner.test(loader, typ, code, name).await
+    }
+}
+
+#[async_trait]
+impl trade::Strategy for Plugged<dyn trade::Strategy> {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+    async fn init(&mut self, ctx: Arc<Context>, params: Option<Params>) -> Result<()> {
+        self.inner.init(ctx, params).await
+    }
+    async fn destroy(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.destroy(ctx).await
+    }
+    async fn on_start(&self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_start(ctx).await
+    }
+    async fn on_open(&self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_open(ctx, event).await
+    }
+    async fn on_close(&self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_close(ctx, event).await
+    }
+    async fn on_end(&self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_end(ctx).await
+    }
+    async fn on_trade(&self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+        self.inner.on_trade(ctx, quots).await
+    }
+}
+
+#[async_trait]
+impl Risk for Plugged<dyn Risk> {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+    async fn init(&mut self, ctx: Arc<Context>, params: Option<Params>) -> Result<()> {
+        self.inner.init(ctx, params).await
+    }
+    async fn destroy(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.destroy(ctx).await
+    }
+    async fn on_start(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_start(ctx).await
+    }
+    async fn on_open(&mut self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_open(ctx, event).await
+    }
+    async fn on_close(&mut self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_close(ctx, event).await
+    }
+    async fn on_end(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_end(ctx).await
+    }
+    async fn on_risk(&mut self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+        self.inner.on_risk(ctx, quots).await
+    }
+    async fn on_signal(&mut self, ctx: Arc<Context>, signal: Signal) -> Result<Option<Signal>> {
+        self.inner.on_signal(ctx, signal).await
+    }
+}
+
+#[async_trait]
+impl Broker for Plugged<dyn Broker> {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+    async fn init(&mut self, ctx: Arc<Context>, params: Option<Params>) -> Result<()> {
+        self.inner.init(ctx, params).await
+    }
+    async fn destroy(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.destroy(ctx).await
+    }
+    async fn on_start(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_start(ctx).await
+    }
+    async fn on_open(&mut self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_open(ctx, event).await
+    }
+    async fn on_close(&mut self, ctx: Arc<Context>, event: QuotEvent) -> Result<()> {
+        self.inner.on_close(ctx, event).await
+    }
+    async fn on_end(&mut self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_end(ctx).await
+    }
+    async fn on_entrust(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
+        self.inner.on_entrust(ctx, entrust).await
+    }
+    async fn on_cancel(&self, ctx: Arc<Context>, entrust: Entrust) -> Result<()> {
+        self.inner.on_cancel(ctx, entrust).await
+    }
+    async fn on_poll(&self, ctx: Arc<Context>) -> Result<()> {
+        self.inner.on_poll(ctx).await
+    }
+    async fn on_quot(&self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+        self.inner.on_quot(ctx, quots).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::CStr,
+        path::{Path, PathBuf},
+        process::Command,
+        sync::{Arc, OnceLock},
+        time::{Duration, SystemTime},
+    };
+
+    use super::{
+        plugin_name, AbiSymbol, Plugin, PluginKind, PluginManager, PLUGIN_ABI, SYMBOL_ABI,
+    };
+
+    /// 示例插件`test_strategy`的动态库，编译到测试程序所在的目标目录，
+    /// 插件须与当前的rwqstrategy一起编译，每次测试都先编译。
+    /// 测试中调用cargo编译依赖本地工具链，且可能等待目标目录的锁，用到的测试默认忽略，
+    /// 运行: `cargo test -p rwqstrategy plugin -- --ignored`
+    fn example_plugin() -> &'static Path {
+        static PATH: OnceLock<PathBuf> = OnceLock::new();
+        PATH.get_or_init(|| {
+            // 测试程序位于target/<profile>/deps
+            let exe = std::env::current_exe().unwrap();
+            let profile = exe.parent().unwrap().parent().unwrap();
+            let path = profile.join(format!(
+                "{}test_strategy.{}",
+                std::env::consts::DLL_PREFIX,
+                std::env::consts::DLL_EXTENSION
+            ));
+            let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("../Cargo.toml");
+            let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+            let mut cmd = Command::new(cargo);
+            cmd.args(["build", "-p", "test_strategy", "--manifest-path"])
+                .arg(manifest)
+                .arg("--target-dir")
+                .arg(profile.parent().unwrap());
+            if profile.ends_with("release") {
+                cmd.arg("--release");
+            }
+            assert!(cmd.status().unwrap().success());
+            assert!(path.exists());
+            path
+        })
+    }
+
+    fn plugin_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rwq-plugin-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_plugin_name() {
+        assert_eq!(plugin_name(Path::new("/a/libmy_plugin.so")), "my_plugin");
+        assert_eq!(plugin_name(Path::new("my_plugin.dll")), "my_plugin");
+        assert_eq!(plugin_name(Path::new("lib.so")), "lib");
+    }
+
+    #[test]
+    fn test_plugin_abi() {
+        assert!(PLUGIN_ABI.starts_with(concat!("rwqstrategy-", env!("CARGO_PKG_VERSION"))));
+        assert!(PLUGIN_ABI.contains("rustc "));
+        assert!(PLUGIN_ABI.ends_with("-abi1\0"));
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("rwq-plugin-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut manager = PluginManager::new(&dir);
+        let rs = manager.reload().unwrap();
+        assert!(!rs.is_changed());
+
+        // 不是动态库，加载失败
+        let path = dir.join(format!("libbad.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&path, b"not a library").unwrap();
+        assert!(Plugin::load(&path).is_err());
+        let rs = manager.reload().unwrap();
+        assert!(!rs.is_changed());
+        assert_eq!(rs.failed.len(), 1);
+        assert!(manager.plugins().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(manager.reload().is_err());
+    }
+
+    #[test]
+    #[ignore = "编译示例插件"]
+    fn test_example_plugin() {
+        let lib = example_plugin();
+        let dir = plugin_dir("example");
+        let path = dir.join(lib.file_name().unwrap());
+        std::fs::copy(lib, &path).unwrap();
+
+        let mut manager = PluginManager::new(&dir);
+        let rs = manager.reload().unwrap();
+        assert_eq!(rs.added, vec!["test_strategy".to_owned()]);
+        let plugin = manager.get("test_strategy").unwrap();
+        assert_eq!(plugin.kinds(), &[PluginKind::Select]);
+        let abi = unsafe {
+            let func = plugin.lib.get::<AbiSymbol>(SYMBOL_ABI.as_bytes()).unwrap();
+            CStr::from_ptr(func()).to_str().unwrap().to_owned()
+        };
+        assert_eq!(abi, PLUGIN_ABI.trim_end_matches('\0'));
+        assert!(plugin.new_trade().is_err());
+        let select = plugin.new_select().unwrap();
+        let lib = Arc::downgrade(&plugin.lib);
+        drop(plugin);
+        assert_eq!(select.name(), "TestStrategy");
+
+        // 插件更新后重新加载，已创建的对象继续使用旧版本动态库
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        let rs = manager.reload().unwrap();
+        assert_eq!(rs.updated, vec!["test_strategy".to_owned()]);
+        assert!(!Arc::ptr_eq(
+            &manager.get("test_strategy").unwrap().lib,
+            &lib.upgrade().unwrap()
+        ));
+        assert_eq!(select.name(), "TestStrategy");
+
+        // 插件删除后，旧版本动态库在对象释放后才卸载
+        std::fs::remove_file(&path).unwrap();
+        let rs = manager.reload().unwrap();
+        assert_eq!(rs.removed, vec!["test_strategy".to_owned()]);
+        assert!(manager.get("test_strategy").is_none());
+        assert_eq!(select.help(), "实例策略");
+        assert!(lib.upgrade().is_some());
+        drop(select);
+        assert!(lib.upgrade().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "编译示例插件"]
+    fn test_abi_mismatch() {
+        // 修改动态库中的ABI版本字符串，模拟不同版本编译的插件
+        let mut data = std::fs::read(example_plugin()).unwrap();
+        let abi = PLUGIN_ABI.as_bytes();
+        let mut other = abi.to_vec();
+        other[abi.len() - 2] = b'0';
+        let mut count = 0;
+        let mut i = 0;
+        while i + abi.len() <= data.len() {
+            if &data[i..i + abi.len()] == abi {
+                data[i..i + abi.len()].copy_from_slice(&other);
+                count += 1;
+                i += abi.len();
+            } else {
+                i += 1;
+            }
+        }
+        assert!(count > 0);
+
+        let dir = plugin_dir("abi");
+        let path = dir.join(format!("libother.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&path, data).unwrap();
+        let err = Plugin::load(&path).err().unwrap().to_string();
+        assert!(err.contains("abi mismatch"), "{}", err);
+        let mut manager = PluginManager::new(&dir);
+        let rs = manager.reload().unwrap();
+        assert_eq!(rs.failed.len(), 1);
+        assert!(manager.plugins().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
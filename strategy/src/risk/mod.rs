@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use rwqdata::RtQuot;
-use rwqtradecmm::QuotEvent;
+use rwqtradecmm::{QuotEvent, Signal};
 
 use crate::{context::Context, Params, Result};
 
@@ -37,6 +37,10 @@ pub trait Risk: Sync + Send {
     async fn on_risk(&mut self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
         Ok(())
     }
+    /// 交易信号风控拦截，返回`None`则信号被拦截，不会产生委托
+    async fn on_signal(&mut self, ctx: Arc<Context>, signal: Signal) -> Result<Option<Signal>> {
+        Ok(Some(signal))
+    }
 }
 
 // emit(buy, adf, 100)
@@ -1,368 +1,493 @@
-// use std::{collections::HashMap, sync::Arc};
-
-// use bson::doc;
-// use futures::future::{join, join_all};
-// use rwqdata::store::Loader;
-
-// use crate::{Error, select::ProgressFunc, Result, select::Strategy, select::StrategyResult, StrategyType};
-// use tokio::{
-//     sync::{broadcast, mpsc},
-//     task::JoinHandle,
-// };
-
-// pub fn strategy_to_data_type(typ: StrategyType) -> rwqdata::store::DataType {
-//     match typ {
-//         StrategyType::Bond => rwqdata::store::DataType::Bond,
-//         StrategyType::Fund => rwqdata::store::DataType::Fund,
-//         StrategyType::Stock => rwqdata::store::DataType::Stock,
-//         StrategyType::Index => rwqdata::store::DataType::Index,
-//         StrategyType::Concept => rwqdata::store::DataType::Concept,
-//         StrategyType::Industry => rwqdata::store::DataType::Industry,
-//     }
-// }
-
-// pub async fn run(
-//     strategy: Arc<Box<dyn Strategy>>,
-//     loader: Arc<Box<dyn Loader>>,
-//     concurrent: usize,
-//     mut shutdown_rx: broadcast::Receiver<()>,
-//     the_codes: Option<HashMap<StrategyType, Vec<(String, String)>>>,
-//     progress_func: Option<ProgressFunc>,
-// ) -> Result<Option<HashMap<StrategyType, Vec<StrategyResult>>>> {
-//     let (shutdown_tx, _) = broadcast::channel(1);
-//     let types = strategy.accept();
-//     let mut test_codes = HashMap::new();
-//     let mut total = 0;
-//     if let Some(the_codes) = the_codes {
-//         for (k, v) in the_codes.into_iter() {
-//             total += v.len();
-//             let codes = split_code(v, concurrent);
-//             test_codes.insert(k, codes);
-//         }
-//     } else {
-//         for typ in types.into_iter() {
-//             let codes = loader
-//                 .load_info(strategy_to_data_type(typ), doc! {}, doc! {}, None)
-//                 .await
-//                 .map_err(|e| Error::Custom(format!("query info error: {:?}", e)))?;
-
-//             total += codes.len();
-
-//             let codes = split_code(codes, concurrent);
-
-//             if !codes.is_empty() {
-//                 test_codes.insert(typ, codes);
-//             }
-//         }
-//     }
-
-//     let (progress_tx, progress_rx, progress_func) = if let Some(progress_func) = progress_func {
-//         let (progress_tx, progress_rx) = mpsc::unbounded_channel();
-
-//         let progress_func = Arc::new(progress_func);
-
-//         (Some(progress_tx), Some(progress_rx), Some(progress_func))
-//     } else {
-//         (None, None, None)
-//     };
-
-//     let mut handlers = HashMap::new();
-//     for (typ, codes_vec) in test_codes.into_iter() {
-//         let mut handler = Vec::new();
-//         for codes in codes_vec.into_iter() {
-//             let s = strategy.clone();
-//             let l = loader.clone();
-//             let rx = shutdown_tx.subscribe();
-//             log::info!("spawn task: {:?}", &typ);
-//             let tx = progress_tx.clone();
-//             let h = tokio::spawn(run_task(typ, s, l, codes, rx, tx));
-//             handler.push(h);
-//         }
-//         handlers.insert(typ, handler);
-//     }
-//     let mut g_handlers = Vec::new();
-//     for (typ, handlers) in handlers.into_iter() {
-//         let h = tokio::spawn(join_group(typ, handlers));
-//         g_handlers.push(h);
-//     }
-//     let mut ret_map = HashMap::new();
-
-//     let p_handler = tokio::spawn(progress_task(
-//         total,
-//         progress_func,
-//         shutdown_tx.subscribe(),
-//         progress_rx,
-//     ));
-
-//     tokio::select! {
-//         (rest, _) = join(join_all(g_handlers), p_handler) => {
-//             log::info!("join_all done");
-//             for res in rest.into_iter() {
-//                 let res = res.map_err(|e| Error::Custom(format!("join error: {}", e.to_string())))?;
-//                 if let Ok(res) = res {
-//                     if let Some((typ, data)) = res {
-//                         ret_map.insert(typ, data);
-//                     }
-//                 }
-//             }
-
-//         },
-//         _ = shutdown_rx.recv() => {
-//             log::info!("receive shutdown signal");
-//             shutdown_tx.send(())
-//             .map_err(|e|Error::Custom(format!("send shutdown signal error x: {}", e.to_string())))?;
-//         }
-//     }
-
-//     let ret_map = if ret_map.is_empty() {
-//         Ok(None)
-//     } else {
-//         Ok(Some(ret_map))
-//     };
-//     log::info!("all task done");
-//     ret_map
-// }
-
-// pub async fn fit(
-//     code: String,
-//     name: String,
-//     typ: StrategyType,
-//     strategy: Arc<Box<dyn Strategy>>,
-//     loader: Arc<Box<dyn Loader>>,
-//     mut shutdown_rx: broadcast::Receiver<()>,
-// ) -> Result<Option<StrategyResult>> {
-//     let types = strategy.accept();
-//     if !types.contains(&typ) {
-//         return Err(Error::Custom(format!(
-//             "strategy not suitable for type: {:?}, only valid for {:?}",
-//             &typ, types
-//         )));
-//     }
-//     tokio::select! {
-//         data = strategy.test(loader.clone(), typ, code, name) => {
-//             return data;
-//         },
-//         _ = shutdown_rx.recv() => {
-//             log::info!("receive shutdown signal");
-//             return Ok(None);
-//         }
-//     }
-// }
-
-// async fn join_group(
-//     typ: StrategyType,
-//     handlers: Vec<JoinHandle<Result<Option<Vec<StrategyResult>>>>>,
-// ) -> Result<Option<(StrategyType, Vec<StrategyResult>)>> {
-//     let rest = join_all(handlers).await;
-//     let mut rs_vec = Vec::new();
-//     for res in rest.into_iter() {
-//         let res = res.map_err(|e| Error::Custom(format!("join error: {}", e.to_string())))?;
-//         if res.is_err() {
-//             log::error!("task run error: {:?}", res);
-//             continue;
-//         }
-//         if let Some(res) = res.unwrap() {
-//             rs_vec.extend(res.into_iter());
-//         }
-//     }
-//     let rs = if rs_vec.is_empty() {
-//         Ok(None)
-//     } else {
-//         Ok(Some((typ.clone(), rs_vec)))
-//     };
-//     log::info!("type {:?} all task done", typ);
-//     rs
-// }
-
-// async fn progress_task(
-//     total: usize,
-//     progress_func: Option<Arc<ProgressFunc>>,
-//     mut shutdown_rx: broadcast::Receiver<()>,
-//     progress_rx: Option<mpsc::UnboundedReceiver<(String, String)>>,
-// ) {
-//     if progress_func.is_none() || progress_rx.is_none() {
-//         return;
-//     }
-//     let progress_func = progress_func.unwrap();
-//     let mut progress_rx = progress_rx.unwrap();
-
-//     log::info!("progress task with {} codes", total);
-//     let mut current: usize = 0;
-//     for _ in 0..total {
-//         tokio::select! {
-//             rs = progress_rx.recv() => {
-//                 current+=1;
-//                 if let Some(rs) = rs {
-//                     let p = (((current * 100) as f32 / total as f32) * 100.0).round() / 100.0;
-//                     let (code, name) = rs;
-//                     progress_func(code.as_str(), name.as_str(), total, current, p);
-//                 } else {
-//                     log::error!("progress_rx receive None");
-//                     break;
-//                 }
-
-//             }
-//             _ = shutdown_rx.recv() => {
-//                 log::info!("run task receive shutdown signal");
-//                 break;
-//             }
-//         }
-//     }
-// }
-
-// async fn run_task(
-//     typ: StrategyType,
-//     strategy: Arc<Box<dyn Strategy>>,
-//     loader: Arc<Box<dyn Loader>>,
-//     codes: Vec<(String, String)>,
-//     mut shutdown_rx: broadcast::Receiver<()>,
-//     progress_tx: Option<mpsc::UnboundedSender<(String, String)>>,
-// ) -> Result<Option<Vec<StrategyResult>>> {
-//     log::info!("run task with {} codes", codes.len());
-//     let mut rs_vec = Vec::new();
-//     for (code, name) in codes {
-//         if let Some(tx) = &progress_tx {
-//             tx.send((code.clone(), name.clone())).map_err(|e| {
-//                 Error::Custom(format!("send progress signal error x: {}", e.to_string()))
-//             })?;
-//         }
-//         tokio::select! {
-//             res = strategy
-//             .test(loader.clone(), typ, code.clone(), name.clone()) => {
-//                 if res.is_err() {
-//                     log::error!("run test with error: {:?}", res);
-//                     continue;
-//                 }
-//                 let res = res.unwrap();
-//                 if let Some(data) = res {
-//                     log::info!("got data: {}({})", name, code);
-//                     rs_vec.push(data);
-//                 }
-//             },
-//             _ = shutdown_rx.recv() => {
-//                 log::info!("run task receive shutdown signal");
-//                 return Ok(None);
-//             }
-//         }
-//     }
-
-//     if rs_vec.is_empty() {
-//         Ok(None)
-//     } else {
-//         Ok(Some(rs_vec))
-//     }
-// }
-
-// fn split_code(codes: Vec<(String, String)>, count: usize) -> Vec<Vec<(String, String)>> {
-//     let len = codes.len();
-//     let task_count = len / count;
-
-//     let mut result = Vec::new();
-//     let mut task_vec = Vec::new();
-//     for code in codes.into_iter() {
-//         task_vec.push(code);
-//         if task_vec.len() < task_count {
-//             continue;
-//         }
-//         result.push(task_vec);
-//         task_vec = Vec::new();
-//     }
-//     if task_vec.len() > 0 {
-//         result.push(task_vec);
-//     }
-//     result
-// }
-
-// #[cfg(test)]
-// mod tests {
-//     use std::sync::Arc;
-
-//     use rwqdata::{
-//         store::{get_loader, Loader},
-//         SyncDest,
-//     };
-//     use tokio::sync::broadcast;
-
-//     use crate::{run, select::Strategy, select::StrategyResult, Result, StrategyType};
-
-//     use async_trait::async_trait;
-
-//     struct TestStrategy {}
-//     #[async_trait]
-//     impl Strategy for TestStrategy {
-//         async fn test(
-//             &self,
-//             _loader: Arc<Box<dyn Loader>>,
-//             _typ: StrategyType,
-//             code: String,
-//             name: String,
-//         ) -> Result<Option<StrategyResult>> {
-//             let codes = vec![
-//                 "sz002805".to_string(),
-//                 "sz300827".to_string(),
-//                 "sz000762".to_string(),
-//             ];
-//             if codes.contains(&code) {
-//                 let rs = StrategyResult {
-//                     code,
-//                     name,
-//                     mark: None,
-//                     stat: None,
-//                 };
-//                 return Ok(Some(rs));
-//             }
-//             Ok(None)
-//         }
-//     }
-//     #[test]
-//     fn test_runner() {
-//         fern::Dispatch::new()
-//             // .filter(|f| f.target().starts_with("rwinq"))
-//             .format(|out, message, record| {
-//                 out.finish(format_args!(
-//                     "{}[{}][{}] {}",
-//                     chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
-//                     record.target(),
-//                     record.level(),
-//                     message
-//                 ))
-//             })
-//             .level(log::LevelFilter::Error)
-//             .chain(std::io::stdout())
-//             .apply()
-//             .unwrap();
-
-//         tokio::runtime::Builder::new_multi_thread()
-//             .enable_all()
-//             .build()
-//             .unwrap()
-//             .block_on(async {
-//                 log::info!("set up logger");
-//                 let (tx, _) = broadcast::channel(1);
-//                 let dest = SyncDest::MongoDB("mongodb://localhost:27017".to_owned());
-
-//                 let (_, loader) = get_loader(&dest, true).await.unwrap();
-//                 let mut strategy: Box<dyn Strategy> = Box::new(TestStrategy {});
-//                 let loader = Arc::new(loader);
-
-//                 strategy
-//                     .prepare(loader.clone(), Some(Default::default()), None)
-//                     .await
-//                     .unwrap();
-//                 let strategy = Arc::new(strategy);
-//                 let result = run(
-//                     strategy,
-//                     loader,
-//                     5,
-//                     tx.subscribe(),
-//                     None,
-//                     Some(Box::new(|code, name, t, c, p| {
-//                         print!("\rprocessing: {}({}) {}/{}({})%", name, code, c, t, p)
-//                     })),
-//                 )
-//                 .await
-//                 .unwrap();
-//                 log::info!("result: {:?}", result);
-//             });
-//     }
-// }
+use std::{collections::HashMap, sync::Arc};
+
+use futures::future::{join, join_all};
+use rwqdata::{
+    store::{DataType, Loader, Query},
+    MarketType,
+};
+
+use crate::{
+    select::{ProgressFunc, Strategy, StrategyResult},
+    Error, Result,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+
+pub fn market_to_data_type(typ: MarketType) -> DataType {
+    match typ {
+        MarketType::Bond => DataType::Bond,
+        MarketType::Fund => DataType::Fund,
+        MarketType::Stock => DataType::Stock,
+    }
+}
+
+/// 并发执行选股策略
+///
+/// - `concurrent` 并发任务数
+/// - `the_codes` 指定的代码，为空时从`loader`加载策略所接受市场的全部代码
+/// - `progress_func` 进度回调，参数为: 代码，名称，总数，当前数，百分比
+///
+/// 单个代码出错只记录日志，不影响其他代码。收到`shutdown_rx`信号时所有任务退出。
+pub async fn run(
+    strategy: Arc<Box<dyn Strategy>>,
+    loader: Arc<Box<dyn Loader>>,
+    concurrent: usize,
+    mut shutdown_rx: broadcast::Receiver<()>,
+    the_codes: Option<HashMap<MarketType, Vec<(String, String)>>>,
+    progress_func: Option<ProgressFunc>,
+) -> Result<Option<HashMap<MarketType, Vec<StrategyResult>>>> {
+    let (shutdown_tx, _) = broadcast::channel(1);
+    let types = strategy.accept();
+    let mut test_codes = HashMap::new();
+    let mut total = 0;
+    if let Some(the_codes) = the_codes {
+        for (k, v) in the_codes.into_iter() {
+            if !types.contains(&k) {
+                return Err(Error::Custom(format!(
+                    "strategy not suitable for type: {:?}, only valid for {:?}",
+                    &k, types
+                )));
+            }
+            total += v.len();
+            let codes = split_code(v, concurrent);
+            if !codes.is_empty() {
+                test_codes.insert(k, codes);
+            }
+        }
+    } else {
+        for typ in types.into_iter() {
+            let codes = loader
+                .load_info(market_to_data_type(typ), Query::new())
+                .await
+                .map_err(|e| Error::Custom(format!("query info error: {:?}", e)))?;
+
+            total += codes.len();
+
+            let codes = split_code(codes, concurrent);
+
+            if !codes.is_empty() {
+                test_codes.insert(typ, codes);
+            }
+        }
+    }
+
+    let (progress_tx, progress_rx, progress_func) = if let Some(progress_func) = progress_func {
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+
+        let progress_func = Arc::new(progress_func);
+
+        (Some(progress_tx), Some(progress_rx), Some(progress_func))
+    } else {
+        (None, None, None)
+    };
+
+    let mut handlers = HashMap::new();
+    for (typ, codes_vec) in test_codes.into_iter() {
+        let mut handler = Vec::new();
+        for codes in codes_vec.into_iter() {
+            let s = strategy.clone();
+            let l = loader.clone();
+            let rx = shutdown_tx.subscribe();
+            log::info!("spawn task: {:?}", &typ);
+            let tx = progress_tx.clone();
+            let h = tokio::spawn(run_task(typ, s, l, codes, rx, tx));
+            handler.push(h);
+        }
+        handlers.insert(typ, handler);
+    }
+    // 任务结束后进度通道关闭，进度任务随之退出
+    drop(progress_tx);
+
+    let mut g_handlers = Vec::new();
+    for (typ, handlers) in handlers.into_iter() {
+        let h = tokio::spawn(join_group(typ, handlers));
+        g_handlers.push(h);
+    }
+    let mut ret_map = HashMap::new();
+
+    let p_handler = tokio::spawn(progress_task(
+        total,
+        progress_func,
+        shutdown_tx.subscribe(),
+        progress_rx,
+    ));
+
+    tokio::select! {
+        (rest, _) = join(join_all(g_handlers), p_handler) => {
+            log::info!("join_all done");
+            for res in rest.into_iter() {
+                let res = res.map_err(|e| Error::Custom(format!("join error: {}", e)))?;
+                match res {
+                    Ok(Some((typ, data))) => {
+                        ret_map.insert(typ, data);
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::error!("task group error: {}", e),
+                }
+            }
+        },
+        _ = shutdown_rx.recv() => {
+            log::info!("receive shutdown signal");
+            shutdown_tx.send(())
+            .map_err(|e|Error::Custom(format!("send shutdown signal error x: {}", e)))?;
+        }
+    }
+
+    let ret_map = if ret_map.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(ret_map))
+    };
+    log::info!("all task done");
+    ret_map
+}
+
+/// 单个代码执行选股策略
+pub async fn fit(
+    code: String,
+    name: String,
+    typ: MarketType,
+    strategy: Arc<Box<dyn Strategy>>,
+    loader: Arc<Box<dyn Loader>>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) -> Result<Option<StrategyResult>> {
+    let types = strategy.accept();
+    if !types.contains(&typ) {
+        return Err(Error::Custom(format!(
+            "strategy not suitable for type: {:?}, only valid for {:?}",
+            &typ, types
+        )));
+    }
+    tokio::select! {
+        data = strategy.test(loader.clone(), typ, code, name) => {
+            data
+        },
+        _ = shutdown_rx.recv() => {
+            log::info!("receive shutdown signal");
+            Ok(None)
+        }
+    }
+}
+
+async fn join_group(
+    typ: MarketType,
+    handlers: Vec<JoinHandle<Result<Option<Vec<StrategyResult>>>>>,
+) -> Result<Option<(MarketType, Vec<StrategyResult>)>> {
+    let rest = join_all(handlers).await;
+    let mut rs_vec = Vec::new();
+    for res in rest.into_iter() {
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                log::error!("task join error: {}", e);
+                continue;
+            }
+        };
+        match res {
+            Ok(Some(res)) => rs_vec.extend(res),
+            Ok(None) => {}
+            Err(e) => log::error!("task run error: {}", e),
+        }
+    }
+    let rs = if rs_vec.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some((typ, rs_vec)))
+    };
+    log::info!("type {:?} all task done", typ);
+    rs
+}
+
+async fn progress_task(
+    total: usize,
+    progress_func: Option<Arc<ProgressFunc>>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+    progress_rx: Option<mpsc::UnboundedReceiver<(String, String)>>,
+) {
+    let (progress_func, mut progress_rx) = match (progress_func, progress_rx) {
+        (Some(progress_func), Some(progress_rx)) => (progress_func, progress_rx),
+        _ => return,
+    };
+
+    log::info!("progress task with {} codes", total);
+    let mut current: usize = 0;
+    for _ in 0..total {
+        tokio::select! {
+            rs = progress_rx.recv() => {
+                current+=1;
+                if let Some(rs) = rs {
+                    let p = (((current * 100) as f32 / total as f32) * 100.0).round() / 100.0;
+                    let (code, name) = rs;
+                    progress_func(code.as_str(), name.as_str(), total, current, p);
+                } else {
+                    log::info!("progress channel closed");
+                    break;
+                }
+
+            }
+            _ = shutdown_rx.recv() => {
+                log::info!("progress task receive shutdown signal");
+                break;
+            }
+        }
+    }
+}
+
+async fn run_task(
+    typ: MarketType,
+    strategy: Arc<Box<dyn Strategy>>,
+    loader: Arc<Box<dyn Loader>>,
+    codes: Vec<(String, String)>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+    progress_tx: Option<mpsc::UnboundedSender<(String, String)>>,
+) -> Result<Option<Vec<StrategyResult>>> {
+    log::info!("run task with {} codes", codes.len());
+    let mut rs_vec = Vec::new();
+    for (code, name) in codes {
+        if let Some(tx) = &progress_tx {
+            tx.send((code.clone(), name.clone()))
+                .map_err(|e| Error::Custom(format!("send progress signal error x: {}", e)))?;
+        }
+        tokio::select! {
+            res = strategy
+            .test(loader.clone(), typ, code.clone(), name.clone()) => {
+                match res {
+                    Ok(Some(data)) => {
+                        log::info!("got data: {}({})", name, code);
+                        rs_vec.push(data);
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::error!("run test {}({}) with error: {}", name, code, e),
+                }
+            },
+            _ = shutdown_rx.recv() => {
+                log::info!("run task receive shutdown signal");
+                return Ok(None);
+            }
+        }
+    }
+
+    if rs_vec.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(rs_vec))
+    }
+}
+
+/// 代码按并发数分组，最多`count`组
+pub fn split_code(codes: Vec<(String, String)>, count: usize) -> Vec<Vec<(String, String)>> {
+    let count = count.max(1);
+    let task_count = codes.len().div_ceil(count);
+
+    let mut result = Vec::new();
+    let mut task_vec = Vec::new();
+    for code in codes.into_iter() {
+        task_vec.push(code);
+        if task_vec.len() < task_count {
+            continue;
+        }
+        result.push(task_vec);
+        task_vec = Vec::new();
+    }
+    if !task_vec.is_empty() {
+        result.push(task_vec);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path, sync::Arc};
+
+    use chrono::NaiveDate;
+    use rwqdata::{
+        store::{get_loader, Loader, Order, Query},
+        Bar, FileFormat, MarketType, StockInfo, SyncDest,
+    };
+    use serde::Serialize;
+    use tokio::sync::broadcast;
+
+    use crate::{
+        run,
+        select::{Strategy, StrategyResult},
+        split_code, Result,
+    };
+
+    use async_trait::async_trait;
+
+    /// 选出区间内上涨的代码，没有k线的代码出错
+    struct TestStrategy {}
+    #[async_trait]
+    impl Strategy for TestStrategy {
+        async fn test(
+            &self,
+            loader: Arc<Box<dyn Loader>>,
+            _typ: MarketType,
+            code: String,
+            name: String,
+        ) -> Result<Option<StrategyResult>> {
+            let bars = loader
+                .load_stock_daily(Query::new().code(&code).sort("trade_date", Order::Asc))
+                .await
+                .map_err(|e| crate::Error::Custom(e.to_string()))?;
+            let (first, last) = match (bars.first(), bars.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => return Err(crate::Error::Custom(format!("{} has no bars", code))),
+            };
+            if last.close > first.close {
+                let rs = StrategyResult {
+                    code,
+                    name,
+                    mark: None,
+                    stat: None,
+                };
+                return Ok(Some(rs));
+            }
+            Ok(None)
+        }
+    }
+
+    /// 按文件存储的CSV格式写入一个数据段，文本加引号，数值不加引号
+    fn write_csv<T: Serialize>(dir: &Path, data: &[T]) {
+        let rows: Vec<_> = data
+            .iter()
+            .map(|e| match serde_json::to_value(e).unwrap() {
+                serde_json::Value::Object(row) => row,
+                _ => unreachable!(),
+            })
+            .collect();
+        let header: Vec<_> = rows[0].keys().cloned().collect();
+        let mut csv = header
+            .iter()
+            .map(|h| format!("\"{}\"", h))
+            .collect::<Vec<_>>()
+            .join(",");
+        for row in rows.iter() {
+            let fields: Vec<_> = header
+                .iter()
+                .map(|h| match &row[h] {
+                    serde_json::Value::String(s) => format!("\"{}\"", s),
+                    v => v.to_string(),
+                })
+                .collect();
+            csv = csv + "\n" + &fields.join(",");
+        }
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("00000001.csv"), csv + "\n").unwrap();
+    }
+
+    /// 写入股票信息及日线，sz000001没有日线
+    fn seed(root: &Path) {
+        let date = |d: u32| {
+            NaiveDate::from_ymd_opt(2023, 3, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        let codes = ["sz000001", "sz002805", "sz300827", "sh600000"];
+        let info: Vec<_> = codes
+            .iter()
+            .map(|code| StockInfo {
+                code: code.to_string(),
+                name: code.to_string(),
+                block: "主板".to_owned(),
+                is_margin: false,
+                listing_date: date(1),
+            })
+            .collect();
+        write_csv(&root.join("stock_info"), &info);
+        for (code, closes) in [
+            ("sz002805", [10.0, 10.5, 11.0]),
+            ("sz300827", [20.0, 19.5, 20.5]),
+            ("sh600000", [8.0, 8.2, 7.9]),
+        ] {
+            let bars: Vec<_> = closes
+                .iter()
+                .enumerate()
+                .map(|(i, close)| Bar {
+                    code: code.to_owned(),
+                    name: code.to_owned(),
+                    trade_date: date(i as u32 + 1),
+                    open: *close,
+                    close: *close,
+                    high: *close,
+                    low: *close,
+                    ..Default::default()
+                })
+                .collect();
+            write_csv(&root.join("stock_daily").join(code), &bars);
+        }
+    }
+
+    #[test]
+    fn test_split_code() {
+        let codes: Vec<_> = (0..10).map(|i| (i.to_string(), i.to_string())).collect();
+        let rs = split_code(codes.clone(), 3);
+        assert_eq!(rs.len(), 3);
+        assert_eq!(rs.iter().map(|v| v.len()).sum::<usize>(), 10);
+        assert_eq!(split_code(codes.clone(), 20).len(), 10);
+        assert_eq!(split_code(codes, 0).len(), 1);
+        assert!(split_code(vec![], 5).is_empty());
+    }
+
+    #[test]
+    fn test_runner() {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let root =
+                    std::env::temp_dir().join(format!("rwqstrategy-runner-{}", std::process::id()));
+                let _ = std::fs::remove_dir_all(&root);
+                seed(&root);
+
+                let (tx, _) = broadcast::channel(1);
+                let dest = SyncDest::File(root.clone(), FileFormat::Csv);
+                let (_, loader) = get_loader(&dest, false).await.unwrap();
+                let mut strategy: Box<dyn Strategy> = Box::new(TestStrategy {});
+                let loader = Arc::new(loader);
+
+                strategy
+                    .prepare(loader.clone(), Some(Default::default()), None)
+                    .await
+                    .unwrap();
+                let strategy = Arc::new(strategy);
+                let selected = |result: Option<HashMap<MarketType, Vec<StrategyResult>>>| {
+                    let mut codes: Vec<_> = result.unwrap()[&MarketType::Stock]
+                        .iter()
+                        .map(|r| r.code.clone())
+                        .collect();
+                    codes.sort();
+                    codes
+                };
+
+                // 指定代码，sz000001出错不影响其他代码
+                let codes: Vec<_> = ["sz000001", "sz002805", "sz300827", "sh600000"]
+                    .iter()
+                    .map(|c| (c.to_string(), c.to_string()))
+                    .collect();
+                let result = run(
+                    strategy.clone(),
+                    loader.clone(),
+                    2,
+                    tx.subscribe(),
+                    Some(HashMap::from([(MarketType::Stock, codes)])),
+                    None,
+                )
+                .await
+                .unwrap();
+                assert_eq!(selected(result), vec!["sz002805", "sz300827"]);
+
+                // 不指定代码时从loader加载全部股票
+                let result = run(strategy, loader, 3, tx.subscribe(), None, None)
+                    .await
+                    .unwrap();
+                assert_eq!(selected(result), vec!["sz002805", "sz300827"]);
+
+                let _ = std::fs::remove_dir_all(&root);
+            });
+    }
+}
@@ -44,6 +44,68 @@ impl StrategyResult {
     }
 }
 
+/// 策略参数类型
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Int,
+    Float,
+    Bool,
+    String,
+    /// 日期，格式: %Y-%m-%d
+    Date,
+}
+
+/// 策略参数说明
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParamDesc {
+    pub name: String,
+    pub typ: ParamType,
+    pub default: Option<String>,
+    pub desc: String,
+}
+
+impl ParamDesc {
+    pub fn new(name: &str, typ: ParamType, default: Option<&str>, desc: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            typ,
+            default: default.map(String::from),
+            desc: desc.to_owned(),
+        }
+    }
+
+    /// 校验参数值是否符合类型
+    pub fn check(&self, value: &str) -> Result<()> {
+        let ok = match self.typ {
+            ParamType::Int => value.parse::<i64>().is_ok(),
+            ParamType::Float => value.parse::<f64>().is_ok(),
+            ParamType::Bool => value.parse::<bool>().is_ok(),
+            ParamType::String => true,
+            ParamType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+        };
+        if !ok {
+            return Err(crate::Error::Custom(format!(
+                "param {}={} is not {:?}",
+                self.name, value, self.typ
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 按策略参数说明校验参数，未定义的参数或类型不符均返回错误
+pub fn check_params(desc: &[ParamDesc], params: &Params) -> Result<()> {
+    for (name, value) in params.iter() {
+        let param = desc
+            .iter()
+            .find(|p| &p.name == name)
+            .ok_or(crate::Error::Custom(format!("unknown param: {}", name)))?;
+        param.check(value)?;
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub struct CommonParam {
     pub test_end_date: Option<NaiveDateTime>,
@@ -86,6 +148,10 @@ pub trait Strategy: Sync + Send {
     ) -> Result<()> {
         Ok(())
     }
+    /// 策略参数说明
+    fn params(&self) -> Vec<ParamDesc> {
+        vec![]
+    }
     fn accept(&self) -> Vec<MarketType> {
         vec![MarketType::Stock]
     }
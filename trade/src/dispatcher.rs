@@ -0,0 +1,151 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::NaiveDateTime;
+use rwqstrategy::{broker::Broker, context::Context, risk::Risk};
+use rwqtradecmm::{BrokerEvent, Entrust, Event, Signal, TradeTime, TradeType};
+use tokio::sync::mpsc;
+
+use crate::{Error, Quotation, Result};
+
+/// 事件分发
+///
+/// 接收`Context::emit`发出的事件，交易信号经风控后转为委托，委托发往券商，
+/// 订阅发往行情，券商推送更新账户。
+pub struct Dispatcher {
+    ctx: Arc<Context>,
+    rx: mpsc::Receiver<Event>,
+    now: Option<NaiveDateTime>,
+}
+
+impl Dispatcher {
+    pub fn new(ctx: Arc<Context>, rx: mpsc::Receiver<Event>) -> Self {
+        Self { ctx, rx, now: None }
+    }
+
+    /// 设置当前行情时间，委托及成交时间以此为准
+    pub fn set_now(&mut self, now: Option<NaiveDateTime>) {
+        self.now = now;
+    }
+
+    /// 当前行情时间
+    pub fn now(&self) -> Option<NaiveDateTime> {
+        self.now
+    }
+
+    /// 等待并接收事件
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.rx.recv().await
+    }
+
+    /// 分发队列中的所有事件，直至队列为空
+    ///
+    /// 处理事件时券商等会继续发出事件，每个事件处理前先将队列中的事件移到本地，
+    /// 避免队列满时发送事件阻塞而死锁
+    pub async fn dispatch(
+        &mut self,
+        risk: &mut dyn Risk,
+        broker: &mut dyn Broker,
+        quotation: &mut dyn Quotation,
+    ) -> Result<()> {
+        let mut events = VecDeque::new();
+        loop {
+            while let Ok(event) = self.rx.try_recv() {
+                events.push_back(event);
+            }
+            match events.pop_front() {
+                Some(event) => self.on_event(event, risk, broker, quotation).await?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// 分发单个事件
+    pub async fn on_event(
+        &mut self,
+        event: Event,
+        risk: &mut dyn Risk,
+        broker: &mut dyn Broker,
+        quotation: &mut dyn Quotation,
+    ) -> Result<()> {
+        match event {
+            Event::Signal(signal) => self.on_signal(signal, risk, broker).await?,
+            Event::Subscribe(codes) => quotation.subscribe(&codes).await?,
+            Event::Entrust(entrust) => self.on_entrust(entrust, broker).await?,
+            Event::Broker(event) => self.on_broker(event),
+        }
+        Ok(())
+    }
+
+    /// 信号经风控后冻结并直接发往券商，不再经过事件队列
+    async fn on_signal(
+        &mut self,
+        signal: Signal,
+        risk: &mut dyn Risk,
+        broker: &mut dyn Broker,
+    ) -> Result<()> {
+        {
+            let mut account = self.ctx.account.write().unwrap();
+            account.signal.push(signal.clone());
+        }
+        let signal = risk
+            .on_signal(self.ctx.clone(), signal)
+            .await
+            .map_err(|e| Error::Custom(format!("risk on_signal error: {}", e)))?;
+        if let Some(signal) = signal {
+            let mut entrust = Entrust::from(&signal);
+            if let Some(now) = self.now {
+                entrust.time = now.into();
+            }
+            if let Err(e) = self.ctx.freeze(&entrust) {
+                tracing::warn!("signal {} not entrust: {}", signal.id.as_str(), e);
+                return Ok(());
+            }
+            self.on_entrust(entrust, broker).await?;
+        }
+        Ok(())
+    }
+
+    /// 委托发往券商，撤销委托按`cancel_id`找到活动委托后撤销
+    async fn on_entrust(&self, entrust: Entrust, broker: &mut dyn Broker) -> Result<()> {
+        if !matches!(entrust.typ, TradeType::Cancel) {
+            return broker
+                .on_entrust(self.ctx.clone(), entrust)
+                .await
+                .map_err(|e| Error::Custom(format!("broker on_entrust error: {}", e)));
+        }
+        let target = entrust.cancel_id.as_ref().and_then(|id| {
+            let account = self.ctx.account.read().unwrap();
+            account.entrust.get(id).cloned()
+        });
+        match target {
+            Some(target) => broker
+                .on_cancel(self.ctx.clone(), target)
+                .await
+                .map_err(|e| Error::Custom(format!("broker on_cancel error: {}", e))),
+            None => {
+                tracing::warn!("cancel entrust {:?} not active", entrust.cancel_id);
+                Ok(())
+            }
+        }
+    }
+
+    fn on_broker(&self, event: BrokerEvent) {
+        let mut account = self.ctx.account.write().unwrap();
+        match event {
+            BrokerEvent::Entrust(entrusts) => {
+                for entrust in entrusts.into_iter() {
+                    account.on_entrust(entrust, self.now.map(TradeTime::from));
+                }
+            }
+            BrokerEvent::FundSync((total, available, hold_value)) => {
+                account.total_net_value = total;
+                account.cash_available = available;
+                account.total_hold_value = hold_value;
+            }
+            BrokerEvent::Position(positions) => {
+                account.position = positions.into_iter().map(|p| (p.code.clone(), p)).collect();
+            }
+        }
+    }
+}
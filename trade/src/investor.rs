@@ -2,10 +2,36 @@ use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
 use rwqdata::store::Loader;
-use rwqstrategy::{broker::Broker, risk::Risk, trade::Strategy};
-use rwqtradecmm::Account;
+use rwqstrategy::{broker::Broker, context::Context, risk::Risk, trade::Strategy, Params};
+use rwqtradecmm::{Account, Deal, QuotEvent, QuotOpts, Signal};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
-use crate::Result;
+use crate::{Dispatcher, Equity, Error, Report, ReportOpts, Result};
+
+/// 事件队列大小
+const EVENT_QUEUE_SIZE: usize = 1024;
+
+/// 策略，风控，券商的初始化参数
+#[derive(Debug, Clone, Default)]
+pub struct InvestParams {
+    pub strategy: Option<Params>,
+    pub risk: Option<Params>,
+    pub broker: Option<Params>,
+}
+
+/// 回测结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestResult {
+    /// 回测结束时的账户
+    pub account: Account,
+    /// 成交记录
+    pub deal: Vec<Deal>,
+    /// 交易信号
+    pub signal: Vec<Signal>,
+    /// 每日权益
+    pub equity: Vec<Equity>,
+}
 
 pub struct Investor {
     pub broker: Box<dyn Broker>,
@@ -35,7 +61,218 @@ impl Investor {
     pub async fn invest(&mut self) -> Result<()> {
         Ok(())
     }
-    pub async fn backtest(&mut self) -> Result<()> {
+
+    /// 历史行情回测
+    ///
+    /// 策略需在`init`中通过`ctx.subscribe`订阅行情，行情按`opts`的频率和时间回放，
+    /// 行情事件依次驱动策略，风控，券商，所产生的事件在每个行情事件后处理。
+    pub async fn backtest(
+        &mut self,
+        opts: QuotOpts,
+        params: InvestParams,
+    ) -> Result<BacktestResult> {
+        let (event_tx, event_rx) = mpsc::channel(EVENT_QUEUE_SIZE);
+        let ctx = Arc::new(Context::new(
+            self.loader.clone(),
+            self.account.clone(),
+            event_tx,
+        ));
+        let mut dispatcher = Dispatcher::new(ctx.clone(), event_rx);
+        let mut quotation = crate::backtest_with_loader(opts, self.loader.clone());
+        let mut equity = vec![];
+
+        self.strategy
+            .init(ctx.clone(), params.strategy)
+            .await
+            .map_err(|e| Error::Custom(format!("strategy init error: {}", e)))?;
+        self.risk
+            .init(ctx.clone(), params.risk)
+            .await
+            .map_err(|e| Error::Custom(format!("risk init error: {}", e)))?;
+        self.broker
+            .init(ctx.clone(), params.broker)
+            .await
+            .map_err(|e| Error::Custom(format!("broker init error: {}", e)))?;
+
+        dispatcher
+            .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+            .await?;
+
+        loop {
+            let event = quotation.fetch(None).await?;
+            if event.is_none() {
+                break;
+            }
+            let event = event.unwrap();
+            match &event {
+                QuotEvent::Start => {
+                    self.on_start(&ctx).await?;
+                }
+                QuotEvent::MorningOpen => {
+                    {
+                        let mut account = self.account.write().unwrap();
+                        account.roll_trade_date();
+                    }
+                    self.on_open(&ctx, &event).await?;
+                }
+                QuotEvent::NoonClose => {
+                    self.on_close(&ctx, &event).await?;
+                    dispatcher
+                        .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+                        .await?;
+                    if let Some(now) = dispatcher.now() {
+                        let account = self.account.read().unwrap();
+                        equity.push(Equity {
+                            trade_date: now.date(),
+                            net_value: account.total_net_value,
+                            cash: account.cash_available + account.cash_frozen,
+                            hold_value: account.total_hold_value,
+                        });
+                    }
+                }
+                QuotEvent::Quot(quots) => {
+                    dispatcher.set_now(quots.values().next().map(|q| q.time));
+                    // 先按新行情撮合之前的委托，策略看到的是成交后的账户
+                    self.broker
+                        .on_quot(ctx.clone(), quots.clone())
+                        .await
+                        .map_err(|e| Error::Custom(format!("broker on_quot error: {}", e)))?;
+                    dispatcher
+                        .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+                        .await?;
+                    {
+                        let mut account = self.account.write().unwrap();
+                        account.on_quot(quots);
+                    }
+                    self.strategy
+                        .on_trade(ctx.clone(), quots.clone())
+                        .await
+                        .map_err(|e| Error::Custom(format!("strategy on_trade error: {}", e)))?;
+                    self.risk
+                        .on_risk(ctx.clone(), quots.clone())
+                        .await
+                        .map_err(|e| Error::Custom(format!("risk on_risk error: {}", e)))?;
+                    dispatcher
+                        .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+                        .await?;
+                    self.broker
+                        .on_poll(ctx.clone())
+                        .await
+                        .map_err(|e| Error::Custom(format!("broker on_poll error: {}", e)))?;
+                }
+                QuotEvent::End => {
+                    self.on_end(&ctx).await?;
+                    dispatcher
+                        .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+                        .await?;
+                    break;
+                }
+                _ => {}
+            }
+            dispatcher
+                .dispatch(self.risk.as_mut(), self.broker.as_mut(), quotation.as_mut())
+                .await?;
+        }
+
+        self.strategy
+            .destroy(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("strategy destroy error: {}", e)))?;
+        self.risk
+            .destroy(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("risk destroy error: {}", e)))?;
+        self.broker
+            .destroy(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("broker destroy error: {}", e)))?;
+
+        let account = self.account.read().unwrap().clone();
+        Ok(BacktestResult {
+            deal: account.deal.clone(),
+            signal: account.signal.clone(),
+            account,
+            equity,
+        })
+    }
+
+    /// 回测表现报告，`opts.benchmark`不为空时计算相对基准指数的alpha/beta
+    pub async fn report(&self, result: &BacktestResult, opts: ReportOpts) -> Result<Report> {
+        let mut report = Report::new(
+            result.account.cash_init,
+            result.equity.clone(),
+            &result.deal,
+            opts.risk_free,
+        );
+        if let Some(code) = &opts.benchmark {
+            report
+                .load_benchmark(self.loader.as_ref().as_ref(), code, opts.risk_free)
+                .await?;
+        }
+        Ok(report)
+    }
+
+    async fn on_start(&mut self, ctx: &Arc<Context>) -> Result<()> {
+        self.strategy
+            .on_start(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("strategy on_start error: {}", e)))?;
+        self.risk
+            .on_start(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("risk on_start error: {}", e)))?;
+        self.broker
+            .on_start(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("broker on_start error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn on_open(&mut self, ctx: &Arc<Context>, event: &QuotEvent) -> Result<()> {
+        self.strategy
+            .on_open(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("strategy on_open error: {}", e)))?;
+        self.risk
+            .on_open(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("risk on_open error: {}", e)))?;
+        self.broker
+            .on_open(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("broker on_open error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn on_close(&mut self, ctx: &Arc<Context>, event: &QuotEvent) -> Result<()> {
+        self.strategy
+            .on_close(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("strategy on_close error: {}", e)))?;
+        self.risk
+            .on_close(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("risk on_close error: {}", e)))?;
+        self.broker
+            .on_close(ctx.clone(), event.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("broker on_close error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn on_end(&mut self, ctx: &Arc<Context>) -> Result<()> {
+        self.strategy
+            .on_end(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("strategy on_end error: {}", e)))?;
+        self.risk
+            .on_end(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("risk on_end error: {}", e)))?;
+        self.broker
+            .on_end(ctx.clone())
+            .await
+            .map_err(|e| Error::Custom(format!("broker on_end error: {}", e)))?;
         Ok(())
     }
 }
@@ -45,11 +282,219 @@ pub trait InvestVisitor {
     async fn visit(&self, investor: &Investor);
 }
 
-
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::BTreeSet,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use async_trait::async_trait;
+    use chrono::NaiveDate;
+    use rwqdata::{
+        set_providers, store::get_loader, Bar, BarFreq, DataProvider, FileFormat, Providers,
+        RtQuot, SyncDest,
+    };
+    use rwqstrategy::{
+        context::Context,
+        mystrategy::{broker::simulate::Simulate, risk::dummy::Dummy},
+        trade::Strategy,
+        Params, Result,
+    };
+    use rwqtradecmm::{Account, QuotOpts, Signal, SignalSource, TradeType};
+
+    use super::{InvestParams, Investor};
+    use crate::{Report, ReportOpts};
+
+    const CODE: &str = "sz000001";
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    /// 固定的日线: 日期，开盘，收盘，最高，最低，涨跌幅
+    struct FixedBars;
+
+    #[async_trait]
+    impl DataProvider for FixedBars {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+        async fn fetch_bar(
+            &self,
+            code: &str,
+            _freq: BarFreq,
+            start: Option<NaiveDate>,
+            end: Option<NaiveDate>,
+            _skip_rt: bool,
+        ) -> rwqfetch::Result<Vec<Bar>> {
+            let bars = [
+                ("2023-03-01", 10.0, 10.0, 10.2, 9.9, 0.0),
+                ("2023-03-02", 9.9, 10.2, 10.3, 9.8, 2.0),
+                ("2023-03-03", 10.3, 10.5, 10.6, 10.2, 2.94),
+                ("2023-03-06", 10.6, 10.4, 10.7, 10.3, -0.95),
+                ("2023-03-07", 10.4, 10.5, 10.6, 10.3, 0.96),
+            ];
+            Ok(bars
+                .into_iter()
+                .map(|(d, open, close, high, low, chg_pct)| Bar {
+                    code: code.to_owned(),
+                    name: "平安银行".into(),
+                    trade_date: date(d).and_hms_opt(0, 0, 0).unwrap(),
+                    open,
+                    close,
+                    high,
+                    low,
+                    volume: 1000000,
+                    amount: 100000000.0,
+                    chg_pct,
+                    ..Default::default()
+                })
+                .filter(|bar| start.iter().all(|s| bar.trade_date.date() >= *s))
+                .filter(|bar| end.iter().all(|e| bar.trade_date.date() <= *e))
+                .collect())
+        }
+        async fn fetch_trade_date(&self) -> rwqfetch::Result<BTreeSet<i32>> {
+            Ok([20230301, 20230302, 20230303, 20230306, 20230307]
+                .into_iter()
+                .collect())
+        }
+    }
+
+    /// 第一个行情买入，持仓可用后卖出，记录每个行情的(日期，持仓量，可用量)
+    #[derive(Default)]
+    struct TestStrategy {
+        bought: AtomicBool,
+        seen: Arc<Mutex<Vec<(NaiveDate, u32, u32)>>>,
+    }
+
+    #[async_trait]
+    impl Strategy for TestStrategy {
+        async fn init(&mut self, ctx: Arc<Context>, _params: Option<Params>) -> Result<()> {
+            ctx.subscribe(vec![CODE.into()]).await
+        }
+        async fn on_trade(&self, ctx: Arc<Context>, quots: RtQuot) -> Result<()> {
+            if let Some(quot) = quots.get(CODE) {
+                let (volume, available) = ctx.account.read().unwrap().get_position_volume(CODE);
+                self.seen
+                    .lock()
+                    .unwrap()
+                    .push((quot.time.date(), volume, available));
+                let signal = Signal {
+                    source: SignalSource::Strategy("TestStrategy".into()),
+                    code: quot.code.clone(),
+                    name: quot.name.clone(),
+                    price: quot.now,
+                    ..Default::default()
+                };
+                if available > 0 {
+                    ctx.sell(Signal {
+                        typ: TradeType::Sell,
+                        volume: available,
+                        ..signal
+                    })
+                    .await?;
+                } else if !self.bought.swap(true, Ordering::SeqCst) {
+                    ctx.buy(Signal {
+                        typ: TradeType::Buy,
+                        volume: 100,
+                        ..signal
+                    })
+                    .await?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn assert_near(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
     #[test]
     fn test_investor() {
-        
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            set_providers(Providers::new(vec![Arc::new(FixedBars)]));
+            let dest = SyncDest::File(std::env::temp_dir(), FileFormat::default());
+            let (_, loader) = get_loader(&dest, false).await.unwrap();
+            let account = Account {
+                cash_init: 100000.0,
+                cash_available: 100000.0,
+                broker_fee: 0.00025,
+                hand_fee: 0.000035,
+                transfer_fee: 0.0001,
+                tax_fee: 0.001,
+                ..Default::default()
+            };
+            let strategy = TestStrategy::default();
+            let seen = strategy.seen.clone();
+            let mut investor = Investor::new(
+                Box::new(Simulate::new()),
+                Box::new(strategy),
+                Box::new(Dummy::new()),
+                account,
+                loader,
+            );
+            let opts = QuotOpts {
+                freq: BarFreq::Daily.to_seconds(),
+                start_date: Some(date("2023-03-01").into()),
+                end_date: Some(date("2023-03-07").into()),
+            };
+            let rs = investor
+                .backtest(opts, InvestParams::default())
+                .await
+                .unwrap();
+
+            // 03-01收盘价买入，03-02低开按开盘价成交，当日不可卖出，03-03可卖，03-06高开按开盘价成交
+            let seen = seen.lock().unwrap().clone();
+            assert_eq!(
+                seen,
+                vec![
+                    (date("2023-03-01"), 0, 0),
+                    (date("2023-03-02"), 100, 0),
+                    (date("2023-03-03"), 100, 100),
+                    (date("2023-03-06"), 0, 0),
+                    (date("2023-03-07"), 0, 0),
+                ]
+            );
+            assert_eq!(rs.signal.len(), 2);
+            assert_eq!(rs.deal.len(), 2);
+            let (buy, sell) = (&rs.deal[0], &rs.deal[1]);
+            assert!(matches!(buy.typ, TradeType::Buy));
+            assert_eq!((buy.price, buy.volume), (9.9, 100));
+            assert_eq!(buy.time.date(), date("2023-03-02"));
+            assert!(matches!(sell.typ, TradeType::Sell));
+            assert_eq!((sell.price, sell.volume), (10.6, 100));
+            assert_eq!(sell.time.date(), date("2023-03-06"));
+
+            let fee = (buy.fee + sell.fee) as f64;
+            assert!(rs.account.position.is_empty());
+            assert!(rs.account.entrust.is_empty());
+            assert_near(rs.account.cash_frozen as f64, 0.0);
+            assert_near(rs.account.cash_available as f64, 100070.0 - fee);
+            assert_near(rs.account.close_profit as f64, 70.0 - fee);
+
+            let equity: Vec<_> = rs.equity.iter().map(|e| e.trade_date).collect();
+            assert_eq!(equity.len(), 5);
+            assert_eq!(equity[0], date("2023-03-01"));
+            // 03-02持仓按收盘价计值
+            assert_near(rs.equity[1].hold_value as f64, 1020.0);
+
+            let report: Report = investor.report(&rs, ReportOpts::default()).await.unwrap();
+            assert_eq!(report.trade_days, 5);
+            assert_eq!(report.trade_count, 1);
+            assert_eq!(report.win_rate, 1.0);
+            assert_eq!(report.profit_factor, None);
+            assert_near(report.net_value as f64, 100070.0 - fee);
+            assert_near(report.total_return * 100000.0, 70.0 - fee);
+            assert!(report.benchmark.is_none());
+        });
     }
-}
\ No newline at end of file
+}
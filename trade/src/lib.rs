@@ -4,6 +4,12 @@ pub use quotation::*;
 pub mod investor;
 pub use investor::*;
 
+pub mod dispatcher;
+pub use dispatcher::*;
+
+pub mod report;
+pub use report::*;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -1,13 +1,18 @@
 use std::{
     collections::BTreeMap,
     ops::{Deref, DerefMut},
+    sync::Arc,
     time::Duration,
 };
 
 use crate::{Error, Result};
 use async_trait::async_trait;
 use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
-use rwqdata::{fetch_is_trade_date, fetch_rt_quot, fetch_stock_bar, BarFreq, Quot, RtQuot};
+use rwqdata::{
+    fetch_is_trade_date, fetch_rt_quot, fetch_stock_bar,
+    store::{DataType, Loader, Order, Query},
+    Bar, BarFreq, Quot, RtQuot,
+};
 use rwqtradecmm::{Event, QuotEvent, QuotOpts};
 use tokio::sync::{
     broadcast,
@@ -21,7 +26,12 @@ pub trait Quotation {
 }
 
 pub fn backtest(opts: QuotOpts) -> Box<dyn Quotation> {
-    Box::new(BacktestQuotation::new(opts))
+    Box::new(BacktestQuotation::new(opts, None))
+}
+
+/// 回测行情，分钟线优先回放`loader`中已同步的数据，本地没有数据时从远程获取
+pub fn backtest_with_loader(opts: QuotOpts, loader: Arc<Box<dyn Loader>>) -> Box<dyn Quotation> {
+    Box::new(BacktestQuotation::new(opts, Some(loader)))
 }
 
 pub fn realtime(opts: QuotOpts) -> Box<dyn Quotation> {
@@ -146,6 +156,13 @@ impl MyQuotation {
         {
             return Ok(None);
         }
+        // 换日前，先补全上一交易日未触发的收盘事件
+        if self.trade_date.is_some() && self.trade_date.unwrap() != n.date() && self.base_event[0] {
+            let be = self.fire_base_event(3)?;
+            if be.is_some() {
+                return Ok(be);
+            }
+        }
         self.reset_trade_date(&n);
 
         let be = self.test_base_event(&n)?;
@@ -162,6 +179,7 @@ impl MyQuotation {
 
 struct BacktestQuotation {
     quotation: MyQuotation,
+    loader: Option<Arc<Box<dyn Loader>>>,
     quots: BTreeMap<i64, RtQuot>,
     index: usize,
     iter: Vec<i64>,
@@ -169,8 +187,9 @@ struct BacktestQuotation {
 }
 
 impl BacktestQuotation {
-    fn new(opts: QuotOpts) -> Self {
+    fn new(opts: QuotOpts, loader: Option<Arc<Box<dyn Loader>>>) -> Self {
         Self {
+            loader,
             quotation: MyQuotation {
                 opts: opts,
                 codes: vec![],
@@ -203,12 +222,46 @@ impl BacktestQuotation {
             Some(BarFreq::Min30)
         } else if self.opts.freq == BarFreq::Min60.to_seconds() {
             Some(BarFreq::Min60)
-        } else if self.opts.freq == BarFreq::Min5.to_seconds() {
+        } else if self.opts.freq == BarFreq::Daily.to_seconds() {
             Some(BarFreq::Daily)
         } else {
             None
         }
     }
+    /// 读取已同步的分钟线，依次查找股票，指数，基金，可转债的分钟线表，都没有数据时为空
+    async fn load_minute(
+        &self,
+        code: &str,
+        freq: BarFreq,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> Result<Option<Vec<Bar>>> {
+        let loader = match &self.loader {
+            Some(loader) if !matches!(freq, BarFreq::Daily) => loader,
+            _ => return Ok(None),
+        };
+        let start = start.map(|d| d.and_hms_opt(0, 0, 0).unwrap());
+        let end = end.map(|d| d.and_hms_opt(23, 59, 59).unwrap());
+        for typ in [
+            DataType::Stock,
+            DataType::Index,
+            DataType::Fund,
+            DataType::Bond,
+        ] {
+            let query = Query::new()
+                .code(code)
+                .date_range("trade_date", start, end)
+                .sort("trade_date", Order::Asc);
+            let bars = loader
+                .load_minute(typ, freq, query)
+                .await
+                .map_err(|e| Error::Custom(format!("load minute bar error: {}", e)))?;
+            if !bars.is_empty() {
+                return Ok(Some(bars));
+            }
+        }
+        Ok(None)
+    }
 }
 #[async_trait]
 impl Quotation for BacktestQuotation {
@@ -242,13 +295,25 @@ impl Quotation for BacktestQuotation {
             let end = self.opts.end_date.as_ref().map(|end| end.date());
 
             for code in new_codes {
-                let bars = fetch_stock_bar(&code, None, Some(freq), start, end, true)
-                    .await
-                    .map_err(|e| Error::Custom(format!("{}", e.to_string())))?;
+                let bars = match self.load_minute(&code, freq, start, end).await? {
+                    Some(bars) => Some(bars),
+                    None => {
+                        fetch_stock_bar(&code, None, Some(freq), start, end, true)
+                            .await
+                            .map_err(|e| Error::Custom(format!("{}", e.to_string())))?
+                            .bars
+                    }
+                };
 
-                bars.bars.and_then(|bars| {
+                bars.and_then(|bars| {
                     for bar in bars.iter() {
-                        let ts = bar.trade_date.timestamp();
+                        // 日线的时间为0点，按收盘时间处理，保证行情在开盘事件和收盘事件之间
+                        let trade_date = if matches!(freq, BarFreq::Daily) {
+                            bar.trade_date.date().and_hms_opt(15, 0, 0).unwrap()
+                        } else {
+                            bar.trade_date
+                        };
+                        let ts = trade_date.timestamp();
                         if !self.quots.contains_key(&ts) {
                             self.quots.insert(ts, RtQuot::new());
                         }
@@ -269,7 +334,7 @@ impl Quotation for BacktestQuotation {
                                 amount: bar.amount,
                                 bid: Default::default(),
                                 ask: Default::default(),
-                                time: bar.trade_date,
+                                time: trade_date,
                                 chg: bar.chg_pct / 100.0 * bar.close,
                                 chg_pct: bar.chg_pct,
                                 turnover: bar.turnover,
@@ -281,7 +346,7 @@ impl Quotation for BacktestQuotation {
                                 freq_low: bar.low,
                                 freq_chg: bar.chg_pct / 100.0 * bar.close,
                                 freq_chg_pct: bar.chg_pct,
-                                freq_time: bar.trade_date,
+                                freq_time: trade_date,
                             };
                             quot.insert(code.clone(), new_quot);
                         }
@@ -289,7 +354,12 @@ impl Quotation for BacktestQuotation {
                     Some(())
                 });
             }
-            self.iter.extend(self.quots.keys());
+            // 重新订阅时，保持当前的行情位置
+            let cur = self.iter.get(self.index).copied();
+            self.iter = self.quots.keys().copied().collect();
+            self.index = cur
+                .map(|ts| self.iter.iter().position(|t| *t >= ts).unwrap_or(0))
+                .unwrap_or(self.index);
         }
         Ok(())
     }
@@ -309,6 +379,13 @@ impl Quotation for BacktestQuotation {
             if let Some(event) = base_event {
                 return Ok(Some(event));
             }
+            // 最后一个交易日的收盘事件
+            if self.trade_date.is_some() {
+                let base_event = self.fire_base_event(3)?;
+                if base_event.is_some() {
+                    return Ok(base_event);
+                }
+            }
 
             if !self.is_end {
                 self.is_end = true;
@@ -317,7 +394,9 @@ impl Quotation for BacktestQuotation {
 
             return Ok(None);
         }
-        let ts = *self.iter.get(index).unwrap();
+        let ts = *self.iter.get(index).ok_or(Error::Custom(
+            "no quotation, subscribe codes first".to_string(),
+        ))?;
 
         let n = Utc.timestamp_opt(ts, 0).unwrap().naive_local();
 
@@ -0,0 +1,444 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::NaiveDate;
+use rwqdata::{
+    store::{Cond, Loader, Order, Query},
+    Bar,
+};
+use rwqtradecmm::{Deal, TradeType};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// 年化交易日数
+pub const TRADE_DAYS_PER_YEAR: f64 = 250.0;
+
+/// 每日权益
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Equity {
+    /// 交易日
+    pub trade_date: NaiveDate,
+    /// 总资产
+    pub net_value: f32,
+    /// 可用资金 + 冻结资金
+    pub cash: f32,
+    /// 持仓市值
+    pub hold_value: f32,
+}
+
+/// 报告参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportOpts {
+    /// 基准指数代码，如: sh000300
+    pub benchmark: Option<String>,
+    /// 无风险年化收益率，如: 0.02
+    pub risk_free: f64,
+}
+
+/// 相对基准的表现
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Benchmark {
+    /// 基准指数代码
+    pub code: String,
+    /// 基准区间收益率
+    pub total_return: f64,
+    /// 基准年化收益率
+    pub annual_return: f64,
+    /// 年化alpha
+    pub alpha: f64,
+    /// beta
+    pub beta: f64,
+}
+
+/// 回测表现报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    /// 交易日数
+    pub trade_days: usize,
+    /// 初始资金
+    pub cash_init: f32,
+    /// 期末总资产
+    pub net_value: f32,
+
+    /// 区间收益率
+    pub total_return: f64,
+    /// 年化收益率
+    pub annual_return: f64,
+    /// 年化波动率
+    pub volatility: f64,
+    /// 最大回撤
+    pub max_drawdown: f64,
+    /// 最大回撤持续交易日数(从高点到恢复或结束)
+    pub max_drawdown_days: usize,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub calmar: f64,
+
+    /// 平仓次数
+    pub trade_count: usize,
+    /// 胜率
+    pub win_rate: f64,
+    /// 盈亏比(总盈利/总亏损)，无亏损时为`None`
+    pub profit_factor: Option<f64>,
+    /// 平均持仓天数(自然日，按成交量加权)
+    pub avg_hold_days: f64,
+    /// 年化换手率(成交额/平均总资产)
+    pub turnover: f64,
+
+    /// 基准相对表现
+    pub benchmark: Option<Benchmark>,
+    /// 权益曲线
+    pub equity: Vec<Equity>,
+}
+
+impl Report {
+    /// 按权益曲线和成交记录计算报告，不含基准
+    pub fn new(cash_init: f32, equity: Vec<Equity>, deal: &[Deal], risk_free: f64) -> Self {
+        let mut report = Report {
+            start_date: equity.first().map(|e| e.trade_date),
+            end_date: equity.last().map(|e| e.trade_date),
+            trade_days: equity.len(),
+            cash_init,
+            net_value: equity.last().map(|e| e.net_value).unwrap_or(cash_init),
+            ..Default::default()
+        };
+        if cash_init > 0.0 {
+            report.total_return = (report.net_value / cash_init) as f64 - 1.0;
+        }
+        let years = report.trade_days as f64 / TRADE_DAYS_PER_YEAR;
+        if years > 0.0 && report.total_return > -1.0 {
+            report.annual_return = (1.0 + report.total_return).powf(1.0 / years) - 1.0;
+        }
+
+        let returns = daily_returns(cash_init, &equity);
+        let rf = risk_free / TRADE_DAYS_PER_YEAR;
+        let std = std_dev(&returns);
+        report.volatility = std * TRADE_DAYS_PER_YEAR.sqrt();
+        if std > 0.0 {
+            report.sharpe = (mean(&returns) - rf) / std * TRADE_DAYS_PER_YEAR.sqrt();
+        }
+        let downside = downside_dev(&returns, rf);
+        if downside > 0.0 {
+            report.sortino = (mean(&returns) - rf) / downside * TRADE_DAYS_PER_YEAR.sqrt();
+        }
+
+        let (max_drawdown, max_drawdown_days) = max_drawdown(cash_init, &equity);
+        report.max_drawdown = max_drawdown;
+        report.max_drawdown_days = max_drawdown_days;
+        if max_drawdown > 0.0 {
+            report.calmar = report.annual_return / max_drawdown;
+        }
+
+        let (mut win, mut profit, mut loss) = (0, 0.0, 0.0);
+        for deal in deal.iter().filter(|d| matches!(d.typ, TradeType::Sell)) {
+            report.trade_count += 1;
+            if deal.profit > 0.0 {
+                win += 1;
+                profit += deal.profit as f64;
+            } else {
+                loss -= deal.profit as f64;
+            }
+        }
+        if report.trade_count > 0 {
+            report.win_rate = win as f64 / report.trade_count as f64;
+        }
+        if loss > 0.0 {
+            report.profit_factor = Some(profit / loss);
+        }
+        report.avg_hold_days = avg_hold_days(deal);
+
+        let amount: f64 = deal.iter().map(|d| d.price as f64 * d.volume as f64).sum();
+        let avg_net_value = if equity.is_empty() {
+            cash_init as f64
+        } else {
+            equity.iter().map(|e| e.net_value as f64).sum::<f64>() / equity.len() as f64
+        };
+        if avg_net_value > 0.0 && years > 0.0 {
+            report.turnover = amount / avg_net_value / years;
+        }
+
+        report.equity = equity;
+        report
+    }
+
+    /// 按基准指数日线计算alpha/beta
+    pub fn with_benchmark(&mut self, code: &str, bars: &[Bar], risk_free: f64) {
+        let close: HashMap<NaiveDate, f32> = bars
+            .iter()
+            .map(|bar| (bar.trade_date.date(), bar.close))
+            .collect();
+
+        let (mut strategy, mut benchmark) = (vec![], vec![]);
+        let (mut first, mut last) = (None, None);
+        let mut prev: Option<(f32, f32)> = None;
+        for equity in self.equity.iter() {
+            if let Some(close) = close.get(&equity.trade_date) {
+                if let Some((prev_value, prev_close)) = prev {
+                    if prev_value > 0.0 && prev_close > 0.0 {
+                        strategy.push((equity.net_value / prev_value) as f64 - 1.0);
+                        benchmark.push((close / prev_close) as f64 - 1.0);
+                    }
+                }
+                prev = Some((equity.net_value, *close));
+                first = first.or(Some(*close));
+                last = Some(*close);
+            }
+        }
+
+        let mut result = Benchmark {
+            code: code.to_owned(),
+            ..Default::default()
+        };
+        if let (Some(first), Some(last)) = (first, last) {
+            if first > 0.0 {
+                result.total_return = (last / first) as f64 - 1.0;
+            }
+        }
+        let years = self.trade_days as f64 / TRADE_DAYS_PER_YEAR;
+        if years > 0.0 && result.total_return > -1.0 {
+            result.annual_return = (1.0 + result.total_return).powf(1.0 / years) - 1.0;
+        }
+        let var = std_dev(&benchmark).powi(2);
+        if var > 0.0 {
+            let rf = risk_free / TRADE_DAYS_PER_YEAR;
+            result.beta = covariance(&strategy, &benchmark) / var;
+            result.alpha = (mean(&strategy) - rf - result.beta * (mean(&benchmark) - rf))
+                * TRADE_DAYS_PER_YEAR;
+        }
+        self.benchmark = Some(result);
+    }
+
+    /// 从`loader`加载基准指数日线并计算alpha/beta
+    pub async fn load_benchmark(
+        &mut self,
+        loader: &dyn Loader,
+        code: &str,
+        risk_free: f64,
+    ) -> Result<()> {
+        let (start, end) = match (self.start_date, self.end_date) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Ok(()),
+        };
+        let bars = loader
+            .load_index_daily(
+                Query::new()
+                    .code(code)
+                    .filter(Cond::gte("trade_date", start))
+                    .filter(Cond::lte("trade_date", end))
+                    .sort("trade_date", Order::Asc),
+            )
+            .await
+            .map_err(|e| Error::Custom(format!("load benchmark {} error: {}", code, e)))?;
+        self.with_benchmark(code, &bars, risk_free);
+        Ok(())
+    }
+}
+
+fn daily_returns(cash_init: f32, equity: &[Equity]) -> Vec<f64> {
+    let mut prev = cash_init;
+    let mut returns = Vec::with_capacity(equity.len());
+    for e in equity.iter() {
+        if prev > 0.0 {
+            returns.push((e.net_value / prev) as f64 - 1.0);
+        }
+        prev = e.net_value;
+    }
+    returns
+}
+
+fn mean(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    data.iter().sum::<f64>() / data.len() as f64
+}
+
+fn std_dev(data: &[f64]) -> f64 {
+    covariance(data, data).sqrt()
+}
+
+fn covariance(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let (ma, mb) = (mean(&a[..n]), mean(&b[..n]));
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - ma) * (y - mb))
+        .sum::<f64>()
+        / (n - 1) as f64
+}
+
+fn downside_dev(data: &[f64], target: f64) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = data.iter().map(|r| (r - target).min(0.0).powi(2)).sum();
+    (sum / data.len() as f64).sqrt()
+}
+
+/// 最大回撤及持续交易日数
+fn max_drawdown(cash_init: f32, equity: &[Equity]) -> (f64, usize) {
+    let (mut peak, mut peak_index) = (cash_init as f64, 0);
+    let (mut max_drawdown, mut max_days) = (0.0, 0);
+    for (i, e) in equity.iter().enumerate() {
+        let value = e.net_value as f64;
+        if value >= peak {
+            peak = value;
+            peak_index = i + 1;
+            continue;
+        }
+        let drawdown = 1.0 - value / peak;
+        if drawdown > max_drawdown {
+            max_drawdown = drawdown;
+        }
+        let days = i + 1 - peak_index;
+        if days > max_days {
+            max_days = days;
+        }
+    }
+    (max_drawdown, max_days)
+}
+
+/// 按先进先出匹配买卖成交，计算成交量加权的平均持仓天数
+fn avg_hold_days(deal: &[Deal]) -> f64 {
+    let mut lots: HashMap<&str, VecDeque<(NaiveDate, u32)>> = HashMap::new();
+    let (mut days, mut volume) = (0.0, 0.0);
+    for deal in deal.iter() {
+        let date = deal.time.date();
+        match deal.typ {
+            TradeType::Buy => lots
+                .entry(deal.code.as_str())
+                .or_default()
+                .push_back((date, deal.volume)),
+            TradeType::Sell => {
+                let lots = lots.entry(deal.code.as_str()).or_default();
+                let mut remain = deal.volume;
+                while remain > 0 {
+                    let (buy_date, buy_volume) = match lots.front_mut() {
+                        Some(lot) => lot,
+                        None => break,
+                    };
+                    let v = remain.min(*buy_volume);
+                    days += (date - *buy_date).num_days() as f64 * v as f64;
+                    volume += v as f64;
+                    remain -= v;
+                    *buy_volume -= v;
+                    if *buy_volume == 0 {
+                        lots.pop_front();
+                    }
+                }
+            }
+            TradeType::Cancel => {}
+        }
+    }
+    if volume > 0.0 {
+        days / volume
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+    use rwqdata::Bar;
+    use rwqtradecmm::{Deal, TradeType};
+
+    use super::{Equity, Report};
+
+    fn equity(values: &[f32]) -> Vec<Equity> {
+        let start = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Equity {
+                trade_date: start + Duration::days(i as i64),
+                net_value: *v,
+                cash: *v,
+                hold_value: 0.0,
+            })
+            .collect()
+    }
+
+    fn deal(typ: TradeType, day: u32, price: f32, volume: u32, profit: f32) -> Deal {
+        Deal {
+            typ,
+            code: "sh600000".into(),
+            time: NaiveDate::from_ymd_opt(2023, 1, day).unwrap().into(),
+            price,
+            volume,
+            profit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let deals = vec![
+            deal(TradeType::Buy, 2, 10.0, 1000, 0.0),
+            deal(TradeType::Sell, 4, 11.0, 500, 500.0),
+            deal(TradeType::Sell, 6, 9.0, 500, -500.0),
+        ];
+        let report = Report::new(
+            1000.0,
+            equity(&[1000.0, 1100.0, 990.0, 1045.0, 1200.0]),
+            &deals,
+            0.0,
+        );
+        assert_eq!(report.trade_days, 5);
+        assert!((report.total_return - 0.2).abs() < 1e-6);
+        assert!((report.max_drawdown - 0.1).abs() < 1e-6);
+        assert_eq!(report.max_drawdown_days, 2);
+        assert_eq!(report.trade_count, 2);
+        assert!((report.win_rate - 0.5).abs() < 1e-6);
+        assert_eq!(report.profit_factor, Some(1.0));
+        assert!((report.avg_hold_days - 3.0).abs() < 1e-6);
+        assert!(report.sharpe > 0.0);
+        assert!(report.calmar > 0.0);
+
+        let js = serde_json::to_string(&report).unwrap();
+        let de: Report = serde_json::from_str(&js).unwrap();
+        assert_eq!(de.trade_days, report.trade_days);
+        assert_eq!(de.trade_count, report.trade_count);
+        assert_eq!(de.max_drawdown_days, report.max_drawdown_days);
+        assert_eq!(de.profit_factor, report.profit_factor);
+        assert_eq!(de.net_value, report.net_value);
+        for (a, b) in [
+            (de.total_return, report.total_return),
+            (de.max_drawdown, report.max_drawdown),
+            (de.win_rate, report.win_rate),
+            (de.sharpe, report.sharpe),
+            (de.calmar, report.calmar),
+        ] {
+            assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+        }
+        assert_eq!(de.equity.len(), 5);
+        assert_eq!(de.equity[4].trade_date, report.equity[4].trade_date);
+        assert_eq!(de.equity[4].net_value, 1200.0);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let values = [1000.0, 1100.0, 990.0, 1045.0, 1200.0];
+        let mut report = Report::new(1000.0, equity(&values), &[], 0.0);
+        // 基准与策略同涨跌，beta为1，alpha为0
+        let bars: Vec<_> = report
+            .equity
+            .iter()
+            .map(|e| Bar {
+                trade_date: e.trade_date.and_hms_opt(0, 0, 0).unwrap(),
+                close: e.net_value / 100.0,
+                ..Default::default()
+            })
+            .collect();
+        report.with_benchmark("sh000300", &bars, 0.0);
+        let benchmark = report.benchmark.unwrap();
+        assert!((benchmark.beta - 1.0).abs() < 1e-6);
+        assert!(benchmark.alpha.abs() < 1e-6);
+        assert!((benchmark.total_return - 0.2).abs() < 1e-6);
+    }
+}
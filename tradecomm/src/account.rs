@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
-use rwqcmm::MarketType;
+use rwqcmm::{MarketType, RtQuot};
 use serde::{Deserialize, Serialize};
 
-use crate::{Deal, Entrust, Position, Signal, TradeTime, TradeType, Uuid};
+use crate::{Deal, Entrust, EntrustStatus, Position, Signal, TradeTime, TradeType, Uuid};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccountKind {
@@ -118,8 +118,415 @@ impl Account {
         (fee * 100.0).round() / 100.0
     }
 
+    /// 行情更新持仓盈亏及账户汇总
+    pub fn on_quot(&mut self, quots: &RtQuot) {
+        for (code, position) in self.position.iter_mut() {
+            if let Some(quot) = quots.get(code) {
+                position.on_quot(quot);
+            }
+        }
+        self.update_summary();
+    }
+
+    /// 重新计算持仓市值，持仓盈亏及总盈亏
+    pub fn update_summary(&mut self) {
+        let (mut hold_value, mut cost, mut profit) = (0.0, 0.0, 0.0);
+        for position in self.position.values() {
+            hold_value += position.now * position.volume as f32;
+            cost += position.price * position.volume as f32 + position.fee;
+            profit += position.profit;
+        }
+        self.total_hold_value = hold_value;
+        self.cost = cost;
+        self.profit = profit;
+        self.profit_rate = if cost > 0.0 { profit / cost } else { 0.0 };
+
+        self.total_net_value = self.cash_available + self.cash_frozen + self.total_hold_value;
+        self.total_profit = self.total_net_value - self.cash_init;
+        self.total_profit_rate = if self.cash_init > 0.0 {
+            self.total_profit / self.cash_init
+        } else {
+            0.0
+        };
+    }
+
     pub fn get_est_cost(&self, typ: TradeType, price: f32, volume: u32) -> f32 {
         let cost = self.get_est_fee(typ, price, volume) + price * volume as f32;
         (cost * 100.0).round() / 100.0
     }
+
+    /// 是否T+0交易，可转债T+0，股票及场内基金T+1
+    pub fn is_t0(&self) -> bool {
+        matches!(self.typ, MarketType::Bond)
+    }
+
+    /// 委托冻结，买入冻结预估资金，卖出冻结可用持仓，并记录委托
+    ///
+    /// 资金或可用持仓不足时返回`false`，账户不变，撤销委托不冻结也不记录
+    pub fn freeze(&mut self, entrust: &Entrust) -> bool {
+        match entrust.typ {
+            TradeType::Buy => {
+                let cost = self.get_est_cost(TradeType::Buy, entrust.price, entrust.volume);
+                if self.cash_available < cost {
+                    return false;
+                }
+                self.cash_available -= cost;
+                self.cash_frozen += cost;
+            }
+            TradeType::Sell => match self.position.get_mut(&entrust.code) {
+                Some(position) if position.volume_available >= entrust.volume => {
+                    position.volume_available -= entrust.volume;
+                    position.volume_frozen += entrust.volume;
+                }
+                _ => return false,
+            },
+            TradeType::Cancel => return true,
+        }
+        self.entrust.insert(entrust.id.to_string(), entrust.clone());
+        true
+    }
+
+    /// 券商委托状态更新(`BrokerEvent::Entrust`)
+    ///
+    /// - `Init`/`Commit` 更新委托
+    /// - `PartDeal`/`Deal` 按新增成交量更新资金，持仓及成交记录，`Deal`后委托移除
+    /// - `Cancel` 先处理新增成交，再解冻未成交部分，委托移除
+    ///
+    /// 未记录的委托忽略
+    ///
+    /// `time`为成交时间，`None`则取委托时间
+    pub fn on_entrust(&mut self, entrust: Entrust, time: Option<TradeTime>) {
+        let origin = match self.entrust.get(entrust.id.as_str()) {
+            Some(origin) => origin.clone(),
+            None => return,
+        };
+        if entrust.volume_deal > origin.volume_deal {
+            self.on_deal(&entrust, &origin, time);
+        }
+        match entrust.status {
+            EntrustStatus::Deal | EntrustStatus::Cancel => {
+                self.unfreeze(&entrust);
+                self.entrust.remove(entrust.id.as_str());
+            }
+            _ => {
+                self.entrust.insert(entrust.id.to_string(), entrust);
+            }
+        }
+        self.update_summary();
+    }
+
+    /// 交易日切换，T+1持仓转为可用
+    pub fn roll_trade_date(&mut self) {
+        for position in self.position.values_mut() {
+            position.volume_available = position.volume.saturating_sub(position.volume_frozen);
+        }
+    }
+
+    /// 买入委托成交量由`from`到`to`对应的冻结资金，按委托量比例分摊
+    fn frozen_cash(&self, entrust: &Entrust, from: u32, to: u32) -> f32 {
+        if entrust.volume == 0 || to <= from {
+            return 0.0;
+        }
+        let cost = self.get_est_cost(TradeType::Buy, entrust.price, entrust.volume);
+        let frozen = if to >= entrust.volume {
+            cost - cost * from as f32 / entrust.volume as f32
+        } else {
+            cost * (to - from) as f32 / entrust.volume as f32
+        };
+        frozen.min(self.cash_frozen)
+    }
+
+    /// 解冻未成交部分
+    fn unfreeze(&mut self, entrust: &Entrust) {
+        let volume = entrust.volume.saturating_sub(entrust.volume_deal);
+        if volume == 0 {
+            return;
+        }
+        match entrust.typ {
+            TradeType::Buy => {
+                let frozen = self.frozen_cash(entrust, entrust.volume_deal, entrust.volume);
+                self.cash_frozen -= frozen;
+                self.cash_available += frozen;
+            }
+            TradeType::Sell => {
+                if let Some(position) = self.position.get_mut(&entrust.code) {
+                    let volume = volume.min(position.volume_frozen);
+                    position.volume_frozen -= volume;
+                    position.volume_available += volume;
+                }
+            }
+            TradeType::Cancel => {}
+        }
+    }
+
+    /// 相对`origin`新增的成交，更新资金，持仓，平仓盈亏及成交记录
+    ///
+    /// 成交价按成交均价`price_deal`推算，未提供成交均价则按委托价
+    fn on_deal(&mut self, entrust: &Entrust, origin: &Entrust, time: Option<TradeTime>) {
+        let volume_deal = origin.volume_deal;
+        let volume = entrust.volume_deal - volume_deal;
+        let price = if entrust.price_deal > 0.0 {
+            let amount = entrust.price_deal * entrust.volume_deal as f32
+                - origin.price_deal * volume_deal as f32;
+            amount / volume as f32
+        } else {
+            entrust.price
+        };
+        let fee = self.get_est_fee(entrust.typ, price, volume);
+        let amount = price * volume as f32;
+        let mut deal = Deal::from(entrust);
+        deal.price = price;
+        deal.volume = volume;
+        deal.fee = fee;
+        deal.time = time.unwrap_or_else(|| entrust.time.clone());
+        match entrust.typ {
+            TradeType::Buy => {
+                let frozen = self.frozen_cash(entrust, volume_deal, entrust.volume_deal);
+                self.cash_frozen -= frozen;
+                self.cash_available += frozen - amount - fee;
+
+                let is_t0 = self.is_t0();
+                let position = self
+                    .position
+                    .entry(entrust.code.clone())
+                    .or_insert_with(|| Position {
+                        name: entrust.name.clone(),
+                        code: entrust.code.clone(),
+                        time: deal.time.clone(),
+                        now: price,
+                        max_price: price,
+                        min_price: price,
+                        ..Default::default()
+                    });
+                let total = position.price * position.volume as f32 + amount;
+                position.volume += volume;
+                if is_t0 {
+                    position.volume_available += volume;
+                }
+                position.price = total / position.volume as f32;
+                position.fee += fee;
+            }
+            TradeType::Sell => {
+                self.cash_available += amount - fee;
+                if let Some(position) = self.position.get_mut(&entrust.code) {
+                    let volume = volume.min(position.volume);
+                    // 按卖出比例分摊买入费用
+                    let hold_fee = if position.volume > 0 {
+                        position.fee * volume as f32 / position.volume as f32
+                    } else {
+                        0.0
+                    };
+                    deal.profit = (price - position.price) * volume as f32 - hold_fee - fee;
+                    position.volume -= volume;
+                    position.volume_frozen = position.volume_frozen.saturating_sub(volume);
+                    position.fee -= hold_fee;
+                    if position.volume == 0 {
+                        self.position.remove(&entrust.code);
+                    }
+                }
+                self.close_profit += deal.profit;
+            }
+            TradeType::Cancel => return,
+        }
+        self.deal.push(deal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rwqcmm::MarketType;
+
+    use crate::{Account, Entrust, EntrustStatus, TradeType};
+
+    fn account() -> Account {
+        Account {
+            typ: MarketType::Stock,
+            cash_init: 100000.0,
+            cash_available: 100000.0,
+            broker_fee: 0.00025,
+            hand_fee: 0.000035,
+            transfer_fee: 0.0001,
+            tax_fee: 0.001,
+            ..Default::default()
+        }
+    }
+
+    fn entrust(typ: TradeType, price: f32, volume: u32) -> Entrust {
+        Entrust {
+            code: "sh600000".into(),
+            name: "浦发银行".into(),
+            typ,
+            status: EntrustStatus::Init,
+            price,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    fn update(entrust: &Entrust, status: EntrustStatus, volume_deal: u32) -> Entrust {
+        Entrust {
+            status,
+            volume_deal,
+            ..entrust.clone()
+        }
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_buy_deal() {
+        let mut account = account();
+        let buy = entrust(TradeType::Buy, 10.0, 1000);
+        let cost = account.get_est_cost(TradeType::Buy, 10.0, 1000);
+
+        assert!(account.freeze(&buy));
+        assert_near(account.cash_frozen, cost);
+        assert_near(account.cash_available, 100000.0 - cost);
+
+        account.on_entrust(update(&buy, EntrustStatus::Commit, 0), None);
+        assert_eq!(account.entrust.len(), 1);
+        assert!(account.deal.is_empty());
+
+        account.on_entrust(update(&buy, EntrustStatus::PartDeal, 400), None);
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume, 400);
+        assert_eq!(position.volume_available, 0);
+        assert_near(position.price, 10.0);
+        assert_eq!(account.deal.len(), 1);
+
+        account.on_entrust(update(&buy, EntrustStatus::Deal, 1000), None);
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume, 1000);
+        assert_eq!(account.deal.len(), 2);
+        assert!(account.entrust.is_empty());
+        assert_near(account.cash_frozen, 0.0);
+
+        let fee: f32 = account.deal.iter().map(|d| d.fee).sum();
+        assert_near(position.fee, fee);
+        assert_near(account.cash_available, 100000.0 - 10000.0 - fee);
+        assert_near(account.total_net_value, 100000.0 - fee);
+    }
+
+    #[test]
+    fn test_avg_price() {
+        let mut account = account();
+        let buy1 = entrust(TradeType::Buy, 10.0, 1000);
+        let buy2 = entrust(TradeType::Buy, 12.0, 1000);
+        assert!(account.freeze(&buy1));
+        assert!(account.freeze(&buy2));
+        account.on_entrust(update(&buy1, EntrustStatus::Deal, 1000), None);
+        account.on_entrust(update(&buy2, EntrustStatus::Deal, 1000), None);
+
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume, 2000);
+        assert_near(position.price, 11.0);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut account = account();
+        let buy = entrust(TradeType::Buy, 10.0, 1000);
+        assert!(account.freeze(&buy));
+        account.on_entrust(update(&buy, EntrustStatus::PartDeal, 300), None);
+        account.on_entrust(update(&buy, EntrustStatus::Cancel, 300), None);
+
+        assert!(account.entrust.is_empty());
+        assert_near(account.cash_frozen, 0.0);
+        let fee = account.deal[0].fee;
+        assert_near(account.cash_available, 100000.0 - 3000.0 - fee);
+
+        // 可用不足，卖出冻结失败
+        let sell = entrust(TradeType::Sell, 11.0, 300);
+        assert!(!account.freeze(&sell));
+
+        account.roll_trade_date();
+        assert!(account.freeze(&sell));
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume_available, 0);
+        assert_eq!(position.volume_frozen, 300);
+
+        // 撤销委托不记录为活动委托
+        let cancel = Entrust {
+            cancel_id: Some(sell.id.to_string()),
+            ..entrust(TradeType::Cancel, 0.0, 0)
+        };
+        assert!(account.freeze(&cancel));
+        assert_eq!(account.get_active_entrust("sh600000").len(), 1);
+
+        account.on_entrust(update(&sell, EntrustStatus::Cancel, 0), None);
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume_available, 300);
+        assert_eq!(position.volume_frozen, 0);
+    }
+
+    #[test]
+    fn test_sell_deal() {
+        let mut account = account();
+        let buy = entrust(TradeType::Buy, 10.0, 1000);
+        assert!(account.freeze(&buy));
+        account.on_entrust(update(&buy, EntrustStatus::Deal, 1000), None);
+        let buy_fee = account.deal[0].fee;
+
+        account.roll_trade_date();
+        let sell = entrust(TradeType::Sell, 11.0, 1000);
+        assert!(account.freeze(&sell));
+        account.on_entrust(update(&sell, EntrustStatus::PartDeal, 500), None);
+        let position = account.position.get("sh600000").unwrap();
+        assert_eq!(position.volume, 500);
+        assert_eq!(position.volume_frozen, 500);
+        assert_near(position.fee, buy_fee / 2.0);
+
+        account.on_entrust(update(&sell, EntrustStatus::Deal, 1000), None);
+        assert!(account.position.is_empty());
+        assert!(account.entrust.is_empty());
+        assert_eq!(account.deal.len(), 3);
+
+        let fee: f32 = account.deal.iter().map(|d| d.fee).sum();
+        assert_near(account.close_profit, 1000.0 - fee);
+        assert_near(account.cash_available, 100000.0 + 1000.0 - fee);
+        assert_near(account.total_profit, account.close_profit);
+    }
+
+    #[test]
+    fn test_price_deal() {
+        let mut account = account();
+        let buy = entrust(TradeType::Buy, 10.0, 1000);
+        assert!(account.freeze(&buy));
+        account.on_entrust(
+            Entrust {
+                price_deal: 9.8,
+                ..update(&buy, EntrustStatus::PartDeal, 500)
+            },
+            None,
+        );
+        account.on_entrust(
+            Entrust {
+                price_deal: 9.9,
+                ..update(&buy, EntrustStatus::Deal, 1000)
+            },
+            None,
+        );
+        assert_near(account.deal[0].price, 9.8);
+        assert_near(account.deal[1].price, 10.0);
+        assert_near(account.cash_frozen, 0.0);
+
+        let position = account.position.get("sh600000").unwrap();
+        assert_near(position.price, 9.9);
+        let fee: f32 = account.deal.iter().map(|d| d.fee).sum();
+        assert_near(account.cash_available, 100000.0 - 9900.0 - fee);
+    }
+
+    #[test]
+    fn test_t0() {
+        let mut account = Account {
+            typ: MarketType::Bond,
+            ..account()
+        };
+        let buy = entrust(TradeType::Buy, 100.0, 10);
+        assert!(account.freeze(&buy));
+        account.on_entrust(update(&buy, EntrustStatus::Deal, 10), None);
+        assert_eq!(account.get_position_volume("sh600000"), (10, 10));
+    }
 }
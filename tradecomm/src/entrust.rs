@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{Signal, TradeType, Uuid, TradeTime};
+use crate::{Signal, TradeTime, TradeType, Uuid};
 
 /// 委托单状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +49,9 @@ pub struct Entrust {
     pub volume: u32,
     /// 已成交量
     pub volume_deal: u32,
+    /// 成交均价
+    #[serde(default)]
+    pub price_deal: f32,
     /// 已取消量
     pub volume_cancel: u32,
     /// 触发委托的信号id
@@ -57,6 +60,9 @@ pub struct Entrust {
         deserialize_with = "crate::uuid_deserialize"
     )]
     pub signal_id: Uuid,
+    /// 撤销的委托id，仅撤销委托有效
+    #[serde(default)]
+    pub cancel_id: Option<String>,
     /// 描述
     pub desc: String,
 }
@@ -73,10 +79,12 @@ impl From<&Signal> for Entrust {
             price: signal.price,
             volume: signal.volume,
             volume_deal: 0,
+            price_deal: 0.0,
             volume_cancel: 0,
             desc: signal.desc.clone(),
             broker_entrust_id: None,
             signal_id: signal.id.clone(),
+            cancel_id: signal.entrust_id.clone(),
         }
     }
 }
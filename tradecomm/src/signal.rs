@@ -58,6 +58,9 @@ pub struct Signal {
     pub price: f32,
     /// 委托量
     pub volume: u32,
+    /// 撤销的委托id，撤销信号必填
+    #[serde(default)]
+    pub entrust_id: Option<String>,
     /// 描述
     pub desc: String,
 }